@@ -0,0 +1,74 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Scripts the dashboards load, embedded from `assets/` so they render without internet:
+/// (file name, the pinned release it is a copy of)
+const ASSETS: &[(&str, &str)] = &[
+    ("chart.umd.js", "https://cdn.jsdelivr.net/npm/chart.js@4.4.1/dist/chart.umd.js"),
+    ("chartjs-adapter-date-fns.bundle.min.js", "https://cdn.jsdelivr.net/npm/chartjs-adapter-date-fns@3.0.0/dist/chartjs-adapter-date-fns.bundle.min.js"),
+    ("tailwind.js", "https://cdn.tailwindcss.com/3.4.1"),
+];
+
+fn main() {
+    // Commit the binary was built from, so bug reports can be matched to the source
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    embed_assets();
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Generate the gRPC service from proto/, with a bundled protoc so none needs to be installed
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("bundled protoc"));
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/wifi_tracker.proto"], &["proto"])
+        .expect("compile proto/wifi_tracker.proto");
+}
+
+/// Copy each asset from `assets/` into OUT_DIR and write the table `src/assets.rs` includes.
+/// A missing asset is left out with a warning rather than failing a fresh checkout; the
+/// dashboards then render without it, as they never fall back to a CDN.
+fn embed_assets() {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo")).join("assets");
+    std::fs::create_dir_all(&out_dir).expect("create the assets output directory");
+    let mut table = String::from("const ASSETS: &[Asset] = &[\n");
+    for (name, source) in ASSETS {
+        let vendored = Path::new("assets").join(name);
+        let target = out_dir.join(name);
+        println!("cargo:rerun-if-changed={}", vendored.display());
+        if std::fs::copy(&vendored, &target).is_err() {
+            println!(
+                "cargo:warning={} is missing, the dashboards will render without it. Fetch it with: curl -fsSL -o {} {}",
+                vendored.display(), vendored.display(), source
+            );
+            continue;
+        }
+        writeln!(table, "    Asset {{ name: {:?}, bytes: include_bytes!({:?}) }},", name, target.display().to_string())
+            .expect("write to a string");
+    }
+    table.push_str("];\n");
+    std::fs::write(out_dir.join("table.rs"), table).expect("write the asset table");
+}
@@ -0,0 +1,279 @@
+use crate::metrics::*;
+
+/// Samples needed on each band before their histories are compared
+const MIN_BAND_SAMPLES: usize = 30;
+/// Difference in degraded samples (percentage points) worth recommending a band
+const MIN_BAND_DEGRADED_GAP: f64 = 10.0;
+/// Band switches that suggest the client keeps getting steered back and forth
+const BAND_SWITCH_LIMIT: usize = 3;
+/// Neighbor congestion up to which a wide channel is still worth it
+const WIDE_CHANNEL_MAX_CONGESTION: f64 = 0.5;
+/// How much less congested another channel must be before moving is recommended
+const MIN_CONGESTION_GAIN: f64 = 0.5;
+/// Extra congestion charged to DFS channels for the CAC wait and radar evacuations
+const DFS_PENALTY: f64 = 0.25;
+
+/// 2.4 GHz channels that don't overlap each other
+const CHANNELS_2_4GHZ: [u32; 3] = [1, 6, 11];
+/// 5 GHz 80 MHz blocks by primary channel
+const BLOCKS_5GHZ: [[u32; 4]; 6] = [
+    [36, 40, 44, 48],
+    [52, 56, 60, 64],
+    [100, 104, 108, 112],
+    [116, 120, 124, 128],
+    [132, 136, 140, 144],
+    [149, 153, 157, 161],
+];
+
+/// Router vendors recognizable from their default SSIDs, with where to find channel settings
+const ROUTER_GUIDES: [(&str, &str, &str); 9] = [
+    ("netgear", "NETGEAR", "https://kb.netgear.com/"),
+    ("orbi", "NETGEAR Orbi", "https://kb.netgear.com/"),
+    ("tp-link", "TP-Link", "https://www.tp-link.com/support/faq/"),
+    ("deco", "TP-Link Deco", "https://www.tp-link.com/support/faq/"),
+    ("asus", "ASUS", "https://www.asus.com/support/"),
+    ("linksys", "Linksys", "https://support.linksys.com/"),
+    ("eero", "eero", "https://support.eero.com/"),
+    ("xfinity", "Xfinity", "https://www.xfinity.com/support/internet"),
+    ("unifi", "Ubiquiti UniFi", "https://help.ui.com/"),
+];
+
+/// How the connection behaved while on one band
+#[derive(Debug, Clone)]
+pub struct BandPerformance {
+    pub band: WifiBand,
+    pub samples: usize,
+    pub signal_avg_dbm: f64,
+    pub latency_avg_ms: Option<f64>,
+    pub packet_loss_avg_percent: f64,
+    pub degraded_percent: f64,
+}
+
+/// A channel and width to configure on the router
+#[derive(Debug, Clone)]
+struct ChannelPlan {
+    channel: u32,
+    width_mhz: u32,
+    congestion: f64,
+    neighbors: usize,
+}
+
+/// Per-band history, ordered 2.4 / 5 / 6 GHz
+pub fn band_performance(snapshots: &[WifiSnapshot], thresholds: &AlertThresholds) -> Vec<BandPerformance> {
+    let mut bands: Vec<(WifiBand, Vec<&WifiSnapshot>)> = Vec::new();
+    for snapshot in snapshots {
+        let Some(ref wifi) = snapshot.wifi_info else { continue };
+        if wifi.band == WifiBand::Unknown {
+            continue;
+        }
+        match bands.iter_mut().find(|(band, _)| *band == wifi.band) {
+            Some((_, samples)) => samples.push(snapshot),
+            None => bands.push((wifi.band.clone(), vec![snapshot])),
+        }
+    }
+
+    let mut performance: Vec<BandPerformance> = bands.into_iter().map(|(band, samples)| {
+        let n = samples.len() as f64;
+        let latencies: Vec<f64> = samples.iter().filter_map(|s| s.latency.average_latency_ms).collect();
+        BandPerformance {
+            band,
+            samples: samples.len(),
+            signal_avg_dbm: samples.iter()
+                .filter_map(|s| s.wifi_info.as_ref())
+                .map(|w| w.signal_strength_dbm as f64)
+                .sum::<f64>() / n,
+            latency_avg_ms: (!latencies.is_empty())
+                .then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+            packet_loss_avg_percent: samples.iter().map(|s| s.latency.packet_loss_percent).sum::<f64>() / n,
+            degraded_percent: samples.iter()
+                .filter(|s| thresholds.is_degraded(s.latency.average_latency_ms, s.latency.packet_loss_percent))
+                .count() as f64 / n * 100.0,
+        }
+    }).collect();
+    performance.sort_by_key(|p| match p.band {
+        WifiBand::Band2_4GHz => 0,
+        WifiBand::Band5GHz => 1,
+        _ => 2,
+    });
+    performance
+}
+
+/// Concrete band/channel/width recommendations from the latest neighbor scan,
+/// per-band history and DFS evacuations
+pub fn channel_recommendations(snapshots: &[WifiSnapshot], events: &[NetworkEvent], thresholds: &AlertThresholds) -> Vec<String> {
+    let mut recommendations = Vec::new();
+    let Some(current) = snapshots.iter().rev().find_map(|s| s.wifi_info.as_ref()) else {
+        return recommendations;
+    };
+    let guide = router_guide(&current.ssid, current.gateway.as_deref());
+
+    let dfs_exits = events.iter()
+        .filter(|e| e.event_type == EventType::ChannelChange)
+        .filter(|e| e.details.get("dfs_exit").and_then(|v| v.as_bool()) == Some(true))
+        .count();
+    let band_switches = events.iter().filter(|e| e.event_type == EventType::BandSwitch).count();
+
+    // Other networks from the most recent scan; our own SSID covers mesh nodes too
+    let scan = snapshots.iter().rev().find(|s| !s.neighbors.is_empty()).map(|s| s.neighbors.as_slice());
+    let others: Vec<&NeighborNetwork> = scan.unwrap_or_default().iter()
+        .filter(|n| n.ssid != current.ssid)
+        .collect();
+
+    match current.band {
+        WifiBand::Band2_4GHz if scan.is_some() => {
+            let (now, now_count) = congestion_2_4ghz(&others, current.channel);
+            let best = plan_2_4ghz(&others);
+            if best.channel != current.channel
+                && (now - best.congestion >= MIN_CONGESTION_GAIN || !CHANNELS_2_4GHZ.contains(&current.channel))
+            {
+                recommendations.push(format!(
+                    "Set 2.4 GHz to channel {} at {} MHz: {} neighboring network(s) overlap channel {}, {} overlap channel {}{}",
+                    best.channel, best.width_mhz, now_count, current.channel, best.neighbors, best.channel, guide
+                ));
+            }
+        }
+        WifiBand::Band5GHz => {
+            let on_dfs = current.band.is_dfs(current.channel);
+            let dfs_note = if dfs_exits > 0 {
+                format!(" Radar forced {} change(s) off DFS channels, so channels 52-144 are avoided.", dfs_exits)
+            } else {
+                String::new()
+            };
+            match scan {
+                Some(_) => {
+                    let best = plan_5ghz(&others, dfs_exits > 0);
+                    let now = block_5ghz(current.channel).map(|block| block_congestion(&others, block));
+                    let crowded = now.is_some_and(|(c, _)| c - best.congestion >= MIN_CONGESTION_GAIN);
+                    if best.channel != current.channel && (crowded || (on_dfs && dfs_exits > 0)) {
+                        let now_count = now.map(|(_, n)| n).unwrap_or(0);
+                        recommendations.push(format!(
+                            "Set 5 GHz to channel {} at {} MHz: {} neighboring network(s) use channels {} around channel {}, {} use {}.{}{}",
+                            best.channel, best.width_mhz, now_count, block_label(current.channel), current.channel,
+                            best.neighbors, block_label(best.channel), dfs_note, guide
+                        ));
+                    }
+                }
+                None if on_dfs && dfs_exits > 0 => {
+                    recommendations.push(format!(
+                        "Set 5 GHz to a non-DFS channel (36-48 or 149-161) instead of channel {}.{}{}",
+                        current.channel, dfs_note, guide
+                    ));
+                }
+                None => {}
+            }
+        }
+        _ => {}
+    }
+
+    // Compare how each band actually performed
+    let history = band_performance(snapshots, thresholds);
+    let compared: Vec<&BandPerformance> = history.iter()
+        .filter(|p| p.samples >= MIN_BAND_SAMPLES && p.band != WifiBand::Band6GHz)
+        .collect();
+    if let [a, b] = compared[..] {
+        let (better, worse) = if a.degraded_percent <= b.degraded_percent { (a, b) } else { (b, a) };
+        if worse.degraded_percent - better.degraded_percent >= MIN_BAND_DEGRADED_GAP {
+            let fixed_band = if better.band == WifiBand::Band5GHz {
+                "give 5 GHz its own SSID or disable band steering"
+            } else {
+                "give 2.4 GHz its own SSID for this device, or move the router closer to improve 5 GHz coverage"
+            };
+            recommendations.push(format!(
+                "Prefer {}: degraded in {:.0}% of samples ({:.0} dBm avg) vs {:.0}% on {} ({:.0} dBm avg); {}{}",
+                better.band.label(), better.degraded_percent, better.signal_avg_dbm,
+                worse.degraded_percent, worse.band.label(), worse.signal_avg_dbm, fixed_band, guide
+            ));
+        }
+    } else if band_switches > BAND_SWITCH_LIMIT {
+        recommendations.push(format!(
+            "Pin this device to one band: it switched bands {} times; give 5 GHz its own SSID or disable band steering{}",
+            band_switches, guide
+        ));
+    }
+
+    recommendations
+}
+
+/// Neighbor congestion on a 2.4 GHz channel: signal-weighted count of networks within 4 channels
+fn congestion_2_4ghz(others: &[&NeighborNetwork], channel: u32) -> (f64, usize) {
+    congestion(others, |n| n.band == WifiBand::Band2_4GHz && n.channel.abs_diff(channel) < 5)
+}
+
+fn plan_2_4ghz(others: &[&NeighborNetwork]) -> ChannelPlan {
+    CHANNELS_2_4GHZ.iter()
+        .map(|&channel| {
+            let (congestion, neighbors) = congestion_2_4ghz(others, channel);
+            ChannelPlan { channel, width_mhz: 20, congestion, neighbors }
+        })
+        .min_by(|a, b| a.congestion.total_cmp(&b.congestion))
+        .expect("2.4 GHz channel list is not empty")
+}
+
+fn block_5ghz(channel: u32) -> Option<&'static [u32; 4]> {
+    BLOCKS_5GHZ.iter().find(|block| block.contains(&channel))
+}
+
+fn block_label(channel: u32) -> String {
+    block_5ghz(channel)
+        .map(|block| format!("{}-{}", block[0], block[3]))
+        .unwrap_or_else(|| channel.to_string())
+}
+
+fn block_congestion(others: &[&NeighborNetwork], channels: &[u32]) -> (f64, usize) {
+    congestion(others, |n| n.band == WifiBand::Band5GHz && channels.contains(&n.channel))
+}
+
+/// Least congested 80 MHz block, narrowed to 40 or 20 MHz when neighbors are inside it
+fn plan_5ghz(others: &[&NeighborNetwork], avoid_dfs: bool) -> ChannelPlan {
+    let (block, block_score) = BLOCKS_5GHZ.iter()
+        .filter(|block| !(avoid_dfs && WifiBand::Band5GHz.is_dfs(block[0])))
+        .map(|block| {
+            let penalty = if WifiBand::Band5GHz.is_dfs(block[0]) { DFS_PENALTY } else { 0.0 };
+            (block, block_congestion(others, block).0 + penalty)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("5 GHz block list is not empty");
+
+    // Quietest 40 MHz half, then the quietest channel in it
+    let half = [&block[..2], &block[2..]].into_iter()
+        .min_by(|a, b| block_congestion(others, a).0.total_cmp(&block_congestion(others, b).0))
+        .expect("block has two halves");
+    let channel = *half.iter()
+        .min_by(|a, b| block_congestion(others, &[**a]).0.total_cmp(&block_congestion(others, &[**b]).0))
+        .expect("half has two channels");
+
+    let (congestion, neighbors) = block_congestion(others, block);
+    let width_mhz = if block_score <= WIDE_CHANNEL_MAX_CONGESTION {
+        80
+    } else if block_congestion(others, half).0 <= WIDE_CHANNEL_MAX_CONGESTION {
+        40
+    } else {
+        20
+    };
+    ChannelPlan { channel, width_mhz, congestion, neighbors }
+}
+
+/// Signal-weighted congestion (a full-strength neighbor counts as 1) and neighbor count
+fn congestion(others: &[&NeighborNetwork], overlaps: impl Fn(&NeighborNetwork) -> bool) -> (f64, usize) {
+    others.iter()
+        .filter(|n| overlaps(n))
+        .fold((0.0, 0), |(weight, count), n| (weight + n.signal_quality_percent as f64 / 100.0, count + 1))
+}
+
+/// Where to change the setting: vendor instructions when the SSID gives the router away,
+/// and the router's admin page
+fn router_guide(ssid: &str, gateway: Option<&str>) -> String {
+    let ssid = ssid.to_lowercase();
+    let mut parts = Vec::new();
+    if let Some((_, vendor, url)) = ROUTER_GUIDES.iter().find(|(hint, _, _)| ssid.contains(hint)) {
+        parts.push(format!("{} instructions: {}", vendor, url));
+    }
+    if let Some(gateway) = gateway {
+        parts.push(format!("admin page: http://{}", gateway));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join("; "))
+    }
+}
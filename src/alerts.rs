@@ -0,0 +1,235 @@
+//! Alert rules read from a TOML file (`--alert-rules`), evaluated against every snapshot's
+//! metrics, the same names `/api/v1/timeseries` serves:
+//!
+//! ```toml
+//! [[rule]]
+//! name = "packet-loss"
+//! condition = "packet_loss > 5"
+//! samples = 3            # for 3 consecutive snapshots
+//! severity = "warning"
+//!
+//! [[rule]]
+//! name = "internet-down"
+//! condition = "internet_reachable == false"
+//! for = "60s"            # held for at least a minute
+//! severity = "critical"
+//! cooldown = "30m"
+//! ```
+//!
+//! A rule fires once when its condition has held long enough and resolves when it stops
+//! holding. It doesn't fire again until `cooldown` (default 10 minutes) has passed since it
+//! last fired.
+
+use crate::metrics::{Alert, EventSeverity, WifiSnapshot};
+use crate::storage::timeseries_points;
+use crate::web::parse_bucket;
+use anyhow::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use std::path::Path;
+use uuid::Uuid;
+
+const DEFAULT_COOLDOWN: TimeDelta = TimeDelta::minutes(10);
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    condition: String,
+    /// Consecutive snapshots the condition must hold in
+    samples: Option<u32>,
+    /// How long the condition must hold, e.g. `60s` or `5m`
+    #[serde(rename = "for")]
+    duration: Option<String>,
+    severity: Option<String>,
+    cooldown: Option<String>,
+    /// Said when the rule fires, instead of the condition and the value
+    message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    /// Longer operators first, so `>=` isn't read as `>`
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        (">=", Comparison::AtLeast),
+        ("<=", Comparison::AtMost),
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        (">", Comparison::Above),
+        ("<", Comparison::Below),
+    ];
+
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::AtLeast => value >= threshold,
+            Comparison::Below => value < threshold,
+            Comparison::AtMost => value <= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => value != threshold,
+        }
+    }
+}
+
+/// `<metric> <operator> <value>`, where the value is a number, optionally with `%`, or
+/// `true`/`false` for the 1/0 metrics such as `internet_reachable`
+#[derive(Debug, Clone)]
+struct Condition {
+    metric: String,
+    comparison: Comparison,
+    threshold: f64,
+}
+
+impl std::str::FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let Some((at, operator, comparison)) = Comparison::OPERATORS.iter()
+            .filter_map(|(operator, comparison)| Some((s.find(operator)?, *operator, *comparison)))
+            .min_by_key(|(at, operator, _)| (*at, std::cmp::Reverse(operator.len())))
+        else {
+            anyhow::bail!("no comparison in {:?}; expected e.g. \"packet_loss > 5\"", s);
+        };
+        let metric = s[..at].trim();
+        if metric.is_empty() || metric.contains(char::is_whitespace) {
+            anyhow::bail!("{:?} doesn't start with a metric name", s);
+        }
+        let threshold = match s[at + operator.len()..].trim() {
+            "true" => 1.0,
+            "false" => 0.0,
+            value => value.trim_end_matches('%').trim().parse()
+                .map_err(|_| anyhow::anyhow!("{:?} isn't a number, true or false in {:?}", value, s))?,
+        };
+        Ok(Self { metric: metric.to_string(), comparison, threshold })
+    }
+}
+
+struct Rule {
+    name: String,
+    condition_text: String,
+    condition: Condition,
+    samples: u32,
+    duration: Option<TimeDelta>,
+    severity: EventSeverity,
+    cooldown: TimeDelta,
+    message: Option<String>,
+}
+
+#[derive(Default)]
+struct RuleState {
+    /// Consecutive snapshots the condition has held in, and since when
+    streak: u32,
+    since: Option<DateTime<Utc>>,
+    active: Option<Alert>,
+    last_fired: Option<DateTime<Utc>>,
+}
+
+/// The loaded rules and where each stands
+pub struct AlertRules {
+    rules: Vec<(Rule, RuleState)>,
+}
+
+impl AlertRules {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read alert rules {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Invalid alert rules in {:?}", path))
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let file: RulesFile = toml::from_str(text)?;
+        let mut rules = Vec::new();
+        for config in file.rules {
+            let name = config.name.trim().to_string();
+            if name.is_empty() {
+                anyhow::bail!("a rule has no name");
+            }
+            if rules.iter().any(|(rule, _): &(Rule, RuleState)| rule.name == name) {
+                anyhow::bail!("more than one rule is named {:?}", name);
+            }
+            let rule = || format!("rule {:?}", name);
+            let duration = |value: &str| parse_bucket(value)
+                .ok_or_else(|| anyhow::anyhow!("{:?} isn't a duration like 30s, 5m, 1h or 1d", value));
+            rules.push((Rule {
+                condition: config.condition.parse().with_context(rule)?,
+                condition_text: config.condition.trim().to_string(),
+                samples: config.samples.unwrap_or(1).max(1),
+                duration: config.duration.as_deref().map(duration).transpose().with_context(rule)?,
+                severity: config.severity.as_deref().unwrap_or("warning").parse::<EventSeverity>()
+                    .map_err(anyhow::Error::msg).with_context(rule)?,
+                cooldown: match config.cooldown.as_deref() {
+                    None => DEFAULT_COOLDOWN,
+                    Some("0") => TimeDelta::zero(),
+                    Some(value) => duration(value).with_context(rule)?,
+                },
+                message: config.message,
+                name,
+            }, RuleState::default()));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check every rule against the snapshot's metrics, returning the alerts it fired and
+    /// the ones it resolved. `muted` is asked when a rule fires.
+    pub fn evaluate(&mut self, snapshot: &WifiSnapshot, muted: impl Fn() -> bool) -> Vec<Alert> {
+        let points = timeseries_points(snapshot);
+        let now = snapshot.timestamp;
+        let mut changed = Vec::new();
+        for (rule, state) in &mut self.rules {
+            // A metric the snapshot doesn't have, e.g. latency while offline, doesn't match
+            let value = points.iter().find(|(metric, _)| *metric == *rule.condition.metric).map(|(_, value)| *value);
+            let Some(value) = value.filter(|v| rule.condition.comparison.holds(*v, rule.condition.threshold)) else {
+                state.streak = 0;
+                state.since = None;
+                if let Some(mut alert) = state.active.take() {
+                    alert.resolved_at = Some(now);
+                    changed.push(alert);
+                }
+                continue;
+            };
+
+            state.streak += 1;
+            let since = *state.since.get_or_insert(now);
+            let held = state.streak >= rule.samples && rule.duration.is_none_or(|d| now - since >= d);
+            let cooling = state.last_fired.is_some_and(|t| now - t < rule.cooldown);
+            if state.active.is_some() || !held || cooling {
+                continue;
+            }
+            let alert = Alert {
+                id: Uuid::new_v4().to_string(),
+                name: rule.name.clone(),
+                severity: rule.severity.clone(),
+                condition: rule.condition_text.clone(),
+                message: rule.message.clone()
+                    .unwrap_or_else(|| format!("{} ({} was {})", rule.condition_text, rule.condition.metric, value)),
+                value,
+                fired_at: now,
+                resolved_at: None,
+                muted: muted(),
+            };
+            state.last_fired = Some(now);
+            state.active = Some(alert.clone());
+            changed.push(alert);
+        }
+        changed
+    }
+}
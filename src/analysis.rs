@@ -1,10 +1,45 @@
+use crate::advisor::{self, BandPerformance};
 use crate::metrics::*;
+use crate::periodicity::{self, PeriodicPattern};
 use crate::storage::MetricsStore;
+use chrono::Utc;
 
-pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
-    let stats = store.get_statistics(None, None)?;
-    let events = store.get_events(None, None, None, None)?;
-    let event_counts = store.get_event_counts_by_type(None, None)?;
+/// Full report over the whole database, or over one monitoring session
+pub fn generate_report(store: &dyn MetricsStore, session: Option<&Session>) -> anyhow::Result<String> {
+    let (start, end) = session.map(Session::range).unzip();
+    build_report(store, start.as_deref(), end.as_deref(), session)
+}
+
+/// Full report over a time range (ISO 8601); a missing end is open
+pub fn generate_range_report(store: &dyn MetricsStore, start: Option<&str>, end: Option<&str>) -> anyhow::Result<String> {
+    build_report(store, start, end, None)
+}
+
+fn build_report(
+    store: &dyn MetricsStore,
+    start: Option<&str>,
+    end: Option<&str>,
+    session: Option<&Session>,
+) -> anyhow::Result<String> {
+    let thresholds = AlertThresholds::for_session(session);
+    let stats = store.get_statistics(start, end)?;
+    let events = store.get_events(start, end, None, None, None, 0)?;
+    let event_counts = store.get_event_counts_by_type(start, end)?;
+    let adapter = store.get_latest_adapter_info()?;
+    let mut snapshots = store.get_snapshots(start, end, None, 0)?;
+    snapshots.retain(|s| store.in_scope(s));
+    let efficiency = analyze_efficiency(&snapshots);
+    let wired = compare_wired(&snapshots, &thresholds);
+    let guest = compare_guest(&snapshots, &thresholds);
+    let patterns = periodicity::detect(&snapshots);
+    let bands = advisor::band_performance(&snapshots, &thresholds);
+    // Databases from before incidents existed have no incidents table, nor sessions
+    let incidents = store.get_incidents(start, end, None).unwrap_or_default();
+    let whole_database = session.is_none() && start.is_none() && end.is_none();
+    let sessions = if whole_database { store.get_sessions().unwrap_or_default() } else { Vec::new() };
+    let ip_changes = public_ip_changes(&events, &incidents);
+    // Nor annotations, before those existed
+    let annotations = store.get_annotations(start, end).unwrap_or_default();
 
     let mut report = String::new();
 
@@ -18,7 +53,47 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
         stats.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
         stats.end_time.format("%Y-%m-%d %H:%M:%S UTC")
     ));
-    report.push_str(&format!("Total Samples: {}\n\n", stats.sample_count));
+    if let Some(session) = session {
+        report.push_str(&format!("Session: {}{}\n",
+            session.id,
+            session.host.as_deref().map(|h| format!(" on {}", h)).unwrap_or_default()
+        ));
+    }
+    if thresholds != AlertThresholds::default() {
+        report.push_str(&format!(
+            "Thresholds: signal {}/{} dBm, latency {}/{} ms, jitter {} ms, loss {}/{}% (warning/critical)\n",
+            thresholds.signal_strength_warning_dbm,
+            thresholds.signal_strength_critical_dbm,
+            thresholds.latency_warning_ms,
+            thresholds.latency_critical_ms,
+            thresholds.jitter_warning_ms,
+            thresholds.packet_loss_warning_percent,
+            thresholds.packet_loss_critical_percent,
+        ));
+    }
+    report.push_str(&format!("Total Samples: {}\n", stats.sample_count));
+    if stats.reduced_sampling_percent > 0.0 {
+        report.push_str(&format!(
+            "Reduced Sampling: {:.1}% of the period (battery/metered), sampled less often\n",
+            stats.reduced_sampling_percent
+        ));
+    }
+    if stats.other_network_samples > 0 {
+        report.push_str(&format!(
+            "Other Networks: {} samples taken away from the monitored network (excluded)\n",
+            stats.other_network_samples
+        ));
+    }
+    if stats.maintenance_samples > 0 {
+        report.push_str(&format!(
+            "Maintenance: {} samples taken in maintenance windows (excluded)\n",
+            stats.maintenance_samples
+        ));
+    }
+    if stats.paused_secs > 0 {
+        report.push_str(&format!("Paused: {} (excluded from uptime)\n", format_secs(stats.paused_secs)));
+    }
+    report.push('\n');
 
     // Overall Health Score
     let health_score = calculate_health_score(&stats);
@@ -36,6 +111,46 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
     report.push_str(&format!("  Total Disconnections:      {:>6}\n", stats.total_disconnections));
     report.push_str(&format!("  Average Packet Loss:       {:>6.2}%\n\n", stats.packet_loss_avg_percent));
 
+    // Internet Failures by Cause
+    let causes = internet_failure_causes(&snapshots);
+    if !causes.is_empty() {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                    INTERNET FAILURES BY CAUSE                      \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        let total: usize = causes.iter().map(|(_, count)| count).sum();
+        for (outcome, count) in &causes {
+            report.push_str(&format!("  {:<28} {:>6}  ({:>5.1}%)\n",
+                outcome.label(), count, *count as f64 / total as f64 * 100.0));
+        }
+        let https_only = snapshots.iter()
+            .filter(|s| s.connectivity.internet_reachable)
+            .filter(|s| s.connectivity.https_outcome.is_some_and(|o| o != HttpOutcome::Success))
+            .count();
+        if https_only > 0 {
+            report.push_str(&format!("  HTTPS failed while HTTP worked: {} samples\n", https_only));
+        }
+        report.push('\n');
+    }
+
+    // Incidents
+    if !incidents.is_empty() {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                             INCIDENTS                              \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format_incidents(&incidents));
+        report.push('\n');
+    }
+
+    // Annotations among incidents and pauses
+    if !annotations.is_empty() {
+        let pauses = store.get_pauses(start, end).unwrap_or_default();
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                             TIMELINE                              \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format_timeline(&annotations, &incidents, &pauses));
+        report.push('\n');
+    }
+
     // Signal Quality
     report.push_str("───────────────────────────────────────────────────────────────────\n");
     report.push_str("                          SIGNAL QUALITY                            \n");
@@ -76,8 +191,142 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
     if let Some(jitter) = stats.jitter_avg_ms {
         report.push_str(&format!("  Average Jitter:    {:>8.1} ms  {}\n", jitter, jitter_rating(jitter)));
     }
+    if let Some(jitter) = stats.rfc3550_jitter_avg_ms {
+        report.push_str(&format!("  RFC 3550 Jitter:   {:>8.1} ms  (VoIP-style, per target)\n", jitter));
+    }
+    if let Some(mos) = stats.mos_avg {
+        report.push_str(&format!("  Call Quality MOS:  {:>8.2}     {}\n", mos, mos_rating(mos)));
+        report.push_str(&format!("  Worst MOS:         {:>8.2}     ({:.1}% of samples below {})\n",
+            stats.mos_min.unwrap_or(mos), stats.poor_call_percent, MOS_POOR_CALL));
+    }
     report.push('\n');
 
+    // Throughput Efficiency
+    if let Some(ref eff) = efficiency {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                       THROUGHPUT EFFICIENCY                        \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format!("  Avg Negotiated Rate: {:>8.1} Mbps\n", eff.negotiated_avg_mbps));
+        report.push_str(&format!("  Peak Throughput:     {:>8.1} Mbps\n", eff.peak_throughput_mbps));
+        if let Some(busy) = eff.busy_efficiency_avg_percent {
+            report.push_str(&format!("  Busy Efficiency:     {:>8.1}%  (achieved vs. negotiated while under load)\n", busy));
+        }
+        for (label, avg) in &eff.by_signal {
+            report.push_str(&format!("    {:<18} {:>6.1}%\n", label, avg));
+        }
+        report.push_str(&format!("  Collapse Intervals:  {:>8}\n\n", eff.collapse_intervals.len()));
+    }
+
+    // WiFi vs Wired
+    if let Some(ref cmp) = wired {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                          WIFI VS WIRED                             \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format!("  Wired Interface:   {}\n", cmp.interface_name));
+        report.push_str(&format!("  Paired Samples:    {:>6}\n", cmp.samples));
+        let fmt_ms = |v: Option<f64>| v.map(|v| format!("{:.1} ms", v)).unwrap_or_else(|| "n/a".to_string());
+        report.push_str(&format!("  Avg Latency:       WiFi {:>10}   Wired {:>10}\n",
+            fmt_ms(cmp.wifi_latency_avg_ms), fmt_ms(cmp.wired_latency_avg_ms)));
+        report.push_str(&format!("  Avg Packet Loss:   WiFi {:>8.2}%   Wired {:>8.2}%\n",
+            cmp.wifi_loss_avg_percent, cmp.wired_loss_avg_percent));
+        report.push_str(&format!("  Degraded Samples:  WiFi {:>9}   Wired {:>9}   Both {}\n",
+            cmp.wifi_degraded, cmp.wired_degraded, cmp.both_degraded));
+        report.push_str(&format!("\n  Verdict: {}\n\n", cmp.verdict()));
+    }
+
+    // Main vs Guest network
+    if let Some(ref cmp) = guest {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                      MAIN VS GUEST NETWORK                         \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format!("  Guest SSID:        {}{}\n", cmp.ssid,
+            if cmp.switched { " (main adapter switched over)" } else { "" }));
+        report.push_str(&format!("  Guest Probes:      {:>6} ({} could not connect)\n", cmp.samples, cmp.failed));
+        let fmt_ms = |v: Option<f64>| v.map(|v| format!("{:.1} ms", v)).unwrap_or_else(|| "n/a".to_string());
+        let fmt_pct = |v: Option<f64>| v.map(|v| format!("{:.0}%", v)).unwrap_or_else(|| "n/a".to_string());
+        report.push_str(&format!("  Avg Signal:        Main {:>10}   Guest {:>10}\n",
+            fmt_pct(cmp.main_signal_avg_percent), fmt_pct(cmp.guest_signal_avg_percent)));
+        report.push_str(&format!("  Avg Latency:       Main {:>10}   Guest {:>10}\n",
+            fmt_ms(cmp.main_latency_avg_ms), fmt_ms(cmp.guest_latency_avg_ms)));
+        report.push_str(&format!("  Avg Packet Loss:   Main {:>8.2}%   Guest {:>8.2}%\n",
+            cmp.main_loss_avg_percent, cmp.guest_loss_avg_percent));
+        report.push_str(&format!("  Degraded Samples:  Main {:>9}   Guest {:>9}\n",
+            cmp.main_degraded, cmp.guest_degraded));
+        report.push_str(&format!("\n  Verdict: {}\n\n", cmp.verdict()));
+    }
+
+    // Periodic Patterns
+    if !patterns.is_empty() {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                         PERIODIC PATTERNS                          \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        for pattern in &patterns {
+            report.push_str(&format!("  {}\n", pattern.describe()));
+            report.push_str("  Likely culprits:\n");
+            for culprit in &pattern.culprits {
+                report.push_str(&format!("    - {}\n", culprit));
+            }
+            report.push('\n');
+        }
+    }
+
+    // Public IP
+    if !ip_changes.is_empty() {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                        PUBLIC IP CHANGES                           \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        for change in &ip_changes {
+            report.push_str(&format!("  {}  {}\n", change.timestamp.format("%Y-%m-%d %H:%M:%S"), change.description));
+            if let Some((started_at, secs)) = change.after_outage {
+                report.push_str(&format!("                       after the outage at {} ({})\n",
+                    started_at.format("%H:%M:%S"), format_secs(secs)));
+            }
+        }
+        report.push('\n');
+    }
+
+    // Band Performance
+    if !bands.is_empty() {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                         BAND PERFORMANCE                           \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        for band in &bands {
+            report.push_str(&format_band_performance(band));
+        }
+        report.push('\n');
+    }
+
+    // Adapter & Driver
+    if let Some(ref adapter) = adapter {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                         ADAPTER & DRIVER                           \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format!("  Adapter:           {}\n", adapter.driver_description));
+        report.push_str(&format!("  Driver Version:    {}\n", adapter.driver_version));
+        report.push_str(&format!("  Driver Date:       {}\n", adapter.driver_date.as_deref().unwrap_or("unknown")));
+        report.push_str(&format!("  Allow Power Off:   {}\n", match adapter.allow_turn_off_device {
+            Some(true) => "Enabled",
+            Some(false) => "Disabled",
+            None => "unknown",
+        }));
+        if let Some(mode) = adapter.power_saving_mode_ac {
+            report.push_str(&format!("  Power Saving (AC): {}\n", power_saving_mode_name(mode)));
+        }
+        if let Some(mode) = adapter.power_saving_mode_dc {
+            report.push_str(&format!("  Power Saving (DC): {}\n", power_saving_mode_name(mode)));
+        }
+        report.push('\n');
+    }
+
+    // Sessions side by side
+    if sessions.len() > 1 {
+        report.push_str("───────────────────────────────────────────────────────────────────\n");
+        report.push_str("                             SESSIONS                              \n");
+        report.push_str("───────────────────────────────────────────────────────────────────\n\n");
+        report.push_str(&format_sessions(store, &sessions)?);
+        report.push('\n');
+    }
+
     // Event Summary
     report.push_str("───────────────────────────────────────────────────────────────────\n");
     report.push_str("                          EVENT SUMMARY                             \n");
@@ -100,7 +349,7 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
     report.push_str("                         ISSUES DETECTED                            \n");
     report.push_str("───────────────────────────────────────────────────────────────────\n\n");
 
-    let issues = analyze_issues(&stats, &events, &event_counts);
+    let issues = gather_issues(&stats, &events, &event_counts, efficiency.as_ref(), wired.as_ref(), guest.as_ref(), &patterns, &ip_changes, adapter.as_ref());
     if issues.is_empty() {
         report.push_str("  No significant issues detected.\n\n");
     } else {
@@ -115,7 +364,10 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
     report.push_str("                        RECOMMENDATIONS                             \n");
     report.push_str("───────────────────────────────────────────────────────────────────\n\n");
 
-    let recommendations = generate_recommendations(&stats, &events, &event_counts);
+    let mut recommendations = generate_recommendations(&stats, &events, &event_counts, &snapshots, &thresholds);
+    if let Some(ref adapter) = adapter {
+        recommendations.splice(0..0, adapter_recommendations(adapter));
+    }
     if recommendations.is_empty() {
         report.push_str("  Your WiFi connection appears to be stable. No immediate actions needed.\n\n");
     } else {
@@ -153,6 +405,303 @@ pub fn generate_report(store: &MetricsStore) -> anyhow::Result<String> {
     Ok(report)
 }
 
+/// Compact terminal summary for quick checks (e.g. over SSH): uptime, health score,
+/// worst hour and top 3 issues. ANSI colors are used when `color` is set.
+pub fn generate_summary(store: &dyn MetricsStore, session: Option<&Session>, color: bool) -> anyhow::Result<String> {
+    let (start, end) = session.map(Session::range).unzip();
+    let (start, end) = (start.as_deref(), end.as_deref());
+    let thresholds = AlertThresholds::for_session(session);
+    let stats = store.get_statistics(start, end)?;
+    let events = store.get_events(start, end, None, None, None, 0)?;
+    let event_counts = store.get_event_counts_by_type(start, end)?;
+    let adapter = store.get_latest_adapter_info()?;
+    let mut snapshots = store.get_snapshots(start, end, None, 0)?;
+    snapshots.retain(|s| store.in_scope(s));
+    let efficiency = analyze_efficiency(&snapshots);
+    let wired = compare_wired(&snapshots, &thresholds);
+    let guest = compare_guest(&snapshots, &thresholds);
+    let patterns = periodicity::detect(&snapshots);
+    let incidents = store.get_incidents(start, end, None).unwrap_or_default();
+    let ip_changes = public_ip_changes(&events, &incidents);
+
+    let paint = |code: &str, text: String| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text };
+    let grade = |good: bool, fair: bool| if good { "32" } else if fair { "33" } else { "31" };
+
+    let health_score = calculate_health_score(&stats);
+    let issues = gather_issues(&stats, &events, &event_counts, efficiency.as_ref(), wired.as_ref(), guest.as_ref(), &patterns, &ip_changes, adapter.as_ref());
+
+    let mut out = String::new();
+    out.push_str(&format!("{} {} to {} ({} samples)\n",
+        paint("1", "WiFi stability".to_string()),
+        stats.start_time.format("%Y-%m-%d %H:%M"),
+        stats.end_time.format("%Y-%m-%d %H:%M UTC"),
+        stats.sample_count
+    ));
+    out.push_str(&format!("  Health:   {}\n", paint(
+        grade(health_score >= 75, health_score >= 60),
+        format!("{}/100 {}", health_score, health_rating(health_score)),
+    )));
+    out.push_str(&format!("  Uptime:   WiFi {}  Internet {}  ({} disconnects)\n",
+        paint(grade(stats.connection_uptime_percent >= 99.9, stats.connection_uptime_percent >= 99.0),
+            format!("{:.1}%", stats.connection_uptime_percent)),
+        paint(grade(stats.internet_uptime_percent >= 99.9, stats.internet_uptime_percent >= 99.0),
+            format!("{:.1}%", stats.internet_uptime_percent)),
+        stats.total_disconnections
+    ));
+    if let Some(mos) = stats.mos_avg {
+        out.push_str(&format!("  Calls:    MOS {} {}, {:.1}% of samples below {}\n",
+            paint(grade(mos >= 4.0, mos >= MOS_POOR_CALL), format!("{:.2}", mos)),
+            mos_rating(mos),
+            stats.poor_call_percent,
+            MOS_POOR_CALL
+        ));
+    }
+
+    if let Some(longest) = incidents.iter().max_by_key(|i| i.duration_secs) {
+        let open = incidents.iter().filter(|i| i.status == IncidentStatus::Open).count();
+        out.push_str(&format!("  Incidents: {} ({} open), longest {} ({})\n",
+            incidents.len(),
+            open,
+            paint("31", format_secs(longest.duration_secs)),
+            longest.root_cause.label()
+        ));
+    }
+
+    match worst_hour(&snapshots, &thresholds) {
+        Some(hour) => out.push_str(&format!("  Worst:    {} UTC - {}\n",
+            hour.hour.format("%Y-%m-%d %H:00"),
+            paint("31", format!("{:.0}% degraded samples, avg {}, loss {:.1}%",
+                hour.degraded_percent,
+                hour.latency_avg_ms.map(|l| format!("{:.0}ms", l)).unwrap_or_else(|| "n/a".to_string()),
+                hour.packet_loss_avg_percent)),
+        )),
+        None => out.push_str(&format!("  Worst:    {}\n", paint("32", "no degraded hours".to_string()))),
+    }
+
+    if issues.is_empty() {
+        out.push_str(&format!("  Issues:   {}\n", paint("32", "none".to_string())));
+    } else {
+        out.push_str(&format!("  Issues:   {} found, top {}:\n", issues.len(), issues.len().min(3)));
+        for issue in issues.iter().take(3) {
+            out.push_str(&format!("    {} {}\n", paint("33", "•".to_string()), issue));
+        }
+    }
+
+    Ok(out)
+}
+
+/// Incident totals by root cause, then the most recent incidents
+fn format_incidents(incidents: &[Incident]) -> String {
+    let mut out = String::new();
+    let total_secs: i64 = incidents.iter().map(|i| i.duration_secs).sum();
+    out.push_str(&format!("  Total Incidents:   {:>6}   ({} total)\n", incidents.len(), format_secs(total_secs)));
+
+    let mut by_cause: Vec<(IncidentCause, usize, i64)> = Vec::new();
+    for incident in incidents {
+        match by_cause.iter_mut().find(|(cause, _, _)| *cause == incident.root_cause) {
+            Some(entry) => {
+                entry.1 += 1;
+                entry.2 += incident.duration_secs;
+            }
+            None => by_cause.push((incident.root_cause, 1, incident.duration_secs)),
+        }
+    }
+    by_cause.sort_by_key(|(_, _, secs)| std::cmp::Reverse(*secs));
+    for (cause, count, secs) in &by_cause {
+        out.push_str(&format!("    {:<24} {:>4}  {:>10}\n", cause.label(), count, format_secs(*secs)));
+    }
+
+    out.push_str("\n  Recent:\n");
+    for incident in incidents.iter().take(10) {
+        let status = match incident.status {
+            IncidentStatus::Open => " [open]",
+            IncidentStatus::Resolved => "",
+        };
+        out.push_str(&format!("    {}  {:>10}  {:<24} {}{}\n",
+            incident.started_at.format("%Y-%m-%d %H:%M:%S"),
+            format_secs(incident.duration_secs),
+            incident.root_cause.label(),
+            incident.summary,
+            status
+        ));
+        for note in &incident.notes {
+            out.push_str(&format!("      note: {}\n", note.text));
+        }
+    }
+    out
+}
+
+/// Annotations interleaved with incidents and pauses, oldest first, so notes like
+/// "rebooted router" sit next to what happened around them
+fn format_timeline(annotations: &[Annotation], incidents: &[Incident], pauses: &[PauseWindow]) -> String {
+    let mut entries: Vec<(chrono::DateTime<Utc>, String)> = Vec::new();
+    for annotation in annotations {
+        entries.push((annotation.timestamp, format!("{:<10}  {}", "note", annotation.text)));
+    }
+    for incident in incidents {
+        entries.push((incident.started_at, format!("{:<10}  {} for {}: {}",
+            "incident",
+            incident.root_cause.label(),
+            format_secs(incident.duration_secs),
+            incident.summary
+        )));
+    }
+    for pause in pauses {
+        let length = pause.ended_at.unwrap_or_else(Utc::now) - pause.started_at;
+        let reason = pause.reason.as_deref().map(|r| format!(": {}", r)).unwrap_or_default();
+        entries.push((pause.started_at, format!("{:<10}  {}{}", "paused", format_secs(length.num_seconds()), reason)));
+    }
+    entries.sort_by_key(|(at, _)| *at);
+
+    let mut out = String::new();
+    let skipped = entries.len().saturating_sub(TIMELINE_ENTRIES);
+    if skipped > 0 {
+        out.push_str(&format!("  ({} earlier entries not shown)\n", skipped));
+    }
+    for (at, text) in entries.iter().skip(skipped) {
+        out.push_str(&format!("  {}  {}\n", at.format("%Y-%m-%d %H:%M:%S"), text));
+    }
+    out
+}
+
+/// Most recent entries the report's timeline lists
+const TIMELINE_ENTRIES: usize = 40;
+
+/// The most recent sessions with their uptime, latency, loss and health score
+fn format_sessions(store: &dyn MetricsStore, sessions: &[(Session, i64)]) -> anyhow::Result<String> {
+    let mut out = String::new();
+    out.push_str("  Session   Started            Length  Samples  WiFi Up  Inet Up   Latency   Loss  Drops  Score\n");
+    for (session, _) in sessions.iter().take(10) {
+        let (start, end) = session.range();
+        let stats = store.get_statistics(Some(&start), Some(&end))?;
+        let length = session.ended_at.unwrap_or_else(Utc::now) - session.started_at;
+        out.push_str(&format!("  {:<8}  {}  {:>8}  {:>7}  {:>6.1}%  {:>6.1}%  {:>8}  {:>4.1}%  {:>5}  {:>5}{}\n",
+            &session.id[..8.min(session.id.len())],
+            session.started_at.format("%Y-%m-%d %H:%M"),
+            format_secs(length.num_seconds()),
+            stats.sample_count,
+            stats.connection_uptime_percent,
+            stats.internet_uptime_percent,
+            stats.latency_avg_ms.map(|l| format!("{:.0}ms", l)).unwrap_or_else(|| "n/a".to_string()),
+            stats.packet_loss_avg_percent,
+            stats.total_disconnections,
+            calculate_health_score(&stats),
+            if session.ended_at.is_none() { "  [running]" } else { "" }
+        ));
+    }
+    if sessions.len() > 10 {
+        out.push_str(&format!("  ... and {} older sessions\n", sessions.len() - 10));
+    }
+    Ok(out)
+}
+
+fn format_secs(secs: i64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// The hour with the highest share of degraded samples
+struct WorstHour {
+    hour: chrono::DateTime<Utc>,
+    degraded_percent: f64,
+    latency_avg_ms: Option<f64>,
+    packet_loss_avg_percent: f64,
+}
+
+fn worst_hour(snapshots: &[WifiSnapshot], thresholds: &AlertThresholds) -> Option<WorstHour> {
+    use chrono::{DurationRound, TimeDelta};
+
+    // hour -> (samples, degraded, latencies, loss sum)
+    let mut hours: std::collections::BTreeMap<chrono::DateTime<Utc>, (usize, usize, Vec<f64>, f64)> = Default::default();
+    for snapshot in snapshots {
+        let Ok(hour) = snapshot.timestamp.duration_trunc(TimeDelta::hours(1)) else { continue };
+        let entry = hours.entry(hour).or_default();
+        entry.0 += 1;
+        let degraded = snapshot.wifi_info.is_none()
+            || !snapshot.connectivity.internet_reachable
+            || thresholds.is_degraded(snapshot.latency.average_latency_ms, snapshot.latency.packet_loss_percent);
+        entry.1 += degraded as usize;
+        entry.2.extend(snapshot.latency.average_latency_ms);
+        entry.3 += snapshot.latency.packet_loss_percent;
+    }
+
+    hours.into_iter()
+        .filter(|(_, (_, degraded, _, _))| *degraded > 0)
+        .map(|(hour, (samples, degraded, latencies, loss))| WorstHour {
+            hour,
+            degraded_percent: degraded as f64 / samples as f64 * 100.0,
+            latency_avg_ms: (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+            packet_loss_avg_percent: loss / samples as f64,
+        })
+        .max_by(|a, b| a.degraded_percent.partial_cmp(&b.degraded_percent).unwrap()
+            .then(a.latency_avg_ms.unwrap_or(0.0).partial_cmp(&b.latency_avg_ms.unwrap_or(0.0)).unwrap()))
+}
+
+/// Everything the report lists under ISSUES DETECTED, most important first
+#[allow(clippy::too_many_arguments)] // one input per analysis, shared by report and summary
+fn gather_issues(
+    stats: &PeriodStatistics,
+    events: &[NetworkEvent],
+    event_counts: &[(String, i64)],
+    efficiency: Option<&EfficiencySummary>,
+    wired: Option<&WiredComparison>,
+    guest: Option<&GuestComparison>,
+    patterns: &[PeriodicPattern],
+    ip_changes: &[PublicIpChange],
+    adapter: Option<&AdapterInfo>,
+) -> Vec<String> {
+    let mut issues = analyze_issues(stats, events, event_counts);
+    if let Some(eff) = efficiency {
+        if !eff.collapse_intervals.is_empty() {
+            let worst = &eff.collapse_intervals[0];
+            issues.push(format!(
+                "Throughput collapsed {} time(s) while the negotiated rate was fine (e.g. {} at {:.0} Mbps link, {:.2} Mbps achieved, {:.0}ms latency) - indicates congestion or airtime contention",
+                eff.collapse_intervals.len(),
+                worst.timestamp.format("%Y-%m-%d %H:%M"),
+                worst.negotiated_mbps,
+                worst.achieved_mbps,
+                worst.latency_ms
+            ));
+        }
+    }
+    if let Some(cmp) = wired {
+        if cmp.wifi_degraded > 0 || cmp.wired_degraded > 0 {
+            issues.push(cmp.verdict());
+        }
+    }
+    if let Some(cmp) = guest {
+        if cmp.differs() {
+            issues.push(cmp.verdict());
+        }
+    }
+    for pattern in patterns {
+        issues.push(format!(
+            "{} - likely {}",
+            pattern.describe(),
+            pattern.culprits.first().map(String::as_str).unwrap_or("a scheduled job")
+        ));
+    }
+    let after_outage: Vec<&PublicIpChange> = ip_changes.iter().filter(|c| c.after_outage.is_some()).collect();
+    if let Some(first) = after_outage.first() {
+        issues.push(format!(
+            "Public IP changed right after {} outage(s) (e.g. {}) - the ISP reassigned the address, so the drops come from the ISP side (forced reconnect or lease renewal), not the WiFi",
+            after_outage.len(),
+            first.timestamp.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    let failovers = ip_changes.iter().filter(|c| c.isp_changed).count();
+    if failovers > 0 {
+        issues.push(format!("Traffic moved to a different ISP (ASN) {} time(s) - a failover or a VPN changed the route out", failovers));
+    }
+    if let Some(adapter) = adapter {
+        issues.extend(analyze_adapter(adapter));
+    }
+    issues
+}
+
 fn calculate_health_score(stats: &PeriodStatistics) -> u32 {
     let mut score = 100u32;
 
@@ -254,6 +803,24 @@ fn jitter_rating(ms: f64) -> &'static str {
     }
 }
 
+/// Share of samples with poor call quality worth listing as an issue
+const POOR_CALL_ISSUE_PERCENT: f64 = 5.0;
+
+/// Will a voice/video call be OK? Bands follow ITU-T G.107 user satisfaction
+fn mos_rating(mos: f64) -> &'static str {
+    if mos >= 4.3 {
+        "(Excellent - calls sound like a landline)"
+    } else if mos >= 4.0 {
+        "(Good - calls will be fine)"
+    } else if mos >= MOS_POOR_CALL {
+        "(Fair - occasional glitches)"
+    } else if mos >= 3.1 {
+        "(Poor - many calls will suffer)"
+    } else {
+        "(Bad - calls will be hard to hold)"
+    }
+}
+
 fn analyze_issues(
     stats: &PeriodStatistics,
     _events: &[NetworkEvent],
@@ -339,6 +906,25 @@ fn analyze_issues(
         ));
     }
 
+    // Call quality issues
+    if stats.poor_call_percent >= POOR_CALL_ISSUE_PERCENT {
+        issues.push(format!(
+            "Estimated call quality was poor (MOS below {}) in {:.1}% of samples - expect choppy or dropped voice/video calls",
+            MOS_POOR_CALL, stats.poor_call_percent
+        ));
+    }
+
+    let adapter_resets = event_counts.iter()
+        .find(|(t, _)| t == "AdapterReset")
+        .map(|(_, c)| *c)
+        .unwrap_or(0);
+    if adapter_resets > 0 {
+        issues.push(format!(
+            "WiFi adapter reset {} time(s) - the driver or Windows power management re-initialized the device",
+            adapter_resets
+        ));
+    }
+
     // Event-based issues
     for (event_type, count) in event_counts {
         if *count > 5 {
@@ -367,10 +953,401 @@ fn analyze_issues(
     issues
 }
 
+/// Achieved vs. negotiated throughput over the monitoring period
+struct EfficiencySummary {
+    negotiated_avg_mbps: f64,
+    peak_throughput_mbps: f64,
+    busy_efficiency_avg_percent: Option<f64>,
+    by_signal: Vec<(&'static str, f64)>,
+    collapse_intervals: Vec<CollapseInterval>,
+}
+
+/// An interval where the link looked healthy but little traffic got through
+struct CollapseInterval {
+    timestamp: chrono::DateTime<Utc>,
+    negotiated_mbps: f64,
+    achieved_mbps: f64,
+    latency_ms: f64,
+}
+
+/// Intervals moving more than this are considered "under load"
+const BUSY_THRESHOLD_MBPS: f64 = 1.0;
+
+fn analyze_efficiency(snapshots: &[WifiSnapshot]) -> Option<EfficiencySummary> {
+    let mut ordered: Vec<&WifiSnapshot> = snapshots.iter().collect();
+    ordered.sort_by_key(|s| s.timestamp);
+
+    // (timestamp, negotiated, achieved, signal, latency) per interval
+    let mut intervals = Vec::new();
+    for pair in ordered.windows(2) {
+        let (prev, cur) = (pair[0], pair[1]);
+        let Some(ref wifi) = cur.wifi_info else { continue };
+        let secs = (cur.timestamp - prev.timestamp).num_milliseconds() as f64 / 1000.0;
+        if secs <= 0.0 || wifi.link_speed_mbps == 0 {
+            continue;
+        }
+
+        let bytes = |s: &WifiSnapshot| {
+            match (s.system_info.adapter_bytes_received, s.system_info.adapter_bytes_sent) {
+                (Some(rx), Some(tx)) => rx + tx,
+                _ => s.system_info.bytes_received + s.system_info.bytes_sent,
+            }
+        };
+        let (before, after) = (bytes(prev), bytes(cur));
+        if after < before {
+            continue; // counter reset
+        }
+
+        let achieved = (after - before) as f64 * 8.0 / secs / 1_000_000.0;
+        let negotiated = wifi.rx_rate_mbps.max(wifi.tx_rate_mbps).unwrap_or(wifi.link_speed_mbps) as f64;
+        intervals.push((cur.timestamp, negotiated, achieved, wifi.signal_strength_dbm, cur.latency.average_latency_ms));
+    }
+
+    if intervals.is_empty() {
+        return None;
+    }
+
+    let negotiated_avg_mbps = intervals.iter().map(|i| i.1).sum::<f64>() / intervals.len() as f64;
+    let peak_throughput_mbps = intervals.iter().map(|i| i.2).fold(0.0, f64::max);
+
+    let busy: Vec<_> = intervals.iter().filter(|i| i.2 >= BUSY_THRESHOLD_MBPS).collect();
+    let busy_efficiency_avg_percent = (!busy.is_empty())
+        .then(|| busy.iter().map(|i| i.2 / i.1 * 100.0).sum::<f64>() / busy.len() as f64);
+
+    let buckets: [(&'static str, i32, i32); 3] = [
+        ("Signal > -60 dBm:", -60, 0),
+        ("Signal -60..-70:", -70, -60),
+        ("Signal < -70 dBm:", -200, -70),
+    ];
+    let by_signal = buckets.iter().filter_map(|(label, low, high)| {
+        let values: Vec<f64> = busy.iter()
+            .filter(|i| i.3 > *low && i.3 <= *high)
+            .map(|i| i.2 / i.1 * 100.0)
+            .collect();
+        (!values.is_empty()).then(|| (*label, values.iter().sum::<f64>() / values.len() as f64))
+    }).collect();
+
+    // Healthy negotiated rate, high latency, yet almost nothing moving: others are eating the airtime
+    let mut collapse_intervals: Vec<CollapseInterval> = intervals.iter()
+        .filter_map(|&(timestamp, negotiated, achieved, _, latency)| {
+            let latency = latency?;
+            (negotiated >= negotiated_avg_mbps * 0.75 && latency >= 100.0 && achieved < negotiated * 0.01)
+                .then_some(CollapseInterval { timestamp, negotiated_mbps: negotiated, achieved_mbps: achieved, latency_ms: latency })
+        })
+        .collect();
+    collapse_intervals.sort_by(|a, b| b.latency_ms.partial_cmp(&a.latency_ms).unwrap());
+
+    Some(EfficiencySummary {
+        negotiated_avg_mbps,
+        peak_throughput_mbps,
+        busy_efficiency_avg_percent,
+        by_signal,
+        collapse_intervals,
+    })
+}
+
+/// WiFi measured side by side with a wired baseline
+struct WiredComparison {
+    interface_name: String,
+    samples: usize,
+    wifi_latency_avg_ms: Option<f64>,
+    wired_latency_avg_ms: Option<f64>,
+    wifi_loss_avg_percent: f64,
+    wired_loss_avg_percent: f64,
+    wifi_degraded: usize,
+    wired_degraded: usize,
+    both_degraded: usize,
+}
+
+impl WiredComparison {
+    /// Is the trouble WiFi-specific, or does it hit the whole LAN/ISP link?
+    fn verdict(&self) -> String {
+        if self.wifi_degraded == 0 && self.wired_degraded == 0 {
+            return "Neither link showed degraded samples".to_string();
+        }
+        if self.wifi_degraded == 0 {
+            return format!(
+                "Wired link degraded in {} sample(s) while WiFi was fine - check the Ethernet cable/port",
+                self.wired_degraded
+            );
+        }
+        let shared = self.both_degraded as f64 / self.wifi_degraded as f64 * 100.0;
+        if shared >= 50.0 {
+            format!(
+                "Problems affect the whole LAN/ISP link: the wired baseline was also degraded in {:.0}% of the {} degraded WiFi samples",
+                shared, self.wifi_degraded
+            )
+        } else {
+            format!(
+                "Problems are WiFi-specific: the wired baseline was healthy in {:.0}% of the {} degraded WiFi samples",
+                100.0 - shared, self.wifi_degraded
+            )
+        }
+    }
+}
+
+/// The guest SSID measured at the same moments as the main network
+struct GuestComparison {
+    ssid: String,
+    switched: bool,
+    samples: usize,
+    failed: usize,
+    main_signal_avg_percent: Option<f64>,
+    guest_signal_avg_percent: Option<f64>,
+    main_latency_avg_ms: Option<f64>,
+    guest_latency_avg_ms: Option<f64>,
+    main_loss_avg_percent: f64,
+    guest_loss_avg_percent: f64,
+    main_degraded: usize,
+    guest_degraded: usize,
+}
+
+/// Latency gap (and relative factor) beyond which one network counts as slower
+const GUEST_LATENCY_GAP_MS: f64 = 20.0;
+const GUEST_LATENCY_FACTOR: f64 = 1.5;
+/// Loss gap beyond which one network counts as lossier
+const GUEST_LOSS_GAP_PERCENT: f64 = 2.0;
+
+impl GuestComparison {
+    fn slower(a: Option<f64>, b: Option<f64>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a - b >= GUEST_LATENCY_GAP_MS && a >= b * GUEST_LATENCY_FACTOR,
+            _ => false,
+        }
+    }
+
+    fn guest_worse(&self) -> bool {
+        Self::slower(self.guest_latency_avg_ms, self.main_latency_avg_ms)
+            || self.guest_loss_avg_percent - self.main_loss_avg_percent >= GUEST_LOSS_GAP_PERCENT
+    }
+
+    fn main_worse(&self) -> bool {
+        Self::slower(self.main_latency_avg_ms, self.guest_latency_avg_ms)
+            || self.main_loss_avg_percent - self.guest_loss_avg_percent >= GUEST_LOSS_GAP_PERCENT
+    }
+
+    fn differs(&self) -> bool {
+        self.guest_worse() || self.main_worse()
+    }
+
+    fn verdict(&self) -> String {
+        let latency = |v: Option<f64>| v.map(|v| format!("{:.0}ms", v)).unwrap_or_else(|| "n/a".to_string());
+        let figures = format!(
+            "main {} / {:.1}% loss, guest {} / {:.1}% loss",
+            latency(self.main_latency_avg_ms), self.main_loss_avg_percent,
+            latency(self.guest_latency_avg_ms), self.guest_loss_avg_percent
+        );
+        if self.guest_worse() && !self.main_worse() {
+            format!(
+                "Guest network {} performs worse than the main network ({}) - typical of router guest isolation or rate limits",
+                self.ssid, figures
+            )
+        } else if self.main_worse() && !self.guest_worse() {
+            format!(
+                "Main network performs worse than guest network {} ({}) - the main network's settings (band steering, QoS, security mode) are suspect",
+                self.ssid, figures
+            )
+        } else if self.differs() {
+            format!("Main and guest network {} differ in mixed ways ({})", self.ssid, figures)
+        } else {
+            format!("Main and guest network {} perform similarly ({})", self.ssid, figures)
+        }
+    }
+}
+
+/// Recovery lookups run on the first snapshot back online, so allow a little slack
+const IP_CHANGE_AFTER_OUTAGE_SECS: i64 = 300;
+
+/// A public IP change, tied to the outage it followed if any
+struct PublicIpChange {
+    timestamp: chrono::DateTime<Utc>,
+    description: String,
+    isp_changed: bool,
+    /// Start and length of the incident the change came out of
+    after_outage: Option<(chrono::DateTime<Utc>, i64)>,
+}
+
+fn public_ip_changes(events: &[NetworkEvent], incidents: &[Incident]) -> Vec<PublicIpChange> {
+    let mut changes: Vec<PublicIpChange> = events.iter()
+        .filter(|e| e.event_type == EventType::PublicIpChange)
+        .map(|e| PublicIpChange {
+            timestamp: e.timestamp,
+            description: e.description.clone(),
+            isp_changed: e.details["isp_changed"].as_bool().unwrap_or(false),
+            after_outage: incidents.iter()
+                .find(|i| i.started_at <= e.timestamp
+                    && i.ended_at.is_none_or(|end| (e.timestamp - end).num_seconds() <= IP_CHANGE_AFTER_OUTAGE_SECS))
+                .map(|i| (i.started_at, i.duration_secs)),
+        })
+        .collect();
+    changes.sort_by_key(|c| c.timestamp);
+    changes
+}
+
+fn compare_guest(snapshots: &[WifiSnapshot], thresholds: &AlertThresholds) -> Option<GuestComparison> {
+    let probed: Vec<(&WifiSnapshot, &GuestMetrics)> = snapshots.iter()
+        .filter_map(|s| s.guest.as_ref().map(|g| (s, g)))
+        .collect();
+    if probed.is_empty() {
+        return None;
+    }
+    let paired: Vec<(&WifiSnapshot, &GuestMetrics)> = probed.iter()
+        .filter(|(s, g)| g.connected && s.wifi_info.is_some())
+        .cloned()
+        .collect();
+
+    let avg = |values: Vec<f64>| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    Some(GuestComparison {
+        ssid: probed[0].1.ssid.clone(),
+        switched: probed.iter().any(|(_, g)| g.switched),
+        samples: paired.len(),
+        failed: probed.len() - paired.len(),
+        main_signal_avg_percent: avg(paired.iter().filter_map(|(s, _)| s.wifi_info.as_ref()).map(|w| w.signal_quality_percent as f64).collect()),
+        guest_signal_avg_percent: avg(paired.iter().filter_map(|(_, g)| g.signal_quality_percent).map(|q| q as f64).collect()),
+        main_latency_avg_ms: avg(paired.iter().filter_map(|(s, _)| s.latency.average_latency_ms).collect()),
+        guest_latency_avg_ms: avg(paired.iter().filter_map(|(_, g)| g.average_latency_ms).collect()),
+        main_loss_avg_percent: avg(paired.iter().map(|(s, _)| s.latency.packet_loss_percent).collect()).unwrap_or(0.0),
+        guest_loss_avg_percent: avg(paired.iter().map(|(_, g)| g.packet_loss_percent).collect()).unwrap_or(0.0),
+        main_degraded: paired.iter().filter(|(s, _)| thresholds.is_degraded(s.latency.average_latency_ms, s.latency.packet_loss_percent)).count(),
+        guest_degraded: paired.iter().filter(|(_, g)| thresholds.is_degraded(g.average_latency_ms, g.packet_loss_percent)).count(),
+    })
+}
+
+fn format_band_performance(band: &BandPerformance) -> String {
+    format!(
+        "  {:<8} {:>6} samples  {:>6.1} dBm  {:>10}  {:>6.2}% loss  {:>5.1}% degraded\n",
+        band.band.label(),
+        band.samples,
+        band.signal_avg_dbm,
+        band.latency_avg_ms.map(|l| format!("{:.1} ms", l)).unwrap_or_else(|| "n/a".to_string()),
+        band.packet_loss_avg_percent,
+        band.degraded_percent
+    )
+}
+
+/// Failed internet checks counted by the HTTP check's outcome class, most common first
+fn internet_failure_causes(snapshots: &[WifiSnapshot]) -> Vec<(HttpOutcome, usize)> {
+    let mut causes: Vec<(HttpOutcome, usize)> = HttpOutcome::ALL.iter()
+        .map(|outcome| {
+            let count = snapshots.iter()
+                .filter(|s| !s.connectivity.internet_reachable)
+                .filter(|s| s.connectivity.http_outcome == Some(*outcome))
+                .count();
+            (*outcome, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    causes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    causes
+}
+
+fn compare_wired(snapshots: &[WifiSnapshot], thresholds: &AlertThresholds) -> Option<WiredComparison> {
+    let paired: Vec<(&WifiSnapshot, &WiredMetrics)> = snapshots.iter()
+        .filter_map(|s| s.wired.as_ref().filter(|w| w.link_up).map(|w| (s, w)))
+        .collect();
+    if paired.is_empty() {
+        return None;
+    }
+
+    let avg = |values: Vec<f64>| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    let mut cmp = WiredComparison {
+        interface_name: paired[0].1.interface_name.clone(),
+        samples: paired.len(),
+        wifi_latency_avg_ms: avg(paired.iter().filter_map(|(s, _)| s.latency.average_latency_ms).collect()),
+        wired_latency_avg_ms: avg(paired.iter().filter_map(|(_, w)| w.average_latency_ms).collect()),
+        wifi_loss_avg_percent: avg(paired.iter().map(|(s, _)| s.latency.packet_loss_percent).collect()).unwrap_or(0.0),
+        wired_loss_avg_percent: avg(paired.iter().map(|(_, w)| w.packet_loss_percent).collect()).unwrap_or(0.0),
+        wifi_degraded: 0,
+        wired_degraded: 0,
+        both_degraded: 0,
+    };
+
+    for (snapshot, wired) in &paired {
+        let wifi_bad = snapshot.wifi_info.is_none()
+            || thresholds.is_degraded(snapshot.latency.average_latency_ms, snapshot.latency.packet_loss_percent);
+        let wired_bad = thresholds.is_degraded(wired.average_latency_ms, wired.packet_loss_percent);
+        cmp.wifi_degraded += wifi_bad as usize;
+        cmp.wired_degraded += wired_bad as usize;
+        cmp.both_degraded += (wifi_bad && wired_bad) as usize;
+    }
+
+    Some(cmp)
+}
+
+/// Drivers older than this are flagged as a likely cause of drops
+const DRIVER_MAX_AGE_DAYS: i64 = 730;
+
+fn driver_age_days(adapter: &AdapterInfo) -> Option<i64> {
+    adapter.driver_date().map(|date| (Utc::now().date_naive() - date).num_days())
+}
+
+fn aggressive_power_saving(adapter: &AdapterInfo) -> bool {
+    adapter.power_saving_mode_ac.map(|m| m >= 2).unwrap_or(false)
+        || adapter.power_saving_mode_dc.map(|m| m >= 3).unwrap_or(false)
+}
+
+fn analyze_adapter(adapter: &AdapterInfo) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if let Some(age) = driver_age_days(adapter) {
+        if age > DRIVER_MAX_AGE_DAYS {
+            issues.push(format!(
+                "WiFi driver {} is {} years old ({}) - outdated drivers are a common cause of drops",
+                adapter.driver_version,
+                age / 365,
+                adapter.driver_date.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+
+    if adapter.allow_turn_off_device == Some(true) {
+        issues.push(
+            "Windows is allowed to turn off the WiFi adapter to save power - this can cause periodic disconnects".to_string()
+        );
+    }
+
+    if aggressive_power_saving(adapter) {
+        issues.push(format!(
+            "Wireless adapter power saving is set aggressively (AC: {}, DC: {})",
+            adapter.power_saving_mode_ac.map(power_saving_mode_name).unwrap_or("unknown"),
+            adapter.power_saving_mode_dc.map(power_saving_mode_name).unwrap_or("unknown")
+        ));
+    }
+
+    issues
+}
+
+fn adapter_recommendations(adapter: &AdapterInfo) -> Vec<String> {
+    let mut recommendations = Vec::new();
+
+    if driver_age_days(adapter).map(|age| age > DRIVER_MAX_AGE_DAYS).unwrap_or(false) {
+        recommendations.push(format!(
+            "Update the {} driver from the vendor's website ({}) rather than relying on Windows Update",
+            adapter.driver_description, adapter.vendor
+        ));
+    }
+
+    if adapter.allow_turn_off_device == Some(true) {
+        recommendations.push(
+            "In Device Manager > Network adapters > Power Management, uncheck \"Allow the computer to turn off this device to save power\"".to_string()
+        );
+    }
+
+    if aggressive_power_saving(adapter) {
+        recommendations.push(
+            "Set Power Options > Wireless Adapter Settings > Power Saving Mode to \"Maximum Performance\"".to_string()
+        );
+    }
+
+    recommendations
+}
+
 fn generate_recommendations(
     stats: &PeriodStatistics,
-    _events: &[NetworkEvent],
+    events: &[NetworkEvent],
     event_counts: &[(String, i64)],
+    snapshots: &[WifiSnapshot],
+    thresholds: &AlertThresholds,
 ) -> Vec<String> {
     let mut recommendations = Vec::new();
 
@@ -389,35 +1366,8 @@ fn generate_recommendations(
         }
     }
 
-    // Band-related recommendations
-    let band_switches = event_counts.iter()
-        .find(|(t, _)| t == "BandSwitch")
-        .map(|(_, c)| *c)
-        .unwrap_or(0);
-
-    if band_switches > 3 {
-        recommendations.push(
-            "Consider disabling band steering on your router and manually selecting 5GHz".to_string()
-        );
-        recommendations.push(
-            "If 5GHz is unstable, try using 2.4GHz for better range at lower speeds".to_string()
-        );
-    }
-
-    // Channel-related recommendations
-    let channel_changes = event_counts.iter()
-        .find(|(t, _)| t == "ChannelChange")
-        .map(|(_, c)| *c)
-        .unwrap_or(0);
-
-    if channel_changes > 5 {
-        recommendations.push(
-            "Use a WiFi analyzer app to find the least congested channel".to_string()
-        );
-        recommendations.push(
-            "Manually set your router to a specific channel instead of auto".to_string()
-        );
-    }
+    // Band, channel and width recommendations from neighbor scans and band history
+    recommendations.extend(advisor::channel_recommendations(snapshots, events, thresholds));
 
     // BSSID-related recommendations
     let bssid_changes = event_counts.iter()
@@ -466,9 +1416,6 @@ fn generate_recommendations(
         recommendations.push(
             "Packet loss can be caused by interference - check for nearby electronics (microwaves, cordless phones)".to_string()
         );
-        recommendations.push(
-            "Try changing your WiFi channel to reduce interference".to_string()
-        );
         recommendations.push(
             "Check your router and modem for overheating issues".to_string()
         );
@@ -0,0 +1,30 @@
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Browsers may reuse an asset this long without asking again; the versions are pinned in build.rs
+const ASSET_MAX_AGE_SECS: u64 = 7 * 24 * 3600;
+
+/// A third-party script the dashboards load, embedded by build.rs
+struct Asset {
+    name: &'static str,
+    bytes: &'static [u8],
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets/table.rs"));
+
+/// Serve an embedded asset at `/assets/<name>`
+pub async fn handler(Path(name): Path<String>) -> Response {
+    match ASSETS.iter().find(|asset| asset.name == name) {
+        Some(asset) => (
+            [
+                (header::CONTENT_TYPE, "application/javascript; charset=utf-8".to_string()),
+                (header::CACHE_CONTROL, format!("public, max-age={}", ASSET_MAX_AGE_SECS)),
+            ],
+            asset.bytes,
+        ).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
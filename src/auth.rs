@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use base64::Engine;
+
+/// Cookie the dashboard keeps the token in after opening it with `?token=`
+const TOKEN_COOKIE: &str = "wifi_tracker_token";
+
+/// Credentials the web API and dashboard require; open to anyone when neither is set
+#[derive(Clone, Default)]
+pub struct WebAuth {
+    token: Option<String>,
+    basic: Option<(String, String)>,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+impl WebAuth {
+    /// `basic` is `user:password`
+    pub fn new(token: Option<String>, basic: Option<String>) -> anyhow::Result<Self> {
+        let token = token.filter(|t| !t.is_empty());
+        let basic = basic
+            .map(|b| match b.split_once(':') {
+                Some((user, password)) if !user.is_empty() && !password.is_empty() => Ok((user.to_string(), password.to_string())),
+                _ => Err(anyhow::anyhow!("--basic-auth must be user:password")),
+            })
+            .transpose()?;
+        Ok(Self { token, basic })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || self.basic.is_some()
+    }
+
+    /// The dashboard URL to open in the app window, which can't send a header itself
+    pub fn dashboard_url(&self, url: &str) -> String {
+        match self.token {
+            Some(ref token) => format!("{}{}token={}", url, if url.contains('?') { '&' } else { '?' }, percent_encode(token)),
+            None => url.to_string(),
+        }
+    }
+
+    /// Add the credentials to a request to a running monitor's API
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match (&self.token, &self.basic) {
+            (Some(token), _) => request.bearer_auth(token),
+            (None, Some((user, password))) => request.basic_auth(user, Some(password)),
+            (None, None) => request,
+        }
+    }
+
+    /// Whether a request with these headers may proceed, for services outside the web
+    /// router such as the gRPC API
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub fn allows_headers(&self, headers: &HeaderMap) -> bool {
+        !self.is_enabled() || self.allows(headers, None)
+    }
+
+    fn allows(&self, headers: &HeaderMap, query_token: Option<&str>) -> bool {
+        if let Some(ref token) = self.token {
+            let cookie = headers
+                .get_all(header::COOKIE)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .filter_map(|c| c.trim().split_once('='))
+                .find(|(name, _)| *name == TOKEN_COOKIE)
+                .map(|(_, value)| value);
+            let bearer = authorization(headers, "Bearer ");
+            if [bearer.as_deref(), query_token].into_iter().flatten().any(|t| same(t.as_bytes(), token.as_bytes()))
+                || cookie.is_some_and(|c| same(c.as_bytes(), percent_encode(token).as_bytes()))
+            {
+                return true;
+            }
+        }
+        if let Some((ref user, ref password)) = self.basic {
+            let decoded = authorization(headers, "Basic ")
+                .and_then(|b| base64::engine::general_purpose::STANDARD.decode(b.trim()).ok());
+            let expected = format!("{}:{}", user, password);
+            if decoded.is_some_and(|d| same(&d, expected.as_bytes())) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Middleware rejecting requests without valid credentials, when any are configured. A
+/// token given as `?token=` is kept in a cookie, so the dashboard's own requests and its
+/// live updates carry it from then on.
+pub async fn require(State(auth): State<WebAuth>, request: Request, next: Next) -> Response {
+    if !auth.is_enabled() {
+        return next.run(request).await;
+    }
+    let query_token = Query::<TokenQuery>::try_from_uri(request.uri()).ok().and_then(|q| q.0.token);
+    if !auth.allows(request.headers(), query_token.as_deref()) {
+        let challenge = if auth.basic.is_some() { "Basic realm=\"wifi-stability-tracker\"" } else { "Bearer" };
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, challenge)],
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Authentication required"
+            })),
+        ).into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if let (Some(token), Some(given)) = (&auth.token, query_token) {
+        if !same(given.as_bytes(), token.as_bytes()) {
+            return response;
+        }
+        let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", TOKEN_COOKIE, percent_encode(token));
+        if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+    }
+    response
+}
+
+/// The value of an `Authorization` header using `scheme`
+fn authorization(headers: &HeaderMap, scheme: &str) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let prefix = value.get(..scheme.len())?;
+    prefix.eq_ignore_ascii_case(scheme).then(|| value[scheme.len()..].to_string())
+}
+
+/// Compare without returning early, so the time taken doesn't reveal how much matched
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+// Without the `pcap` feature only the error path in `new` is reachable
+#![cfg_attr(not(feature = "pcap"), allow(dead_code))]
+
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+/// Upper bound on buffered packet data, whatever the window length
+const MAX_BUFFER_BYTES: usize = 64 * 1024 * 1024;
+/// Bytes kept per packet; headers are what matter for outage forensics
+const SNAPLEN: usize = 256;
+
+/// Keeps the last few seconds of the WiFi adapter's traffic in memory and writes
+/// it to a pcap file when an outage is detected. Needs the `pcap` feature.
+#[derive(Clone)]
+pub struct RollingCapture {
+    window: Duration,
+    output_dir: PathBuf,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+#[derive(Default)]
+struct CaptureState {
+    started: bool,
+    packets: VecDeque<(DateTime<Utc>, u32, Vec<u8>)>,
+    bytes: usize,
+}
+
+impl CaptureState {
+    fn push(&mut self, at: DateTime<Utc>, original_len: u32, data: Vec<u8>, window: Duration) {
+        self.bytes += data.len();
+        self.packets.push_back((at, original_len, data));
+
+        let cutoff = at - chrono::Duration::from_std(window).unwrap_or_default();
+        while let Some((ts, _, front)) = self.packets.front() {
+            if *ts >= cutoff && self.bytes <= MAX_BUFFER_BYTES {
+                break;
+            }
+            self.bytes -= front.len();
+            self.packets.pop_front();
+        }
+    }
+}
+
+impl RollingCapture {
+    pub fn new(window_secs: u64, output_dir: PathBuf) -> anyhow::Result<Self> {
+        if !cfg!(feature = "pcap") {
+            anyhow::bail!("Packet capture needs a build with the `pcap` feature (cargo build --features pcap)");
+        }
+        std::fs::create_dir_all(&output_dir)?;
+        Ok(Self {
+            window: Duration::from_secs(window_secs),
+            output_dir,
+            state: Arc::new(Mutex::new(CaptureState::default())),
+        })
+    }
+
+    /// Start capturing on the adapter with this MAC address, once
+    pub fn ensure_started(&self, adapter_mac: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.started || adapter_mac.is_empty() {
+            return;
+        }
+        state.started = true;
+        drop(state);
+
+        let capture = self.clone();
+        let mac = adapter_mac.to_lowercase();
+        std::thread::spawn(move || {
+            if let Err(e) = capture.run(&mac) {
+                tracing::error!("Packet capture stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(feature = "pcap")]
+    fn run(&self, mac: &str) -> anyhow::Result<()> {
+        use pnet_datalink::Channel;
+
+        let interface = pnet_datalink::interfaces()
+            .into_iter()
+            .find(|i| i.mac.is_some_and(|m| m.to_string() == mac))
+            .ok_or_else(|| anyhow::anyhow!("No capture interface with MAC {}", mac))?;
+        let mut rx = match pnet_datalink::channel(&interface, Default::default())? {
+            Channel::Ethernet(_, rx) => rx,
+            _ => anyhow::bail!("Unsupported capture channel type"),
+        };
+        info!("Capturing the last {}s of traffic on {}", self.window.as_secs(), interface.name);
+
+        loop {
+            let packet = rx.next()?;
+            let data = packet[..packet.len().min(SNAPLEN)].to_vec();
+            self.state.lock().unwrap().push(Utc::now(), packet.len() as u32, data, self.window);
+        }
+    }
+
+    #[cfg(not(feature = "pcap"))]
+    fn run(&self, _mac: &str) -> anyhow::Result<()> {
+        anyhow::bail!("built without the `pcap` feature")
+    }
+
+    /// Write the buffered window to `<output_dir>/<timestamp>-<label>.pcap`
+    pub fn dump(&self, label: &str) -> anyhow::Result<PathBuf> {
+        let packets: Vec<_> = self.state.lock().unwrap().packets.iter().cloned().collect();
+        let path = self.output_dir.join(format!("{}-{}.pcap", Utc::now().format("%Y%m%d-%H%M%S"), label));
+
+        let mut out = BufWriter::new(File::create(&path)?);
+        // Classic pcap header: magic, v2.4, UTC, accuracy, snaplen, LINKTYPE_ETHERNET
+        out.write_all(&0xa1b2c3d4u32.to_le_bytes())?;
+        out.write_all(&2u16.to_le_bytes())?;
+        out.write_all(&4u16.to_le_bytes())?;
+        out.write_all(&0i32.to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&(SNAPLEN as u32).to_le_bytes())?;
+        out.write_all(&1u32.to_le_bytes())?;
+        for (at, original_len, data) in &packets {
+            out.write_all(&(at.timestamp() as u32).to_le_bytes())?;
+            out.write_all(&at.timestamp_subsec_micros().to_le_bytes())?;
+            out.write_all(&(data.len() as u32).to_le_bytes())?;
+            out.write_all(&original_len.to_le_bytes())?;
+            out.write_all(data)?;
+        }
+        out.flush()?;
+
+        info!("Wrote {} packets around the outage to {}", packets.len(), path.display());
+        Ok(path)
+    }
+}
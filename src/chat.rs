@@ -0,0 +1,228 @@
+use crate::metrics::{Alert, EventSeverity, NetworkEvent, WifiSnapshot};
+use crate::webhook;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// Most of an event's details shown as fields, beside the current stats
+const MAX_DETAILS: usize = 6;
+
+/// A chat service taking incoming webhooks, each with its own message format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatService {
+    Slack,
+    Discord,
+    Teams,
+}
+
+/// One incoming webhook URL and the least severe events sent to it
+#[derive(Debug, Clone)]
+pub struct ChatTarget {
+    pub service: ChatService,
+    pub url: String,
+    pub min_severity: EventSeverity,
+}
+
+/// What a message says, before it is put in a service's format
+struct Notice<'a> {
+    severity: &'a EventSeverity,
+    resolved: bool,
+    title: String,
+    text: &'a str,
+    timestamp: DateTime<Utc>,
+    fields: Vec<(String, String)>,
+}
+
+/// Posts events and alert rule changes to Slack, Discord and Teams channels as formatted
+/// messages: colored by severity, with the event's details and the current Wi-Fi stats.
+/// Delivery runs in the background with the webhooks' retries.
+pub struct ChatNotifier {
+    targets: Vec<(ChatTarget, mpsc::UnboundedSender<String>)>,
+    host: Option<String>,
+    dashboard_url: String,
+}
+
+impl ChatNotifier {
+    pub fn spawn(targets: Vec<ChatTarget>, dashboard_url: String) -> Option<Self> {
+        if targets.is_empty() {
+            return None;
+        }
+        let targets = targets.into_iter().map(|target| {
+            info!("Sending {:?} and more severe events to {:?}", target.min_severity, target.service);
+            let (bodies, receiver) = mpsc::unbounded_channel();
+            // The URL is the credential, so it stays out of the log
+            tokio::spawn(webhook::run(format!("{:?}", target.service), target.url.clone(), None, receiver));
+            (target, bodies)
+        }).collect();
+        Some(Self { targets, host: sysinfo::System::host_name(), dashboard_url })
+    }
+
+    pub fn send(&self, event: &NetworkEvent, snapshot: &WifiSnapshot) {
+        let mut fields = details(&event.details);
+        fields.extend(stats(snapshot));
+        self.post(Notice {
+            severity: &event.severity,
+            resolved: false,
+            title: format!("{:?}", event.event_type),
+            text: &event.description,
+            timestamp: event.timestamp,
+            fields,
+        });
+    }
+
+    /// An alert rule fired or resolved
+    pub fn send_alert(&self, alert: &Alert, snapshot: &WifiSnapshot) {
+        let resolved = alert.resolved_at.is_some();
+        let mut fields = vec![
+            ("Condition".to_string(), alert.condition.clone()),
+            ("Value".to_string(), format_number(alert.value)),
+        ];
+        fields.extend(stats(snapshot));
+        self.post(Notice {
+            severity: &alert.severity,
+            resolved,
+            title: match resolved {
+                true => format!("Resolved: {}", alert.name),
+                false => format!("Alert: {}", alert.name),
+            },
+            text: &alert.message,
+            timestamp: alert.resolved_at.unwrap_or(alert.fired_at),
+            fields,
+        });
+    }
+
+    fn post(&self, notice: Notice) {
+        for (target, bodies) in &self.targets {
+            if *notice.severity < target.min_severity {
+                continue;
+            }
+            let body = match target.service {
+                ChatService::Slack => self.slack(&notice),
+                ChatService::Discord => self.discord(&notice),
+                ChatService::Teams => self.teams(&notice),
+            };
+            let _ = bodies.send(body.to_string());
+        }
+    }
+
+    fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or("wifi-tracker")
+    }
+
+    fn slack(&self, notice: &Notice) -> serde_json::Value {
+        json!({
+            "text": format!("{:?} on {}: {}", notice.severity, self.host(), notice.title),
+            "attachments": [{
+                "color": format!("#{:06X}", color(notice)),
+                "title": notice.title,
+                "title_link": self.dashboard_url,
+                "text": notice.text,
+                "fields": notice.fields.iter()
+                    .map(|(name, value)| json!({ "title": name, "value": value, "short": true }))
+                    .collect::<Vec<_>>(),
+                "footer": self.host(),
+                "ts": notice.timestamp.timestamp(),
+            }]
+        })
+    }
+
+    fn discord(&self, notice: &Notice) -> serde_json::Value {
+        json!({
+            "embeds": [{
+                "title": notice.title,
+                "url": self.dashboard_url,
+                "description": notice.text,
+                "color": color(notice),
+                "fields": notice.fields.iter()
+                    .map(|(name, value)| json!({ "name": name, "value": value, "inline": true }))
+                    .collect::<Vec<_>>(),
+                "footer": { "text": format!("{:?} on {}", notice.severity, self.host()) },
+                "timestamp": notice.timestamp.to_rfc3339(),
+            }]
+        })
+    }
+
+    /// An Adaptive Card, as Teams channel workflows expect
+    fn teams(&self, notice: &Notice) -> serde_json::Value {
+        let color = match (notice.resolved, notice.severity) {
+            (true, _) => "Good",
+            (false, EventSeverity::Info) => "Accent",
+            (false, EventSeverity::Warning) => "Warning",
+            (false, _) => "Attention",
+        };
+        json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": [
+                        { "type": "TextBlock", "text": notice.title, "weight": "Bolder", "size": "Medium", "color": color, "wrap": true },
+                        { "type": "TextBlock", "text": format!("{:?} on {} at {}", notice.severity, self.host(), notice.timestamp.format("%Y-%m-%d %H:%M:%S UTC")), "isSubtle": true, "spacing": "None", "wrap": true },
+                        { "type": "TextBlock", "text": notice.text, "wrap": true },
+                        { "type": "FactSet", "facts": notice.fields.iter()
+                            .map(|(name, value)| json!({ "title": name, "value": value }))
+                            .collect::<Vec<_>>() },
+                    ],
+                    "actions": [{ "type": "Action.OpenUrl", "title": "Open dashboard", "url": self.dashboard_url }],
+                }
+            }]
+        })
+    }
+}
+
+/// Bar color: green once resolved, otherwise by severity
+fn color(notice: &Notice) -> u32 {
+    match (notice.resolved, notice.severity) {
+        (true, _) => 0x2EB67D,
+        (false, EventSeverity::Info) => 0x439FE0,
+        (false, EventSeverity::Warning) => 0xECB22E,
+        (false, EventSeverity::Error) => 0xE8692D,
+        (false, EventSeverity::Critical) => 0xD0021B,
+    }
+}
+
+/// The event's simple detail values, e.g. the old and new BSSID of a roam
+fn details(details: &serde_json::Value) -> Vec<(String, String)> {
+    let Some(details) = details.as_object() else {
+        return Vec::new();
+    };
+    details.iter()
+        .filter_map(|(name, value)| match value {
+            serde_json::Value::String(s) if !s.is_empty() => Some((name.clone(), s.clone())),
+            serde_json::Value::Number(n) => Some((name.clone(), n.as_f64().map(format_number).unwrap_or_else(|| n.to_string()))),
+            serde_json::Value::Bool(b) => Some((name.clone(), b.to_string())),
+            _ => None,
+        })
+        .take(MAX_DETAILS)
+        .collect()
+}
+
+/// The stats at the time of the message
+fn stats(snapshot: &WifiSnapshot) -> Vec<(String, String)> {
+    let mut stats = Vec::new();
+    if let Some(ref wifi) = snapshot.wifi_info {
+        stats.push(("Network".to_string(), wifi.ssid.clone()));
+        stats.push(("Signal".to_string(), format!("{} dBm", wifi.signal_strength_dbm)));
+    }
+    if let Some(latency) = snapshot.latency.average_latency_ms {
+        stats.push(("Latency".to_string(), format!("{:.1} ms", latency)));
+    }
+    stats.push(("Packet loss".to_string(), format!("{:.1}%", snapshot.latency.packet_loss_percent)));
+    stats.push(("Internet".to_string(), match snapshot.connectivity.internet_reachable {
+        true => "reachable".to_string(),
+        false => "unreachable".to_string(),
+    }));
+    stats
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::probe::CommandRunner;
+use crate::storage::MetricsStore;
+use chrono::{Duration, Utc};
+use std::process::Output;
+
+/// Number of recent events included in a bundle
+const RECENT_EVENT_LIMIT: u32 = 200;
+
+/// System commands captured verbatim in the bundle
+const DIAGNOSTIC_COMMANDS: [(&str, &str, &[&str]); 5] = [
+    ("wlan_interfaces", "netsh", &["wlan", "show", "interfaces"]),
+    ("adapter_capabilities", "netsh", &["wlan", "show", "drivers"]),
+    ("routing_table", "route", &["print"]),
+    ("arp_table", "arp", &["-a"]),
+    ("dns_config", "ipconfig", &["/all"]),
+];
+
+/// Collect the "send me your diagnostics" bundle: latest snapshot, adapter details,
+/// routing/ARP/DNS state and recent events. Each command is killed if it hangs.
+pub async fn collect_bundle(store: &dyn MetricsStore) -> anyhow::Result<serde_json::Value> {
+    let since = (Utc::now() - Duration::hours(24)).to_rfc3339();
+    let events = store.get_events(Some(&since), None, None, None, Some(RECENT_EVENT_LIMIT), 0)?;
+
+    let runner = CommandRunner::default();
+    let mut commands = serde_json::Map::new();
+    for (name, program, args) in DIAGNOSTIC_COMMANDS {
+        let command_line = format!("{} {}", program, args.join(" "));
+        commands.insert(name.to_string(), command_json(&command_line, runner.output(program, args).await));
+    }
+
+    Ok(serde_json::json!({
+        "generated_at": Utc::now().to_rfc3339(),
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "hostname": std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")).ok(),
+        "os": std::env::consts::OS,
+        "current_snapshot": store.get_latest_snapshot()?,
+        "adapter_info": store.get_latest_adapter_info()?,
+        "commands": commands,
+        "recent_events": events,
+    }))
+}
+
+/// A command's captured output, or the reason it produced none, as JSON
+pub fn command_json<E: std::fmt::Display>(command_line: &str, output: Result<Output, E>) -> serde_json::Value {
+    match output {
+        Ok(output) => serde_json::json!({
+            "command": command_line,
+            "exit_code": output.status.code(),
+            "stdout": String::from_utf8_lossy(&output.stdout),
+            "stderr": String::from_utf8_lossy(&output.stderr),
+        }),
+        Err(e) => serde_json::json!({
+            "command": command_line,
+            "error": e.to_string(),
+        }),
+    }
+}
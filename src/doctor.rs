@@ -0,0 +1,247 @@
+use crate::metrics::PingResult;
+use crate::monitor::{parse_ping_output, HTTP_CHECK_URL};
+use crate::probe::CommandRunner;
+use chrono::{DateTime, Utc};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::time::Duration;
+
+/// System commands the monitor runs: (program, what it is used for, required)
+const COMMANDS: [(&str, &str, bool); 10] = [
+    ("netsh", "WiFi interface details and neighbor scans", true),
+    ("ping", "latency and loss", true),
+    ("nslookup", "DNS checks", true),
+    ("ipconfig", "IP configuration", true),
+    ("route", "default route", true),
+    ("netstat", "TCP retransmission counters", false),
+    ("tracert", "outage traceroutes", false),
+    ("wevtutil", "--wlan-event-log", false),
+    ("powershell", "adapter power management setting", false),
+    ("powercfg", "WiFi power saving mode", false),
+];
+
+/// Clock offsets from an HTTP server's Date header beyond which timestamps are suspect
+const CLOCK_WARN_SECS: i64 = 30;
+const CLOCK_FAIL_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One prerequisite check, with a fix to suggest when it didn't pass
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// Verify everything `monitor` relies on, so missing pieces show up now instead of
+/// as silently empty fields in the collected data
+pub async fn run(database: &Path, log_dir: &Path, port: u16) -> Vec<Check> {
+    let runner = CommandRunner::default();
+    let mut checks = Vec::new();
+
+    for (program, purpose, required) in COMMANDS {
+        let name = format!("command: {}", program);
+        checks.push(match which::which(program) {
+            Ok(path) => Check::ok(&name, path.display().to_string()),
+            Err(_) if required => Check::fail(
+                &name,
+                format!("not found; needed for {}", purpose),
+                "It ships with Windows; make sure C:\\Windows\\System32 is on PATH",
+            ),
+            Err(_) => Check::warn(
+                &name,
+                format!("not found; {} will be missing", purpose),
+                "It ships with Windows; make sure C:\\Windows\\System32 is on PATH",
+            ),
+        });
+    }
+
+    checks.push(check_wlan(&runner).await);
+    checks.push(check_icmp(&runner).await);
+    checks.push(check_writable_file("database", database));
+    checks.push(check_writable_dir("log directory", log_dir));
+    checks.push(check_port(port));
+    checks.push(check_clock().await);
+    checks
+}
+
+/// Render the checks as a list, with fixes under the ones that didn't pass
+pub fn format(checks: &[Check], color: bool) -> String {
+    let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_string() };
+
+    let mut out = String::new();
+    for check in checks {
+        let mark = match check.status {
+            Status::Ok => paint("32", " ok "),
+            Status::Warn => paint("33", "warn"),
+            Status::Fail => paint("31", "FAIL"),
+        };
+        out.push_str(&format!("[{}] {:<22} {}\n", mark, check.name, check.detail));
+        if let Some(ref fix) = check.fix {
+            out.push_str(&format!("       {:<22} fix: {}\n", "", fix));
+        }
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    out.push_str(&format!("\n{} ok, {} warnings, {} failed\n", count(Status::Ok), count(Status::Warn), count(Status::Fail)));
+    out
+}
+
+/// The WLAN service is running, netsh may read WiFi details, and there is an adapter
+async fn check_wlan(runner: &CommandRunner) -> Check {
+    const NAME: &str = "wlan service";
+    let output = match runner.output("netsh", &["wlan", "show", "interfaces"]).await {
+        Ok(output) => output,
+        Err(e) => return Check::fail(NAME, e.to_string(), "Check that netsh is available (see above)"),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+    if stdout.contains("wlansvc") && stdout.contains("not running") {
+        Check::fail(
+            NAME,
+            "the WLAN AutoConfig service is not running",
+            "Start it from an elevated prompt: `net start wlansvc` (and set it to Automatic in services.msc)",
+        )
+    } else if stdout.contains("location permission") {
+        Check::fail(
+            NAME,
+            "Windows denies netsh access to WiFi details without location permission",
+            "Settings > Privacy & security > Location: turn on location services and \"Let desktop apps access your location\"",
+        )
+    } else if !stdout.lines().any(|line| line.trim_start().starts_with("name")) {
+        Check::warn(NAME, "no wireless interface found", "Enable the WiFi adapter, or check its driver in Device Manager")
+    } else {
+        let count = stdout.lines().filter(|line| line.trim_start().starts_with("name")).count();
+        Check::ok(NAME, format!("{} wireless interface(s)", count))
+    }
+}
+
+/// Echo requests can be sent and answered
+async fn check_icmp(runner: &CommandRunner) -> Check {
+    const NAME: &str = "icmp";
+    let mut result = PingResult {
+        target: "127.0.0.1".to_string(),
+        resolved_ip: None,
+        packets_sent: 1,
+        packets_received: 0,
+        packet_loss_percent: 100.0,
+        min_ms: None,
+        avg_ms: None,
+        max_ms: None,
+        stddev_ms: None,
+        individual_times_ms: Vec::new(),
+        packet_size: None,
+        rfc3550_jitter_ms: None,
+        error: None,
+    };
+    match runner.output("ping", &["-n", "1", "-w", "1000", "127.0.0.1"]).await {
+        Ok(output) => parse_ping_output(&String::from_utf8_lossy(&output.stdout), &mut result),
+        Err(e) => return Check::fail(NAME, e.to_string(), "Check that ping is available (see above)"),
+    }
+
+    if result.packets_received > 0 {
+        Check::ok(NAME, "loopback ping answered")
+    } else {
+        Check::fail(
+            NAME,
+            "loopback ping got no reply, so every latency and loss figure would read as an outage",
+            "Allow ICMP echo in Windows Defender Firewall or your security software (\"File and Printer Sharing (Echo Request)\")",
+        )
+    }
+}
+
+/// The database can be created or written where it is
+fn check_writable_file(name: &str, path: &Path) -> Check {
+    let existed = path.exists();
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if !parent.exists() {
+        return Check::fail(name, format!("directory {} does not exist", parent.display()), "Create it, or pass a different --database");
+    }
+
+    match OpenOptions::new().append(true).create(true).open(path) {
+        Ok(_) => {
+            if !existed {
+                let _ = std::fs::remove_file(path);
+            }
+            Check::ok(name, format!("{} is writable", path.display()))
+        }
+        Err(e) => Check::fail(
+            name,
+            format!("cannot write {}: {}", path.display(), e),
+            "Pass a --database in a folder you own, or check the file isn't read-only or open in another program",
+        ),
+    }
+}
+
+/// The log directory exists (or can be created) and accepts new files
+fn check_writable_dir(name: &str, dir: &Path) -> Check {
+    let probe = dir.join(".doctor-write-test");
+    let result = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&probe, b"ok"));
+    let _ = std::fs::remove_file(&probe);
+    match result {
+        Ok(()) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(
+            name,
+            format!("cannot write to {}: {}", dir.display(), e),
+            "Pass a --log-dir in a folder you own",
+        ),
+    }
+}
+
+/// Nothing else is listening on the dashboard port
+fn check_port(port: u16) -> Check {
+    let name = "dashboard port";
+    match std::net::TcpListener::bind(("0.0.0.0", port)) {
+        Ok(_) => Check::ok(name, format!("{} is free", port)),
+        Err(e) => Check::fail(
+            name,
+            format!("cannot listen on {}: {}", port, e),
+            "Stop the other program (`netstat -ano | findstr :<port>` shows its PID) or pass a different --port",
+        ),
+    }
+}
+
+/// The local clock agrees with a web server's Date header
+async fn check_clock() -> Check {
+    const NAME: &str = "clock";
+    let date = async {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().ok()?;
+        let response = client.head(HTTP_CHECK_URL).send().await.ok()?;
+        let header = response.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+        DateTime::parse_from_rfc2822(header).ok()
+    }
+    .await;
+    let Some(server_time) = date else {
+        return Check::warn(NAME, "could not compare with an internet time source", "Re-run `doctor` once online");
+    };
+
+    let skew = (Utc::now() - server_time.with_timezone(&Utc)).num_seconds();
+    let detail = format!("{:+}s from internet time", skew);
+    if skew.abs() >= CLOCK_FAIL_SECS {
+        Check::fail(NAME, detail, "Resync the clock (`w32tm /resync` as Administrator); timestamps and HTTPS checks depend on it")
+    } else if skew.abs() >= CLOCK_WARN_SECS {
+        Check::warn(NAME, detail, "Resync the clock (`w32tm /resync` as Administrator)")
+    } else {
+        Check::ok(NAME, detail)
+    }
+}
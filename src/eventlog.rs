@@ -0,0 +1,239 @@
+use crate::metrics::*;
+use crate::probe::CommandRunner;
+use tracing::{debug, warn};
+
+const WLAN_AUTOCONFIG_LOG: &str = "Microsoft-Windows-WLAN-AutoConfig/Operational";
+
+/// Event IDs we care about: connection failure, disconnect, security failure
+const WATCHED_EVENT_IDS: [u32; 3] = [8002, 8003, 11006];
+
+/// Tails the WLAN-AutoConfig operational log and converts entries into network events
+pub struct WlanEventLogCollector {
+    last_record_id: Option<u64>,
+}
+
+/// A single parsed entry from the WLAN-AutoConfig log
+#[derive(Debug, Clone)]
+struct WlanLogEntry {
+    event_id: u32,
+    record_id: u64,
+    time_created: Option<String>,
+    data: Vec<(String, String)>,
+}
+
+impl WlanLogEntry {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.data.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl WlanEventLogCollector {
+    pub fn new() -> Self {
+        Self { last_record_id: None }
+    }
+
+    /// Returns events logged since the previous poll. The first poll only records
+    /// the current position so historical entries are not replayed.
+    pub async fn poll(&mut self, runner: &CommandRunner) -> Vec<NetworkEvent> {
+        let Some(last_id) = self.last_record_id else {
+            // Left unset on failure and retried next poll; starting from 0 would replay the whole log
+            match self.latest_record_id(runner).await {
+                Ok(id) => {
+                    self.last_record_id = Some(id);
+                    debug!("WLAN event log positioned at record {}", id);
+                }
+                Err(e) => warn!("Failed to find the end of the WLAN-AutoConfig event log, retrying next poll: {}", e),
+            }
+            return Vec::new();
+        };
+
+        let ids = WATCHED_EVENT_IDS.iter()
+            .map(|id| format!("EventID={}", id))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        let query = format!("*[System[({}) and EventRecordID>{}]]", ids, last_id);
+
+        let entries = match self.query(runner, &query, None).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read WLAN-AutoConfig event log: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut events = Vec::new();
+        for entry in entries {
+            self.last_record_id = Some(self.last_record_id.unwrap_or(0).max(entry.record_id));
+            events.push(entry_to_event(&entry));
+        }
+        events
+    }
+
+    /// The newest record's id, or 0 when the log is empty
+    async fn latest_record_id(&self, runner: &CommandRunner) -> anyhow::Result<u64> {
+        let entries = self.query(runner, "*", Some(1)).await?;
+        Ok(entries.into_iter().map(|e| e.record_id).max().unwrap_or(0))
+    }
+
+    async fn query(&self, runner: &CommandRunner, query: &str, newest: Option<u32>) -> anyhow::Result<Vec<WlanLogEntry>> {
+        let mut args = vec![
+            "qe".to_string(),
+            WLAN_AUTOCONFIG_LOG.to_string(),
+            format!("/q:{}", query),
+            "/f:xml".to_string(),
+        ];
+        if let Some(count) = newest {
+            args.push("/rd:true".to_string());
+            args.push(format!("/c:{}", count));
+        }
+
+        let output = runner.output("wevtutil", &args).await?;
+        if !output.status.success() {
+            anyhow::bail!("wevtutil exited with {}: {}",
+                output.status, String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        Ok(parse_wevtutil_xml(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+fn entry_to_event(entry: &WlanLogEntry) -> NetworkEvent {
+    let ssid = entry.field("SSID").or_else(|| entry.field("ProfileName")).unwrap_or("unknown network");
+    let reason = entry.field("Reason").or_else(|| entry.field("FailureReason"));
+    let reason_code = entry.field("ReasonCode").and_then(parse_reason_code);
+    let ieee_reason = reason_code.and_then(ieee80211_reason);
+
+    let (severity, action) = match entry.event_id {
+        8002 => (EventSeverity::Error, "failed to connect to"),
+        11006 => (EventSeverity::Error, "security negotiation failed for"),
+        _ => {
+            let user_initiated = reason.map(|r| r.to_lowercase().contains("by the user")).unwrap_or(false);
+            if user_initiated {
+                (EventSeverity::Info, "disconnected from")
+            } else {
+                (EventSeverity::Error, "disconnected from")
+            }
+        }
+    };
+
+    let mut description = format!("WLAN AutoConfig {} {}", action, ssid);
+    match (reason_code, ieee_reason, reason) {
+        (Some(code), Some(name), _) => description.push_str(&format!(" (reason {}: {})", code, name)),
+        (_, _, Some(text)) => description.push_str(&format!(" ({})", text.trim_end_matches('.'))),
+        _ => {}
+    }
+
+    let fields: serde_json::Map<String, serde_json::Value> = entry.data.iter()
+        .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+        .collect();
+
+    let mut event = NetworkEvent::new(EventType::WlanDisconnectReason, severity, &description)
+        .with_details(serde_json::json!({
+            "source": WLAN_AUTOCONFIG_LOG,
+            "event_id": entry.event_id,
+            "record_id": entry.record_id,
+            "logged_at": entry.time_created,
+            "reason": reason,
+            "reason_code": reason_code,
+            "ieee80211_reason": ieee_reason,
+            "fields": fields
+        }));
+
+    if let Some(logged_at) = entry.time_created.as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+    {
+        event.timestamp = logged_at.with_timezone(&chrono::Utc);
+    }
+
+    event
+}
+
+/// Parse the concatenated `<Event>` documents printed by `wevtutil qe /f:xml`
+fn parse_wevtutil_xml(xml: &str) -> Vec<WlanLogEntry> {
+    let mut entries = Vec::new();
+
+    for chunk in xml.split("<Event ").skip(1) {
+        let event_id = tag_text(chunk, "EventID").and_then(|v| v.parse().ok());
+        let record_id = tag_text(chunk, "EventRecordID").and_then(|v| v.parse().ok());
+        let (Some(event_id), Some(record_id)) = (event_id, record_id) else {
+            continue;
+        };
+
+        let time_created = chunk.split("SystemTime=").nth(1).and_then(|rest| {
+            let quote = rest.chars().next()?;
+            rest[quote.len_utf8()..].split(quote).next().map(|s| s.to_string())
+        });
+
+        let mut data = Vec::new();
+        for item in chunk.split("<Data Name=").skip(1) {
+            let Some(quote) = item.chars().next() else { continue };
+            let mut parts = item[quote.len_utf8()..].splitn(2, quote);
+            let name = parts.next().unwrap_or_default();
+            let value = parts.next()
+                .and_then(|rest| rest.split_once('>'))
+                .map(|(_, rest)| rest.split("</Data>").next().unwrap_or_default())
+                .unwrap_or_default();
+            data.push((name.to_string(), unescape_xml(value.trim())));
+        }
+
+        entries.push(WlanLogEntry { event_id, record_id, time_created, data });
+    }
+
+    entries
+}
+
+fn tag_text<'a>(chunk: &'a str, tag: &str) -> Option<&'a str> {
+    let start = chunk.find(&format!("<{}", tag))?;
+    let rest = &chunk[start..];
+    let open_end = rest.find('>')?;
+    let close = rest.find(&format!("</{}>", tag))?;
+    Some(rest[open_end + 1..close].trim())
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_reason_code(value: &str) -> Option<u32> {
+    let value = value.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Describe an IEEE 802.11 reason code as reported by the access point
+fn ieee80211_reason(code: u32) -> Option<&'static str> {
+    let name = match code {
+        1 => "Unspecified reason",
+        2 => "Previous authentication no longer valid",
+        3 => "Deauthenticated because station is leaving",
+        4 => "Disassociated due to inactivity",
+        5 => "Disassociated because AP is unable to handle all associated stations",
+        6 => "Class 2 frame received from nonauthenticated station",
+        7 => "Class 3 frame received from nonassociated station",
+        8 => "Disassociated because station is leaving the BSS",
+        9 => "Station requesting association is not authenticated",
+        10 => "Disassociated because the power capability is unacceptable",
+        11 => "Disassociated because the supported channels are unacceptable",
+        13 => "Invalid information element",
+        14 => "Message integrity code (MIC) failure",
+        15 => "4-way handshake timeout",
+        16 => "Group key handshake timeout",
+        17 => "Information element in 4-way handshake differs",
+        18 => "Invalid group cipher",
+        19 => "Invalid pairwise cipher",
+        20 => "Invalid AKMP",
+        23 => "IEEE 802.1X authentication failed",
+        24 => "Cipher suite rejected because of security policy",
+        34 => "Disassociated because of excessive frame loss",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -0,0 +1,161 @@
+use crate::metrics::{Alert, NetworkEvent};
+use crate::webhook::{Payload, WebhookFilter};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Runs waiting per command while one is still going; further ones are dropped
+const MAX_QUEUED: usize = 100;
+/// Output lines quoted in the log when a command fails
+const MAX_OUTPUT_LINES: usize = 5;
+
+/// One run of a command: the webhook payload for stdin and the variables beside it
+struct Invocation {
+    stdin: String,
+    env: Vec<(&'static str, String)>,
+}
+
+/// Runs commands for matching events and alert rule changes, e.g. a script power-cycling
+/// the router's smart plug when an `internet-down` rule fires. Each command gets the
+/// webhook payload as JSON on stdin and its main fields as `WIFI_TRACKER_*` environment
+/// variables. A command runs in a background task, one run at a time, and is killed when
+/// it takes longer than the timeout.
+pub struct ExecHooks {
+    commands: Vec<mpsc::Sender<Invocation>>,
+    filter: WebhookFilter,
+    host: Option<String>,
+}
+
+impl ExecHooks {
+    /// Start running `commands` through the shell (`sh -c`, or `cmd /C` on Windows)
+    pub fn spawn(commands: Vec<String>, filter: WebhookFilter, timeout: Duration) -> Option<Self> {
+        if commands.is_empty() {
+            return None;
+        }
+        info!("Running {} command(s) for {:?} and more severe events", commands.len(), filter.min_severity);
+        let commands = commands.into_iter().map(|command| {
+            let (invocations, receiver) = mpsc::channel(MAX_QUEUED);
+            tokio::spawn(run(command, timeout, receiver));
+            invocations
+        }).collect();
+        Some(Self { commands, filter, host: sysinfo::System::host_name() })
+    }
+
+    pub fn send(&self, event: &NetworkEvent) {
+        if !self.filter.matches(event) {
+            return;
+        }
+        let mut env = vec![
+            ("WIFI_TRACKER_KIND", "event".to_string()),
+            ("WIFI_TRACKER_EVENT_TYPE", format!("{:?}", event.event_type)),
+            ("WIFI_TRACKER_SEVERITY", format!("{:?}", event.severity)),
+            ("WIFI_TRACKER_DESCRIPTION", event.description.clone()),
+            ("WIFI_TRACKER_TIMESTAMP", event.timestamp.to_rfc3339()),
+        ];
+        if let Some(incident) = event.details.get("incident_id").and_then(|v| v.as_str()) {
+            env.push(("WIFI_TRACKER_INCIDENT_ID", incident.to_string()));
+        }
+        self.invoke(Payload { host: self.host.as_deref(), event: Some(event), alert: None }, env);
+    }
+
+    /// An alert rule fired or resolved; the event type filter doesn't apply
+    pub fn send_alert(&self, alert: &Alert) {
+        if alert.severity < self.filter.min_severity {
+            return;
+        }
+        let env = vec![
+            ("WIFI_TRACKER_KIND", "alert".to_string()),
+            ("WIFI_TRACKER_ALERT_NAME", alert.name.clone()),
+            ("WIFI_TRACKER_ALERT_STATE", match alert.resolved_at {
+                Some(_) => "resolved".to_string(),
+                None => "firing".to_string(),
+            }),
+            ("WIFI_TRACKER_SEVERITY", format!("{:?}", alert.severity)),
+            ("WIFI_TRACKER_DESCRIPTION", alert.message.clone()),
+            ("WIFI_TRACKER_TIMESTAMP", alert.resolved_at.unwrap_or(alert.fired_at).to_rfc3339()),
+        ];
+        self.invoke(Payload { host: self.host.as_deref(), event: None, alert: Some(alert) }, env);
+    }
+
+    fn invoke(&self, payload: Payload, mut env: Vec<(&'static str, String)>) {
+        let stdin = match serde_json::to_string(&payload) {
+            Ok(stdin) => stdin,
+            Err(e) => {
+                warn!("Failed to encode a command hook payload: {}", e);
+                return;
+            }
+        };
+        if let Some(host) = payload.host {
+            env.push(("WIFI_TRACKER_HOST", host.to_string()));
+        }
+        for command in &self.commands {
+            let invocation = Invocation { stdin: stdin.clone(), env: env.clone() };
+            if command.try_send(invocation).is_err() {
+                warn!("A command hook is behind, skipped running it for an event");
+            }
+        }
+    }
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+/// Run `command` for each invocation in turn, killing it after `timeout`
+async fn run(command: String, timeout: Duration, mut receiver: mpsc::Receiver<Invocation>) {
+    while let Some(invocation) = receiver.recv().await {
+        let child = shell(&command)
+            .envs(invocation.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Failed to start command hook `{}`: {}", command, e);
+                continue;
+            }
+        };
+        // Writing stdin is timed too, as a command that never reads it would block the
+        // write once the pipe fills
+        let (stdin, input) = (child.stdin.take(), invocation.stdin);
+        let finished = async move {
+            if let Some(mut stdin) = stdin {
+                // A command that ignores stdin may exit before reading it; that's fine
+                let _ = stdin.write_all(input.as_bytes()).await;
+            }
+            child.wait_with_output().await
+        };
+
+        // Dropping the future on timeout drops the child, and kill_on_drop kills it
+        match tokio::time::timeout(timeout, finished).await {
+            Ok(Ok(output)) if output.status.success() => debug!("Command hook `{}` finished", command),
+            Ok(Ok(output)) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let lines: Vec<&str> = stderr.lines().rev().take(MAX_OUTPUT_LINES).collect();
+                let tail = lines.into_iter().rev().collect::<Vec<_>>().join(" | ");
+                warn!("Command hook `{}` failed ({}): {}", command, output.status, tail);
+            }
+            Ok(Err(e)) => warn!("Command hook `{}` failed: {}", command, e),
+            Err(_) => warn!("Command hook `{}` did not finish within {}s, killed it", command, timeout.as_secs()),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use crate::metrics::{Annotation, Session, WifiSnapshot};
+use crate::storage::MetricsStore;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File formats `export` can write
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON document with statistics, events and snapshots, one record per line
+    Json,
+    /// A directory with one Parquet file per table (needs the `parquet` feature)
+    Parquet,
+    /// InfluxDB line protocol, one `wifi` record per snapshot plus `wifi_ping` and `wifi_event` records
+    Influx,
+    /// One row per snapshot with the main metrics, for spreadsheets
+    Csv,
+}
+
+impl ExportFormat {
+    /// Where the export goes when `--output` isn't given
+    pub fn default_output(&self) -> PathBuf {
+        match self {
+            ExportFormat::Json => PathBuf::from("wifi_export.json"),
+            ExportFormat::Parquet => PathBuf::from("wifi_export"),
+            ExportFormat::Influx => PathBuf::from("wifi_export.lp"),
+            ExportFormat::Csv => PathBuf::from("wifi_export.csv"),
+        }
+    }
+}
+
+/// Sessions don't overlap, so a snapshot belongs to the one whose span holds it
+fn session_at(sessions: &[Session], at: DateTime<Utc>) -> Option<&Session> {
+    sessions.iter().find(|s| s.started_at <= at && s.ended_at.is_none_or(|end| at <= end))
+}
+
+/// The range's annotations, oldest first. Databases from before annotations existed have none.
+fn annotations_oldest_first(store: &dyn MetricsStore, start: Option<&str>, end: Option<&str>) -> Vec<Annotation> {
+    let mut annotations = store.get_annotations(start, end).unwrap_or_default();
+    annotations.reverse();
+    annotations
+}
+
+/// Write statistics, annotations, events and snapshots from the range as one JSON document,
+/// returning the number of snapshots. Records are streamed from the store oldest first, one
+/// per line, so memory use stays flat however long the range.
+pub fn write_json(store: &dyn MetricsStore, path: &Path, start: Option<&str>, end: Option<&str>) -> anyhow::Result<usize> {
+    write_json_to(store, std::io::BufWriter::new(std::fs::File::create(path)?), start, end)
+}
+
+/// `write_json` into any writer, e.g. an HTTP response body
+pub fn write_json_to(store: &dyn MetricsStore, mut out: impl Write, start: Option<&str>, end: Option<&str>) -> anyhow::Result<usize> {
+    let statistics = store.get_statistics(start, end)?;
+    let annotations = annotations_oldest_first(store, start, end);
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"exported_at\": {},", serde_json::to_string(&Utc::now().to_rfc3339())?)?;
+    writeln!(out, "  \"statistics\": {},", serde_json::to_string(&statistics)?)?;
+    write_json_array(&mut out, "annotations", |f| {
+        let count = annotations.len();
+        annotations.into_iter().try_for_each(f)?;
+        Ok(count)
+    })?;
+    writeln!(out, ",")?;
+    write_json_array(&mut out, "events", |f| store.for_each_event(start, end, f))?;
+    writeln!(out, ",")?;
+    let count = write_json_array(&mut out, "snapshots", |f| store.for_each_snapshot(start, end, f))?;
+    writeln!(out, "\n}}")?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// `"name": [...]` with one compact record per line, fed by `visit`
+fn write_json_array<T: serde::Serialize>(
+    out: &mut impl Write,
+    name: &str,
+    visit: impl FnOnce(&mut dyn FnMut(T) -> anyhow::Result<()>) -> anyhow::Result<usize>,
+) -> anyhow::Result<usize> {
+    write!(out, "  \"{}\": [", name)?;
+    let mut written = 0;
+    visit(&mut |record| {
+        out.write_all(if written == 0 { b"\n    " } else { b",\n    " })?;
+        serde_json::to_writer(&mut *out, &record)?;
+        written += 1;
+        Ok(())
+    })?;
+    write!(out, "{}]", if written == 0 { "" } else { "\n  " })?;
+    Ok(written)
+}
+
+/// Write the range's snapshots and annotations as InfluxDB line protocol, tagged with the
+/// host of the session that recorded them, returning how many snapshots were written
+pub fn write_line_protocol(store: &dyn MetricsStore, path: &Path, start: Option<&str>, end: Option<&str>) -> anyhow::Result<usize> {
+    let sessions: Vec<Session> = store.get_sessions().unwrap_or_default().into_iter().map(|(s, _)| s).collect();
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let count = store.for_each_snapshot(start, end, &mut |snapshot| {
+        let host = session_at(&sessions, snapshot.timestamp).and_then(|s| s.host.as_deref());
+        out.write_all(crate::influx::snapshot_lines(&snapshot, host).as_bytes())?;
+        Ok(())
+    })?;
+    for annotation in annotations_oldest_first(store, start, end) {
+        let host = session_at(&sessions, annotation.timestamp).and_then(|s| s.host.as_deref());
+        out.write_all(crate::influx::annotation_line(&annotation, host).as_bytes())?;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Columns of the CSV export, one row per snapshot
+const CSV_COLUMNS: [&str; 27] = [
+    "id", "timestamp", "session_id", "ssid", "bssid", "band", "channel", "signal_dbm",
+    "signal_quality_percent", "link_speed_mbps", "connected", "router_reachable", "internet_reachable",
+    "router_latency_ms", "latency_avg_ms", "latency_min_ms", "latency_max_ms", "jitter_ms",
+    "packet_loss_percent", "mos", "dns_resolution_ms", "dns_failures", "throughput_rx_bytes_per_sec",
+    "throughput_tx_bytes_per_sec", "interval_secs", "other_network", "event_count",
+];
+
+/// Write the range's snapshots as CSV with the same columns as the Parquet snapshots
+/// table, less the full JSON, returning how many were written
+pub fn write_csv(store: &dyn MetricsStore, path: &Path, start: Option<&str>, end: Option<&str>) -> anyhow::Result<usize> {
+    write_csv_to(store, std::io::BufWriter::new(std::fs::File::create(path)?), start, end)
+}
+
+/// `write_csv` into any writer, e.g. an HTTP response body
+pub fn write_csv_to(store: &dyn MetricsStore, mut out: impl Write, start: Option<&str>, end: Option<&str>) -> anyhow::Result<usize> {
+    fn opt(value: Option<impl ToString>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+
+    let sessions: Vec<Session> = store.get_sessions().unwrap_or_default().into_iter().map(|(s, _)| s).collect();
+    writeln!(out, "{}", CSV_COLUMNS.join(","))?;
+    let count = store.for_each_snapshot(start, end, &mut |s: WifiSnapshot| {
+        let wifi = s.wifi_info.as_ref();
+        let row: [String; CSV_COLUMNS.len()] = [
+            s.id.clone(),
+            s.timestamp.to_rfc3339(),
+            opt(session_at(&sessions, s.timestamp).map(|s| &s.id)),
+            opt(wifi.map(|w| &w.ssid)),
+            opt(wifi.map(|w| &w.bssid)),
+            opt(wifi.map(|w| w.band.label())),
+            opt(wifi.map(|w| w.channel)),
+            opt(wifi.map(|w| w.signal_strength_dbm)),
+            opt(wifi.map(|w| w.signal_quality_percent)),
+            opt(wifi.map(|w| w.link_speed_mbps)),
+            s.connectivity.is_connected.to_string(),
+            s.connectivity.router_reachable.to_string(),
+            s.connectivity.internet_reachable.to_string(),
+            opt(s.latency.router_latency_ms),
+            opt(s.latency.average_latency_ms),
+            opt(s.latency.min_latency_ms),
+            opt(s.latency.max_latency_ms),
+            opt(s.latency.jitter_ms),
+            s.latency.packet_loss_percent.to_string(),
+            opt(s.latency.mos),
+            opt(s.dns_metrics.average_resolution_time_ms),
+            s.dns_metrics.failures.to_string(),
+            opt(s.system_info.throughput_rx_bytes_per_sec),
+            opt(s.system_info.throughput_tx_bytes_per_sec),
+            opt(s.interval_secs),
+            s.other_network.to_string(),
+            s.events.len().to_string(),
+        ];
+        let fields: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+        writeln!(out, "{}", fields.join(","))?;
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// Quote a field that holds a separator, quote or line break, e.g. an SSID with a comma
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// The parts of a JSON export that `read_json_export` reads back; statistics and the flat
+/// event list are derived from the snapshots
+#[derive(serde::Deserialize)]
+struct JsonExport {
+    exported_at: Option<String>,
+    /// Missing from exports made before annotations existed
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    snapshots: Vec<WifiSnapshot>,
+}
+
+/// What `import` adds to a database from a JSON export
+pub struct Imported {
+    /// A new session spanning the snapshots
+    pub session: Session,
+    /// Oldest first, each with its events
+    pub snapshots: Vec<WifiSnapshot>,
+    pub annotations: Vec<Annotation>,
+}
+
+/// Read a file written by `export --format json` back as a new session holding its
+/// snapshots, with its annotations. `None` for an export without snapshots.
+pub fn read_json_export(path: &Path) -> anyhow::Result<Option<Imported>> {
+    let file = std::fs::File::open(path).map_err(|e| anyhow::anyhow!("Could not open {:?}: {}", path, e))?;
+    let export: JsonExport = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("{:?} is not a JSON export: {}", path, e))?;
+    let mut snapshots = export.snapshots;
+    // Exports list the newest snapshot first
+    snapshots.sort_by_key(|s| s.timestamp);
+    let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) else {
+        return Ok(None);
+    };
+
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at: first.timestamp,
+        ended_at: Some(last.timestamp),
+        // The export doesn't say which machine recorded it
+        host: None,
+        config: serde_json::json!({
+            "imported_from": path.display().to_string(),
+            "exported_at": export.exported_at,
+        }),
+    };
+    Ok(Some(Imported { session, snapshots, annotations: export.annotations }))
+}
+
+/// Write snapshots, events, timeseries, incidents, pauses, annotations and sessions from
+/// the range to `<dir>/<table>.parquet`, returning the files written
+#[cfg(feature = "parquet")]
+pub fn write_parquet(store: &dyn MetricsStore, dir: &Path, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<PathBuf>> {
+    use crate::metrics::NetworkEvent;
+    use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray};
+    use std::sync::Arc;
+
+    fn times(values: impl Iterator<Item = Option<DateTime<Utc>>>) -> ArrayRef {
+        let micros: TimestampMicrosecondArray = values.map(|t| t.map(|t| t.timestamp_micros())).collect();
+        Arc::new(micros.with_timezone("UTC"))
+    }
+    fn strings<S: AsRef<str>>(values: impl Iterator<Item = Option<S>>) -> ArrayRef {
+        Arc::new(values.collect::<StringArray>())
+    }
+    fn floats(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+        Arc::new(values.collect::<Float64Array>())
+    }
+    fn ints(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+        Arc::new(values.collect::<Int64Array>())
+    }
+    fn bools(values: impl Iterator<Item = bool>) -> ArrayRef {
+        Arc::new(values.map(Some).collect::<BooleanArray>())
+    }
+    fn json(value: &impl serde::Serialize) -> Option<String> {
+        serde_json::to_string(value).ok()
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+
+    let sessions: Vec<Session> = store.get_sessions().unwrap_or_default().into_iter().map(|(s, _)| s).collect();
+
+    written.push(write_chunked(dir, "snapshots", RECORD_CHUNK_ROWS, |f| store.for_each_snapshot(start, end, f), |s: &[WifiSnapshot]| vec![
+        ("id", strings(s.iter().map(|s| Some(&s.id)))),
+        ("timestamp", times(s.iter().map(|s| Some(s.timestamp)))),
+        ("session_id", strings(s.iter().map(|s| session_at(&sessions, s.timestamp).map(|s| s.id.as_str())))),
+        ("ssid", strings(s.iter().map(|s| s.wifi_info.as_ref().map(|w| &w.ssid)))),
+        ("bssid", strings(s.iter().map(|s| s.wifi_info.as_ref().map(|w| &w.bssid)))),
+        ("band", strings(s.iter().map(|s| s.wifi_info.as_ref().map(|w| w.band.label())))),
+        ("channel", ints(s.iter().map(|s| s.wifi_info.as_ref().map(|w| w.channel as i64)))),
+        ("signal_dbm", ints(s.iter().map(|s| s.wifi_info.as_ref().map(|w| w.signal_strength_dbm as i64)))),
+        ("signal_quality_percent", ints(s.iter().map(|s| s.wifi_info.as_ref().map(|w| w.signal_quality_percent as i64)))),
+        ("link_speed_mbps", ints(s.iter().map(|s| s.wifi_info.as_ref().map(|w| w.link_speed_mbps as i64)))),
+        ("connected", bools(s.iter().map(|s| s.connectivity.is_connected))),
+        ("router_reachable", bools(s.iter().map(|s| s.connectivity.router_reachable))),
+        ("internet_reachable", bools(s.iter().map(|s| s.connectivity.internet_reachable))),
+        ("router_latency_ms", floats(s.iter().map(|s| s.latency.router_latency_ms))),
+        ("latency_avg_ms", floats(s.iter().map(|s| s.latency.average_latency_ms))),
+        ("latency_min_ms", floats(s.iter().map(|s| s.latency.min_latency_ms))),
+        ("latency_max_ms", floats(s.iter().map(|s| s.latency.max_latency_ms))),
+        ("jitter_ms", floats(s.iter().map(|s| s.latency.jitter_ms))),
+        ("packet_loss_percent", floats(s.iter().map(|s| Some(s.latency.packet_loss_percent)))),
+        ("mos", floats(s.iter().map(|s| s.latency.mos))),
+        ("dns_resolution_ms", floats(s.iter().map(|s| s.dns_metrics.average_resolution_time_ms))),
+        ("dns_failures", ints(s.iter().map(|s| Some(s.dns_metrics.failures as i64)))),
+        ("throughput_rx_bytes_per_sec", floats(s.iter().map(|s| s.system_info.throughput_rx_bytes_per_sec))),
+        ("throughput_tx_bytes_per_sec", floats(s.iter().map(|s| s.system_info.throughput_tx_bytes_per_sec))),
+        ("interval_secs", ints(s.iter().map(|s| s.interval_secs.map(|i| i as i64)))),
+        ("other_network", bools(s.iter().map(|s| s.other_network))),
+        ("event_count", ints(s.iter().map(|s| Some(s.events.len() as i64)))),
+        // Everything else, for fields without a column of their own
+        ("data", strings(s.iter().map(json))),
+    ])?);
+
+    written.push(write_chunked(dir, "events", RECORD_CHUNK_ROWS, |f| store.for_each_event(start, end, f), |e: &[NetworkEvent]| vec![
+        ("id", strings(e.iter().map(|e| Some(&e.id)))),
+        ("timestamp", times(e.iter().map(|e| Some(e.timestamp)))),
+        ("event_type", strings(e.iter().map(|e| Some(format!("{:?}", e.event_type))))),
+        ("severity", strings(e.iter().map(|e| Some(format!("{:?}", e.severity))))),
+        ("description", strings(e.iter().map(|e| Some(&e.description)))),
+        ("incident_id", strings(e.iter().map(|e| e.details["incident_id"].as_str()))),
+        ("details", strings(e.iter().map(|e| json(&e.details)))),
+    ])?);
+
+    written.push(write_chunked(
+        dir,
+        "timeseries",
+        POINT_CHUNK_ROWS,
+        |f| store.for_each_timeseries_point(start, end, &mut |timestamp, metric, value| f((timestamp, metric, value))),
+        |points: &[(DateTime<Utc>, String, f64)]| vec![
+            ("timestamp", times(points.iter().map(|(t, _, _)| Some(*t)))),
+            ("metric", strings(points.iter().map(|(_, m, _)| Some(m)))),
+            ("value", floats(points.iter().map(|(_, _, v)| Some(*v)))),
+        ],
+    )?);
+
+    // Databases from before incidents, pauses or sessions existed lack those tables
+    let incidents = store.get_incidents(start, end, None).unwrap_or_default();
+    let i = &incidents;
+    written.push(write_table(dir, "incidents", vec![
+        ("id", strings(i.iter().map(|i| Some(&i.id)))),
+        ("status", strings(i.iter().map(|i| Some(format!("{:?}", i.status))))),
+        ("started_at", times(i.iter().map(|i| Some(i.started_at)))),
+        ("ended_at", times(i.iter().map(|i| i.ended_at))),
+        ("duration_secs", ints(i.iter().map(|i| Some(i.duration_secs)))),
+        ("root_cause", strings(i.iter().map(|i| Some(i.root_cause.label())))),
+        ("severity", strings(i.iter().map(|i| Some(format!("{:?}", i.severity))))),
+        ("summary", strings(i.iter().map(|i| Some(&i.summary)))),
+        ("event_count", ints(i.iter().map(|i| Some(i.event_ids.len() as i64)))),
+        ("notes", strings(i.iter().map(|i| json(&i.notes)))),
+    ])?);
+
+    let pauses = store.get_pauses(start, end).unwrap_or_default();
+    let p = &pauses;
+    written.push(write_table(dir, "pauses", vec![
+        ("id", strings(p.iter().map(|p| Some(&p.id)))),
+        ("started_at", times(p.iter().map(|p| Some(p.started_at)))),
+        ("ended_at", times(p.iter().map(|p| p.ended_at))),
+        ("reason", strings(p.iter().map(|p| p.reason.as_ref()))),
+    ])?);
+
+    let annotations = annotations_oldest_first(store, start, end);
+    let a = &annotations;
+    written.push(write_table(dir, "annotations", vec![
+        ("id", strings(a.iter().map(|a| Some(&a.id)))),
+        ("timestamp", times(a.iter().map(|a| Some(a.timestamp)))),
+        ("text", strings(a.iter().map(|a| Some(&a.text)))),
+        ("created_at", times(a.iter().map(|a| Some(a.created_at)))),
+    ])?);
+
+    let in_range = |s: &&Session| {
+        let (from, to) = s.range();
+        start.is_none_or(|start| to.as_str() >= start) && end.is_none_or(|end| from.as_str() <= end)
+    };
+    let s: Vec<&Session> = sessions.iter().filter(in_range).collect();
+    written.push(write_table(dir, "sessions", vec![
+        ("id", strings(s.iter().map(|s| Some(&s.id)))),
+        ("started_at", times(s.iter().map(|s| Some(s.started_at)))),
+        ("ended_at", times(s.iter().map(|s| s.ended_at))),
+        ("host", strings(s.iter().map(|s| s.host.as_ref()))),
+        ("config", strings(s.iter().map(|s| json(&s.config)))),
+    ])?);
+
+    Ok(written)
+}
+
+/// Rows per record batch and row group for snapshots and events, which are up to several KB each
+#[cfg(feature = "parquet")]
+const RECORD_CHUNK_ROWS: usize = 2_000;
+/// Rows per record batch and row group for timeseries points
+#[cfg(feature = "parquet")]
+const POINT_CHUNK_ROWS: usize = 100_000;
+
+/// Write a table that fits in memory in one batch
+#[cfg(feature = "parquet")]
+fn write_table(dir: &Path, name: &str, columns: Vec<(&str, arrow::array::ArrayRef)>) -> anyhow::Result<PathBuf> {
+    let path = dir.join(format!("{}.parquet", name));
+    let mut writer = None;
+    write_batch(&path, &mut writer, columns)?;
+    if let Some(writer) = writer {
+        writer.close()?;
+    }
+    Ok(path)
+}
+
+/// Write the rows `visit` produces in batches of `chunk_rows`, turning each batch into
+/// columns with `columns`, so a table never has to fit in memory at once
+#[cfg(feature = "parquet")]
+fn write_chunked<T>(
+    dir: &Path,
+    name: &str,
+    chunk_rows: usize,
+    visit: impl FnOnce(&mut dyn FnMut(T) -> anyhow::Result<()>) -> anyhow::Result<usize>,
+    columns: impl Fn(&[T]) -> Vec<(&'static str, arrow::array::ArrayRef)>,
+) -> anyhow::Result<PathBuf> {
+    let path = dir.join(format!("{}.parquet", name));
+    let mut writer = None;
+    let mut rows = Vec::with_capacity(chunk_rows);
+    visit(&mut |row| {
+        rows.push(row);
+        if rows.len() == chunk_rows {
+            write_batch(&path, &mut writer, columns(&rows))?;
+            rows.clear();
+        }
+        Ok(())
+    })?;
+    // An empty table still gets a file with its schema
+    if !rows.is_empty() || writer.is_none() {
+        write_batch(&path, &mut writer, columns(&rows))?;
+    }
+    if let Some(writer) = writer {
+        writer.close()?;
+    }
+    Ok(path)
+}
+
+/// Append one record batch as a row group of its own, creating the file with the first one
+#[cfg(feature = "parquet")]
+fn write_batch(
+    path: &Path,
+    writer: &mut Option<parquet::arrow::ArrowWriter<std::fs::File>>,
+    columns: Vec<(&str, arrow::array::ArrayRef)>,
+) -> anyhow::Result<()> {
+    use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+    let batch = arrow::record_batch::RecordBatch::try_from_iter(columns)?;
+    let writer = match writer {
+        Some(writer) => writer,
+        None => {
+            let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+            writer.insert(ArrowWriter::try_new(std::fs::File::create(path)?, batch.schema(), Some(properties))?)
+        }
+    };
+    writer.write(&batch)?;
+    // The writer holds a row group in memory until it is flushed
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn write_parquet(_store: &dyn MetricsStore, _dir: &Path, _start: Option<&str>, _end: Option<&str>) -> anyhow::Result<Vec<PathBuf>> {
+    anyhow::bail!("built without the `parquet` feature; rebuild with `cargo build --release --features parquet`")
+}
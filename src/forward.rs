@@ -0,0 +1,203 @@
+use crate::metrics::{Incident, Session, WifiSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(30);
+/// Snapshots kept for retry while the collector is down; older ones are dropped first
+const MAX_BACKLOG: usize = 5000;
+/// Snapshots per request, keeping each one well under the aggregation server's body limit
+const FORWARD_BATCH: usize = 100;
+
+/// One request to a collector: the aggregation server's `/api/v1/ingest`, or any endpoint
+/// accepting the same JSON
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Upload {
+    /// The agent's host name; the server keeps each host's data apart under it
+    pub host: String,
+    /// The monitoring session the snapshots belong to
+    pub session: Session,
+    #[serde(default)]
+    pub snapshots: Vec<WifiSnapshot>,
+    /// Incidents opened or updated since the last upload
+    #[serde(default)]
+    pub incidents: Vec<Incident>,
+}
+
+/// A snapshot waiting to be forwarded, as kept in the spool file
+#[derive(Serialize, Deserialize)]
+struct Pending {
+    session: Session,
+    snapshot: WifiSnapshot,
+}
+
+/// Boxed, as both payloads are large and the channel holds many items
+enum Item {
+    Snapshot(Box<Pending>),
+    Incident(Box<(Session, Incident)>),
+}
+
+/// Posts each snapshot and incident to a remote collector from a background task, so a
+/// slow or unreachable collector never holds up monitoring. While it is unreachable,
+/// snapshots are also appended to a spool file, and ones a restart left there are sent first.
+#[derive(Clone)]
+pub struct Forwarder {
+    items: mpsc::UnboundedSender<Item>,
+    session: Session,
+}
+
+impl Forwarder {
+    /// Start forwarding. `url` is the full endpoint, e.g.
+    /// `https://wifi.example.com:8080/api/v1/ingest`; `token` is sent as `Authorization: Bearer <token>`
+    pub fn spawn(url: String, token: Option<String>, spool: PathBuf, session: Session) -> Self {
+        let (items, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(url, token, spool, receiver));
+        Self { items, session }
+    }
+
+    pub fn send(&self, snapshot: &WifiSnapshot) {
+        let _ = self.items.send(Item::Snapshot(Box::new(Pending { session: self.session.clone(), snapshot: snapshot.clone() })));
+    }
+
+    pub fn send_incident(&self, incident: &Incident) {
+        let _ = self.items.send(Item::Incident(Box::new((self.session.clone(), incident.clone()))));
+    }
+}
+
+async fn run(url: String, token: Option<String>, spool: PathBuf, mut receiver: mpsc::UnboundedReceiver<Item>) {
+    let client = match reqwest::Client::builder().timeout(FORWARD_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Forwarding disabled: {}", e);
+            return;
+        }
+    };
+    info!("Forwarding snapshots to {}", url);
+
+    // Unsent snapshots, the first `spooled` of which are in the spool file, and the latest
+    // state of each unsent incident; retried with the next snapshot
+    let mut backlog = read_spool(&spool).await;
+    let mut spooled = backlog.len();
+    if spooled > 0 {
+        info!("Forwarding {} snapshots left in {:?} first", spooled, spool);
+    }
+    let mut incidents: Vec<Incident> = Vec::new();
+    let mut current: Option<Session> = None;
+    let mut failing = false;
+    loop {
+        while !backlog.is_empty() || !incidents.is_empty() {
+            let session = backlog.front().map(|p| p.session.clone());
+            let batch = backlog.iter()
+                .take(FORWARD_BATCH)
+                .take_while(|p| session.as_ref().is_some_and(|s| s.id == p.session.id))
+                .count();
+            // Incidents go out with the current session when no snapshots are waiting
+            let Some(session) = session.or_else(|| current.clone()) else {
+                break;
+            };
+            let upload = Upload {
+                host: session.host.clone().unwrap_or_else(|| "unknown".to_string()),
+                session,
+                snapshots: backlog.iter().take(batch).map(|p| p.snapshot.clone()).collect(),
+                incidents: incidents.clone(),
+            };
+            let mut request = client.post(&url).json(&upload);
+            if let Some(ref token) = token {
+                request = request.bearer_auth(token);
+            }
+            let sent = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    if failing {
+                        info!("Forwarding recovered, sending {} queued snapshots", backlog.len());
+                        failing = false;
+                    }
+                    true
+                }
+                // Retrying won't fix a malformed upload or bad credentials
+                Ok(response) if response.status().is_client_error() && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    warn!("{} rejected {} snapshots ({}): {}", url, batch, status, body.trim());
+                    true
+                }
+                result => {
+                    if !failing {
+                        let reason = match result {
+                            Ok(response) => response.status().to_string(),
+                            Err(e) => e.to_string(),
+                        };
+                        warn!("Forwarding to {} failed, buffering up to {} snapshots in {:?}: {}", url, MAX_BACKLOG, spool, reason);
+                        failing = true;
+                    }
+                    false
+                }
+            };
+            if !sent {
+                // Keep what can't be sent across a restart
+                match append_spool(&spool, backlog.range(spooled..)).await {
+                    Ok(()) => spooled = backlog.len(),
+                    Err(e) => warn!("Failed to write the forwarding spool {:?}: {}", spool, e),
+                }
+                break;
+            }
+            backlog.drain(..batch);
+            spooled = spooled.saturating_sub(batch);
+            incidents.clear();
+            if backlog.is_empty() && spooled == 0 {
+                let _ = tokio::fs::remove_file(&spool).await;
+            }
+        }
+
+        let Some(item) = receiver.recv().await else {
+            break;
+        };
+        let mut queue = |item| match item {
+            Item::Snapshot(pending) => backlog.push_back(*pending),
+            Item::Incident(incident) => {
+                let (session, incident) = *incident;
+                current = Some(session);
+                incidents.retain(|i| i.id != incident.id);
+                incidents.push(incident);
+            }
+        };
+        queue(item);
+        while let Ok(item) = receiver.try_recv() {
+            queue(item);
+        }
+        if backlog.len() > MAX_BACKLOG {
+            let dropped = backlog.len() - MAX_BACKLOG;
+            backlog.drain(..dropped);
+            spooled = spooled.saturating_sub(dropped);
+        }
+    }
+}
+
+/// Snapshots a previous run couldn't forward, newest `MAX_BACKLOG`; unreadable lines are skipped
+async fn read_spool(spool: &PathBuf) -> VecDeque<Pending> {
+    let Ok(contents) = tokio::fs::read_to_string(spool).await else {
+        return VecDeque::new();
+    };
+    let mut pending: VecDeque<Pending> = contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    if pending.len() > MAX_BACKLOG {
+        pending.drain(..pending.len() - MAX_BACKLOG);
+    }
+    pending
+}
+
+async fn append_spool(spool: &PathBuf, pending: impl Iterator<Item = &Pending>) -> anyhow::Result<()> {
+    let mut lines = String::new();
+    for p in pending {
+        lines.push_str(&serde_json::to_string(p)?);
+        lines.push('\n');
+    }
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(spool).await?;
+    file.write_all(lines.as_bytes()).await?;
+    Ok(())
+}
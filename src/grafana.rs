@@ -0,0 +1,214 @@
+//! Grafana JSON datasource endpoints under `/api/v1/grafana`, so Grafana can chart the
+//! stored timeseries and show events as annotations without an export step. Add a "JSON"
+//! datasource (simpod-json-datasource) with the URL `http://<host>:8080/api/v1/grafana`.
+//!
+//! Every timeseries metric is a target. The `events` target returns the range's events as
+//! a table instead.
+
+use crate::metrics::{EventSeverity, NetworkEvent};
+use crate::storage::{BucketSize, MetricsStore};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// The target that returns events as a table rather than a metric
+const EVENTS_TARGET: &str = "events";
+
+/// Points per series when Grafana doesn't say how many it can draw
+const DEFAULT_MAX_POINTS: u32 = 1000;
+
+/// Most events returned as annotations or table rows per request
+const MAX_EVENTS: u32 = 1000;
+
+const SEVERITIES: [EventSeverity; 4] = [
+    EventSeverity::Info,
+    EventSeverity::Warning,
+    EventSeverity::Error,
+    EventSeverity::Critical,
+];
+
+type Store = Arc<dyn MetricsStore>;
+
+/// `/api/v1/grafana` and the datasource calls below it
+pub fn routes<S: Clone + Send + Sync + 'static>(store: Store) -> Router<S> {
+    Router::new()
+        .route("/api/v1/grafana", get(test_handler))
+        // Grafana appends paths to the datasource URL, and some versions add a slash first
+        .route("/api/v1/grafana/", get(test_handler))
+        .route("/api/v1/grafana/search", post(search_handler))
+        .route("/api/v1/grafana/metrics", post(metrics_handler))
+        .route("/api/v1/grafana/query", post(query_handler))
+        .route("/api/v1/grafana/annotations", post(annotations_handler))
+        .with_state(store)
+}
+
+#[derive(Deserialize)]
+struct Range {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+impl Range {
+    fn bounds(&self) -> (String, String) {
+        (self.from.to_rfc3339(), self.to.to_rfc3339())
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct SearchRequest {
+    /// What has been typed so far; metrics starting with it are listed
+    #[serde(default)]
+    target: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryRequest {
+    range: Range,
+    max_data_points: Option<u32>,
+    targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    hide: bool,
+}
+
+#[derive(Deserialize)]
+struct AnnotationsRequest {
+    range: Range,
+    #[serde(default)]
+    annotation: AnnotationQuery,
+}
+
+#[derive(Deserialize, Default)]
+struct AnnotationQuery {
+    /// A severity (events at least that severe) or an event type; empty means warning
+    #[serde(default)]
+    query: String,
+}
+
+fn internal(e: anyhow::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "success": false, "error": e.to_string() })),
+    ).into_response()
+}
+
+/// Grafana's "Save & test"
+async fn test_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "success": true, "status": "ok" }))
+}
+
+/// Target names for the query editor: `events` and the metrics starting with what was typed
+async fn search_handler(State(store): State<Store>, body: Option<Json<SearchRequest>>) -> Response {
+    let prefix = body.map(|Json(b)| b.target).unwrap_or_default();
+    match targets(store.as_ref(), &prefix) {
+        Ok(names) => Json(names).into_response(),
+        Err(e) => internal(e),
+    }
+}
+
+/// The same names as `{label, value}` pairs, for newer plugin versions
+async fn metrics_handler(State(store): State<Store>, body: Option<Json<SearchRequest>>) -> Response {
+    let prefix = body.map(|Json(b)| b.target).unwrap_or_default();
+    match targets(store.as_ref(), &prefix) {
+        Ok(names) => Json(names.into_iter()
+            .map(|name| serde_json::json!({ "label": name, "value": name }))
+            .collect::<Vec<_>>()).into_response(),
+        Err(e) => internal(e),
+    }
+}
+
+fn targets(store: &dyn MetricsStore, prefix: &str) -> anyhow::Result<Vec<String>> {
+    let mut names = store.get_metric_names(prefix)?;
+    if EVENTS_TARGET.starts_with(prefix) {
+        names.insert(0, EVENTS_TARGET.to_string());
+    }
+    Ok(names)
+}
+
+/// One series of `[value, epoch ms]` pairs per metric target, averaged over buckets sized
+/// to Grafana's `maxDataPoints`, or the events table for `events`
+async fn query_handler(State(store): State<Store>, Json(request): Json<QueryRequest>) -> Response {
+    let (start, end) = request.range.bounds();
+    let max_points = request.max_data_points.filter(|p| *p > 0).unwrap_or(DEFAULT_MAX_POINTS);
+    let mut results = Vec::new();
+    for target in request.targets.iter().filter(|t| !t.hide && !t.target.is_empty()) {
+        if target.target == EVENTS_TARGET {
+            match store.get_events(Some(&start), Some(&end), None, None, Some(MAX_EVENTS), 0) {
+                Ok(events) => results.push(events_table(&events)),
+                Err(e) => return internal(e),
+            }
+            continue;
+        }
+        match store.get_timeseries_buckets(&target.target, Some(&start), Some(&end), BucketSize::MaxPoints(max_points)) {
+            Ok((_, buckets)) => results.push(serde_json::json!({
+                "target": target.target,
+                "datapoints": buckets.iter()
+                    .map(|b| serde_json::json!([b.avg, b.timestamp.timestamp_millis()]))
+                    .collect::<Vec<_>>()
+            })),
+            Err(e) => return internal(e),
+        }
+    }
+    Json(results).into_response()
+}
+
+fn events_table(events: &[NetworkEvent]) -> serde_json::Value {
+    serde_json::json!({
+        "type": "table",
+        "columns": [
+            { "text": "Time", "type": "time" },
+            { "text": "Type", "type": "string" },
+            { "text": "Severity", "type": "string" },
+            { "text": "Description", "type": "string" },
+        ],
+        "rows": events.iter().map(|e| serde_json::json!([
+            e.timestamp.timestamp_millis(),
+            format!("{:?}", e.event_type),
+            format!("{:?}", e.severity),
+            e.description,
+        ])).collect::<Vec<_>>()
+    })
+}
+
+/// Events in the range as annotations, tagged with their type and severity. The
+/// annotation's query picks them: a severity shows events at least that severe, anything
+/// else is taken as an event type, and an empty query shows warnings and worse.
+async fn annotations_handler(State(store): State<Store>, Json(request): Json<AnnotationsRequest>) -> Response {
+    let (start, end) = request.range.bounds();
+    let query = request.annotation.query.trim();
+    let (min_severity, event_type) = match query {
+        "" => (EventSeverity::Warning, None),
+        query => match query.parse::<EventSeverity>() {
+            Ok(severity) => (severity, None),
+            Err(_) => (EventSeverity::Info, Some(query)),
+        },
+    };
+    // One query per severity, so frequent info events don't crowd the rest out of the limit
+    let mut events = Vec::new();
+    for severity in SEVERITIES.iter().filter(|s| **s >= min_severity) {
+        let severity = format!("{:?}", severity);
+        match store.get_events(Some(&start), Some(&end), Some(&severity), event_type, Some(MAX_EVENTS), 0) {
+            Ok(found) => events.extend(found),
+            Err(e) => return internal(e),
+        }
+    }
+    Json(events.iter()
+        .map(|e| serde_json::json!({
+            "time": e.timestamp.timestamp_millis(),
+            "title": format!("{:?}", e.event_type),
+            "text": e.description,
+            "tags": [format!("{:?}", e.event_type), format!("{:?}", e.severity)],
+        }))
+        .collect::<Vec<_>>()).into_response()
+}
@@ -0,0 +1,403 @@
+//! GraphQL view of the stored data at `/graphql`, for dashboards that want several kinds of
+//! data, and only some of their fields, in one request. Opening `/graphql` in a browser
+//! shows GraphiQL to explore the schema.
+//!
+//! Snapshots expose their commonly charted fields as typed ones; `raw` holds the whole
+//! snapshot as JSON for everything else.
+
+use crate::metrics::{NetworkEvent, PeriodStatistics, PingResult, WifiInfo, WifiSnapshot};
+use crate::storage::{self, BucketSize, MetricsStore};
+use crate::web;
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Json, Object, Result, Schema, SimpleObject};
+use async_graphql_axum::GraphQL;
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Deepest nesting a query may use; the schema itself is at most four levels deep
+const MAX_DEPTH: usize = 8;
+
+/// Most fields a query may ask for, counting each field of each list item once
+const MAX_COMPLEXITY: usize = 5000;
+
+type Store = Arc<dyn MetricsStore>;
+
+/// `/graphql`: GraphiQL on GET, queries on POST
+pub fn routes<S: Clone + Send + Sync + 'static>(store: Store) -> Router<S> {
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(store)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish();
+    Router::new().route("/graphql", get(graphiql).post_service(GraphQL::new(schema)))
+}
+
+async fn graphiql() -> impl IntoResponse {
+    // Relative, so it also works on an aggregation server's /hosts/<name>/graphql
+    Html(GraphiQLSource::build().endpoint("graphql").finish())
+}
+
+fn store<'a>(ctx: &Context<'a>) -> &'a Store {
+    ctx.data_unchecked::<Store>()
+}
+
+fn range(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> (Option<String>, Option<String>) {
+    (start.map(|t| t.to_rfc3339()), end.map(|t| t.to_rfc3339()))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The latest snapshot
+    async fn current(&self, ctx: &Context<'_>) -> Result<Option<Snapshot>> {
+        Ok(store(ctx).get_latest_snapshot()?.map(Snapshot))
+    }
+
+    /// Snapshots in the range, newest first; `limit` is capped at 1000
+    async fn snapshots(
+        &self,
+        ctx: &Context<'_>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        limit: Option<u32>,
+        #[graphql(default)] offset: u64,
+    ) -> Result<Vec<Snapshot>> {
+        let (start, end) = range(start, end);
+        let limit = limit.unwrap_or(web::DEFAULT_PAGE_SIZE).clamp(1, web::MAX_PAGE_SIZE);
+        let snapshots = store(ctx).get_snapshots(start.as_deref(), end.as_deref(), Some(limit), offset)?;
+        Ok(snapshots.into_iter().map(Snapshot).collect())
+    }
+
+    /// Events in the range, newest first, optionally of one severity (info, warning, error
+    /// or critical) or type (e.g. `ConnectionDropped`); `limit` is capped at 1000
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        severity: Option<String>,
+        event_type: Option<String>,
+        limit: Option<u32>,
+        #[graphql(default)] offset: u64,
+    ) -> Result<Vec<Event>> {
+        let (start, end) = range(start, end);
+        let limit = limit.unwrap_or(web::DEFAULT_PAGE_SIZE).clamp(1, web::MAX_PAGE_SIZE);
+        let events = store(ctx).get_events(start.as_deref(), end.as_deref(), severity.as_deref(), event_type.as_deref(), Some(limit), offset)?;
+        Ok(events.into_iter().map(Event).collect())
+    }
+
+    /// How often each event type occurred in the range, most frequent first
+    async fn event_counts(&self, ctx: &Context<'_>, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Result<Vec<EventCount>> {
+        let (start, end) = range(start, end);
+        let counts = store(ctx).get_event_counts_by_type(start.as_deref(), end.as_deref())?;
+        Ok(counts.into_iter().map(|(event_type, count)| EventCount { event_type, count }).collect())
+    }
+
+    /// One metric's points, as `/api/v1/timeseries` returns them. `bucket` (e.g. `5m`) or
+    /// `maxPoints` aggregate them per bucket with min, max and sample counts.
+    async fn timeseries(
+        &self,
+        ctx: &Context<'_>,
+        metric: String,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        bucket: Option<String>,
+        max_points: Option<u32>,
+    ) -> Result<Timeseries> {
+        let (start, end) = range(start, end);
+        let size = match (bucket, max_points) {
+            (Some(bucket), _) => Some(BucketSize::Fixed(web::parse_bucket(&bucket).ok_or_else(|| {
+                format!("Invalid bucket {:?}, expected a size like 30s, 5m, 1h or 1d", bucket)
+            })?)),
+            (None, Some(points)) => Some(BucketSize::MaxPoints(points)),
+            (None, None) => None,
+        };
+        let store = store(ctx);
+        let (bucket_secs, points) = match size {
+            Some(size) => {
+                let (bucket, buckets) = store.get_timeseries_buckets(&metric, start.as_deref(), end.as_deref(), size)?;
+                let points = buckets.into_iter().map(|b| TimeseriesPoint {
+                    timestamp: b.timestamp,
+                    value: b.avg,
+                    min: Some(b.min),
+                    max: Some(b.max),
+                    samples: Some(b.samples),
+                }).collect();
+                (Some(bucket.num_milliseconds() as f64 / 1000.0), points)
+            }
+            None => {
+                let points = store.get_timeseries(&metric, start.as_deref(), end.as_deref())?;
+                (None, points.into_iter().filter_map(|(timestamp, value)| Some(TimeseriesPoint {
+                    timestamp: storage::parse_time(&timestamp)?,
+                    value,
+                    min: None,
+                    max: None,
+                    samples: None,
+                })).collect())
+            }
+        };
+        Ok(Timeseries { metric, bucket_secs, points })
+    }
+
+    /// Names of the stored metrics starting with `prefix`, e.g. `target_latency:`
+    async fn metrics(&self, ctx: &Context<'_>, #[graphql(default)] prefix: String) -> Result<Vec<String>> {
+        Ok(store(ctx).get_metric_names(&prefix)?)
+    }
+
+    /// Statistics for the range
+    async fn statistics(&self, ctx: &Context<'_>, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Result<PeriodStatistics> {
+        let (start, end) = range(start, end);
+        Ok(store(ctx).get_statistics(start.as_deref(), end.as_deref())?)
+    }
+}
+
+pub struct Snapshot(WifiSnapshot);
+
+#[Object]
+impl Snapshot {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    /// The connected network; null while disconnected
+    async fn wifi(&self) -> Option<Wifi> {
+        self.0.wifi_info.clone().map(Wifi)
+    }
+
+    async fn is_connected(&self) -> bool {
+        self.0.connectivity.is_connected
+    }
+
+    async fn router_reachable(&self) -> bool {
+        self.0.connectivity.router_reachable
+    }
+
+    async fn internet_reachable(&self) -> bool {
+        self.0.connectivity.internet_reachable
+    }
+
+    async fn http_response_time_ms(&self) -> Option<u64> {
+        self.0.connectivity.http_response_time_ms
+    }
+
+    /// Average round trip over all ping targets
+    async fn latency_avg_ms(&self) -> Option<f64> {
+        self.0.latency.average_latency_ms
+    }
+
+    async fn latency_min_ms(&self) -> Option<f64> {
+        self.0.latency.min_latency_ms
+    }
+
+    async fn latency_max_ms(&self) -> Option<f64> {
+        self.0.latency.max_latency_ms
+    }
+
+    async fn router_latency_ms(&self) -> Option<f64> {
+        self.0.latency.router_latency_ms
+    }
+
+    async fn jitter_ms(&self) -> Option<f64> {
+        self.0.latency.jitter_ms
+    }
+
+    async fn packet_loss_percent(&self) -> f64 {
+        self.0.latency.packet_loss_percent
+    }
+
+    /// Estimated call quality, 1 (bad) to 4.5 (best)
+    async fn mos(&self) -> Option<f64> {
+        self.0.latency.mos
+    }
+
+    /// Each ping target's results
+    async fn targets(&self) -> Vec<PingTarget> {
+        self.0.latency.targets.iter().cloned().map(PingTarget).collect()
+    }
+
+    async fn dns_resolution_ms(&self) -> Option<f64> {
+        self.0.dns_metrics.average_resolution_time_ms
+    }
+
+    async fn dns_failures(&self) -> u32 {
+        self.0.dns_metrics.failures
+    }
+
+    async fn interval_secs(&self) -> Option<u64> {
+        self.0.interval_secs
+    }
+
+    /// Access points seen, on neighbor scan cycles
+    async fn neighbor_count(&self) -> usize {
+        self.0.neighbors.len()
+    }
+
+    async fn events(&self) -> Vec<Event> {
+        self.0.events.iter().cloned().map(Event).collect()
+    }
+
+    /// The whole snapshot as `/api/v1/snapshots` returns it
+    async fn raw(&self) -> Json<WifiSnapshot> {
+        Json(self.0.clone())
+    }
+}
+
+pub struct Wifi(WifiInfo);
+
+#[Object]
+impl Wifi {
+    async fn ssid(&self) -> &str {
+        &self.0.ssid
+    }
+
+    async fn bssid(&self) -> &str {
+        &self.0.bssid
+    }
+
+    async fn signal_dbm(&self) -> i32 {
+        self.0.signal_strength_dbm
+    }
+
+    async fn signal_quality_percent(&self) -> u8 {
+        self.0.signal_quality_percent
+    }
+
+    async fn channel(&self) -> u32 {
+        self.0.channel
+    }
+
+    /// e.g. "5 GHz"
+    async fn band(&self) -> &str {
+        self.0.band.label()
+    }
+
+    async fn phy_type(&self) -> &str {
+        &self.0.phy_type
+    }
+
+    async fn link_speed_mbps(&self) -> u32 {
+        self.0.link_speed_mbps
+    }
+
+    async fn rx_rate_mbps(&self) -> Option<u32> {
+        self.0.rx_rate_mbps
+    }
+
+    async fn tx_rate_mbps(&self) -> Option<u32> {
+        self.0.tx_rate_mbps
+    }
+
+    async fn security_type(&self) -> &str {
+        &self.0.security_type
+    }
+
+    async fn gateway(&self) -> Option<&str> {
+        self.0.gateway.as_deref()
+    }
+}
+
+pub struct PingTarget(PingResult);
+
+#[Object]
+impl PingTarget {
+    async fn target(&self) -> &str {
+        &self.0.target
+    }
+
+    async fn packets_sent(&self) -> u32 {
+        self.0.packets_sent
+    }
+
+    async fn packets_received(&self) -> u32 {
+        self.0.packets_received
+    }
+
+    async fn packet_loss_percent(&self) -> f64 {
+        self.0.packet_loss_percent
+    }
+
+    async fn avg_ms(&self) -> Option<f64> {
+        self.0.avg_ms
+    }
+
+    async fn min_ms(&self) -> Option<f64> {
+        self.0.min_ms
+    }
+
+    async fn max_ms(&self) -> Option<f64> {
+        self.0.max_ms
+    }
+
+    async fn stddev_ms(&self) -> Option<f64> {
+        self.0.stddev_ms
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+}
+
+pub struct Event(NetworkEvent);
+
+#[Object]
+impl Event {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    /// e.g. `ConnectionDropped`
+    async fn event_type(&self) -> String {
+        format!("{:?}", self.0.event_type)
+    }
+
+    /// Info, Warning, Error or Critical
+    async fn severity(&self) -> String {
+        format!("{:?}", self.0.severity)
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    /// Event-specific details
+    async fn details(&self) -> Json<serde_json::Value> {
+        Json(self.0.details.clone())
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct EventCount {
+    event_type: String,
+    count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct TimeseriesPoint {
+    /// The point's time, or the start of its bucket
+    timestamp: DateTime<Utc>,
+    /// The point's value, or the average over its bucket
+    value: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    samples: Option<i64>,
+}
+
+#[derive(SimpleObject)]
+pub struct Timeseries {
+    metric: String,
+    /// Bucket size the points were aggregated over, with `bucket` or `maxPoints`
+    bucket_secs: Option<f64>,
+    points: Vec<TimeseriesPoint>,
+}
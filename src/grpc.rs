@@ -0,0 +1,286 @@
+//! gRPC API beside the web API, for agents that embed the monitor and speak gRPC. The
+//! service is defined in `proto/wifi_tracker.proto`: a server-streaming `StreamSnapshots`
+//! and query calls mirroring `/api/v1`. It takes the web API's credentials.
+
+use crate::auth::WebAuth;
+use crate::monitor::MonitorHandle;
+use crate::shutdown::ShutdownHandle;
+use crate::storage::MetricsStore;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Serve the gRPC API on `addr` until shutdown, when given
+#[cfg(not(feature = "grpc"))]
+pub fn spawn(
+    addr: Option<SocketAddr>,
+    _store: Arc<dyn MetricsStore>,
+    _monitor: Option<MonitorHandle>,
+    _auth: WebAuth,
+    _shutdown: ShutdownHandle,
+) -> anyhow::Result<Option<JoinHandle<()>>> {
+    match addr {
+        Some(_) => anyhow::bail!("--grpc-port needs a build with the `grpc` feature; rebuild with `cargo build --release --features grpc`"),
+        None => Ok(None),
+    }
+}
+
+/// Serve the gRPC API on `addr` until shutdown, when given
+#[cfg(feature = "grpc")]
+pub fn spawn(
+    addr: Option<SocketAddr>,
+    store: Arc<dyn MetricsStore>,
+    monitor: Option<MonitorHandle>,
+    auth: WebAuth,
+    shutdown: ShutdownHandle,
+) -> anyhow::Result<Option<JoinHandle<()>>> {
+    let Some(addr) = addr else {
+        return Ok(None);
+    };
+    let service = service::Service { store, monitor, shutdown: shutdown.clone() };
+    let check = move |request: tonic::Request<()>| {
+        if auth.allows_headers(&request.metadata().clone().into_headers()) {
+            Ok(request)
+        } else {
+            Err(tonic::Status::unauthenticated("Authentication required"))
+        }
+    };
+    let server = tonic::transport::Server::builder()
+        .add_service(proto::wifi_tracker_server::WifiTrackerServer::with_interceptor(service, check));
+    tracing::info!("gRPC API listening on {}", addr);
+    Ok(Some(tokio::spawn(async move {
+        if let Err(e) = server.serve_with_shutdown(addr, async move { shutdown.wait().await }).await {
+            tracing::error!("gRPC server error: {}", e);
+        }
+    })))
+}
+
+#[cfg(feature = "grpc")]
+mod proto {
+    tonic::include_proto!("wifi_tracker.v1");
+}
+
+#[cfg(feature = "grpc")]
+mod service {
+    use super::proto::{self, wifi_tracker_server::WifiTracker};
+    use crate::metrics::{NetworkEvent, PeriodStatistics, WifiSnapshot};
+    use crate::monitor::MonitorHandle;
+    use crate::shutdown::ShutdownHandle;
+    use crate::storage::{BucketSize, MetricsStore};
+    use crate::web;
+    use futures_util::stream::{self, Stream, StreamExt};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use tokio::sync::broadcast;
+    use tonic::{Request, Response, Status};
+    use tracing::debug;
+
+    pub struct Service {
+        pub store: Arc<dyn MetricsStore>,
+        pub monitor: Option<MonitorHandle>,
+        pub shutdown: ShutdownHandle,
+    }
+
+    fn internal(e: anyhow::Error) -> Status {
+        Status::internal(e.to_string())
+    }
+
+    fn page_size(limit: Option<u32>) -> u32 {
+        limit.unwrap_or(web::DEFAULT_PAGE_SIZE).clamp(1, web::MAX_PAGE_SIZE)
+    }
+
+    fn snapshot(s: &WifiSnapshot) -> proto::Snapshot {
+        let wifi = s.wifi_info.as_ref();
+        proto::Snapshot {
+            id: s.id.clone(),
+            timestamp: s.timestamp.to_rfc3339(),
+            is_connected: s.connectivity.is_connected,
+            router_reachable: s.connectivity.router_reachable,
+            internet_reachable: s.connectivity.internet_reachable,
+            ssid: wifi.map(|w| w.ssid.clone()),
+            bssid: wifi.map(|w| w.bssid.clone()),
+            signal_dbm: wifi.map(|w| w.signal_strength_dbm),
+            channel: wifi.map(|w| w.channel),
+            latency_avg_ms: s.latency.average_latency_ms,
+            jitter_ms: s.latency.jitter_ms,
+            packet_loss_percent: s.latency.packet_loss_percent,
+            mos: s.latency.mos,
+            events: s.events.iter().map(event).collect(),
+            json: serde_json::to_string(s).unwrap_or_default(),
+        }
+    }
+
+    fn event(e: &NetworkEvent) -> proto::Event {
+        proto::Event {
+            id: e.id.clone(),
+            timestamp: e.timestamp.to_rfc3339(),
+            event_type: format!("{:?}", e.event_type),
+            severity: format!("{:?}", e.severity),
+            description: e.description.clone(),
+            details_json: e.details.to_string(),
+        }
+    }
+
+    fn statistics(s: &PeriodStatistics) -> proto::Statistics {
+        proto::Statistics {
+            start_time: s.start_time.to_rfc3339(),
+            end_time: s.end_time.to_rfc3339(),
+            sample_count: s.sample_count,
+            signal_strength_avg_dbm: s.signal_strength_avg_dbm,
+            signal_strength_min_dbm: s.signal_strength_min_dbm,
+            signal_strength_max_dbm: s.signal_strength_max_dbm,
+            latency_avg_ms: s.latency_avg_ms,
+            latency_p95_ms: s.latency_p95_ms,
+            latency_p99_ms: s.latency_p99_ms,
+            jitter_avg_ms: s.jitter_avg_ms,
+            mos_avg: s.mos_avg,
+            packet_loss_avg_percent: s.packet_loss_avg_percent,
+            connection_uptime_percent: s.connection_uptime_percent,
+            internet_uptime_percent: s.internet_uptime_percent,
+            total_disconnections: s.total_disconnections,
+            warning_events: s.warning_events,
+            error_events: s.error_events,
+            critical_events: s.critical_events,
+            json: serde_json::to_string(s).unwrap_or_default(),
+        }
+    }
+
+    type SnapshotStream = Pin<Box<dyn Stream<Item = Result<proto::Snapshot, Status>> + Send>>;
+
+    #[tonic::async_trait]
+    impl WifiTracker for Service {
+        type StreamSnapshotsStream = SnapshotStream;
+
+        /// Ends at shutdown, so open streams don't hold up the server's graceful stop
+        async fn stream_snapshots(&self, request: Request<proto::StreamSnapshotsRequest>) -> Result<Response<SnapshotStream>, Status> {
+            let Some(ref monitor) = self.monitor else {
+                return Err(Status::unavailable("Monitoring is not running in this process"));
+            };
+            let updates = monitor.subscribe();
+            let latest = match request.into_inner().include_latest {
+                true => self.store.get_latest_snapshot().map_err(internal)?,
+                false => None,
+            };
+            let live = stream::unfold((updates, self.shutdown.clone()), |(mut updates, shutdown)| async move {
+                loop {
+                    tokio::select! {
+                        update = updates.recv() => match update {
+                            Ok(s) => return Some((Ok(snapshot(&s)), (updates, shutdown))),
+                            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                                debug!("gRPC snapshot stream fell {} snapshots behind", missed);
+                            }
+                            Err(broadcast::error::RecvError::Closed) => return None,
+                        },
+                        _ = shutdown.wait() => return None,
+                    }
+                }
+            });
+            let stream = stream::iter(latest.map(|s| Ok(snapshot(&s)))).chain(live);
+            Ok(Response::new(Box::pin(stream)))
+        }
+
+        async fn get_current(&self, _request: Request<proto::GetCurrentRequest>) -> Result<Response<proto::Snapshot>, Status> {
+            match self.store.get_latest_snapshot().map_err(internal)? {
+                Some(s) => Ok(Response::new(snapshot(&s))),
+                None => Err(Status::not_found("No data collected yet")),
+            }
+        }
+
+        async fn list_snapshots(&self, request: Request<proto::ListSnapshotsRequest>) -> Result<Response<proto::ListSnapshotsResponse>, Status> {
+            let request = request.into_inner();
+            let (start, end) = (request.start.as_deref(), request.end.as_deref());
+            let snapshots = self.store
+                .get_snapshots(start, end, Some(page_size(request.limit)), request.offset)
+                .map_err(internal)?;
+            let total = self.store.count_snapshots(start, end).map_err(internal)?;
+            Ok(Response::new(proto::ListSnapshotsResponse {
+                snapshots: snapshots.iter().map(snapshot).collect(),
+                total,
+            }))
+        }
+
+        async fn list_events(&self, request: Request<proto::ListEventsRequest>) -> Result<Response<proto::ListEventsResponse>, Status> {
+            let request = request.into_inner();
+            let (start, end) = (request.start.as_deref(), request.end.as_deref());
+            let (severity, event_type) = (request.severity.as_deref(), request.event_type.as_deref());
+            let events = self.store
+                .get_events(start, end, severity, event_type, Some(page_size(request.limit)), request.offset)
+                .map_err(internal)?;
+            let total = self.store.count_events(start, end, severity, event_type).map_err(internal)?;
+            Ok(Response::new(proto::ListEventsResponse {
+                events: events.iter().map(event).collect(),
+                total,
+            }))
+        }
+
+        async fn get_timeseries(&self, request: Request<proto::GetTimeseriesRequest>) -> Result<Response<proto::GetTimeseriesResponse>, Status> {
+            let request = request.into_inner();
+            let (start, end) = (request.start.as_deref(), request.end.as_deref());
+            let size = match (request.bucket.as_deref(), request.max_points) {
+                (Some(bucket), _) => match web::parse_bucket(bucket) {
+                    Some(bucket) => Some(BucketSize::Fixed(bucket)),
+                    None => return Err(Status::invalid_argument(format!(
+                        "Invalid bucket {:?}, expected a size like 30s, 5m, 1h or 1d", bucket
+                    ))),
+                },
+                (None, Some(points)) => Some(BucketSize::MaxPoints(points)),
+                (None, None) => None,
+            };
+            let (bucket_secs, points) = match size {
+                Some(size) => {
+                    let (bucket, buckets) = self.store
+                        .get_timeseries_buckets(&request.metric, start, end, size)
+                        .map_err(internal)?;
+                    let points = buckets.into_iter().map(|b| proto::TimeseriesPoint {
+                        timestamp: b.timestamp.to_rfc3339(),
+                        value: b.avg,
+                        min: Some(b.min),
+                        max: Some(b.max),
+                        samples: Some(b.samples),
+                    }).collect();
+                    (Some(bucket.num_milliseconds() as f64 / 1000.0), points)
+                }
+                None => {
+                    let points = self.store.get_timeseries(&request.metric, start, end).map_err(internal)?;
+                    (None, points.into_iter().map(|(timestamp, value)| proto::TimeseriesPoint {
+                        timestamp,
+                        value,
+                        ..Default::default()
+                    }).collect())
+                }
+            };
+            Ok(Response::new(proto::GetTimeseriesResponse { metric: request.metric, bucket_secs, points }))
+        }
+
+        async fn get_statistics(&self, request: Request<proto::RangeRequest>) -> Result<Response<proto::Statistics>, Status> {
+            let request = request.into_inner();
+            let stats = self.store
+                .get_statistics(request.start.as_deref(), request.end.as_deref())
+                .map_err(internal)?;
+            Ok(Response::new(statistics(&stats)))
+        }
+
+        async fn get_event_counts(&self, request: Request<proto::RangeRequest>) -> Result<Response<proto::GetEventCountsResponse>, Status> {
+            let request = request.into_inner();
+            let counts = self.store
+                .get_event_counts_by_type(request.start.as_deref(), request.end.as_deref())
+                .map_err(internal)?;
+            Ok(Response::new(proto::GetEventCountsResponse {
+                counts: counts.into_iter().map(|(event_type, count)| proto::EventCount { event_type, count }).collect(),
+            }))
+        }
+
+        async fn list_sessions(&self, _request: Request<proto::ListSessionsRequest>) -> Result<Response<proto::ListSessionsResponse>, Status> {
+            let sessions = self.store.get_sessions().map_err(internal)?;
+            Ok(Response::new(proto::ListSessionsResponse {
+                sessions: sessions.into_iter().map(|(session, snapshot_count)| proto::Session {
+                    id: session.id,
+                    started_at: session.started_at.to_rfc3339(),
+                    ended_at: session.ended_at.map(|t| t.to_rfc3339()),
+                    host: session.host,
+                    snapshot_count,
+                }).collect(),
+            }))
+        }
+    }
+}
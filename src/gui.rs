@@ -1,34 +1,45 @@
+use crate::shutdown::ShutdownHandle;
 use anyhow::Result;
+use std::time::{Duration, Instant};
 use tracing::info;
 use tao::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    platform::run_return::EventLoopExtRunReturn,
     window::WindowBuilder,
     dpi::LogicalSize,
 };
 use wry::WebViewBuilder;
 use rfd::MessageDialog;
 
-pub fn launch_gui(port: u16) -> Result<()> {
-    info!("Launching GUI window on port {}", port);
+/// How often the window checks whether shutdown was triggered elsewhere (e.g. Ctrl+C)
+const SHUTDOWN_POLL: Duration = Duration::from_millis(250);
 
-    let event_loop = EventLoop::new();
+/// Show the dashboard in a window until the user closes it or shutdown is triggered.
+/// Closing the window triggers shutdown.
+pub fn launch_gui(url: &str, shutdown: ShutdownHandle) -> Result<()> {
+    info!("Launching GUI window for {}", url);
+
+    let mut event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("WiFi Stability Tracker")
         .with_inner_size(LogicalSize::new(1400, 900))
         .with_resizable(true)
         .build(&event_loop)?;
 
-    let url = format!("http://localhost:{}", port);
-    
     let _webview = WebViewBuilder::new(&window)
-        .with_url(&url)
+        .with_url(url)
         .build()?;
 
     info!("GUI window created, loading dashboard from {}", url);
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+    event_loop.run_return(|event, _, control_flow| {
+        *control_flow = ControlFlow::WaitUntil(Instant::now() + SHUTDOWN_POLL);
+
+        if shutdown.is_triggered() {
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
 
         match event {
             Event::WindowEvent {
@@ -44,10 +55,8 @@ pub fn launch_gui(port: u16) -> Result<()> {
 
                 if result == rfd::MessageDialogResult::Yes {
                     info!("User confirmed exit - shutting down");
+                    shutdown.trigger();
                     *control_flow = ControlFlow::Exit;
-                    
-                    // Force process exit to stop all background threads
-                    std::process::exit(0);
                 } else {
                     info!("User canceled exit");
                 }
@@ -55,4 +64,6 @@ pub fn launch_gui(port: u16) -> Result<()> {
             _ => {}
         }
     });
+
+    Ok(())
 }
@@ -0,0 +1,147 @@
+use crate::metrics::*;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long the connection must stay healthy before an incident is resolved, so
+/// a flapping connection is one incident rather than many
+const QUIET_PERIOD_SECS: i64 = 60;
+
+/// Groups the events of consecutive bad snapshots into incidents
+pub struct IncidentTracker {
+    open: Option<Incident>,
+    /// First healthy snapshot since the last bad one
+    recovered_at: Option<DateTime<Utc>>,
+    /// Bad snapshots of the open incident per failing layer
+    cause_counts: HashMap<IncidentCause, u32>,
+}
+
+impl IncidentTracker {
+    /// Start tracking, continuing an incident left open by a previous run
+    pub fn new(open: Option<Incident>) -> Self {
+        let mut cause_counts = HashMap::new();
+        if let Some(ref incident) = open {
+            cause_counts.insert(incident.root_cause, 1);
+        }
+        Self {
+            open,
+            recovered_at: None,
+            cause_counts,
+        }
+    }
+
+    /// Fold a snapshot into the open incident, opening or resolving one as needed.
+    /// `serious_condition` says an ongoing condition is still at error level or worse,
+    /// though its repeated events were dropped. The snapshot's events are tagged with the
+    /// incident's id. Returns the incident when it changed and should be saved.
+    pub fn observe(&mut self, snapshot: &mut WifiSnapshot, serious_condition: bool) -> Option<Incident> {
+        let cause = classify(snapshot, serious_condition);
+
+        if let Some(cause) = cause {
+            self.recovered_at = None;
+            *self.cause_counts.entry(cause).or_default() += 1;
+            let root_cause = self.root_cause();
+            let incident = self.open.get_or_insert_with(|| {
+                let summary = snapshot.events.iter()
+                    .max_by_key(|e| e.severity.clone())
+                    .map(|e| e.description.clone())
+                    .unwrap_or_else(|| cause.label().to_string());
+                warn!("Incident opened: {}", summary);
+                Incident {
+                    id: Uuid::new_v4().to_string(),
+                    status: IncidentStatus::Open,
+                    started_at: snapshot.timestamp,
+                    ended_at: None,
+                    duration_secs: 0,
+                    root_cause: cause,
+                    severity: EventSeverity::Warning,
+                    summary,
+                    event_ids: Vec::new(),
+                    notes: Vec::new(),
+                }
+            });
+            incident.root_cause = root_cause;
+            incident.duration_secs = (snapshot.timestamp - incident.started_at).num_seconds();
+            attach_events(incident, snapshot);
+            return Some(incident.clone());
+        }
+
+        // Healthy snapshot: keep collecting follow-up events (restorations) until quiet
+        let incident = self.open.as_mut()?;
+        let recovered_at = *self.recovered_at.get_or_insert(snapshot.timestamp);
+        let attached = attach_events(incident, snapshot);
+        if (snapshot.timestamp - recovered_at).num_seconds() < QUIET_PERIOD_SECS {
+            return attached.then(|| incident.clone());
+        }
+
+        incident.status = IncidentStatus::Resolved;
+        incident.ended_at = Some(recovered_at);
+        incident.duration_secs = (recovered_at - incident.started_at).num_seconds();
+        info!(
+            "Incident resolved after {}s ({})",
+            incident.duration_secs, incident.root_cause.label()
+        );
+        self.cause_counts.clear();
+        self.recovered_at = None;
+        self.open.take()
+    }
+
+    /// The layer that failed in most of the incident's bad snapshots; ties go to the lower layer
+    fn root_cause(&self) -> IncidentCause {
+        self.cause_counts.iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(cause, _)| *cause)
+            .unwrap_or(IncidentCause::Unknown)
+    }
+}
+
+/// Add the snapshot's events to the incident; returns whether there were any
+fn attach_events(incident: &mut Incident, snapshot: &mut WifiSnapshot) -> bool {
+    for event in &mut snapshot.events {
+        event.add_detail("incident_id", serde_json::json!(incident.id));
+        incident.event_ids.push(event.id.clone());
+        if event.severity > incident.severity {
+            incident.severity = event.severity.clone();
+        }
+    }
+    !snapshot.events.is_empty()
+}
+
+/// Which layer a snapshot shows as failing, if any
+fn classify(snapshot: &WifiSnapshot, serious_condition: bool) -> Option<IncidentCause> {
+    let connectivity = &snapshot.connectivity;
+    let router_log = |category: &str| snapshot.events.iter().any(|e| {
+        e.event_type == EventType::RouterLog && e.details["category"] == category
+    });
+
+    if router_log("wan_down") {
+        return Some(IncidentCause::Isp);
+    }
+    if snapshot.wifi_info.is_none() || !connectivity.is_connected {
+        return Some(IncidentCause::Wifi);
+    }
+    if !connectivity.router_reachable {
+        return Some(IncidentCause::LocalNetwork);
+    }
+    if !connectivity.internet_reachable {
+        return Some(if connectivity.http_outcome == Some(HttpOutcome::DnsError) {
+            IncidentCause::Dns
+        } else {
+            IncidentCause::Isp
+        });
+    }
+
+    // Connected and online, but something serious was reported
+    let serious: Vec<_> = snapshot.events.iter()
+        .filter(|e| e.severity >= EventSeverity::Error)
+        .filter(|e| !matches!(e.event_type, EventType::TestAlert | EventType::RouterLog))
+        .collect();
+    if serious.is_empty() && !serious_condition {
+        None
+    } else if !serious.is_empty() && serious.iter().all(|e| e.event_type == EventType::DnsFailure) {
+        Some(IncidentCause::Dns)
+    } else {
+        Some(IncidentCause::Performance)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::metrics::{Annotation, WifiSnapshot};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Snapshots kept for retry while the endpoint is down; older ones are dropped first
+const MAX_BACKLOG: usize = 1000;
+
+/// One InfluxDB line protocol record per snapshot (`wifi`), per ping target (`wifi_ping`)
+/// and per event (`wifi_event`), timestamped in nanoseconds
+pub fn snapshot_lines(snapshot: &WifiSnapshot, host: Option<&str>) -> String {
+    let mut out = String::new();
+    let timestamp = snapshot.timestamp.timestamp_nanos_opt().unwrap_or_default();
+    let wifi = snapshot.wifi_info.as_ref();
+    let latency = &snapshot.latency;
+
+    let mut tags = Tags::default();
+    tags.add("host", host);
+    tags.add("ssid", wifi.map(|w| w.ssid.as_str()));
+    tags.add("bssid", wifi.map(|w| w.bssid.as_str()));
+    tags.add("band", wifi.map(|w| w.band.label()));
+    let mut fields = Fields::default();
+    fields.bool("connected", snapshot.connectivity.is_connected);
+    fields.bool("router_reachable", snapshot.connectivity.router_reachable);
+    fields.bool("internet_reachable", snapshot.connectivity.internet_reachable);
+    fields.int("signal_dbm", wifi.map(|w| w.signal_strength_dbm as i64));
+    fields.int("signal_quality_percent", wifi.map(|w| w.signal_quality_percent as i64));
+    fields.int("channel", wifi.map(|w| w.channel as i64));
+    fields.int("link_speed_mbps", wifi.map(|w| w.link_speed_mbps as i64));
+    fields.float("router_latency_ms", latency.router_latency_ms);
+    fields.float("latency_avg_ms", latency.average_latency_ms);
+    fields.float("latency_min_ms", latency.min_latency_ms);
+    fields.float("latency_max_ms", latency.max_latency_ms);
+    fields.float("jitter_ms", latency.jitter_ms);
+    fields.float("rfc3550_jitter_ms", latency.rfc3550_jitter_ms);
+    fields.float("packet_loss_percent", Some(latency.packet_loss_percent));
+    fields.float("mos", latency.mos);
+    fields.float("dns_resolution_ms", snapshot.dns_metrics.average_resolution_time_ms);
+    fields.int("dns_failures", Some(snapshot.dns_metrics.failures as i64));
+    fields.float("throughput_rx_bytes_per_sec", snapshot.system_info.throughput_rx_bytes_per_sec);
+    fields.float("throughput_tx_bytes_per_sec", snapshot.system_info.throughput_tx_bytes_per_sec);
+    fields.bool("other_network", snapshot.other_network);
+    fields.bool("maintenance", snapshot.maintenance);
+    fields.int("events", Some(snapshot.events.len() as i64));
+    push_line(&mut out, "wifi", &tags, &fields, timestamp);
+
+    for target in &latency.targets {
+        let mut tags = Tags::default();
+        tags.add("host", host);
+        tags.add("target", Some(&target.target));
+        let mut fields = Fields::default();
+        fields.float("avg_ms", target.avg_ms);
+        fields.float("min_ms", target.min_ms);
+        fields.float("max_ms", target.max_ms);
+        fields.float("packet_loss_percent", Some(target.packet_loss_percent));
+        push_line(&mut out, "wifi_ping", &tags, &fields, timestamp);
+    }
+
+    for event in &snapshot.events {
+        let mut tags = Tags::default();
+        tags.add("host", host);
+        tags.add("type", Some(&format!("{:?}", event.event_type)));
+        tags.add("severity", Some(&format!("{:?}", event.severity)));
+        let mut fields = Fields::default();
+        fields.string("description", &event.description);
+        let at = event.timestamp.timestamp_nanos_opt().unwrap_or(timestamp);
+        push_line(&mut out, "wifi_event", &tags, &fields, at);
+    }
+    out
+}
+
+/// A `wifi_annotation` record, which Grafana can show as an annotation
+pub fn annotation_line(annotation: &Annotation, host: Option<&str>) -> String {
+    let mut out = String::new();
+    let mut tags = Tags::default();
+    tags.add("host", host);
+    let mut fields = Fields::default();
+    fields.string("text", &annotation.text);
+    let timestamp = annotation.timestamp.timestamp_nanos_opt().unwrap_or_default();
+    push_line(&mut out, "wifi_annotation", &tags, &fields, timestamp);
+    out
+}
+
+fn push_line(out: &mut String, measurement: &str, tags: &Tags, fields: &Fields, timestamp: i64) {
+    // A record needs at least one field
+    if !fields.0.is_empty() {
+        let _ = writeln!(out, "{}{} {} {}", measurement, tags.0, fields.0, timestamp);
+    }
+}
+
+/// `,key=value` pairs; empty values are left out since InfluxDB rejects them
+#[derive(Default)]
+struct Tags(String);
+
+impl Tags {
+    fn add(&mut self, key: &str, value: Option<&str>) {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            let _ = write!(self.0, ",{}={}", key, escape_tag(value));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Fields(String);
+
+impl Fields {
+    fn separator(&mut self) {
+        if !self.0.is_empty() {
+            self.0.push(',');
+        }
+    }
+
+    fn float(&mut self, key: &str, value: Option<f64>) {
+        if let Some(value) = value.filter(|v| v.is_finite()) {
+            self.separator();
+            let _ = write!(self.0, "{}={}", key, value);
+        }
+    }
+
+    fn int(&mut self, key: &str, value: Option<i64>) {
+        if let Some(value) = value {
+            self.separator();
+            let _ = write!(self.0, "{}={}i", key, value);
+        }
+    }
+
+    fn bool(&mut self, key: &str, value: bool) {
+        self.separator();
+        let _ = write!(self.0, "{}={}", key, value);
+    }
+
+    fn string(&mut self, key: &str, value: &str) {
+        self.separator();
+        let _ = write!(self.0, "{}=\"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+}
+
+fn escape_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ").replace('\n', "\\n")
+}
+
+/// Posts each snapshot to an InfluxDB (or VictoriaMetrics, Telegraf, ...) write endpoint
+/// from a background task, so a slow or unreachable server never holds up monitoring
+#[derive(Clone)]
+pub struct InfluxPush {
+    lines: mpsc::UnboundedSender<String>,
+    host: Option<String>,
+}
+
+impl InfluxPush {
+    /// Start the writer. `url` is the full write URL, e.g.
+    /// `http://localhost:8086/api/v2/write?org=home&bucket=wifi`; `token` is sent as
+    /// `Authorization: Token <token>`
+    pub fn spawn(url: String, token: Option<String>) -> Self {
+        let (lines, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(url, token, receiver));
+        Self { lines, host: sysinfo::System::host_name() }
+    }
+
+    pub fn send(&self, snapshot: &WifiSnapshot) {
+        let _ = self.lines.send(snapshot_lines(snapshot, self.host.as_deref()));
+    }
+}
+
+async fn run(url: String, token: Option<String>, mut receiver: mpsc::UnboundedReceiver<String>) {
+    let client = match reqwest::Client::builder().timeout(WRITE_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("InfluxDB writes disabled: {}", e);
+            return;
+        }
+    };
+    info!("Writing snapshots to InfluxDB at {}", url);
+
+    // Unsent snapshots, retried with the next one
+    let mut backlog: VecDeque<String> = VecDeque::new();
+    let mut failing = false;
+    while let Some(lines) = receiver.recv().await {
+        backlog.push_back(lines);
+        while let Ok(lines) = receiver.try_recv() {
+            backlog.push_back(lines);
+        }
+        if backlog.len() > MAX_BACKLOG {
+            backlog.drain(..backlog.len() - MAX_BACKLOG);
+        }
+
+        let mut request = client.post(&url).body(backlog.iter().map(String::as_str).collect::<String>());
+        if let Some(ref token) = token {
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Token {}", token));
+        }
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                if failing {
+                    info!("InfluxDB writes recovered, sent {} queued snapshots", backlog.len());
+                    failing = false;
+                }
+                backlog.clear();
+            }
+            // Retrying won't fix a malformed write or bad credentials
+            Ok(response) if response.status().is_client_error() && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                warn!("InfluxDB rejected {} snapshots ({}): {}", backlog.len(), status, body.trim());
+                backlog.clear();
+            }
+            result => {
+                if !failing {
+                    let reason = match result {
+                        Ok(response) => response.status().to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                    warn!("InfluxDB write to {} failed, queueing up to {} snapshots: {}", url, MAX_BACKLOG, reason);
+                    failing = true;
+                }
+            }
+        }
+    }
+}
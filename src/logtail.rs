@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use utoipa::ToSchema;
+
+/// Records kept for pages opened after they were logged
+const MAX_RECENT: usize = 1000;
+/// Records a slow log viewer may fall behind before it skips some
+const STREAM_BUFFER: usize = 256;
+
+/// One log line as the web API returns it
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    /// ERROR, WARN, INFO, DEBUG or TRACE
+    pub level: String,
+    /// The module that logged it, e.g. `wifi_stability_tracker::monitor`
+    pub target: String,
+    pub message: String,
+    /// The record's other fields as `key=value` pairs
+    pub fields: String,
+    #[serde(skip)]
+    severity: Level,
+}
+
+impl LogRecord {
+    /// Whether the record is at `level` or more severe
+    pub fn at_least(&self, level: Level) -> bool {
+        self.severity <= level
+    }
+}
+
+struct Tail {
+    recent: Mutex<VecDeque<Arc<LogRecord>>>,
+    sender: broadcast::Sender<Arc<LogRecord>>,
+}
+
+static TAIL: OnceLock<Tail> = OnceLock::new();
+
+fn tail() -> &'static Tail {
+    TAIL.get_or_init(|| Tail {
+        recent: Mutex::new(VecDeque::with_capacity(MAX_RECENT)),
+        sender: broadcast::channel(STREAM_BUFFER).0,
+    })
+}
+
+/// A layer keeping the process's log output for the dashboard's log viewer, beside the
+/// console and the rolling files in `logs/`. It sees what the subscriber's filter lets through.
+pub fn layer() -> LogTail {
+    LogTail
+}
+
+pub struct LogTail;
+
+impl<S: Subscriber> Layer<S> for LogTail {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = Fields::default();
+        event.record(&mut visitor);
+        let record = Arc::new(LogRecord {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+            severity: *metadata.level(),
+        });
+
+        let tail = tail();
+        if let Ok(mut recent) = tail.recent.lock() {
+            if recent.len() == MAX_RECENT {
+                recent.pop_front();
+            }
+            recent.push_back(record.clone());
+        }
+        let _ = tail.sender.send(record);
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.add(field, format_args!("{}", value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.add(field, format_args!("{:?}", value));
+        }
+    }
+}
+
+impl Fields {
+    fn add(&mut self, field: &Field, value: std::fmt::Arguments) {
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={}", field.name(), value);
+    }
+}
+
+/// The last `limit` records at `level` or more severe, oldest first
+pub fn recent(level: Level, limit: usize) -> Vec<Arc<LogRecord>> {
+    let Ok(recent) = tail().recent.lock() else {
+        return Vec::new();
+    };
+    let mut records: Vec<_> = recent.iter().rev().filter(|r| r.at_least(level)).take(limit).cloned().collect();
+    records.reverse();
+    records
+}
+
+/// Each record as soon as it is logged
+pub fn subscribe() -> broadcast::Receiver<Arc<LogRecord>> {
+    tail().sender.subscribe()
+}
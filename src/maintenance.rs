@@ -0,0 +1,66 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+
+/// A recurring window in the monitor's local time during which notifications are held
+/// back, e.g. `03:00-03:30` for a nightly router reboot. It may cross midnight
+/// (`23:00-06:00`) and may be limited to some days (`sun 02:00-04:00`, `sat,sun 00:00-23:59`);
+/// a window crossing midnight belongs to the day it starts on.
+#[derive(Debug, Clone)]
+pub struct MaintenanceWindow {
+    /// Every day when empty
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    text: String,
+}
+
+impl std::str::FromStr for MaintenanceWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let (days, times) = match text.rsplit_once(char::is_whitespace) {
+            Some((days, times)) => (days.trim(), times),
+            None => ("", text),
+        };
+        let days = days.split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(|d| d.parse::<Weekday>().map_err(|_| format!("'{}' isn't a day, e.g. mon or sunday", d)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((start, end)) = times.split_once('-') else {
+            return Err(format!("'{}' isn't a window like 03:00-03:30", text));
+        };
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M")
+            .map_err(|_| format!("'{}' isn't a time like 03:00", t.trim()));
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            return Err(format!("'{}' starts and ends at the same time", text));
+        }
+        Ok(Self { days, start, end, text: text.to_string() })
+    }
+}
+
+impl std::fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl MaintenanceWindow {
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&Local);
+        let (day, time) = (local.weekday(), local.time());
+        let on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        if self.start < self.end {
+            on(day) && time >= self.start && time < self.end
+        } else {
+            // Crosses midnight: the evening part is today's, the morning part yesterday's
+            (on(day) && time >= self.start) || (on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+/// The first of `windows` containing `at`
+pub fn active(windows: &[MaintenanceWindow], at: DateTime<Utc>) -> Option<&MaintenanceWindow> {
+    windows.iter().find(|w| w.contains(at))
+}
@@ -0,0 +1,68 @@
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+/// Host name advertised when `--mdns` is given without one, i.e. `wifi-tracker.local`
+pub const DEFAULT_MDNS_NAME: &str = "wifi-tracker";
+
+/// The dashboard announced on the local network over multicast DNS, as `<name>.local` and an
+/// `_http._tcp` (or `_https._tcp`) service, so phones and other machines can find it without
+/// knowing this one's address. Withdrawn when dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    /// Announce the dashboard listening on `addr`. On an unspecified address every interface's
+    /// addresses are announced, following them as they change.
+    pub fn start(name: &str, addr: SocketAddr, https: bool) -> anyhow::Result<Self> {
+        if addr.ip().is_loopback() {
+            anyhow::bail!("the dashboard listens on {} only; pass --bind 0.0.0.0 to reach it from other devices", addr.ip());
+        }
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            anyhow::bail!("--mdns {:?} must be letters, digits and hyphens", name);
+        }
+
+        let service_type = if https { "_https._tcp.local." } else { "_http._tcp.local." };
+        let host_name = format!("{}.local.", name);
+        let instance = match sysinfo::System::host_name() {
+            Some(host) => format!("WiFi Stability Tracker on {}", host),
+            None => "WiFi Stability Tracker".to_string(),
+        };
+        let properties = [("path", "/")];
+        let service = if addr.ip().is_unspecified() {
+            ServiceInfo::new(service_type, &instance, &host_name, "", addr.port(), &properties[..])?.enable_addr_auto()
+        } else {
+            ServiceInfo::new(service_type, &instance, &host_name, addr.ip(), addr.port(), &properties[..])?
+        };
+
+        let daemon = ServiceDaemon::new()?;
+        let fullname = service.get_fullname().to_string();
+        daemon.register(service)?;
+        info!("Advertising the dashboard on the local network as {}://{}.local:{}",
+            if https { "https" } else { "http" }, name, addr.port());
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // Goodbye packets, so other devices forget the service now rather than when its record expires
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Start advertising when `--mdns` was given, logging why it couldn't be instead of failing
+/// the command: the dashboard still works by address
+pub fn advertise(name: Option<&str>, addr: SocketAddr, https: bool) -> Option<Advertisement> {
+    let name = name?;
+    match Advertisement::start(name, addr, https) {
+        Ok(advertisement) => Some(advertisement),
+        Err(e) => {
+            warn!("Not advertising the dashboard over mDNS: {}", e);
+            None
+        }
+    }
+}
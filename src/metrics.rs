@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Represents a single WiFi measurement snapshot
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct WifiSnapshot {
     pub id: String,
     pub timestamp: DateTime<Utc>,
@@ -12,6 +13,34 @@ pub struct WifiSnapshot {
     pub latency: LatencyMetrics,
     pub dns_metrics: DnsMetrics,
     pub system_info: SystemNetworkInfo,
+    /// Optional wired (Ethernet) baseline measured alongside WiFi
+    #[serde(default)]
+    pub wired: Option<WiredMetrics>,
+    /// Guest SSID measured for comparison; only filled on guest probe cycles
+    #[serde(default)]
+    pub guest: Option<GuestMetrics>,
+    /// Public address and its network; only filled on lookup cycles
+    #[serde(default)]
+    pub public_ip: Option<PublicIpInfo>,
+    /// Collection interval in effect when this snapshot was taken
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Nearby access points; only filled on neighbor scan cycles
+    #[serde(default)]
+    pub neighbors: Vec<NeighborNetwork>,
+    /// How long each part of the collection cycle took
+    #[serde(default)]
+    pub probe_durations: ProbeDurations,
+    /// Battery/metered status and whether sampling was reduced because of it
+    #[serde(default)]
+    pub sampling: SamplingPolicy,
+    /// Taken on a network other than the one given with `--only-ssid`
+    #[serde(default)]
+    pub other_network: bool,
+    /// Taken in a maintenance window with `--maintenance-exclude`, so left out of uptime
+    /// and health statistics
+    #[serde(default)]
+    pub maintenance: bool,
     pub events: Vec<NetworkEvent>,
 }
 
@@ -25,13 +54,52 @@ impl WifiSnapshot {
             latency: LatencyMetrics::default(),
             dns_metrics: DnsMetrics::default(),
             system_info: SystemNetworkInfo::default(),
+            wired: None,
+            guest: None,
+            public_ip: None,
+            interval_secs: None,
+            neighbors: Vec::new(),
+            probe_durations: ProbeDurations::default(),
+            sampling: SamplingPolicy::default(),
+            other_network: false,
+            maintenance: false,
             events: Vec::new(),
         }
     }
 }
 
+/// Wall-clock time of each probe in a collection cycle, in milliseconds. The
+/// connectivity, latency, wired and DNS probes run concurrently, so `total_ms`
+/// is less than their sum.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ProbeDurations {
+    pub wifi_info_ms: u64,
+    pub system_info_ms: u64,
+    pub connectivity_ms: u64,
+    pub latency_ms: u64,
+    pub wired_ms: Option<u64>,
+    pub dns_ms: u64,
+    /// Runs after the other probes, so it is included in `total_ms` in full
+    #[serde(default)]
+    pub guest_ms: Option<u64>,
+    pub total_ms: u64,
+}
+
+/// Sampling policy in effect for a snapshot. While `reduced`, the interval is
+/// longer (see `interval_secs`) and heavy probes may have been skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct SamplingPolicy {
+    /// None when battery status is unknown or not watched
+    pub on_battery: Option<bool>,
+    /// None when the network's cost is unknown or not watched
+    pub metered: Option<bool>,
+    pub reduced: bool,
+    /// Neighbor scans, outage diagnostic bursts and latency investigations were skipped
+    pub heavy_probes_skipped: bool,
+}
+
 /// WiFi adapter and connection information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct WifiInfo {
     pub ssid: String,
     pub bssid: String,
@@ -53,7 +121,7 @@ pub struct WifiInfo {
     pub dns_servers: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum WifiBand {
     Band2_4GHz,
     Band5GHz,
@@ -70,23 +138,207 @@ impl WifiBand {
             _ => WifiBand::Unknown,
         }
     }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WifiBand::Band2_4GHz => "2.4 GHz",
+            WifiBand::Band5GHz => "5 GHz",
+            WifiBand::Band6GHz => "6 GHz",
+            WifiBand::Unknown => "unknown band",
+        }
+    }
+
+    /// Whether a channel on this band is subject to DFS (radar detection), i.e.
+    /// the AP must leave it when radar is seen
+    pub fn is_dfs(&self, channel: u32) -> bool {
+        *self == WifiBand::Band5GHz && (52..=144).contains(&channel)
+    }
 }
 
-/// Connectivity test results
+/// A nearby access point from `netsh wlan show networks mode=bssid`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NeighborNetwork {
+    pub ssid: String,
+    pub bssid: String,
+    pub signal_quality_percent: u8,
+    pub signal_dbm: i32,
+    pub channel: u32,
+    pub band: WifiBand,
+    pub radio_type: String,
+    /// e.g. `WPA2-Personal` or `Open`; missing in scans from older builds
+    #[serde(default)]
+    pub authentication: Option<String>,
+    /// e.g. `CCMP` or `None`
+    #[serde(default)]
+    pub encryption: Option<String>,
+}
+
+/// WiFi adapter driver and power-management settings, collected once per run
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AdapterInfo {
+    pub collected_at: Option<DateTime<Utc>>,
+    pub interface_name: String,
+    pub driver_description: String,
+    pub vendor: String,
+    pub provider: String,
+    pub driver_version: String,
+    pub driver_date: Option<String>,
+    pub radio_types: Vec<String>,
+    pub allow_turn_off_device: Option<bool>,
+    pub power_saving_mode_ac: Option<u32>,
+    pub power_saving_mode_dc: Option<u32>,
+}
+
+impl AdapterInfo {
+    /// Driver date parsed from the format reported by netsh (M/D/YYYY)
+    pub fn driver_date(&self) -> Option<chrono::NaiveDate> {
+        let date = self.driver_date.as_deref()?.trim();
+        chrono::NaiveDate::parse_from_str(date, "%m/%d/%Y")
+            .or_else(|_| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+            .ok()
+    }
+}
+
+/// Describe a Windows wireless adapter power saving mode index
+pub fn power_saving_mode_name(mode: u32) -> &'static str {
+    match mode {
+        0 => "Maximum Performance",
+        1 => "Low Power Saving",
+        2 => "Medium Power Saving",
+        3 => "Maximum Power Saving",
+        _ => "Unknown",
+    }
+}
+
+/// Latency and loss measured through a wired interface, pinned by source address
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
+pub struct WiredMetrics {
+    pub interface_name: String,
+    pub ipv4_address: Option<String>,
+    pub link_up: bool,
+    pub targets: Vec<PingResult>,
+    pub average_latency_ms: Option<f64>,
+    pub packet_loss_percent: f64,
+}
+
+/// Latency and loss on the guest SSID, measured through a second adapter or by
+/// briefly switching the main adapter over
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
+pub struct GuestMetrics {
+    pub ssid: String,
+    pub interface_name: String,
+    /// The main adapter left the monitored network for this measurement
+    pub switched: bool,
+    pub connected: bool,
+    pub signal_quality_percent: Option<u8>,
+    pub ipv4_address: Option<String>,
+    pub targets: Vec<PingResult>,
+    pub average_latency_ms: Option<f64>,
+    pub packet_loss_percent: f64,
+    pub error: Option<String>,
+}
+
+/// The address the internet sees, with the network (ASN) and ISP announcing it
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, ToSchema)]
+pub struct PublicIpInfo {
+    pub ip: String,
+    pub asn: Option<u32>,
+    pub isp: Option<String>,
+}
+
+/// Connectivity test results
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct ConnectivityMetrics {
     pub is_connected: bool,
+    #[serde(default)]
+    pub adapter_present: bool,
     pub loopback_reachable: bool,
     pub router_reachable: bool,
     pub internet_reachable: bool,
     pub http_test_success: bool,
     pub http_response_time_ms: Option<u64>,
+    /// Outcome class and status code of the plain-HTTP check
+    #[serde(default)]
+    pub http_outcome: Option<HttpOutcome>,
+    #[serde(default)]
+    pub http_status: Option<u16>,
+    /// Outcome class of the HTTPS check, which also surfaces TLS failures
+    #[serde(default)]
+    pub https_outcome: Option<HttpOutcome>,
     pub tcp_connections_established: u32,
     pub tcp_connections_failed: u32,
 }
 
+/// What an HTTP connectivity check ran into
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+pub enum HttpOutcome {
+    Success,
+    /// Usually a captive portal intercepting the request
+    Redirect,
+    ClientError,
+    ServerError,
+    Timeout,
+    DnsError,
+    TlsError,
+    ConnectError,
+    OtherError,
+}
+
+impl HttpOutcome {
+    pub const ALL: [HttpOutcome; 9] = [
+        HttpOutcome::Success,
+        HttpOutcome::Redirect,
+        HttpOutcome::ClientError,
+        HttpOutcome::ServerError,
+        HttpOutcome::Timeout,
+        HttpOutcome::DnsError,
+        HttpOutcome::TlsError,
+        HttpOutcome::ConnectError,
+        HttpOutcome::OtherError,
+    ];
+
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            200..=299 => HttpOutcome::Success,
+            300..=399 => HttpOutcome::Redirect,
+            400..=499 => HttpOutcome::ClientError,
+            500..=599 => HttpOutcome::ServerError,
+            _ => HttpOutcome::OtherError,
+        }
+    }
+
+    /// Stable numeric code used for the categorical `http_outcome`/`https_outcome` timeseries
+    pub fn code(&self) -> u8 {
+        match self {
+            HttpOutcome::Success => 0,
+            HttpOutcome::Redirect => 1,
+            HttpOutcome::ClientError => 2,
+            HttpOutcome::ServerError => 3,
+            HttpOutcome::Timeout => 4,
+            HttpOutcome::DnsError => 5,
+            HttpOutcome::TlsError => 6,
+            HttpOutcome::ConnectError => 7,
+            HttpOutcome::OtherError => 8,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HttpOutcome::Success => "success",
+            HttpOutcome::Redirect => "redirect (captive portal?)",
+            HttpOutcome::ClientError => "4xx response",
+            HttpOutcome::ServerError => "5xx response",
+            HttpOutcome::Timeout => "timeout",
+            HttpOutcome::DnsError => "DNS error",
+            HttpOutcome::TlsError => "TLS error",
+            HttpOutcome::ConnectError => "connection failed",
+            HttpOutcome::OtherError => "other error",
+        }
+    }
+}
+
 /// Latency measurements from ping tests
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct LatencyMetrics {
     pub targets: Vec<PingResult>,
     pub loopback_latency_ms: Option<f64>,
@@ -94,11 +346,104 @@ pub struct LatencyMetrics {
     pub average_latency_ms: Option<f64>,
     pub min_latency_ms: Option<f64>,
     pub max_latency_ms: Option<f64>,
+    /// Standard deviation of all reply times across targets
     pub jitter_ms: Option<f64>,
+    /// Mean of the targets' RFC 3550 jitter, comparable to what VoIP tools report
+    #[serde(default)]
+    pub rfc3550_jitter_ms: Option<f64>,
     pub packet_loss_percent: f64,
+    /// Estimated call quality, 1 (bad) to 4.5 (best); see `estimate_mos`
+    #[serde(default)]
+    pub mos: Option<f64>,
+    /// 1-second background pings aggregated since the previous snapshot
+    #[serde(default)]
+    pub continuous: Vec<ContinuousPingStats>,
+    /// UDP stream to a reflector, aggregated since the previous snapshot
+    #[serde(default)]
+    pub udp_stream: Option<UdpStreamStats>,
+    /// UDP stream to another tracker instance on the LAN (`--peer`)
+    #[serde(default)]
+    pub peer: Option<UdpStreamStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// MOS below which calls are noticeably impaired (ITU-T G.107 "some users dissatisfied")
+pub const MOS_POOR_CALL: f64 = 3.6;
+
+impl LatencyMetrics {
+    /// Mean opinion score a G.711 call would get on this path, from the simplified
+    /// ITU-T G.107 E-model. One-way delay is half the round trip, plus a jitter buffer
+    /// of twice the jitter and 20 ms of packetization. Loss is treated as random,
+    /// with packet loss concealment.
+    pub fn estimate_mos(&self) -> Option<f64> {
+        if self.packet_loss_percent >= 100.0 {
+            return Some(1.0);
+        }
+        let rtt = self.average_latency_ms?;
+        let jitter = self.rfc3550_jitter_ms.or(self.jitter_ms).unwrap_or(0.0);
+        let delay = rtt / 2.0 + 2.0 * jitter + 20.0;
+
+        let delay_impairment = 0.024 * delay + if delay > 177.3 { 0.11 * (delay - 177.3) } else { 0.0 };
+        let loss_impairment = 95.0 * self.packet_loss_percent / (self.packet_loss_percent + 25.1);
+        let r = 93.2 - delay_impairment - loss_impairment;
+
+        Some(if r <= 0.0 {
+            1.0
+        } else if r >= 100.0 {
+            4.5
+        } else {
+            1.0 + 0.035 * r + 7.0e-6 * r * (r - 60.0) * (100.0 - r)
+        })
+    }
+}
+
+/// One-way loss and reordering seen on the UDP reflector stream over a snapshot interval
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UdpStreamStats {
+    pub reflector: String,
+    pub sent: u64,
+    pub received: u64,
+    /// Lost on the way to the reflector
+    pub upstream_lost: u64,
+    /// Lost on the way back
+    pub downstream_lost: u64,
+    pub upstream_loss_percent: f64,
+    pub downstream_loss_percent: f64,
+    pub reordered: u64,
+    pub duplicates: u64,
+    pub rtt_avg_ms: Option<f64>,
+    pub rtt_max_ms: Option<f64>,
+    /// One-way delay towards the reflector/peer, corrected for clock offset
+    #[serde(default)]
+    pub forward_delay_avg_ms: Option<f64>,
+    #[serde(default)]
+    pub forward_delay_max_ms: Option<f64>,
+    /// One-way delay back from the reflector/peer
+    #[serde(default)]
+    pub reverse_delay_avg_ms: Option<f64>,
+    #[serde(default)]
+    pub reverse_delay_max_ms: Option<f64>,
+    /// Estimated clock difference between the two machines (peer minus local)
+    #[serde(default)]
+    pub clock_offset_ms: Option<f64>,
+}
+
+/// Aggregate of the continuous sampler's pings to one target over a snapshot interval
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContinuousPingStats {
+    pub target: String,
+    /// "gateway" or "internet"
+    pub role: String,
+    pub samples: u32,
+    pub received: u32,
+    pub packet_loss_percent: f64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    /// Longest run of consecutive lost pings, i.e. outage length in seconds
+    pub longest_loss_streak: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PingResult {
     pub target: String,
     pub resolved_ip: Option<String>,
@@ -110,18 +455,24 @@ pub struct PingResult {
     pub max_ms: Option<f64>,
     pub stddev_ms: Option<f64>,
     pub individual_times_ms: Vec<f64>,
+    /// Payload size when not the default 32 bytes
+    #[serde(default)]
+    pub packet_size: Option<u32>,
+    /// Smoothed RFC 3550 interarrival jitter for this target, carried across snapshots
+    #[serde(default)]
+    pub rfc3550_jitter_ms: Option<f64>,
     pub error: Option<String>,
 }
 
 /// DNS resolution metrics
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct DnsMetrics {
     pub queries: Vec<DnsQueryResult>,
     pub average_resolution_time_ms: Option<f64>,
     pub failures: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DnsQueryResult {
     pub domain: String,
     pub dns_server: String,
@@ -132,7 +483,7 @@ pub struct DnsQueryResult {
 }
 
 /// System-level network information
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct SystemNetworkInfo {
     pub bytes_sent: u64,
     pub bytes_received: u64,
@@ -142,19 +493,59 @@ pub struct SystemNetworkInfo {
     pub errors_out: u64,
     pub drops_in: u64,
     pub drops_out: u64,
+    /// Cumulative bytes received on the WiFi adapter itself; drops when its counters reset
+    #[serde(default)]
+    pub adapter_bytes_received: Option<u64>,
+    #[serde(default)]
+    pub adapter_bytes_sent: Option<u64>,
+    /// Receive/transmit rate since the previous snapshot in bytes per second
+    /// (WiFi adapter when known, otherwise all interfaces)
+    #[serde(default)]
+    pub throughput_rx_bytes_per_sec: Option<f64>,
+    #[serde(default)]
+    pub throughput_tx_bytes_per_sec: Option<f64>,
+    /// Cumulative TCP segment counters (IPv4 + IPv6) from `netstat -s`
+    #[serde(default)]
+    pub tcp_segments_sent: Option<u64>,
+    #[serde(default)]
+    pub tcp_segments_received: Option<u64>,
+    #[serde(default)]
+    pub tcp_segments_retransmitted: Option<u64>,
+    /// Retransmitted share of segments sent since the previous snapshot
+    #[serde(default)]
+    pub tcp_retransmit_percent: Option<f64>,
+    #[serde(default)]
+    pub tcp_retransmits_per_sec: Option<f64>,
+    /// Preferred IPv4 default route at the time of the snapshot
+    #[serde(default)]
+    pub default_route: Option<DefaultRoute>,
     pub active_connections: u32,
     pub cpu_usage_percent: f32,
     pub memory_usage_percent: f32,
 }
 
+/// The IPv4 default route Windows is currently using
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct DefaultRoute {
+    pub gateway: String,
+    /// Local address of the interface carrying the route
+    pub interface_address: String,
+    pub metric: u32,
+    /// Whether the route goes out through the monitored WiFi adapter
+    pub via_wifi: bool,
+    /// Number of competing default routes (e.g. Ethernet dock, VPN)
+    pub candidates: usize,
+}
+
 /// Network events that may indicate issues
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NetworkEvent {
     pub id: String,
     pub timestamp: DateTime<Utc>,
     pub event_type: EventType,
     pub severity: EventSeverity,
     pub description: String,
+    #[schema(value_type = Object)]
     pub details: serde_json::Value,
 }
 
@@ -174,9 +565,162 @@ impl NetworkEvent {
         self.details = details;
         self
     }
+
+    /// Add one key to the details object, creating it if needed
+    pub fn add_detail(&mut self, key: &str, value: serde_json::Value) {
+        if !self.details.is_object() {
+            self.details = serde_json::json!({});
+        }
+        self.details[key] = value;
+    }
+}
+
+/// Events from one outage window grouped together, from the first bad snapshot
+/// until the connection has stayed healthy for a while
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Incident {
+    pub id: String,
+    pub status: IncidentStatus,
+    pub started_at: DateTime<Utc>,
+    /// When the connection recovered; `None` while the incident is open
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_secs: i64,
+    pub root_cause: IncidentCause,
+    pub severity: EventSeverity,
+    /// Description of the event that opened the incident
+    pub summary: String,
+    pub event_ids: Vec<String>,
+    pub notes: Vec<IncidentNote>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum IncidentStatus {
+    Open,
+    Resolved,
+}
+
+/// The layer that failed, judged from the incident's snapshots and events
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, ToSchema)]
+pub enum IncidentCause {
+    /// Adapter lost its association with the access point
+    Wifi,
+    /// Associated, but the router didn't answer
+    LocalNetwork,
+    /// Router reachable, internet not
+    Isp,
+    /// Only name resolution failed
+    Dns,
+    /// Still connected, but with heavy loss or latency
+    Performance,
+    Unknown,
+}
+
+impl IncidentCause {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IncidentCause::Wifi => "WiFi disconnect",
+            IncidentCause::LocalNetwork => "router/LAN unreachable",
+            IncidentCause::Isp => "ISP/upstream outage",
+            IncidentCause::Dns => "DNS failure",
+            IncidentCause::Performance => "degraded performance",
+            IncidentCause::Unknown => "unknown",
+        }
+    }
+}
+
+/// A note added to an incident after the fact, e.g. "ISP confirmed area outage"
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IncidentNote {
+    pub added_at: DateTime<Utc>,
+    pub text: String,
+}
+
+/// A stretch of time monitoring was deliberately paused (sleep, planned disconnect).
+/// No snapshots are taken, and statistics leave it out.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PauseWindow {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    /// None while still paused
+    pub ended_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
+/// A note pinned to a point in time, e.g. "rebooted router" or "moved to the kitchen"
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Annotation {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One run of `monitor`, from start to shutdown. Automatic restarts continue the
+/// same session.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Session {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    /// None while running
+    pub ended_at: Option<DateTime<Utc>>,
+    pub host: Option<String>,
+    /// Monitoring settings the session ran with
+    #[schema(value_type = Object)]
+    pub config: serde_json::Value,
+}
+
+impl Session {
+    /// Start and end as query bounds; a running session extends to now
+    pub fn range(&self) -> (String, String) {
+        (self.started_at.to_rfc3339(), self.ended_at.unwrap_or_else(Utc::now).to_rfc3339())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Outage alerts are silenced from `started_at` until `until`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MuteWindow {
+    pub started_at: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/// Which incident was last alerted on and whether alerts are muted. Kept in the
+/// database so a restart neither repeats an outage alert nor forgets a mute.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct AlertState {
+    pub last_notified_incident: Option<String>,
+    pub last_notified_at: Option<DateTime<Utc>>,
+    pub mute: Option<MuteWindow>,
+}
+
+impl AlertState {
+    /// The mute in effect at `at`, if any
+    pub fn active_mute(&self, at: DateTime<Utc>) -> Option<&MuteWindow> {
+        self.mute.as_ref().filter(|m| m.started_at <= at && at < m.until)
+    }
+}
+
+/// An alert fired by one of the `--alert-rules`, resolved once its condition stops holding
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Alert {
+    pub id: String,
+    /// The rule's name
+    pub name: String,
+    pub severity: EventSeverity,
+    /// The rule's condition, e.g. `packet_loss > 5`
+    pub condition: String,
+    pub message: String,
+    /// The metric's value in the sample that fired it
+    pub value: f64,
+    pub fired_at: DateTime<Utc>,
+    /// When the condition stopped holding; null while the alert is active
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Fired while alerts were muted, so no notification went out
+    #[serde(default)]
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum EventType {
     ConnectionDropped,
     ConnectionRestored,
@@ -185,21 +729,32 @@ pub enum EventType {
     HighLatency,
     LatencyNormalized,
     PacketLoss,
+    PacketLossRecovered,
     DnsFailure,
     DnsRecovered,
     BandSwitch,
     ChannelChange,
     BssidChange,
     IpAddressChange,
+    DefaultRouteChange,
     GatewayUnreachable,
     InternetUnreachable,
     HighJitter,
+    JitterNormalized,
+    TcpRetransmissions,
+    TcpRetransmissionsNormalized,
     AdapterReset,
     SpeedDegraded,
     SpeedRecovered,
+    WlanDisconnectReason,
+    RouterLog,
+    ProbeTimedOut,
+    PublicIpChange,
+    DiagnosticsRun,
+    TestAlert,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
 pub enum EventSeverity {
     Info,
     Warning,
@@ -207,8 +762,24 @@ pub enum EventSeverity {
     Critical,
 }
 
-/// Thresholds for detecting issues
-#[derive(Debug, Clone)]
+impl std::str::FromStr for EventSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "info" => Ok(EventSeverity::Info),
+            "warning" => Ok(EventSeverity::Warning),
+            "error" => Ok(EventSeverity::Error),
+            "critical" => Ok(EventSeverity::Critical),
+            _ => Err(format!("unknown severity '{}' (expected info, warning, error or critical)", s)),
+        }
+    }
+}
+
+/// Thresholds for detecting issues. Set with the monitor's flags or a `--thresholds` TOML
+/// file using these field names; fields left out keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AlertThresholds {
     pub signal_strength_warning_dbm: i32,
     pub signal_strength_critical_dbm: i32,
@@ -217,6 +788,16 @@ pub struct AlertThresholds {
     pub jitter_warning_ms: f64,
     pub packet_loss_warning_percent: f64,
     pub packet_loss_critical_percent: f64,
+    pub tcp_retransmit_warning_percent: f64,
+    pub tcp_retransmit_critical_percent: f64,
+    /// Signal must rise this many dB above the warning level to count as recovered
+    pub signal_recovery_margin_db: i32,
+    /// Latency must fall below this fraction of the warning level to count as normalized
+    /// (jitter too)
+    pub latency_recovery_ratio: f64,
+    /// Recovered snapshots in a row before an ongoing condition counts as resolved, so a
+    /// single good sample doesn't split it in two
+    pub resolve_samples: u32,
 }
 
 impl Default for AlertThresholds {
@@ -229,16 +810,89 @@ impl Default for AlertThresholds {
             jitter_warning_ms: 30.0,
             packet_loss_warning_percent: 1.0,
             packet_loss_critical_percent: 5.0,
+            tcp_retransmit_warning_percent: 2.0,
+            tcp_retransmit_critical_percent: 5.0,
+            signal_recovery_margin_db: 5,
+            latency_recovery_ratio: 0.8,
+            resolve_samples: 2,
         }
     }
 }
 
+impl AlertThresholds {
+    /// The thresholds a session ran with, or the defaults for sessions recorded before
+    /// they were kept, and for reports not tied to a session
+    pub fn for_session(session: Option<&Session>) -> Self {
+        session
+            .and_then(|s| s.config.get("thresholds"))
+            .and_then(|t| serde_json::from_value(t.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Warning levels must not be worse than critical ones
+    pub fn validate(&self) -> Result<(), String> {
+        if self.signal_strength_critical_dbm > self.signal_strength_warning_dbm {
+            return Err(format!(
+                "the critical signal level ({} dBm) is above the warning level ({} dBm)",
+                self.signal_strength_critical_dbm, self.signal_strength_warning_dbm
+            ));
+        }
+        let pairs = [
+            ("latency", self.latency_warning_ms, self.latency_critical_ms),
+            ("packet loss", self.packet_loss_warning_percent, self.packet_loss_critical_percent),
+            ("TCP retransmit", self.tcp_retransmit_warning_percent, self.tcp_retransmit_critical_percent),
+        ];
+        for (name, warning, critical) in pairs {
+            if warning > critical {
+                return Err(format!("the {} warning level ({}) is above the critical level ({})", name, warning, critical));
+            }
+        }
+        if self.resolve_samples == 0 {
+            return Err("conditions need at least 1 recovered sample to resolve".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.latency_recovery_ratio) {
+            return Err(format!("the latency recovery ratio ({}) must be between 0 and 1", self.latency_recovery_ratio));
+        }
+        Ok(())
+    }
+
+    /// A sample counts as degraded at the warning levels
+    pub fn is_degraded(&self, latency_ms: Option<f64>, loss_percent: f64) -> bool {
+        loss_percent >= self.packet_loss_warning_percent
+            || latency_ms.is_some_and(|l| l >= self.latency_warning_ms)
+    }
+}
+
+/// One metric's points within a bucket of time, aggregated
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TimeseriesBucket {
+    /// Start of the bucket
+    pub timestamp: DateTime<Utc>,
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    pub samples: i64,
+}
+
 /// Statistics for a time period
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct PeriodStatistics {
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
     pub sample_count: u32,
+    /// Share of the period sampled under a reduced (battery/metered) policy
+    #[serde(default)]
+    pub reduced_sampling_percent: f64,
+    /// Time monitoring was paused in the period; not counted in uptime
+    #[serde(default)]
+    pub paused_secs: i64,
+    /// Snapshots taken on other networks than `--only-ssid`, left out of these statistics
+    #[serde(default)]
+    pub other_network_samples: u32,
+    /// Snapshots taken in excluded maintenance windows, left out of these statistics
+    #[serde(default)]
+    pub maintenance_samples: u32,
     
     // Signal statistics
     pub signal_strength_avg_dbm: Option<f64>,
@@ -253,6 +907,16 @@ pub struct PeriodStatistics {
     pub latency_p95_ms: Option<f64>,
     pub latency_p99_ms: Option<f64>,
     pub jitter_avg_ms: Option<f64>,
+    #[serde(default)]
+    pub rfc3550_jitter_avg_ms: Option<f64>,
+    /// Estimated call quality (MOS), average and worst snapshot
+    #[serde(default)]
+    pub mos_avg: Option<f64>,
+    #[serde(default)]
+    pub mos_min: Option<f64>,
+    /// Share of snapshots whose MOS was below `MOS_POOR_CALL`
+    #[serde(default)]
+    pub poor_call_percent: f64,
     
     // Reliability statistics
     pub packet_loss_avg_percent: f64,
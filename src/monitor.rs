@@ -1,19 +1,469 @@
+use crate::capture::RollingCapture;
+use crate::diagnostics;
+use crate::eventlog::WlanEventLogCollector;
+use crate::incidents::IncidentTracker;
 use crate::metrics::*;
-use crate::storage::MetricsStore;
-use std::process::Command;
+use crate::power::{PowerPolicy, PowerState};
+use crate::probe::{CommandRunner, CommandTimeouts, PingOptions, PingTarget};
+use crate::forward::Forwarder;
+use crate::webhook::Webhooks;
+use crate::email::EmailNotifier;
+use crate::chat::ChatNotifier;
+use crate::toast::DesktopToasts;
+use crate::exec::ExecHooks;
+use crate::alerts::AlertRules;
+use crate::maintenance::{self, MaintenanceWindow};
+use crate::influx::InfluxPush;
+use crate::mqtt::MqttPublish;
+use crate::publicip::PublicIpLookup;
+use crate::reflector::UdpStreamProbe;
+use crate::sampler::LatencySampler;
+use crate::selfmon::{self, GuardAction, ResourceGuard};
+use crate::shutdown::ShutdownHandle;
+use crate::storage::{self, MetricsStore};
+use crate::writer::SnapshotWriter;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::time;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::time::{self, MissedTickBehavior};
 use tracing::{debug, error, info, warn};
 use sysinfo::{Networks, System};
 
 pub struct WifiMonitor {
-    store: Arc<MetricsStore>,
+    store: Arc<dyn MetricsStore>,
     interval_secs: u64,
-    ping_targets: Vec<String>,
+    ping_targets: Vec<PingTarget>,
+    /// Used for the loopback and router pings; targets carry their own
+    ping_options: PingOptions,
     dns_servers: Vec<String>,
     thresholds: AlertThresholds,
     last_state: Option<MonitorState>,
+    wlan_event_log: Option<WlanEventLogCollector>,
+    handle: MonitorHandle,
+    injected_events: mpsc::UnboundedReceiver<NetworkEvent>,
+    diagnose_requests: mpsc::UnboundedReceiver<DiagnoseReply>,
+    dry_run: Option<OutputFormat>,
+    link_baselines: HashMap<String, LinkRateBaseline>,
+    signal_degradation: Degradation,
+    latency_degradation: Degradation,
+    dns_degradation: Degradation,
+    jitter_degradation: Degradation,
+    loss_degradation: Degradation,
+    retransmit_degradation: Degradation,
+    /// One per ping target, in the same order
+    jitter_estimators: Vec<JitterEstimator>,
+    sampler: Option<LatencySampler>,
+    resource_guard: Option<ResourceGuard>,
+    wired_interface: Option<String>,
+    guest: Option<GuestProbe>,
+    last_guest_probe: Option<Instant>,
+    public_ip: Option<PublicIpLookup>,
+    retention: Option<chrono::TimeDelta>,
+    max_db_bytes: Option<u64>,
+    influx: Option<InfluxPush>,
+    mqtt: Option<MqttPublish>,
+    forwarder: Option<Forwarder>,
+    webhooks: Option<Webhooks>,
+    email: Option<EmailNotifier>,
+    chat: Option<ChatNotifier>,
+    toasts: Option<DesktopToasts>,
+    exec: Option<ExecHooks>,
+    alert_rules: Option<AlertRules>,
+    interface: Option<String>,
+    udp_probe: Option<UdpStreamProbe>,
+    peer_probe: Option<UdpStreamProbe>,
+    peer_listen: Option<std::net::SocketAddr>,
+    syslog_listen: Option<std::net::SocketAddr>,
+    capture: Option<RollingCapture>,
+    fast_interval_secs: Option<u64>,
+    current_interval_secs: u64,
+    calm_since: Option<Instant>,
+    last_neighbor_scan: Option<Instant>,
+    /// The next snapshot is the first after a pause or a guest network switch, so
+    /// counter deltas span the gap
+    resumed: bool,
+    runner: CommandRunner,
+    incidents: IncidentTracker,
+    /// Incident the last outage alert was for, persisted so a restart doesn't repeat it
+    notified_incident: Option<String>,
+    shutdown: ShutdownHandle,
+    power: PowerState,
+    only_ssid: Option<String>,
+    include_other_networks: bool,
+    /// SSID of the last connected snapshot, so disconnects are attributed to that network
+    last_seen_ssid: Option<String>,
+    on_other_network: bool,
+    maintenance: Vec<MaintenanceWindow>,
+    exclude_maintenance: bool,
+    /// Whether the last snapshot was taken in a maintenance window
+    in_maintenance: bool,
+}
+
+/// An ongoing degraded condition, tracked until it has cleared its recovery band for
+/// enough snapshots in a row. Its events are reported when it starts and again only when
+/// it gets more severe, so a value hovering around a threshold is one condition, not a
+/// warning per snapshot.
+#[derive(Debug, Clone, Default)]
+struct Degradation {
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    worst: f64,
+    /// Ties the condition's events to its recovery event
+    id: Option<String>,
+    /// The most severe event reported for the condition so far
+    reported: Option<EventSeverity>,
+    /// The most severe event the latest snapshot raised for it, reported or not
+    current: Option<EventSeverity>,
+    /// First of the recovered snapshots in a row, and how many there have been
+    clear_since: Option<chrono::DateTime<chrono::Utc>>,
+    clear_streak: u32,
+}
+
+/// A condition that cleared
+struct Resolved {
+    id: String,
+    started_at: chrono::DateTime<chrono::Utc>,
+    duration_secs: i64,
+    worst: f64,
+}
+
+impl Degradation {
+    /// Feed one sample; returns the condition once it has been recovered for
+    /// `resolve_samples` snapshots in a row, timed to the first of them
+    fn track(
+        &mut self,
+        at: chrono::DateTime<chrono::Utc>,
+        value: f64,
+        degraded: bool,
+        recovered: bool,
+        lower_is_worse: bool,
+        resolve_samples: u32,
+    ) -> Option<Resolved> {
+        if let Some(since) = self.since {
+            if recovered {
+                let clear_since = *self.clear_since.get_or_insert(at);
+                self.clear_streak += 1;
+                if self.clear_streak < resolve_samples {
+                    return None;
+                }
+                let resolved = Resolved {
+                    id: self.id.take().unwrap_or_default(),
+                    started_at: since,
+                    duration_secs: (clear_since - since).num_seconds(),
+                    worst: self.worst,
+                };
+                *self = Self::default();
+                return Some(resolved);
+            }
+            self.clear_since = None;
+            self.clear_streak = 0;
+            let worse = if lower_is_worse { value < self.worst } else { value > self.worst };
+            if worse {
+                self.worst = value;
+            }
+        } else if degraded {
+            self.since = Some(at);
+            self.worst = value;
+            self.id = Some(uuid::Uuid::new_v4().to_string());
+        }
+        None
+    }
+
+    /// Whether an event of `severity` for the condition is reported: the first, as
+    /// `started`, and any more severe than those before it, as `escalated`
+    fn admit(&mut self, severity: &EventSeverity) -> Option<&'static str> {
+        if self.current.as_ref().is_none_or(|c| severity > c) {
+            self.current = Some(severity.clone());
+        }
+        let stage = match self.reported {
+            None => "started",
+            Some(ref reported) if severity > reported => "escalated",
+            Some(_) => return None,
+        };
+        self.reported = Some(severity.clone());
+        Some(stage)
+    }
+}
+
+/// RFC 3550 interarrival jitter for one ping target, J += (|D| - J) / 16, where D
+/// is the difference between consecutive round-trip times. Lost replies are skipped.
+#[derive(Debug, Clone, Default)]
+struct JitterEstimator {
+    jitter: Option<f64>,
+    last_rtt: Option<f64>,
+}
+
+impl JitterEstimator {
+    fn update(&mut self, rtts: &[f64]) -> Option<f64> {
+        for &rtt in rtts {
+            if let Some(last) = self.last_rtt {
+                let jitter = self.jitter.unwrap_or(0.0);
+                self.jitter = Some(jitter + ((rtt - last).abs() - jitter) / 16.0);
+            }
+            self.last_rtt = Some(rtt);
+        }
+        self.jitter
+    }
+}
+
+/// Learned negotiated link rate for one access point (BSSID)
+#[derive(Debug, Clone, Default)]
+struct LinkRateBaseline {
+    samples: u32,
+    rx_mbps: f64,
+    tx_mbps: f64,
+    degraded_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Samples needed before a baseline is trusted
+const BASELINE_MIN_SAMPLES: u32 = 12;
+/// Weight of each new sample in the moving average
+const BASELINE_ALPHA: f64 = 0.05;
+/// Rate below this fraction of the baseline counts as degraded
+const SPEED_DEGRADED_RATIO: f64 = 0.5;
+/// Rate must climb back above this fraction of the baseline to count as recovered
+const SPEED_RECOVERED_RATIO: f64 = 0.75;
+
+/// Retransmissions per cycle below this are too few to alert on
+const TCP_RETRANSMIT_MIN_SEGMENTS: f64 = 10.0;
+
+/// Consecutive lost 1s pings reported as a short outage
+const SHORT_OUTAGE_MIN_SECS: u32 = 3;
+
+/// How WiFi and network details are read: netsh, ping, ipconfig and friends
+pub(crate) const PLATFORM_BACKEND: &str = "windows-cli";
+
+/// Stored snapshots a slow dashboard connection may fall behind by before it skips ahead
+const LIVE_UPDATES_CAPACITY: usize = 16;
+
+/// How long conditions must stay non-critical before the fast interval is relaxed
+const FAST_INTERVAL_HOLD: Duration = Duration::from_secs(30);
+
+/// Connectivity check endpoints; both answer 204 when nothing is in the way
+pub(crate) const HTTP_CHECK_URL: &str = "http://www.gstatic.com/generate_204";
+const HTTPS_CHECK_URL: &str = "https://www.gstatic.com/generate_204";
+const HTTP_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Echo requests per target in the outage diagnostic burst
+const BURST_PING_COUNT: u32 = 10;
+/// Domain resolved through every resolver in the outage diagnostic burst
+const BURST_DNS_DOMAIN: &str = "www.msftconnecttest.com";
+
+/// DNS answers slower than this point at the resolver during a latency spike
+const INVESTIGATION_SLOW_DNS_MS: f64 = 200.0;
+
+/// How often nearby access points are scanned for channel recommendations
+pub(crate) const NEIGHBOR_SCAN_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How long to wait for an adapter to join a network and get an address
+const WLAN_CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Where and how often the guest SSID is measured
+#[derive(Debug, Clone)]
+pub struct GuestProbe {
+    pub ssid: String,
+    /// Second adapter kept on the guest network; without one the main adapter
+    /// switches over for the measurement and back
+    pub interface: Option<String>,
+    pub every: Duration,
+}
+
+/// How snapshots are printed in dry-run mode
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Pretty,
+    Json,
+}
+
+/// Where an on-demand diagnostics snapshot is sent once taken
+type DiagnoseReply = oneshot::Sender<anyhow::Result<WifiSnapshot>>;
+
+/// Cloneable handle used by other tasks (e.g. the web server) to talk to a running monitor
+#[derive(Clone)]
+pub struct MonitorHandle {
+    injector: mpsc::UnboundedSender<NetworkEvent>,
+    paused: Arc<watch::Sender<Option<PauseWindow>>>,
+    /// When monitoring was stopped, while it is
+    stopped: Arc<watch::Sender<Option<chrono::DateTime<chrono::Utc>>>>,
+    saved: broadcast::Sender<Arc<WifiSnapshot>>,
+    diagnose: mpsc::UnboundedSender<DiagnoseReply>,
+}
+
+impl MonitorHandle {
+    /// Stop taking snapshots until `resume`. Returns the new pause window, or
+    /// `None` when already paused.
+    pub fn pause(&self, reason: Option<String>) -> Option<PauseWindow> {
+        let window = PauseWindow {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: chrono::Utc::now(),
+            ended_at: None,
+            reason,
+        };
+        let paused = self.paused.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(window.clone());
+            true
+        });
+        paused.then_some(window)
+    }
+
+    /// Continue monitoring; returns whether it was paused
+    pub fn resume(&self) -> bool {
+        self.paused.send_if_modified(|current| current.take().is_some())
+    }
+
+    /// The current pause window, if paused
+    pub fn paused(&self) -> Option<PauseWindow> {
+        self.paused.borrow().clone()
+    }
+
+    /// Stop taking snapshots until `start`, after saving the ones queued. Unlike a pause,
+    /// no pause window is recorded. Returns when monitoring stopped, or `None` when it
+    /// already was.
+    pub fn stop(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let now = chrono::Utc::now();
+        let stopped = self.stopped.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(now);
+            true
+        });
+        stopped.then_some(now)
+    }
+
+    /// Take snapshots again after `stop`; returns when monitoring had stopped, or `None`
+    /// when it wasn't stopped
+    pub fn start(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let mut since = None;
+        self.stopped.send_if_modified(|current| {
+            since = current.take();
+            since.is_some()
+        });
+        since
+    }
+
+    /// When monitoring was stopped, if it is
+    pub fn stopped(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        *self.stopped.borrow()
+    }
+
+    /// Whether the monitoring loop is still running; false once it has exited or panicked
+    pub fn is_running(&self) -> bool {
+        !self.injector.is_closed()
+    }
+
+    /// Pause and resume by typing `p` and Enter in the console
+    pub fn toggle_pause_on_enter(&self) {
+        use std::io::{BufRead, IsTerminal};
+        if !std::io::stdin().is_terminal() {
+            return;
+        }
+        info!("Type p and press Enter to pause or resume monitoring");
+
+        // A plain thread, since a blocking stdin read would hold up runtime shutdown
+        let handle = self.clone();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if !line.trim().eq_ignore_ascii_case("p") {
+                    continue;
+                }
+                if !handle.resume() {
+                    handle.pause(Some("console".to_string()));
+                }
+            }
+        });
+    }
+
+    /// Each snapshot, with its events, as soon as it is stored
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<WifiSnapshot>> {
+        self.saved.subscribe()
+    }
+
+    /// Take a snapshot right away, outside the interval, with the full diagnostic burst
+    /// (pings, traceroute, AP scan and DNS through every resolver). Returns it once it
+    /// is queued for storage.
+    pub async fn diagnose(&self) -> anyhow::Result<WifiSnapshot> {
+        let (reply, snapshot) = oneshot::channel();
+        self.diagnose
+            .send(reply)
+            .map_err(|_| anyhow::anyhow!("Monitor is not running"))?;
+        snapshot
+            .await
+            .map_err(|_| anyhow::anyhow!("Monitoring was stopped or paused before the diagnostics ran"))?
+    }
+
+    /// Queue an event to be recorded with the next snapshot, going through the
+    /// same logging and storage path as detected events
+    pub fn inject_event(&self, event: NetworkEvent) -> anyhow::Result<()> {
+        self.injector
+            .send(event)
+            .map_err(|_| anyhow::anyhow!("Monitor is not running"))
+    }
+}
+
+/// Build the synthetic event used to verify the alert pipeline end to end
+pub fn test_alert_event(severity: EventSeverity, source: &str) -> NetworkEvent {
+    NetworkEvent::new(
+        EventType::TestAlert,
+        severity.clone(),
+        &format!("Test alert ({:?}) - verifying the alert pipeline", severity),
+    ).with_details(serde_json::json!({
+        "synthetic": true,
+        "source": source
+    }))
+}
+
+/// The Info event closing a condition, with its id, start and duration beside `details`
+fn recovery_event(event_type: EventType, description: &str, resolved: &Resolved, details: serde_json::Value) -> NetworkEvent {
+    let mut event = NetworkEvent::new(event_type, EventSeverity::Info, description).with_details(details);
+    event.add_detail("condition", serde_json::json!("resolved"));
+    event.add_detail("condition_id", serde_json::json!(resolved.id));
+    event.add_detail("degraded_since", serde_json::json!(resolved.started_at.to_rfc3339()));
+    event.add_detail("degraded_duration_secs", serde_json::json!(resolved.duration_secs));
+    event
+}
+
+/// Whether alerts are muted at `at`; rule alerts follow the outage alert mute
+fn alerts_muted(store: &dyn MetricsStore, at: chrono::DateTime<chrono::Utc>) -> bool {
+    match store.get_alert_state() {
+        Ok(state) => state.active_mute(at).is_some(),
+        Err(e) => {
+            error!("Failed to load alert state: {}", e);
+            false
+        }
+    }
+}
+
+/// Record an event from a network that isn't monitored without alerting on it
+fn quiet_event(event: &mut NetworkEvent) {
+    event.add_detail("other_network", serde_json::json!(true));
+    if event.severity > EventSeverity::Info {
+        event.add_detail("original_severity", serde_json::json!(format!("{:?}", event.severity)));
+        event.severity = EventSeverity::Info;
+    }
+}
+
+/// The public address moved; a new ASN means traffic now leaves through another ISP
+fn public_ip_event(previous: &PublicIpInfo, current: &PublicIpInfo) -> NetworkEvent {
+    let isp = |info: &PublicIpInfo| info.isp.clone().unwrap_or_else(|| "unknown ISP".to_string());
+    let description = if previous.asn != current.asn {
+        format!("Public IP changed from {} to {} and the ISP from {} to {}", previous.ip, current.ip, isp(previous), isp(current))
+    } else {
+        format!("Public IP changed from {} to {} ({})", previous.ip, current.ip, isp(current))
+    };
+    NetworkEvent::new(EventType::PublicIpChange, EventSeverity::Warning, &description)
+        .with_details(serde_json::json!({
+            "old_ip": previous.ip,
+            "new_ip": current.ip,
+            "old_asn": previous.asn,
+            "new_asn": current.asn,
+            "old_isp": previous.isp,
+            "new_isp": current.isp,
+            "isp_changed": previous.asn != current.asn,
+        }))
 }
 
 #[derive(Debug, Clone)]
@@ -26,99 +476,917 @@ struct MonitorState {
     last_band: Option<WifiBand>,
     last_signal_dbm: Option<i32>,
     last_ip: Option<String>,
+    last_ipv6: Option<String>,
+    last_gateway: Option<String>,
     internet_was_reachable: bool,
+    adapter_present: bool,
+    adapter_missing_since: Option<chrono::DateTime<chrono::Utc>>,
+    last_adapter_bytes_received: Option<u64>,
+    last_default_route: Option<DefaultRoute>,
+    last_timestamp: chrono::DateTime<chrono::Utc>,
+    last_system_info: SystemNetworkInfo,
 }
 
 impl WifiMonitor {
     pub fn new(
-        store: Arc<MetricsStore>,
+        store: Arc<dyn MetricsStore>,
         interval_secs: u64,
-        ping_targets: Vec<String>,
+        ping_targets: Vec<PingTarget>,
         dns_servers: Vec<String>,
     ) -> Self {
+        let (injector, injected_events) = mpsc::unbounded_channel();
+        let (diagnose, diagnose_requests) = mpsc::unbounded_channel();
+        let paused = Arc::new(watch::Sender::new(None));
+        let stopped = Arc::new(watch::Sender::new(None));
+        let (saved, _) = broadcast::channel(LIVE_UPDATES_CAPACITY);
         Self {
             store,
             interval_secs,
             ping_targets,
+            ping_options: PingOptions::default(),
             dns_servers,
             thresholds: AlertThresholds::default(),
             last_state: None,
+            wlan_event_log: None,
+            handle: MonitorHandle { injector, paused, stopped, saved, diagnose },
+            injected_events,
+            diagnose_requests,
+            dry_run: None,
+            link_baselines: HashMap::new(),
+            signal_degradation: Degradation::default(),
+            latency_degradation: Degradation::default(),
+            dns_degradation: Degradation::default(),
+            jitter_degradation: Degradation::default(),
+            loss_degradation: Degradation::default(),
+            retransmit_degradation: Degradation::default(),
+            jitter_estimators: Vec::new(),
+            sampler: None,
+            resource_guard: None,
+            wired_interface: None,
+            guest: None,
+            last_guest_probe: None,
+            public_ip: None,
+            retention: None,
+            max_db_bytes: None,
+            influx: None,
+            mqtt: None,
+            forwarder: None,
+            webhooks: None,
+            email: None,
+            chat: None,
+            toasts: None,
+            exec: None,
+            alert_rules: None,
+            interface: None,
+            udp_probe: None,
+            peer_probe: None,
+            peer_listen: None,
+            syslog_listen: None,
+            capture: None,
+            fast_interval_secs: None,
+            current_interval_secs: interval_secs,
+            calm_since: None,
+            last_neighbor_scan: None,
+            resumed: false,
+            runner: CommandRunner::default(),
+            incidents: IncidentTracker::new(None),
+            notified_incident: None,
+            shutdown: ShutdownHandle::new(),
+            power: PowerState::new(PowerPolicy::default()),
+            only_ssid: None,
+            include_other_networks: false,
+            last_seen_ssid: None,
+            on_other_network: false,
+            maintenance: Vec::new(),
+            exclude_maintenance: false,
+            in_maintenance: false,
         }
     }
 
+    /// Kill netsh, ping, nslookup and the other probe commands when they overrun these timeouts
+    pub fn with_command_timeouts(mut self, timeouts: CommandTimeouts) -> Self {
+        self.runner = CommandRunner::new(timeouts);
+        self
+    }
+
+    /// Stop after the current snapshot once this is triggered
+    pub fn with_shutdown(mut self, shutdown: ShutdownHandle) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Sample less often and skip heavy probes on battery or metered connections
+    pub fn with_power_policy(mut self, policy: PowerPolicy) -> Self {
+        self.power = PowerState::new(policy);
+        self
+    }
+
+    /// Count, size, timeout and TTL for the loopback and router latency pings
+    pub fn with_ping_options(mut self, options: PingOptions) -> Self {
+        self.ping_options = options;
+        self
+    }
+
+    /// Tag snapshots taken on any other network, and unless `include_other_networks`
+    /// keep them out of alerting (events are downgraded to info, no incidents)
+    pub fn with_only_ssid(mut self, ssid: Option<String>, include_other_networks: bool) -> Self {
+        self.only_ssid = ssid;
+        self.include_other_networks = include_other_networks;
+        self
+    }
+
+    /// Print snapshots instead of persisting them
+    pub fn with_dry_run(mut self, format: Option<OutputFormat>) -> Self {
+        self.dry_run = format;
+        self
+    }
+
+    /// Watch the tracker's own memory/handle usage, optionally restarting at a limit
+    pub fn with_resource_guard(mut self, guard: ResourceGuard) -> Self {
+        self.resource_guard = Some(guard);
+        self
+    }
+
+    /// Monitor this wireless interface (name, description or GUID) instead of the first one netsh lists
+    pub fn with_interface(mut self, interface: Option<String>) -> Self {
+        self.interface = interface;
+        self
+    }
+
+    /// Keep a UDP stream running to a reflector (`host:port`) at `rate_pps` packets per second
+    pub fn with_reflector(mut self, reflector: Option<String>, rate_pps: u32) -> Self {
+        self.udp_probe = reflector.map(|r| UdpStreamProbe::new(r, rate_pps));
+        self
+    }
+
+    /// Probe another tracker instance (`host:port`) to measure one-way delay/loss on the LAN
+    pub fn with_peer(mut self, peer: Option<String>, rate_pps: u32) -> Self {
+        self.peer_probe = peer.map(|p| UdpStreamProbe::new(p, rate_pps));
+        self
+    }
+
+    /// Answer peer probes from another instance on this address
+    pub fn with_peer_listen(mut self, addr: Option<std::net::SocketAddr>) -> Self {
+        self.peer_listen = addr;
+        self
+    }
+
+    /// Accept router syslog on this UDP/TCP address and record entries as events
+    pub fn with_syslog_listen(mut self, addr: Option<std::net::SocketAddr>) -> Self {
+        self.syslog_listen = addr;
+        self
+    }
+
+    /// Keep a rolling packet capture and save it when the connection or internet drops
+    pub fn with_capture(mut self, capture: Option<RollingCapture>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Switch to this shorter interval while a critical condition is active
+    pub fn with_fast_interval(mut self, fast_interval_secs: Option<u64>) -> Self {
+        self.fast_interval_secs = fast_interval_secs.filter(|fast| *fast > 0 && *fast < self.interval_secs);
+        self
+    }
+
+    /// Also measure latency/loss through a wired interface as a baseline
+    pub fn with_wired_interface(mut self, interface: Option<String>) -> Self {
+        self.wired_interface = interface;
+        self
+    }
+
+    /// Periodically measure a guest SSID to compare with the monitored network
+    pub fn with_guest_probe(mut self, guest: Option<GuestProbe>) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Track the public IP and its ISP, raising an event when either changes
+    pub fn with_public_ip_lookup(mut self, lookup: Option<PublicIpLookup>) -> Self {
+        self.public_ip = lookup;
+        self
+    }
+
+    /// Delete data older than this while running
+    pub fn with_retention(mut self, max_age: Option<chrono::TimeDelta>) -> Self {
+        self.retention = max_age;
+        self
+    }
+
+    /// Drop the oldest raw data whenever the database outgrows this many bytes
+    pub fn with_max_db_size(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_db_bytes = max_bytes;
+        self
+    }
+
+    /// Also send every snapshot to an InfluxDB-compatible endpoint
+    pub fn with_influx_push(mut self, push: Option<InfluxPush>) -> Self {
+        self.influx = push;
+        self
+    }
+
+    /// Also publish every snapshot and its events to an MQTT broker
+    pub fn with_mqtt(mut self, mqtt: Option<MqttPublish>) -> Self {
+        self.mqtt = mqtt;
+        self
+    }
+
+    /// Raise events at these levels instead of the defaults
+    pub fn with_thresholds(mut self, thresholds: AlertThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Also forward every snapshot and incident to a remote collector
+    pub fn with_forwarder(mut self, forwarder: Option<Forwarder>) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    /// Also POST matching events to webhooks
+    pub fn with_webhooks(mut self, webhooks: Option<Webhooks>) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Also email alert rule firings and severe events
+    pub fn with_email(mut self, email: Option<EmailNotifier>) -> Self {
+        self.email = email;
+        self
+    }
+
+    /// Also post events and alert rule changes to chat channels
+    pub fn with_chat(mut self, chat: Option<ChatNotifier>) -> Self {
+        self.chat = chat;
+        self
+    }
+
+    /// Also show desktop notifications for severe events and alerts
+    pub fn with_toasts(mut self, toasts: Option<DesktopToasts>) -> Self {
+        self.toasts = toasts;
+        self
+    }
+
+    /// Also run commands for events and alert rule changes
+    pub fn with_exec(mut self, exec: Option<ExecHooks>) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    /// Hold back notifications during these windows, still recording events. With
+    /// `exclude`, their snapshots are also left out of uptime and health statistics.
+    pub fn with_maintenance(mut self, windows: Vec<MaintenanceWindow>, exclude: bool) -> Self {
+        self.maintenance = windows;
+        self.exclude_maintenance = exclude;
+        self
+    }
+
+    /// Fire and resolve alerts by these rules
+    pub fn with_alert_rules(mut self, rules: Option<AlertRules>) -> Self {
+        self.alert_rules = rules;
+        self
+    }
+
+    pub fn handle(&self) -> MonitorHandle {
+        self.handle.clone()
+    }
+
+    /// Ingest disconnect reasons from the WLAN-AutoConfig event log
+    pub fn with_wlan_event_log(mut self, enabled: bool) -> Self {
+        self.wlan_event_log = enabled.then(WlanEventLogCollector::new);
+        self
+    }
+
     pub async fn start(mut self) {
         info!("Starting WiFi monitoring with {}s interval", self.interval_secs);
         let mut interval = time::interval(Duration::from_secs(self.interval_secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // Background 1s pings to the gateway and first internet target
+        if let Some(target) = self.ping_targets.first() {
+            let sampler = LatencySampler::new(target.host.clone());
+            tokio::spawn(sampler.clone().run());
+            self.sampler = Some(sampler);
+        }
+        if let Some(ref probe) = self.udp_probe {
+            tokio::spawn(probe.clone().run());
+        }
+        if let Some(ref probe) = self.peer_probe {
+            tokio::spawn(probe.clone().run());
+        }
+        if let Some(addr) = self.peer_listen {
+            tokio::spawn(async move {
+                if let Err(e) = crate::reflector::run_reflector(addr).await {
+                    error!("Peer responder on {} stopped: {}", addr, e);
+                }
+            });
+        }
+        if let Some(addr) = self.syslog_listen {
+            let handle = self.handle();
+            tokio::spawn(async move {
+                if let Err(e) = crate::syslog::run_listener(addr, handle).await {
+                    error!("Syslog listener on {} stopped: {}", addr, e);
+                }
+            });
+        }
+
+        if self.dry_run.is_none() {
+            tokio::spawn(storage::maintain(self.store.clone(), self.retention, self.shutdown.clone()));
+            if let Some(max_bytes) = self.max_db_bytes {
+                tokio::spawn(storage::cap_size(self.store.clone(), max_bytes, self.shutdown.clone()));
+            }
+        }
+        let writer = self.dry_run.is_none().then(|| SnapshotWriter::spawn(self.store.clone(), self.handle.saved.clone()));
+
+        // Record adapter driver and power settings once per run
+        let adapter_info = self.collect_adapter_info().await;
+        info!(
+            driver = %adapter_info.driver_description,
+            version = %adapter_info.driver_version,
+            date = ?adapter_info.driver_date,
+            "WiFi adapter"
+        );
+        if self.dry_run.is_none() {
+            if let Err(e) = self.store.save_adapter_info(&adapter_info) {
+                error!("Failed to save adapter info: {}", e);
+            }
+        }
+
+        // Pick up an incident still open from before a restart
+        match self.store.get_incidents(None, None, Some("open")) {
+            Ok(open) => self.incidents = IncidentTracker::new(open.into_iter().next()),
+            Err(e) => error!("Failed to load open incidents: {}", e),
+        }
+        match self.store.get_alert_state() {
+            Ok(state) => {
+                if let Some(mute) = state.active_mute(chrono::Utc::now()) {
+                    info!("Outage alerts muted until {}", mute.until.format("%Y-%m-%d %H:%M:%S UTC"));
+                }
+                self.notified_incident = state.last_notified_incident;
+            }
+            Err(e) => error!("Failed to load alert state: {}", e),
+        }
 
         loop {
-            interval.tick().await;
-            
-            match self.collect_snapshot().await {
-                Ok(snapshot) => {
+            // Shutdown is only honored between snapshots, so the last one is complete
+            // An on-demand diagnostics request takes a snapshot without waiting for the tick
+            let diagnose = tokio::select! {
+                _ = interval.tick() => None,
+                Some(reply) = self.diagnose_requests.recv() => Some(reply),
+                _ = self.shutdown.wait() => break,
+            };
+
+            if self.handle.stopped().is_some() {
+                if let Some(ref writer) = writer {
+                    writer.flush().await;
+                }
+                if !self.wait_while_stopped().await {
+                    break;
+                }
+                interval.reset();
+                continue;
+            }
+
+            if let Some(window) = self.handle.paused() {
+                if !self.wait_while_paused(window).await {
+                    break;
+                }
+                interval.reset();
+                continue;
+            }
+
+            match self.collect_snapshot(diagnose.is_some()).await {
+                Ok(mut snapshot) => {
+                    // Group the snapshot's events into the current incident; other
+                    // networks' problems aren't the monitored network's incidents
+                    let suppressed = self.suppressed(&snapshot);
+                    let incident = if suppressed {
+                        None
+                    } else {
+                        let serious = self.serious_condition();
+                        self.incidents.observe(&mut snapshot, serious)
+                    };
+
+                    if let Some(ref incident) = incident {
+                        self.alert_on(incident);
+                    }
+                    let alerts = match self.alert_rules {
+                        Some(ref mut rules) if !suppressed => {
+                            let (store, quiet) = (&self.store, self.in_maintenance);
+                            rules.evaluate(&snapshot, || quiet || alerts_muted(store.as_ref(), snapshot.timestamp))
+                        }
+                        _ => Vec::new(),
+                    };
+                    for alert in &alerts {
+                        self.notify_alert(alert, &snapshot);
+                    }
+
                     // Log summary
                     self.log_snapshot_summary(&snapshot);
                     
-                    // Store the snapshot
-                    if let Err(e) = self.store.save_snapshot(&snapshot) {
-                        error!("Failed to save snapshot: {}", e);
+                    // Store the snapshot, or just print it in dry-run mode
+                    if let Some(format) = self.dry_run {
+                        print_snapshot(&snapshot, format);
+                    } else if let Some(ref writer) = writer {
+                        writer.save_snapshot(&snapshot).await;
+                        if let Some(ref incident) = incident {
+                            writer.save_incident(incident).await;
+                        }
+                        for alert in &alerts {
+                            writer.save_alert(alert).await;
+                        }
+                    }
+                    if let Some(ref influx) = self.influx {
+                        influx.send(&snapshot);
+                    }
+                    if let Some(ref mqtt) = self.mqtt {
+                        mqtt.send(&snapshot);
+                    }
+                    if let Some(ref forwarder) = self.forwarder {
+                        forwarder.send(&snapshot);
+                        if let Some(ref incident) = incident {
+                            forwarder.send_incident(incident);
+                        }
+                    }
+                    // Maintenance windows hold events back from notifications; test alerts
+                    // still go through, as they check the notifications themselves
+                    let notified: Vec<&NetworkEvent> = snapshot.events.iter()
+                        .filter(|e| !self.in_maintenance || e.event_type == EventType::TestAlert)
+                        .collect();
+                    if let Some(ref webhooks) = self.webhooks {
+                        for event in &notified {
+                            webhooks.send(event);
+                        }
+                    }
+                    if let Some(ref email) = self.email {
+                        for event in &notified {
+                            email.send(event);
+                        }
+                    }
+                    if let Some(ref chat) = self.chat {
+                        for event in &notified {
+                            chat.send(event, &snapshot);
+                        }
+                    }
+                    if let Some(ref exec) = self.exec {
+                        for event in &notified {
+                            exec.send(event);
+                        }
+                    }
+                    if let Some(ref mut toasts) = self.toasts {
+                        // Desktop notifications keep quiet during a mute, like outage alerts
+                        let wanted = notified.iter().any(|e| toasts.wants(&e.severity));
+                        if wanted && !alerts_muted(self.store.as_ref(), snapshot.timestamp) {
+                            for event in &notified {
+                                toasts.send(event);
+                            }
+                        }
                     }
                     
                     // Update state for next iteration
                     self.update_state(&snapshot);
+
+                    if self.adapt_interval(&snapshot) {
+                        let period = Duration::from_secs(self.current_interval_secs);
+                        interval = time::interval_at(time::Instant::now() + period, period);
+                        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    }
+
+                    if let Some(reply) = diagnose {
+                        let _ = reply.send(Ok(snapshot));
+                    }
                 }
                 Err(e) => {
                     error!("Failed to collect snapshot: {}", e);
+                    if let Some(reply) = diagnose {
+                        let _ = reply.send(Err(e));
+                    }
                 }
             }
+
+            // Check our own footprint between cycles, and restart once queued snapshots are stored
+            if let Some(ref mut guard) = self.resource_guard {
+                if guard.check(&self.runner).await == GuardAction::Restart {
+                    if let Some(ref writer) = writer {
+                        writer.flush().await;
+                        selfmon::restart_process();
+                    }
+                }
+            }
+        }
+
+        if let Some(writer) = writer {
+            writer.close().await;
+        }
+        info!("Monitoring stopped");
+    }
+
+    /// Log an alert rule firing or resolving and pass it to the webhooks, email, chat,
+    /// command hooks and desktop notifications. Muted alerts are stored but go nowhere else, resolving included.
+    fn notify_alert(&mut self, alert: &Alert, snapshot: &WifiSnapshot) {
+        match (alert.resolved_at, alert.muted) {
+            (Some(_), _) => info!(alert_id = %alert.id, "Alert {} resolved", alert.name),
+            (None, true) => info!(alert_id = %alert.id, "Alert {} muted: {}", alert.name, alert.message),
+            (None, false) if alert.severity >= EventSeverity::Error => error!(alert_id = %alert.id, "Alert {}: {}", alert.name, alert.message),
+            (None, false) if alert.severity == EventSeverity::Warning => warn!(alert_id = %alert.id, "Alert {}: {}", alert.name, alert.message),
+            (None, false) => info!(alert_id = %alert.id, "Alert {}: {}", alert.name, alert.message),
+        }
+        if alert.muted {
+            return;
+        }
+        if let Some(ref webhooks) = self.webhooks {
+            webhooks.send_alert(alert);
+        }
+        if let Some(ref email) = self.email {
+            email.send_alert(alert);
+        }
+        if let Some(ref chat) = self.chat {
+            chat.send_alert(alert, snapshot);
+        }
+        if let Some(ref exec) = self.exec {
+            exec.send_alert(alert);
+        }
+        if let Some(ref mut toasts) = self.toasts {
+            toasts.send_alert(alert);
+        }
+    }
+
+    /// Raise one outage alert per incident, unless alerts are muted or a maintenance window
+    /// is on. Held back alerts are not sent later, and neither is the alert for an incident
+    /// continued after a restart.
+    fn alert_on(&mut self, incident: &Incident) {
+        if incident.status != IncidentStatus::Open || self.notified_incident.as_deref() == Some(incident.id.as_str()) {
+            return;
+        }
+        let now = chrono::Utc::now();
+        let state = self.store.get_alert_state().unwrap_or_else(|e| {
+            error!("Failed to load alert state: {}", e);
+            AlertState::default()
+        });
+        match state.active_mute(now) {
+            _ if self.in_maintenance => info!(
+                incident_id = %incident.id,
+                "Outage alert held back during a maintenance window: {}",
+                incident.summary
+            ),
+            Some(mute) => info!(
+                incident_id = %incident.id,
+                "Outage alert muted until {}: {}",
+                mute.until.format("%Y-%m-%d %H:%M:%S UTC"),
+                incident.summary
+            ),
+            None => error!(
+                incident_id = %incident.id,
+                "Outage alert: {} ({})",
+                incident.summary,
+                incident.root_cause.label()
+            ),
+        }
+
+        self.notified_incident = Some(incident.id.clone());
+        if let Err(e) = self.store.record_notified(&incident.id, now) {
+            error!("Failed to save alert state: {}", e);
+        }
+    }
+
+    /// Sit out a pause, recording its window. Session state (last readings, open
+    /// incident, degradations) is kept, so monitoring picks up where it left off.
+    /// Returns false if shutdown was requested meanwhile.
+    async fn wait_while_paused(&mut self, mut window: PauseWindow) -> bool {
+        match window.reason {
+            Some(ref reason) => info!("Monitoring paused ({})", reason),
+            None => info!("Monitoring paused"),
+        }
+        if self.dry_run.is_none() {
+            if let Err(e) = self.store.save_pause(&window) {
+                error!("Failed to save pause: {}", e);
+            }
+        }
+
+        let mut paused = self.handle.paused.subscribe();
+        let resumed = tokio::select! {
+            _ = paused.wait_for(|p| p.is_none()) => true,
+            _ = self.shutdown.wait() => false,
+        };
+
+        let ended_at = chrono::Utc::now();
+        window.ended_at = Some(ended_at);
+        if self.dry_run.is_none() {
+            if let Err(e) = self.store.save_pause(&window) {
+                error!("Failed to save pause: {}", e);
+            }
+        }
+        if resumed {
+            info!("Monitoring resumed after {}s", (ended_at - window.started_at).num_seconds());
+        }
+        self.discard_probe_windows();
+        resumed
+    }
+
+    /// Idle from `stop` until `start`; false when shutting down instead
+    async fn wait_while_stopped(&mut self) -> bool {
+        info!("Monitoring stopped on request; waiting to be started again");
+        let mut stopped = self.handle.stopped.subscribe();
+        let started = tokio::select! {
+            _ = stopped.wait_for(|s| s.is_none()) => true,
+            _ = self.shutdown.wait() => false,
+        };
+        if started {
+            info!("Monitoring started");
+        }
+        self.discard_probe_windows();
+        started
+    }
+
+    /// Drop what the background probes saw while no snapshots were taken, so the next
+    /// snapshot only covers its own interval
+    fn discard_probe_windows(&mut self) {
+        if let Some(ref sampler) = self.sampler {
+            sampler.take_window();
         }
+        if let Some(ref probe) = self.udp_probe {
+            probe.take_window();
+        }
+        if let Some(ref probe) = self.peer_probe {
+            probe.take_window();
+        }
+        self.resumed = true;
     }
 
-    async fn collect_snapshot(&self) -> anyhow::Result<WifiSnapshot> {
+    /// Take one snapshot. `on_demand` runs the heavy probes and the diagnostic burst
+    /// regardless of power policy and schedule.
+    async fn collect_snapshot(&mut self, on_demand: bool) -> anyhow::Result<WifiSnapshot> {
+        let cycle_started = Instant::now();
         let mut snapshot = WifiSnapshot::new();
+        snapshot.interval_secs = Some(self.current_interval_secs);
         let mut events = Vec::new();
 
         // Collect WiFi information
-        snapshot.wifi_info = self.collect_wifi_info(&mut events).await;
+        let (wifi_info, wifi_info_ms) = timed(self.collect_wifi_info(&mut events)).await;
+        snapshot.wifi_info = wifi_info;
+        snapshot.probe_durations.wifi_info_ms = wifi_info_ms;
 
-        // Collect system network stats
-        snapshot.system_info = self.collect_system_info();
+        if let (Some(capture), Some(wifi)) = (&self.capture, &snapshot.wifi_info) {
+            capture.ensure_started(&wifi.adapter_mac);
+        }
 
-        // Test connectivity (pass gateway if available)
-        let gateway = snapshot.wifi_info.as_ref().and_then(|w| w.gateway.as_deref());
-        snapshot.connectivity = self.test_connectivity(gateway).await;
+        // Tag measurements taken away from the monitored network
+        let ssid = snapshot.wifi_info.as_ref().map(|w| w.ssid.clone());
+        if ssid.is_some() {
+            self.last_seen_ssid = ssid.clone();
+        }
+        snapshot.other_network = self.on_other_network();
+        snapshot.maintenance = self.in_maintenance(snapshot.timestamp) && self.exclude_maintenance;
+        let suppressed = self.suppressed(&snapshot);
+
+        // Back off on battery or metered connections
+        snapshot.sampling = self.power.refresh(&self.runner, ssid.as_deref()).await;
+        // Heavy probes are also pointless for a network that isn't being monitored
+        let skip_heavy = self.power.skip_heavy_probes() || suppressed;
+
+        // Scan nearby access points every few minutes
+        let scan_due = self.last_neighbor_scan.is_none_or(|t| t.elapsed() >= NEIGHBOR_SCAN_INTERVAL);
+        if on_demand || (!skip_heavy && scan_due) {
+            self.last_neighbor_scan = Some(Instant::now());
+            snapshot.neighbors = self.scan_neighbors().await;
+        }
 
-        // Measure latency (pass gateway for router latency)
-        snapshot.latency = self.measure_latency(gateway).await;
+        // Collect system network stats
+        let system_info_started = Instant::now();
+        let adapter_name = snapshot.wifi_info.as_ref().map(|w| w.adapter_name.clone());
+        snapshot.system_info = self.collect_system_info(adapter_name.as_deref()).await;
+        self.compute_rates(&mut snapshot);
+        let wifi_address = snapshot.wifi_info.as_ref().and_then(|w| w.ipv4_address.as_deref());
+        snapshot.system_info.default_route = self.collect_default_route(wifi_address).await;
+        snapshot.probe_durations.system_info_ms = system_info_started.elapsed().as_millis() as u64;
+
+        // Connectivity (pass gateway if available), latency, the wired baseline and DNS
+        // are independent, so they run side by side and a slow probe doesn't hold up the
+        // others. With a wired baseline the WiFi pings are pinned to the WiFi address so
+        // they don't leave over Ethernet.
+        let gateway = snapshot.wifi_info.as_ref().and_then(|w| w.gateway.as_deref());
+        let wifi_source = self.wired_interface.as_ref()
+            .and(snapshot.wifi_info.as_ref())
+            .and_then(|w| w.ipv4_address.as_deref());
+        let wired = async {
+            match self.wired_interface {
+                Some(ref interface) => Some(self.measure_wired(interface).await),
+                None => None,
+            }
+        };
+        let ((connectivity, connectivity_ms), (latency, latency_ms), (wired, wired_ms), (dns_metrics, dns_ms)) = tokio::join!(
+            timed(self.test_connectivity(gateway)),
+            timed(self.measure_latency(gateway, wifi_source)),
+            timed(wired),
+            timed(self.test_dns()),
+        );
+        snapshot.connectivity = connectivity;
+        snapshot.latency = latency;
+        self.update_jitter(&mut snapshot.latency);
+        snapshot.latency.mos = snapshot.latency.estimate_mos();
+        snapshot.dns_metrics = dns_metrics;
+        snapshot.probe_durations.connectivity_ms = connectivity_ms;
+        snapshot.probe_durations.latency_ms = latency_ms;
+        snapshot.probe_durations.dns_ms = dns_ms;
+        if wired.is_some() {
+            snapshot.probe_durations.wired_ms = Some(wired_ms);
+        }
+        snapshot.wired = wired;
 
-        // Test DNS
-        snapshot.dns_metrics = self.test_dns().await;
+        // Fold in what the continuous sampler saw since the last snapshot
+        if let Some(ref sampler) = self.sampler {
+            snapshot.latency.continuous = sampler.take_window();
+            sampler.set_gateway(gateway);
+        }
+        if let Some(ref probe) = self.udp_probe {
+            snapshot.latency.udp_stream = probe.take_window();
+        }
+        if let Some(ref probe) = self.peer_probe {
+            snapshot.latency.peer = probe.take_window();
+        }
 
         // Detect events based on state changes and thresholds
         self.detect_events(&snapshot, &mut events);
+        self.detect_speed_changes(&snapshot, &mut events);
+        let latency_was_normal = self.latency_degradation.since.is_none();
+        self.detect_recoveries(&snapshot, &mut events);
+        self.debounce(&mut events);
+
+        // Investigate a new latency spike while it is still happening
+        if latency_was_normal && !skip_heavy {
+            if let Some(event) = events.iter_mut().find(|e| e.event_type == EventType::HighLatency) {
+                event.add_detail("investigation", self.investigate_latency(&snapshot).await);
+            }
+        }
+
+        // Pull disconnect reasons the OS logged since the last cycle
+        if let Some(ref mut collector) = self.wlan_event_log {
+            events.extend(collector.poll(&self.runner).await);
+        }
+
+        // Run the full burst when asked for through the API
+        if on_demand {
+            info!("Running diagnostics on request");
+            let burst = self.diagnostic_burst(&snapshot).await;
+            events.push(NetworkEvent::new(
+                EventType::DiagnosticsRun,
+                EventSeverity::Info,
+                "Diagnostics run on request",
+            ).with_details(serde_json::json!({ "diagnostics": burst })));
+        }
+
+        // Include events injected from outside (e.g. test alerts)
+        while let Ok(event) = self.injected_events.try_recv() {
+            events.push(event);
+        }
+
+        // Capture as much as possible the moment connectivity fails
+        let was_ok = self.last_state.as_ref().is_some_and(|s| s.was_connected && s.internet_was_reachable);
+        let failed = snapshot.wifi_info.is_none() || !snapshot.connectivity.internet_reachable;
+        if was_ok && failed && !skip_heavy && !on_demand {
+            if let Some(event) = events.iter_mut().find(|e| {
+                matches!(e.event_type, EventType::ConnectionDropped | EventType::InternetUnreachable)
+            }) {
+                info!("Connectivity failed, running diagnostic burst");
+                event.add_detail("diagnostics", self.diagnostic_burst(&snapshot).await);
+            }
+        }
+
+        // Save the traffic leading up to an outage
+        if let Some(ref capture) = self.capture {
+            for event in events.iter_mut().filter(|e| {
+                matches!(e.event_type, EventType::ConnectionDropped | EventType::InternetUnreachable)
+            }) {
+                match capture.dump(&format!("{:?}", event.event_type)) {
+                    Ok(path) => event.add_detail("pcap_file", serde_json::json!(path.display().to_string())),
+                    Err(e) => error!("Failed to write packet capture: {}", e),
+                }
+            }
+        }
+
+        // Look up the public address every few minutes, and as soon as the internet is back
+        let recovered = self.last_state.as_ref()
+            .is_some_and(|s| !(s.was_connected && s.internet_was_reachable));
+        if let Some(lookup) = self.public_ip.as_mut() {
+            if snapshot.connectivity.internet_reachable && !suppressed && lookup.due(recovered) {
+                if let Some((info, previous)) = lookup.check().await {
+                    if let Some(previous) = previous {
+                        events.push(public_ip_event(&previous, &info));
+                    }
+                    snapshot.public_ip = Some(info);
+                }
+            }
+        }
+
+        // Report each command that hung and had to be killed
+        for timeout in self.runner.take_timeouts() {
+            events.push(NetworkEvent::new(
+                EventType::ProbeTimedOut,
+                EventSeverity::Warning,
+                &format!("`{}` did not finish within {}s and was killed", timeout.command, timeout.timeout.as_secs()),
+            ).with_details(serde_json::json!({
+                "command": timeout.command,
+                "timeout_secs": timeout.timeout.as_secs(),
+            })));
+        }
+
+        if suppressed {
+            for event in events.iter_mut().filter(|e| e.event_type != EventType::TestAlert) {
+                quiet_event(event);
+            }
+        }
+
+        // Compare with the guest network every few minutes
+        let guest_due = self.guest.as_ref()
+            .is_some_and(|g| self.last_guest_probe.is_none_or(|t| t.elapsed() >= g.every));
+        if guest_due && !skip_heavy {
+            self.last_guest_probe = Some(Instant::now());
+            let (guest, guest_ms) = timed(self.measure_guest(snapshot.wifi_info.as_ref())).await;
+            snapshot.probe_durations.guest_ms = guest.is_some().then_some(guest_ms);
+            snapshot.guest = guest;
+        }
 
         snapshot.events = events;
+        snapshot.probe_durations.total_ms = cycle_started.elapsed().as_millis() as u64;
+        self.resumed = snapshot.guest.as_ref().is_some_and(|g| g.switched);
         Ok(snapshot)
     }
 
+    /// Feed each target's reply times into its RFC 3550 jitter estimate
+    fn update_jitter(&mut self, latency: &mut LatencyMetrics) {
+        self.jitter_estimators.resize_with(self.ping_targets.len(), JitterEstimator::default);
+        for (estimator, result) in self.jitter_estimators.iter_mut().zip(latency.targets.iter_mut()) {
+            result.rfc3550_jitter_ms = estimator.update(&result.individual_times_ms);
+        }
+
+        let values: Vec<f64> = latency.targets.iter().filter_map(|t| t.rfc3550_jitter_ms).collect();
+        if !values.is_empty() {
+            latency.rfc3550_jitter_ms = Some(values.iter().sum::<f64>() / values.len() as f64);
+        }
+    }
+
+    /// Whether the current (or, while disconnected, the last) network isn't `--only-ssid`
+    fn on_other_network(&mut self) -> bool {
+        let Some(ref only) = self.only_ssid else { return false };
+        let other = self.last_seen_ssid.as_ref().is_some_and(|ssid| ssid != only);
+        if other && !self.on_other_network {
+            info!(
+                "Connected to {:?}, not {:?}; measurements are tagged as other-network{}",
+                self.last_seen_ssid.as_deref().unwrap_or_default(),
+                only,
+                if self.include_other_networks { "" } else { " and left out of alerting and statistics" }
+            );
+        } else if !other && self.on_other_network {
+            info!("Back on {:?}", only);
+        }
+        self.on_other_network = other;
+        other
+    }
+
+    /// Whether `at` falls in a maintenance window, logging when one starts and ends
+    fn in_maintenance(&mut self, at: chrono::DateTime<chrono::Utc>) -> bool {
+        let window = maintenance::active(&self.maintenance, at);
+        match window {
+            Some(window) if !self.in_maintenance => info!(
+                "Maintenance window {} started; events are recorded but not notified{}",
+                window,
+                if self.exclude_maintenance { " and left out of uptime and health statistics" } else { "" }
+            ),
+            None if self.in_maintenance => info!("Maintenance window ended; notifications resume"),
+            _ => {}
+        }
+        self.in_maintenance = window.is_some();
+        self.in_maintenance
+    }
+
+    /// Whether a snapshot is kept out of alerting
+    fn suppressed(&self, snapshot: &WifiSnapshot) -> bool {
+        snapshot.other_network && !self.include_other_networks
+    }
+
     async fn collect_wifi_info(&self, events: &mut Vec<NetworkEvent>) -> Option<WifiInfo> {
         // Use netsh to get WiFi information on Windows
-        let output = Command::new("netsh")
-            .args(["wlan", "show", "interfaces"])
-            .output();
+        let output = self.runner.output("netsh", &["wlan", "show", "interfaces"]).await;
 
         match output {
             Ok(output) => {
                 let stdout = String::from_utf8_lossy(&output.stdout);
-                self.parse_netsh_output(&stdout, events)
+                let block = select_block(&stdout, "name", self.interface.as_deref());
+                if block.is_none() {
+                    if let Some(ref wanted) = self.interface {
+                        warn!("Wireless interface {:?} not found in netsh output", wanted);
+                    }
+                }
+                self.parse_netsh_output(block.as_deref().unwrap_or_default(), events).await
             }
             Err(e) => {
-                error!("Failed to run netsh: {}", e);
+                error!("{}", e);
                 None
             }
         }
     }
 
-    fn parse_netsh_output(&self, output: &str, events: &mut Vec<NetworkEvent>) -> Option<WifiInfo> {
+    async fn parse_netsh_output(&self, output: &str, events: &mut Vec<NetworkEvent>) -> Option<WifiInfo> {
         let mut wifi_info = WifiInfo {
             ssid: String::new(),
             bssid: String::new(),
@@ -196,7 +1464,7 @@ impl WifiMonitor {
         }
 
         // Get IP configuration
-        if let Ok(output) = Command::new("ipconfig").output() {
+        if let Ok(output) = self.runner.output::<&str>("ipconfig", &[]).await {
             let stdout = String::from_utf8_lossy(&output.stdout);
             self.parse_ipconfig(&stdout, &mut wifi_info);
         }
@@ -214,14 +1482,30 @@ impl WifiMonitor {
                 })));
             }
 
-            if last_state.last_channel.as_ref() != Some(&wifi_info.channel) && last_state.last_channel.is_some() {
+            if let Some(old_channel) = last_state.last_channel.filter(|c| *c != wifi_info.channel) {
+                // The same AP leaving a DFS channel is most likely a radar evacuation
+                let same_ap = last_state.last_bssid.as_deref() == Some(wifi_info.bssid.as_str());
+                let dfs_exit = same_ap
+                    && last_state.last_band.as_ref().is_some_and(|b| b.is_dfs(old_channel));
+                let (severity, description) = if dfs_exit {
+                    (
+                        EventSeverity::Warning,
+                        format!("Channel changed from DFS channel {} to {} (likely radar detection)", old_channel, wifi_info.channel),
+                    )
+                } else {
+                    (
+                        EventSeverity::Info,
+                        format!("Channel changed from {} to {}", old_channel, wifi_info.channel),
+                    )
+                };
                 events.push(NetworkEvent::new(
                     EventType::ChannelChange,
-                    EventSeverity::Info,
-                    &format!("Channel changed from {:?} to {}", last_state.last_channel, wifi_info.channel),
+                    severity,
+                    &description,
                 ).with_details(serde_json::json!({
-                    "old_channel": last_state.last_channel,
-                    "new_channel": wifi_info.channel
+                    "old_channel": old_channel,
+                    "new_channel": wifi_info.channel,
+                    "dfs_exit": dfs_exit
                 })));
             }
 
@@ -235,28 +1519,75 @@ impl WifiMonitor {
                     "new_band": format!("{:?}", wifi_info.band)
                 })));
             }
+
+            // The last known values are carried over while one is missing, e.g. during a DHCP
+            // renewal or on an IPv4-only network, so only a value that is there can have changed
+            let changed = |old: &Option<String>, new: &Option<String>| old.is_some() && new.is_some() && old != new;
+            let ipv4_changed = changed(&last_state.last_ip, &wifi_info.ipv4_address);
+            let ipv6_changed = changed(&last_state.last_ipv6, &wifi_info.ipv6_address);
+            let gateway_changed = changed(&last_state.last_gateway, &wifi_info.gateway);
+
+            if ipv4_changed || ipv6_changed || gateway_changed {
+                let apipa = wifi_info.ipv4_address.as_deref().map(is_apipa).unwrap_or(false);
+                let (severity, description) = if apipa && ipv4_changed {
+                    (
+                        EventSeverity::Error,
+                        format!(
+                            "Fell back to APIPA address {} (DHCP failed), was {:?}",
+                            wifi_info.ipv4_address.as_deref().unwrap_or_default(),
+                            last_state.last_ip
+                        ),
+                    )
+                } else {
+                    let mut changes = Vec::new();
+                    if ipv4_changed {
+                        changes.push(format!("IPv4 {:?} -> {:?}", last_state.last_ip, wifi_info.ipv4_address));
+                    }
+                    if ipv6_changed {
+                        changes.push(format!("IPv6 {:?} -> {:?}", last_state.last_ipv6, wifi_info.ipv6_address));
+                    }
+                    if gateway_changed {
+                        changes.push(format!("gateway {:?} -> {:?}", last_state.last_gateway, wifi_info.gateway));
+                    }
+                    (EventSeverity::Warning, format!("IP configuration changed: {}", changes.join(", ")))
+                };
+
+                events.push(NetworkEvent::new(
+                    EventType::IpAddressChange,
+                    severity,
+                    &description,
+                ).with_details(serde_json::json!({
+                    "old_ipv4": last_state.last_ip,
+                    "new_ipv4": wifi_info.ipv4_address,
+                    "old_ipv6": last_state.last_ipv6,
+                    "new_ipv6": wifi_info.ipv6_address,
+                    "old_gateway": last_state.last_gateway,
+                    "new_gateway": wifi_info.gateway,
+                    "apipa": apipa
+                })));
+            }
         }
 
         Some(wifi_info)
     }
 
     fn parse_ipconfig(&self, output: &str, wifi_info: &mut WifiInfo) {
+        let adapter = wifi_info.adapter_name.to_lowercase();
         let mut in_wifi_section = false;
         
         for line in output.lines() {
-            let line_lower = line.to_lowercase();
-            
-            // Check if we're entering the WiFi adapter section
-            if line_lower.contains("wireless") || line_lower.contains("wi-fi") || line_lower.contains("wlan") {
-                in_wifi_section = true;
+            // Adapter headers are unindented, e.g. "Wireless LAN adapter Wi-Fi:". Match the
+            // adapter netsh reported so virtual adapters (VPN, Hyper-V, WSL) are skipped.
+            if !line.starts_with(' ') && !line.trim().is_empty() {
+                let header = line.trim().trim_end_matches(':').to_lowercase();
+                in_wifi_section = if adapter.is_empty() {
+                    header.contains("wireless") || header.contains("wi-fi") || header.contains("wlan")
+                } else {
+                    header.ends_with(&format!("adapter {}", adapter))
+                };
                 continue;
             }
             
-            // Check if we're leaving the section (new adapter starts)
-            if !line.starts_with(' ') && !line.is_empty() && in_wifi_section && !line.contains(':') {
-                in_wifi_section = false;
-            }
-            
             if in_wifi_section {
                 if let Some((key, value)) = line.split_once(':') {
                     let key = key.trim().to_lowercase();
@@ -276,7 +1607,77 @@ impl WifiMonitor {
         }
     }
 
-    fn collect_system_info(&self) -> SystemNetworkInfo {
+    async fn collect_adapter_info(&self) -> AdapterInfo {
+        let mut info = AdapterInfo {
+            collected_at: Some(chrono::Utc::now()),
+            ..Default::default()
+        };
+
+        // Driver details
+        if let Ok(output) = self.runner.output("netsh", &["wlan", "show", "drivers"]).await {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let block = select_block(&stdout, "interface name", self.interface.as_deref()).unwrap_or_default();
+            for line in block.lines() {
+                if let Some((key, value)) = line.trim().split_once(':') {
+                    let value = value.trim().to_string();
+                    match key.trim().to_lowercase().as_str() {
+                        "interface name" => info.interface_name = value,
+                        "driver" => info.driver_description = value,
+                        "vendor" => info.vendor = value,
+                        "provider" => info.provider = value,
+                        "date" => info.driver_date = Some(value),
+                        "version" => info.driver_version = value,
+                        "radio types supported" => {
+                            info.radio_types = value.split_whitespace().map(|s| s.to_string()).collect();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // "Allow the computer to turn off this device to save power"
+        if !info.interface_name.is_empty() {
+            let script = format!(
+                "(Get-NetAdapterPowerManagement -Name '{}').AllowComputerToTurnOffDevice",
+                info.interface_name.replace('\'', "''")
+            );
+            if let Ok(output) = self.runner.output("powershell", &["-NoProfile", "-Command", &script]).await {
+                info.allow_turn_off_device = match String::from_utf8_lossy(&output.stdout).trim() {
+                    "Enabled" => Some(true),
+                    "Disabled" => Some(false),
+                    _ => None,
+                };
+            }
+        }
+
+        // Wireless Adapter Settings > Power Saving Mode in the active power plan
+        if let Ok(output) = self.runner
+            .output("powercfg", &[
+                "/query",
+                "SCHEME_CURRENT",
+                "19cbb8fa-5279-450e-9fac-8a3d5fedd0c1",
+                "12bbebe6-58d6-4636-95bb-3217ef867c1a",
+            ])
+            .await
+        {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            for line in stdout.lines() {
+                let Some((key, value)) = line.trim().split_once(':') else { continue };
+                let value = u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok();
+                let key = key.to_lowercase();
+                if key.contains("current ac power setting index") {
+                    info.power_saving_mode_ac = value;
+                } else if key.contains("current dc power setting index") {
+                    info.power_saving_mode_dc = value;
+                }
+            }
+        }
+
+        info
+    }
+
+    async fn collect_system_info(&self, adapter_name: Option<&str>) -> SystemNetworkInfo {
         let mut sys = System::new_all();
         sys.refresh_all();
 
@@ -284,7 +1685,12 @@ impl WifiMonitor {
         
         let mut info = SystemNetworkInfo::default();
         
-        for (_interface_name, data) in &networks {
+        for (interface_name, data) in &networks {
+            if Some(interface_name.as_str()) == adapter_name {
+                info.adapter_bytes_received = Some(data.total_received());
+                info.adapter_bytes_sent = Some(data.total_transmitted());
+            }
+
             // Aggregate all network interface stats
             info.bytes_sent += data.total_transmitted();
             info.bytes_received += data.total_received();
@@ -294,82 +1700,330 @@ impl WifiMonitor {
             info.errors_out += data.total_errors_on_transmitted();
         }
 
+        collect_tcp_counters(&self.runner, &mut info).await;
+
         info.cpu_usage_percent = sys.global_cpu_info().cpu_usage();
         info.memory_usage_percent = (sys.used_memory() as f32 / sys.total_memory() as f32) * 100.0;
 
         info
     }
 
+    /// Per-second rates from the cumulative counters of this and the previous snapshot
+    fn compute_rates(&self, snapshot: &mut WifiSnapshot) {
+        let Some(ref last_state) = self.last_state else { return };
+        if self.resumed {
+            return;
+        }
+        let secs = (snapshot.timestamp - last_state.last_timestamp).num_milliseconds() as f64 / 1000.0;
+        if secs <= 0.0 {
+            return;
+        }
+        let previous = &last_state.last_system_info;
+        let info = &mut snapshot.system_info;
+
+        // Counters that went backwards (reboot of the stack, wrap) give no rate this cycle
+        let (rx, tx, prev_rx, prev_tx) = match (info.adapter_bytes_received, previous.adapter_bytes_received) {
+            (Some(rx), Some(prev_rx)) => (
+                rx,
+                info.adapter_bytes_sent.unwrap_or(0),
+                prev_rx,
+                previous.adapter_bytes_sent.unwrap_or(0),
+            ),
+            _ => (info.bytes_received, info.bytes_sent, previous.bytes_received, previous.bytes_sent),
+        };
+        if rx >= prev_rx && tx >= prev_tx {
+            info.throughput_rx_bytes_per_sec = Some((rx - prev_rx) as f64 / secs);
+            info.throughput_tx_bytes_per_sec = Some((tx - prev_tx) as f64 / secs);
+        }
+
+        if let (Some(sent), Some(prev_sent), Some(retrans), Some(prev_retrans)) = (
+            info.tcp_segments_sent, previous.tcp_segments_sent,
+            info.tcp_segments_retransmitted, previous.tcp_segments_retransmitted,
+        ) {
+            if sent >= prev_sent && retrans >= prev_retrans {
+                let sent_delta = sent - prev_sent;
+                let retrans_delta = retrans - prev_retrans;
+                info.tcp_retransmits_per_sec = Some(retrans_delta as f64 / secs);
+                if sent_delta > 0 {
+                    info.tcp_retransmit_percent = Some(retrans_delta as f64 / sent_delta as f64 * 100.0);
+                }
+            }
+        }
+    }
+
+    /// Extended measurements for the first snapshot of an outage: longer pings to the
+    /// gateway and every target, a traceroute, a fresh AP list and DNS through every resolver
+    async fn diagnostic_burst(&self, snapshot: &WifiSnapshot) -> serde_json::Value {
+        let started = Instant::now();
+        let gateway = snapshot.wifi_info.as_ref().and_then(|w| w.gateway.clone())
+            .or_else(|| self.last_state.as_ref().and_then(|s| s.last_gateway.clone()));
+
+        // Pings and the traceroute run side by side so the burst stays short
+        let ping_tasks: Vec<_> = gateway.iter().chain(self.ping_targets.iter().map(|t| &t.host))
+            .map(|target| {
+                let (runner, target) = (self.runner.clone(), target.clone());
+                tokio::spawn(async move { burst_ping(&runner, &target).await })
+            })
+            .collect();
+        let traceroute = self.ping_targets.first().map(|target| {
+            let (runner, target) = (self.runner.clone(), target.host.clone());
+            tokio::spawn(async move {
+                let args = ["-d", "-h", "15", "-w", "500", &target];
+                diagnostics::command_json(&format!("tracert {}", args.join(" ")), runner.output("tracert", &args).await)
+            })
+        });
+
+        let neighbors = self.scan_neighbors().await;
+        let mut resolvers = self.dns_servers.clone();
+        for server in snapshot.wifi_info.iter().flat_map(|w| w.dns_servers.iter()) {
+            if !resolvers.contains(server) {
+                resolvers.push(server.clone());
+            }
+        }
+        let dns_tasks: Vec<_> = resolvers.iter()
+            .map(|resolver| spawn_dns_query(&self.runner, BURST_DNS_DOMAIN, resolver))
+            .collect();
+        let dns = join_probes(dns_tasks).await;
+
+        let pings = join_probes(ping_tasks).await;
+        let traceroute = match traceroute {
+            Some(task) => task.await.ok(),
+            None => None,
+        };
+
+        serde_json::json!({
+            "duration_ms": started.elapsed().as_millis() as u64,
+            "pings": pings,
+            "traceroute": traceroute,
+            "neighbors": neighbors,
+            "dns": dns,
+            "routing_table": diagnostics::command_json("route print -4", self.runner.output("route", &["print", "-4"]).await),
+        })
+    }
+
+    /// Quick probes taken during a latency spike to tell which hop is slow: a router
+    /// ping burst, one DNS query and the HTTP check's time to first byte
+    async fn investigate_latency(&self, snapshot: &WifiSnapshot) -> serde_json::Value {
+        let started = Instant::now();
+        let router = snapshot.wifi_info.as_ref()
+            .and_then(|w| w.gateway.clone())
+            .map(|gw| {
+                let runner = self.runner.clone();
+                tokio::spawn(async move { burst_ping(&runner, &gw).await })
+            });
+
+        let dns = self.dns_servers.first()
+            .map(|server| spawn_dns_query(&self.runner, BURST_DNS_DOMAIN, server));
+        let ttfb = http_ttfb().await;
+        let dns = match dns {
+            Some(task) => task.await.ok(),
+            None => None,
+        };
+        let router = match router {
+            Some(task) => task.await.ok(),
+            None => None,
+        };
+
+        // The slowest hop explains the spike: the router link, name resolution or upstream
+        let spike_ms = snapshot.latency.average_latency_ms.unwrap_or(self.thresholds.latency_warning_ms);
+        let likely_cause = if router.as_ref().is_some_and(|r| {
+            r.packet_loss_percent > 0.0 || r.avg_ms.is_some_and(|avg| avg >= spike_ms * 0.5)
+        }) {
+            "wifi_or_lan"
+        } else if dns.as_ref().is_some_and(|d| {
+            !d.success || d.resolution_time_ms.is_some_and(|t| t >= INVESTIGATION_SLOW_DNS_MS)
+        }) {
+            "dns"
+        } else if ttfb.as_ref().map_or(true, |t| *t >= self.thresholds.latency_warning_ms) {
+            "upstream"
+        } else {
+            "transient"
+        };
+
+        serde_json::json!({
+            "duration_ms": started.elapsed().as_millis() as u64,
+            "router_ping": router,
+            "dns": dns,
+            "http_ttfb_ms": ttfb.as_ref().ok(),
+            "http_error": ttfb.as_ref().err(),
+            "likely_cause": likely_cause,
+        })
+    }
+
+    /// List nearby access points from the OS's last background scan
+    async fn scan_neighbors(&self) -> Vec<NeighborNetwork> {
+        let mut args = vec!["wlan".to_string(), "show".to_string(), "networks".to_string(), "mode=bssid".to_string()];
+        if let Some(ref interface) = self.interface {
+            args.push(format!("interface={}", interface));
+        }
+        match self.runner.output("netsh", &args).await {
+            Ok(output) => parse_neighbor_scan(&String::from_utf8_lossy(&output.stdout)),
+            Err(e) => {
+                debug!("Failed to scan neighboring networks: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Read the active IPv4 default route(s) from `route print`; the lowest metric wins
+    async fn collect_default_route(&self, wifi_address: Option<&str>) -> Option<DefaultRoute> {
+        let output = match self.runner.output("route", &["print", "-4", "0.0.0.0"]).await {
+            Ok(output) => output,
+            Err(e) => {
+                debug!("Failed to run route print: {}", e);
+                return None;
+            }
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let mut routes = Vec::new();
+        let mut in_active = false;
+        for line in stdout.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("Active Routes") {
+                in_active = true;
+                continue;
+            }
+            if trimmed.starts_with("Persistent Routes") {
+                break;
+            }
+            if !in_active {
+                continue;
+            }
+
+            // Network Destination, Netmask, Gateway, Interface, Metric
+            let cols: Vec<&str> = trimmed.split_whitespace().collect();
+            if cols.len() == 5 && cols[0] == "0.0.0.0" && cols[1] == "0.0.0.0" {
+                if let Ok(metric) = cols[4].parse::<u32>() {
+                    routes.push((cols[2].to_string(), cols[3].to_string(), metric));
+                }
+            }
+        }
+
+        let candidates = routes.len();
+        routes.into_iter()
+            .min_by_key(|(_, _, metric)| *metric)
+            .map(|(gateway, interface_address, metric)| DefaultRoute {
+                via_wifi: Some(interface_address.as_str()) == wifi_address,
+                gateway,
+                interface_address,
+                metric,
+                candidates,
+            })
+    }
+
     async fn test_connectivity(&self, gateway: Option<&str>) -> ConnectivityMetrics {
         let mut metrics = ConnectivityMetrics::default();
 
         // Check if we have a WiFi connection
-        let output = Command::new("netsh")
-            .args(["wlan", "show", "interfaces"])
-            .output();
+        let output = self.runner.output("netsh", &["wlan", "show", "interfaces"]).await;
 
         if let Ok(output) = output {
             let stdout = String::from_utf8_lossy(&output.stdout);
-            metrics.is_connected = stdout.to_lowercase().contains("state") 
-                && stdout.to_lowercase().contains("connected");
+            if let Some(block) = select_block(&stdout, "name", self.interface.as_deref()) {
+                metrics.adapter_present = true;
+                metrics.is_connected = block.lines().any(|line| {
+                    line.split_once(':')
+                        .map(|(key, value)| key.trim().eq_ignore_ascii_case("state")
+                            && value.trim().eq_ignore_ascii_case("connected"))
+                        .unwrap_or(false)
+                });
+            }
         }
 
-        // Test loopback (127.0.0.1) - verifies network stack is working
-        let loopback_ping = self.ping_target("127.0.0.1", 2).await;
-        metrics.loopback_reachable = loopback_ping.packets_received > 0;
-        debug!("Loopback ping: {} packets received", loopback_ping.packets_received);
+        // Loopback (verifies the network stack), the router (local network) and the
+        // HTTP/HTTPS checks (internet) are probed at the same time
+        let quick = PingOptions { count: 2, ..PingOptions::default() };
+        let loopback_ping = spawn_ping(&self.runner, "127.0.0.1", &quick, None);
+        let router_ping = gateway.map(|gw| spawn_ping(&self.runner, gw, &quick, None));
+
+        let client = reqwest::Client::builder()
+            .timeout(HTTP_CHECK_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build();
+        let (http, https) = match client {
+            Ok(ref client) => {
+                let start = Instant::now();
+                let (http, https) = tokio::join!(
+                    async { (client.get(HTTP_CHECK_URL).send().await, start.elapsed()) },
+                    client.get(HTTPS_CHECK_URL).send(),
+                );
+                (Some(http), Some(https))
+            }
+            Err(_) => (None, None),
+        };
+
+        if let Ok(loopback_ping) = loopback_ping.await {
+            metrics.loopback_reachable = loopback_ping.packets_received > 0;
+            debug!("Loopback ping: {} packets received", loopback_ping.packets_received);
+        }
 
-        // Test router/gateway connectivity (local network)
-        if let Some(gw) = gateway {
-            let router_ping = self.ping_target(gw, 2).await;
-            metrics.router_reachable = router_ping.packets_received > 0;
-            debug!("Router ping: {} packets received from {}", router_ping.packets_received, gw);
+        if let Some(router_ping) = router_ping {
+            if let Ok(router_ping) = router_ping.await {
+                metrics.router_reachable = router_ping.packets_received > 0;
+                debug!("Router ping: {} packets received from {}", router_ping.packets_received, router_ping.target);
+            }
         } else {
             // If no gateway, assume router is reachable if WiFi is connected
             metrics.router_reachable = metrics.is_connected;
         }
 
-        // Test HTTP connectivity (internet)
-        let start = Instant::now();
-        match reqwest::get("http://www.gstatic.com/generate_204").await {
+        // HTTP connectivity (internet), keeping the failure class
+        let (Some((http, http_elapsed)), Some(https)) = (http, https) else { return metrics };
+        match http {
             Ok(response) => {
-                metrics.http_test_success = response.status().is_success() || response.status().as_u16() == 204;
-                metrics.http_response_time_ms = Some(start.elapsed().as_millis() as u64);
+                let status = response.status().as_u16();
+                metrics.http_status = Some(status);
+                metrics.http_outcome = Some(HttpOutcome::from_status(status));
+                metrics.http_test_success = response.status().is_success();
+                metrics.http_response_time_ms = Some(http_elapsed.as_millis() as u64);
                 metrics.internet_reachable = metrics.http_test_success;
             }
             Err(e) => {
                 debug!("HTTP connectivity test failed: {}", e);
+                metrics.http_outcome = Some(classify_http_error(&e));
                 metrics.http_test_success = false;
                 metrics.internet_reachable = false;
             }
         }
 
+        metrics.https_outcome = Some(match https {
+            Ok(response) => HttpOutcome::from_status(response.status().as_u16()),
+            Err(e) => {
+                debug!("HTTPS connectivity test failed: {}", e);
+                classify_http_error(&e)
+            }
+        });
+
         metrics
     }
 
-    async fn measure_latency(&self, gateway: Option<&str>) -> LatencyMetrics {
+    async fn measure_latency(&self, gateway: Option<&str>, source: Option<&str>) -> LatencyMetrics {
         let mut metrics = LatencyMetrics::default();
         let mut all_times: Vec<f64> = Vec::new();
         let mut total_sent = 0u32;
         let mut total_received = 0u32;
 
+        // Loopback, router and every target are pinged at once
+        let loopback_task = spawn_ping(&self.runner, "127.0.0.1", &self.ping_options, None);
+        let router_task = gateway.map(|gw| spawn_ping(&self.runner, gw, &self.ping_options, None));
+        let target_tasks: Vec<_> = self.ping_targets.iter()
+            .map(|target| spawn_ping(&self.runner, &target.host, &target.options, source))
+            .collect();
+
         // Measure loopback latency
-        let loopback_result = self.ping_target("127.0.0.1", 4).await;
-        if let Some(avg) = loopback_result.avg_ms {
-            metrics.loopback_latency_ms = Some(avg);
+        if let Ok(loopback_result) = loopback_task.await {
+            metrics.loopback_latency_ms = loopback_result.avg_ms;
         }
 
         // Measure router latency
-        if let Some(gw) = gateway {
-            let router_result = self.ping_target(gw, 4).await;
-            if let Some(avg) = router_result.avg_ms {
-                metrics.router_latency_ms = Some(avg);
+        if let Some(task) = router_task {
+            if let Ok(router_result) = task.await {
+                metrics.router_latency_ms = router_result.avg_ms;
             }
         }
 
-        for target in &self.ping_targets {
-            let result = self.ping_target(target, 4).await;
-            
+        for result in join_probes(target_tasks).await {
             if !result.individual_times_ms.is_empty() {
                 all_times.extend(result.individual_times_ms.iter().cloned());
             }
@@ -404,213 +2058,155 @@ impl WifiMonitor {
         metrics
     }
 
-    async fn ping_target(&self, target: &str, count: u32) -> PingResult {
-        let mut result = PingResult {
-            target: target.to_string(),
-            resolved_ip: None,
-            packets_sent: count,
-            packets_received: 0,
-            packet_loss_percent: 100.0,
-            min_ms: None,
-            avg_ms: None,
-            max_ms: None,
-            stddev_ms: None,
-            individual_times_ms: Vec::new(),
-            error: None,
+    /// Ping the internet targets from the wired interface's address
+    async fn measure_wired(&self, interface: &str) -> WiredMetrics {
+        let mut metrics = WiredMetrics {
+            interface_name: interface.to_string(),
+            ipv4_address: interface_ipv4_address(&self.runner, interface).await,
+            ..Default::default()
+        };
+        let Some(source) = metrics.ipv4_address.clone() else {
+            debug!("Wired interface {} has no IPv4 address", interface);
+            metrics.packet_loss_percent = 100.0;
+            return metrics;
         };
+        metrics.link_up = true;
 
-        // Use Windows ping command
-        let output = Command::new("ping")
-            .args(["-n", &count.to_string(), target])
-            .output();
+        let mut times = Vec::new();
+        let (mut sent, mut received) = (0u32, 0u32);
+        let tasks: Vec<_> = self.ping_targets.iter()
+            .map(|target| spawn_ping(&self.runner, &target.host, &target.options, Some(&source)))
+            .collect();
+        for result in join_probes(tasks).await {
+            times.extend(result.individual_times_ms.iter().cloned());
+            sent += result.packets_sent;
+            received += result.packets_received;
+            metrics.targets.push(result);
+        }
 
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                self.parse_ping_output(&stdout, &mut result);
-            }
-            Err(e) => {
-                result.error = Some(format!("Failed to execute ping: {}", e));
-            }
+        if !times.is_empty() {
+            metrics.average_latency_ms = Some(times.iter().sum::<f64>() / times.len() as f64);
+        }
+        if sent > 0 {
+            metrics.packet_loss_percent = (sent - received) as f64 / sent as f64 * 100.0;
         }
 
-        result
+        metrics
     }
 
-    fn parse_ping_output(&self, output: &str, result: &mut PingResult) {
-        let mut times = Vec::new();
-        
-        for line in output.lines() {
-            let line_lower = line.to_lowercase();
-            
-            // Parse individual ping times
-            if line_lower.contains("time=") || line_lower.contains("time<") {
-                if let Some(time_part) = line.split("time").nth(1) {
-                    let time_str: String = time_part
-                        .chars()
-                        .skip_while(|c| !c.is_ascii_digit())
-                        .take_while(|c| c.is_ascii_digit() || *c == '.')
-                        .collect();
-                    
-                    if let Ok(time) = time_str.parse::<f64>() {
-                        times.push(time);
-                    }
-                }
+    /// Ping the internet targets over the guest SSID. Without a second adapter the main
+    /// adapter joins the guest network for the measurement, which needs a main
+    /// connection to return to.
+    async fn measure_guest(&mut self, main: Option<&WifiInfo>) -> Option<GuestMetrics> {
+        let guest = self.guest.clone()?;
+        let (interface, return_to) = match guest.interface {
+            Some(interface) => (interface, None),
+            None => {
+                let main = main.filter(|m| m.ssid != guest.ssid)?;
+                (main.adapter_name.clone(), Some(main.ssid.clone()))
             }
-            
-            // Parse reply from IP
-            if line_lower.contains("reply from") {
-                if let Some(ip_part) = line.split("Reply from").nth(1) {
-                    let ip: String = ip_part
-                        .trim()
-                        .chars()
-                        .take_while(|c| *c != ':' && *c != ' ')
-                        .collect();
-                    result.resolved_ip = Some(ip);
-                }
+        };
+        let mut metrics = GuestMetrics {
+            ssid: guest.ssid.clone(),
+            interface_name: interface.clone(),
+            switched: return_to.is_some(),
+            ..Default::default()
+        };
+
+        // A dedicated adapter is rejoined whenever it has drifted off the guest network
+        let on_guest = wlan_connection(&self.runner, &interface).await.is_some_and(|(ssid, _)| ssid == guest.ssid);
+        if !on_guest {
+            debug!("Connecting {} to guest network {}", interface, guest.ssid);
+            metrics.error = connect_wlan(&self.runner, &interface, &guest.ssid).await.err();
+        }
+        match metrics.error {
+            Some(_) => metrics.packet_loss_percent = 100.0,
+            None => self.ping_guest(&mut metrics).await,
+        }
+
+        if let Some(ref ssid) = return_to {
+            match connect_wlan(&self.runner, &interface, ssid).await {
+                Ok(()) => debug!("{} is back on {}", interface, ssid),
+                Err(e) => error!("Could not return {} to {} after the guest probe: {}", interface, ssid, e),
             }
-            
-            // Parse statistics
-            if line_lower.contains("packets:") || line_lower.contains("received =") {
-                // Windows format: "Packets: Sent = 4, Received = 4, Lost = 0"
-                if let Some(recv_part) = line.split("Received").nth(1) {
-                    let recv_str: String = recv_part
-                        .chars()
-                        .skip_while(|c| !c.is_ascii_digit())
-                        .take_while(|c| c.is_ascii_digit())
-                        .collect();
-                    
-                    if let Ok(recv) = recv_str.parse::<u32>() {
-                        result.packets_received = recv;
-                    }
-                }
+            // Drop what the background probes and the OS event log saw during the switch
+            if let Some(ref sampler) = self.sampler {
+                sampler.take_window();
             }
-            
-            // Parse min/max/avg from statistics line
-            if line_lower.contains("minimum") && line_lower.contains("maximum") {
-                // Windows format: "Minimum = 10ms, Maximum = 15ms, Average = 12ms"
-                for part in line.split(',') {
-                    let part_lower = part.to_lowercase();
-                    let value: String = part
-                        .chars()
-                        .filter(|c| c.is_ascii_digit() || *c == '.')
-                        .collect();
-                    
-                    if let Ok(val) = value.parse::<f64>() {
-                        if part_lower.contains("minimum") {
-                            result.min_ms = Some(val);
-                        } else if part_lower.contains("maximum") {
-                            result.max_ms = Some(val);
-                        } else if part_lower.contains("average") {
-                            result.avg_ms = Some(val);
-                        }
-                    }
-                }
+            if let Some(ref probe) = self.udp_probe {
+                probe.take_window();
+            }
+            if let Some(ref probe) = self.peer_probe {
+                probe.take_window();
+            }
+            if let Some(ref mut collector) = self.wlan_event_log {
+                collector.poll(&self.runner).await;
             }
         }
 
-        result.individual_times_ms = times;
-        
-        if result.packets_sent > 0 {
-            result.packet_loss_percent = 
-                ((result.packets_sent - result.packets_received) as f64 / result.packets_sent as f64) * 100.0;
-        }
-
-        // Calculate stddev if we have individual times
-        if result.individual_times_ms.len() > 1 {
-            let mean = result.individual_times_ms.iter().sum::<f64>() / result.individual_times_ms.len() as f64;
-            let variance: f64 = result.individual_times_ms.iter()
-                .map(|t| (t - mean).powi(2))
-                .sum::<f64>() / result.individual_times_ms.len() as f64;
-            result.stddev_ms = Some(variance.sqrt());
-        }
+        Some(metrics)
     }
 
-    async fn test_dns(&self) -> DnsMetrics {
-        let mut metrics = DnsMetrics::default();
-        let test_domains = vec!["google.com", "cloudflare.com", "microsoft.com"];
-        let mut total_time = 0.0;
-        let mut successful_queries = 0;
+    /// Ping the internet targets from the guest interface's address
+    async fn ping_guest(&self, metrics: &mut GuestMetrics) {
+        metrics.signal_quality_percent = wlan_connection(&self.runner, &metrics.interface_name).await.and_then(|(_, signal)| signal);
+        metrics.ipv4_address = interface_ipv4_address(&self.runner, &metrics.interface_name).await;
+        let Some(source) = metrics.ipv4_address.clone() else {
+            metrics.error = Some(format!("{} has no IPv4 address", metrics.interface_name));
+            metrics.packet_loss_percent = 100.0;
+            return;
+        };
+        metrics.connected = true;
 
-        for dns_server in &self.dns_servers {
-            for domain in &test_domains {
-                let result = self.test_dns_query(domain, dns_server).await;
-                
-                if result.success {
-                    if let Some(time) = result.resolution_time_ms {
-                        total_time += time;
-                        successful_queries += 1;
-                    }
-                } else {
-                    metrics.failures += 1;
-                }
-                
-                metrics.queries.push(result);
-            }
+        let mut times = Vec::new();
+        let (mut sent, mut received) = (0u32, 0u32);
+        let tasks: Vec<_> = self.ping_targets.iter()
+            .map(|target| spawn_ping(&self.runner, &target.host, &target.options, Some(&source)))
+            .collect();
+        for result in join_probes(tasks).await {
+            times.extend(result.individual_times_ms.iter().cloned());
+            sent += result.packets_sent;
+            received += result.packets_received;
+            metrics.targets.push(result);
         }
 
-        if successful_queries > 0 {
-            metrics.average_resolution_time_ms = Some(total_time / successful_queries as f64);
+        if !times.is_empty() {
+            metrics.average_latency_ms = Some(times.iter().sum::<f64>() / times.len() as f64);
         }
+        if sent > 0 {
+            metrics.packet_loss_percent = (sent - received) as f64 / sent as f64 * 100.0;
+        }
+    }
 
-        metrics
-    }
-
-    async fn test_dns_query(&self, domain: &str, dns_server: &str) -> DnsQueryResult {
-        let start = Instant::now();
-        
-        // Use nslookup for DNS testing on Windows
-        let output = Command::new("nslookup")
-            .args([domain, dns_server])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let elapsed = start.elapsed().as_millis() as f64;
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                
-                let mut resolved_ips = Vec::new();
-                let mut in_answer_section = false;
-                
-                for line in stdout.lines() {
-                    if line.contains("Name:") && line.contains(domain) {
-                        in_answer_section = true;
-                        continue;
-                    }
-                    
-                    if in_answer_section && line.contains("Address") {
-                        if let Some(ip) = line.split(':').nth(1) {
-                            let ip = ip.trim();
-                            if !ip.is_empty() && !ip.contains(dns_server) {
-                                resolved_ips.push(ip.to_string());
-                            }
-                        }
-                    }
-                }
-
-                let success = !resolved_ips.is_empty() || output.status.success();
-                
-                DnsQueryResult {
-                    domain: domain.to_string(),
-                    dns_server: dns_server.to_string(),
-                    resolution_time_ms: Some(elapsed),
-                    resolved_ips,
-                    success,
-                    error: if success { None } else { Some(stderr.to_string()) },
-                }
-            }
-            Err(e) => {
-                DnsQueryResult {
-                    domain: domain.to_string(),
-                    dns_server: dns_server.to_string(),
-                    resolution_time_ms: None,
-                    resolved_ips: Vec::new(),
-                    success: false,
-                    error: Some(format!("Failed to execute nslookup: {}", e)),
+    async fn test_dns(&self) -> DnsMetrics {
+        let mut metrics = DnsMetrics::default();
+        let test_domains = ["google.com", "cloudflare.com", "microsoft.com"];
+        let mut total_time = 0.0;
+        let mut successful_queries = 0;
+
+        // Every server/domain pair is queried at once
+        let tasks: Vec<_> = self.dns_servers.iter()
+            .flat_map(|server| test_domains.iter().map(move |domain| (server, *domain)))
+            .map(|(server, domain)| spawn_dns_query(&self.runner, domain, server))
+            .collect();
+        for result in join_probes(tasks).await {
+            if result.success {
+                if let Some(time) = result.resolution_time_ms {
+                    total_time += time;
+                    successful_queries += 1;
                 }
+            } else {
+                metrics.failures += 1;
             }
+
+            metrics.queries.push(result);
+        }
+
+        if successful_queries > 0 {
+            metrics.average_resolution_time_ms = Some(total_time / successful_queries as f64);
         }
+
+        metrics
     }
 
     fn detect_events(&self, snapshot: &WifiSnapshot, events: &mut Vec<NetworkEvent>) {
@@ -673,6 +2269,29 @@ impl WifiMonitor {
             }
         }
 
+        // Check TCP retransmissions (only with enough traffic for the ratio to mean something)
+        if let Some(percent) = snapshot.system_info.tcp_retransmit_percent {
+            let rate = snapshot.system_info.tcp_retransmits_per_sec.unwrap_or(0.0);
+            let severity = if percent >= self.thresholds.tcp_retransmit_critical_percent {
+                Some(EventSeverity::Critical)
+            } else if percent >= self.thresholds.tcp_retransmit_warning_percent {
+                Some(EventSeverity::Warning)
+            } else {
+                None
+            };
+            let enough_traffic = rate * self.interval_secs as f64 >= TCP_RETRANSMIT_MIN_SEGMENTS;
+            if let Some(severity) = severity.filter(|_| enough_traffic) {
+                events.push(NetworkEvent::new(
+                    EventType::TcpRetransmissions,
+                    severity,
+                    &format!("High TCP retransmission rate: {:.1}% of segments ({:.1}/s)", percent, rate),
+                ).with_details(serde_json::json!({
+                    "retransmit_percent": percent,
+                    "retransmits_per_sec": rate
+                })));
+            }
+        }
+
         // Check packet loss
         if snapshot.latency.packet_loss_percent >= self.thresholds.packet_loss_critical_percent {
             events.push(NetworkEvent::new(
@@ -725,6 +2344,110 @@ impl WifiMonitor {
             })));
         }
 
+        // Check for short outages only the 1s sampler can see
+        for stats in &snapshot.latency.continuous {
+            if stats.longest_loss_streak >= SHORT_OUTAGE_MIN_SECS {
+                events.push(NetworkEvent::new(
+                    EventType::PacketLoss,
+                    EventSeverity::Warning,
+                    &format!("Short outage: {} consecutive 1s pings to {} {} lost ({:.1}% loss this interval)",
+                        stats.longest_loss_streak, stats.role, stats.target, stats.packet_loss_percent),
+                ).with_details(serde_json::json!({
+                    "source": "continuous_sampler",
+                    "target": stats.target,
+                    "role": stats.role,
+                    "outage_secs": stats.longest_loss_streak,
+                    "samples": stats.samples,
+                    "received": stats.received
+                })));
+            }
+        }
+
+        // Check one-way loss on the reflector and peer streams
+        let streams = [("udp_reflector", &snapshot.latency.udp_stream), ("peer", &snapshot.latency.peer)];
+        for (source, stream) in streams {
+            let Some(stream) = stream else { continue };
+            let worst = stream.upstream_loss_percent.max(stream.downstream_loss_percent);
+            if worst >= self.thresholds.packet_loss_warning_percent {
+                let direction = if stream.upstream_loss_percent >= stream.downstream_loss_percent { "upstream" } else { "downstream" };
+                events.push(NetworkEvent::new(
+                    EventType::PacketLoss,
+                    if worst >= self.thresholds.packet_loss_critical_percent { EventSeverity::Critical } else { EventSeverity::Warning },
+                    &format!("{} stream loss to {}: {:.1}% upstream, {:.1}% downstream (mostly {})",
+                        if source == "peer" { "Peer" } else { "UDP" },
+                        stream.reflector, stream.upstream_loss_percent, stream.downstream_loss_percent, direction),
+                ).with_details(serde_json::json!({
+                    "source": source,
+                    "direction": direction,
+                    "stream": stream
+                })));
+            }
+        }
+
+        // Check for adapter resets (interface re-enumerated or counters re-initialized)
+        if let Some(ref last_state) = self.last_state {
+            if snapshot.connectivity.adapter_present && !last_state.adapter_present {
+                let missing_since = last_state.adapter_missing_since.unwrap_or(snapshot.timestamp);
+                let downtime_secs = (snapshot.timestamp - missing_since).num_milliseconds() as f64 / 1000.0;
+                events.push(NetworkEvent::new(
+                    EventType::AdapterReset,
+                    EventSeverity::Warning,
+                    &format!("WiFi adapter reappeared after being absent for {:.0}s", downtime_secs),
+                ).with_details(serde_json::json!({
+                    "reason": "interface_reappeared",
+                    "missing_since": missing_since.to_rfc3339(),
+                    "downtime_secs": downtime_secs
+                })));
+            } else if let (Some(previous), Some(current)) =
+                (last_state.last_adapter_bytes_received, snapshot.system_info.adapter_bytes_received)
+            {
+                if current < previous {
+                    events.push(NetworkEvent::new(
+                        EventType::AdapterReset,
+                        EventSeverity::Warning,
+                        "WiFi adapter traffic counters reset (adapter was re-initialized)",
+                    ).with_details(serde_json::json!({
+                        "reason": "counter_reset",
+                        "previous_bytes_received": previous,
+                        "current_bytes_received": current,
+                        "adapter_name": snapshot.wifi_info.as_ref().map(|w| w.adapter_name.clone())
+                    })));
+                }
+            }
+        }
+
+        // Check whether the default route moved to another interface (dock, VPN, tethering)
+        if let Some(ref last_state) = self.last_state {
+            if let (Some(previous), Some(current)) =
+                (&last_state.last_default_route, &snapshot.system_info.default_route)
+            {
+                if previous.interface_address != current.interface_address || previous.gateway != current.gateway {
+                    let (severity, description) = if previous.via_wifi && !current.via_wifi {
+                        (EventSeverity::Warning, format!(
+                            "Default route moved off WiFi to interface {} via {} (metric {})",
+                            current.interface_address, current.gateway, current.metric))
+                    } else if current.via_wifi && !previous.via_wifi {
+                        (EventSeverity::Info, format!(
+                            "Default route moved back to WiFi via {} (metric {})",
+                            current.gateway, current.metric))
+                    } else {
+                        (EventSeverity::Info, format!(
+                            "Default route changed: {} via {} -> {} via {}",
+                            previous.interface_address, previous.gateway,
+                            current.interface_address, current.gateway))
+                    };
+                    events.push(NetworkEvent::new(
+                        EventType::DefaultRouteChange,
+                        severity,
+                        &description,
+                    ).with_details(serde_json::json!({
+                        "previous": previous,
+                        "current": current
+                    })));
+                }
+            }
+        }
+
         // Check for connection restoration
         if let Some(ref last_state) = self.last_state {
             if !last_state.was_connected && snapshot.wifi_info.is_some() {
@@ -745,6 +2468,245 @@ impl WifiMonitor {
         }
     }
 
+    fn detect_recoveries(&mut self, snapshot: &WifiSnapshot, events: &mut Vec<NetworkEvent>) {
+        let at = snapshot.timestamp;
+        let resolve_samples = self.thresholds.resolve_samples;
+
+        if let Some(ref wifi) = snapshot.wifi_info {
+            let dbm = wifi.signal_strength_dbm;
+            let warning = self.thresholds.signal_strength_warning_dbm;
+            let recovery_level = warning + self.thresholds.signal_recovery_margin_db;
+            if let Some(resolved) = self.signal_degradation.track(
+                at, dbm as f64, dbm <= warning, dbm > recovery_level, true, resolve_samples,
+            ) {
+                events.push(recovery_event(
+                    EventType::SignalStrengthRecovered,
+                    &format!("Signal strength recovered to {} dBm after {}s (worst {:.0} dBm)", dbm, resolved.duration_secs, resolved.worst),
+                    &resolved,
+                    serde_json::json!({
+                        "signal_dbm": dbm,
+                        "worst_signal_dbm": resolved.worst,
+                        "recovery_level_dbm": recovery_level
+                    }),
+                ));
+            }
+        }
+
+        if let Some(avg) = snapshot.latency.average_latency_ms {
+            let warning = self.thresholds.latency_warning_ms;
+            let recovery_level = warning * self.thresholds.latency_recovery_ratio;
+            if let Some(resolved) = self.latency_degradation.track(
+                at, avg, avg >= warning, avg < recovery_level, false, resolve_samples,
+            ) {
+                events.push(recovery_event(
+                    EventType::LatencyNormalized,
+                    &format!("Latency normalized to {:.1}ms after {}s (worst {:.1}ms)", avg, resolved.duration_secs, resolved.worst),
+                    &resolved,
+                    serde_json::json!({
+                        "latency_ms": avg,
+                        "worst_latency_ms": resolved.worst,
+                        "recovery_level_ms": recovery_level
+                    }),
+                ));
+            }
+        }
+
+        let failures = snapshot.dns_metrics.failures;
+        if let Some(resolved) = self.dns_degradation.track(
+            at, failures as f64, failures > 0, failures == 0, false, resolve_samples,
+        ) {
+            events.push(recovery_event(
+                EventType::DnsRecovered,
+                &format!("DNS resolution recovered after {}s (up to {:.0} failed queries per cycle)", resolved.duration_secs, resolved.worst),
+                &resolved,
+                serde_json::json!({ "worst_failures": resolved.worst }),
+            ));
+        }
+
+        if let Some(jitter) = snapshot.latency.jitter_ms {
+            let warning = self.thresholds.jitter_warning_ms;
+            let recovery_level = warning * self.thresholds.latency_recovery_ratio;
+            if let Some(resolved) = self.jitter_degradation.track(
+                at, jitter, jitter >= warning, jitter < recovery_level, false, resolve_samples,
+            ) {
+                events.push(recovery_event(
+                    EventType::JitterNormalized,
+                    &format!("Jitter normalized to {:.1}ms after {}s (worst {:.1}ms)", jitter, resolved.duration_secs, resolved.worst),
+                    &resolved,
+                    serde_json::json!({
+                        "jitter_ms": jitter,
+                        "worst_jitter_ms": resolved.worst,
+                        "recovery_level_ms": recovery_level
+                    }),
+                ));
+            }
+        }
+
+        let loss = snapshot.latency.packet_loss_percent;
+        let warning = self.thresholds.packet_loss_warning_percent;
+        if let Some(resolved) = self.loss_degradation.track(
+            at, loss, loss >= warning, loss < warning, false, resolve_samples,
+        ) {
+            events.push(recovery_event(
+                EventType::PacketLossRecovered,
+                &format!("Packet loss recovered to {:.1}% after {}s (worst {:.1}%)", loss, resolved.duration_secs, resolved.worst),
+                &resolved,
+                serde_json::json!({
+                    "packet_loss_percent": loss,
+                    "worst_packet_loss_percent": resolved.worst
+                }),
+            ));
+        }
+
+        // Without enough traffic the ratio says nothing either way, so the condition stands
+        if let Some(percent) = snapshot.system_info.tcp_retransmit_percent {
+            let rate = snapshot.system_info.tcp_retransmits_per_sec.unwrap_or(0.0);
+            let enough_traffic = rate * self.interval_secs as f64 >= TCP_RETRANSMIT_MIN_SEGMENTS;
+            let warning = self.thresholds.tcp_retransmit_warning_percent;
+            if let Some(resolved) = self.retransmit_degradation.track(
+                at, percent, enough_traffic && percent >= warning, enough_traffic && percent < warning, false, resolve_samples,
+            ) {
+                events.push(recovery_event(
+                    EventType::TcpRetransmissionsNormalized,
+                    &format!("TCP retransmissions back to {:.1}% of segments after {}s (worst {:.1}%)", percent, resolved.duration_secs, resolved.worst),
+                    &resolved,
+                    serde_json::json!({
+                        "retransmit_percent": percent,
+                        "worst_retransmit_percent": resolved.worst
+                    }),
+                ));
+            }
+        }
+    }
+
+    /// The ongoing condition a threshold event belongs to. Short outages and stream loss
+    /// are reported as they happen, so only the main ping loss is a condition.
+    fn condition_for(&mut self, event: &NetworkEvent) -> Option<&mut Degradation> {
+        match event.event_type {
+            EventType::SignalStrengthLow => Some(&mut self.signal_degradation),
+            EventType::HighLatency => Some(&mut self.latency_degradation),
+            EventType::DnsFailure => Some(&mut self.dns_degradation),
+            EventType::HighJitter => Some(&mut self.jitter_degradation),
+            EventType::TcpRetransmissions => Some(&mut self.retransmit_degradation),
+            EventType::PacketLoss if event.details.get("source").is_none() => Some(&mut self.loss_degradation),
+            _ => None,
+        }
+    }
+
+    /// Drop repeats of ongoing conditions' events and tag the rest with the condition
+    /// they start or escalate. Runs after `detect_recoveries` has updated the conditions.
+    fn debounce(&mut self, events: &mut Vec<NetworkEvent>) {
+        for condition in self.conditions_mut() {
+            condition.current = None;
+        }
+        events.retain_mut(|event| {
+            let Some(condition) = self.condition_for(event) else {
+                return true;
+            };
+            let Some(stage) = condition.admit(&event.severity) else {
+                return false;
+            };
+            let id = condition.id.clone();
+            event.add_detail("condition", serde_json::json!(stage));
+            event.add_detail("condition_id", serde_json::json!(id));
+            true
+        });
+    }
+
+    fn conditions_mut(&mut self) -> [&mut Degradation; 6] {
+        [
+            &mut self.signal_degradation,
+            &mut self.latency_degradation,
+            &mut self.dns_degradation,
+            &mut self.jitter_degradation,
+            &mut self.loss_degradation,
+            &mut self.retransmit_degradation,
+        ]
+    }
+
+    /// Whether an ongoing condition raised an error or critical event this snapshot,
+    /// reported or not, so an incident stays open while it lasts
+    fn serious_condition(&self) -> bool {
+        [
+            &self.signal_degradation,
+            &self.latency_degradation,
+            &self.dns_degradation,
+            &self.jitter_degradation,
+            &self.loss_degradation,
+            &self.retransmit_degradation,
+        ]
+        .iter()
+        .any(|c| c.current.as_ref().is_some_and(|s| *s >= EventSeverity::Error))
+    }
+
+    fn detect_speed_changes(&mut self, snapshot: &WifiSnapshot, events: &mut Vec<NetworkEvent>) {
+        let Some(ref wifi) = snapshot.wifi_info else { return };
+        if wifi.bssid.is_empty() {
+            return;
+        }
+
+        let rx = wifi.rx_rate_mbps.unwrap_or(wifi.link_speed_mbps) as f64;
+        let tx = wifi.tx_rate_mbps.unwrap_or(wifi.link_speed_mbps) as f64;
+        if rx == 0.0 && tx == 0.0 {
+            return;
+        }
+
+        let baseline = self.link_baselines.entry(wifi.bssid.clone()).or_default();
+        let learned = baseline.samples >= BASELINE_MIN_SAMPLES;
+        let ratio = |current: f64, base: f64| if base > 0.0 { current / base } else { 1.0 };
+        let worst_ratio = ratio(rx, baseline.rx_mbps).min(ratio(tx, baseline.tx_mbps));
+
+        let details = serde_json::json!({
+            "bssid": wifi.bssid,
+            "baseline_rx_mbps": baseline.rx_mbps,
+            "baseline_tx_mbps": baseline.tx_mbps,
+            "current_rx_mbps": rx,
+            "current_tx_mbps": tx,
+            "ratio": worst_ratio
+        });
+
+        match baseline.degraded_since {
+            None if learned && worst_ratio < SPEED_DEGRADED_RATIO => {
+                baseline.degraded_since = Some(snapshot.timestamp);
+                events.push(NetworkEvent::new(
+                    EventType::SpeedDegraded,
+                    EventSeverity::Warning,
+                    &format!(
+                        "Link rate dropped to {:.0}/{:.0} Mbps (rx/tx), baseline {:.0}/{:.0} Mbps",
+                        rx, tx, baseline.rx_mbps, baseline.tx_mbps
+                    ),
+                ).with_details(details));
+            }
+            Some(since) if worst_ratio >= SPEED_RECOVERED_RATIO => {
+                baseline.degraded_since = None;
+                let duration_secs = (snapshot.timestamp - since).num_seconds();
+                let mut details = details;
+                details["degraded_duration_secs"] = serde_json::json!(duration_secs);
+                events.push(NetworkEvent::new(
+                    EventType::SpeedRecovered,
+                    EventSeverity::Info,
+                    &format!(
+                        "Link rate recovered to {:.0}/{:.0} Mbps (rx/tx) after {}s",
+                        rx, tx, duration_secs
+                    ),
+                ).with_details(details));
+            }
+            _ => {}
+        }
+
+        // Only learn from healthy samples so a long degradation doesn't become the new normal
+        if baseline.degraded_since.is_none() {
+            if baseline.samples == 0 {
+                baseline.rx_mbps = rx;
+                baseline.tx_mbps = tx;
+            } else {
+                baseline.rx_mbps += BASELINE_ALPHA * (rx - baseline.rx_mbps);
+                baseline.tx_mbps += BASELINE_ALPHA * (tx - baseline.tx_mbps);
+            }
+            baseline.samples += 1;
+        }
+    }
+
     fn log_snapshot_summary(&self, snapshot: &WifiSnapshot) {
         if let Some(ref wifi) = snapshot.wifi_info {
             info!(
@@ -789,7 +2751,64 @@ impl WifiMonitor {
         }
     }
 
+    /// Shorten the interval while anything critical is going on and relax it once
+    /// things have stayed calm for a while, stretching it on battery or metered
+    /// connections; returns whether the interval changed
+    fn adapt_interval(&mut self, snapshot: &WifiSnapshot) -> bool {
+        let base = self.power.base_interval(self.interval_secs);
+        let Some(fast) = self.fast_interval_secs else {
+            if base == self.current_interval_secs {
+                return false;
+            }
+            info!("Sampling every {}s", base);
+            self.current_interval_secs = base;
+            return true;
+        };
+
+        let latency = &snapshot.latency;
+        let critical = !self.suppressed(snapshot) && (snapshot.wifi_info.is_none()
+            || !snapshot.connectivity.internet_reachable
+            || latency.packet_loss_percent >= self.thresholds.packet_loss_critical_percent
+            || latency.average_latency_ms.is_some_and(|l| l >= self.thresholds.latency_critical_ms)
+            || snapshot.wifi_info.as_ref()
+                .is_some_and(|w| w.signal_strength_dbm <= self.thresholds.signal_strength_critical_dbm)
+            || snapshot.events.iter().any(|e| e.severity == EventSeverity::Critical));
+
+        let wanted = if critical {
+            self.calm_since = None;
+            fast
+        } else if self.current_interval_secs == fast {
+            let calm_since = *self.calm_since.get_or_insert_with(Instant::now);
+            if calm_since.elapsed() >= FAST_INTERVAL_HOLD { base } else { fast }
+        } else {
+            base
+        };
+
+        if wanted == self.current_interval_secs {
+            return false;
+        }
+        if critical {
+            info!("Critical condition active, sampling every {}s", wanted);
+        } else if self.current_interval_secs == fast {
+            info!("Conditions calm for {}s, back to a {}s interval", FAST_INTERVAL_HOLD.as_secs(), wanted);
+        } else {
+            info!("Sampling every {}s", wanted);
+        }
+        self.current_interval_secs = wanted;
+        true
+    }
+
     fn update_state(&mut self, snapshot: &WifiSnapshot) {
+        let previous = self.last_state.as_ref();
+        let adapter_present = snapshot.connectivity.adapter_present;
+        let adapter_missing_since = if adapter_present {
+            None
+        } else {
+            previous.and_then(|s| s.adapter_missing_since).or(Some(snapshot.timestamp))
+        };
+        let last_adapter_bytes_received = snapshot.system_info.adapter_bytes_received
+            .or_else(|| previous.and_then(|s| s.last_adapter_bytes_received));
+
         self.last_state = Some(MonitorState {
             was_connected: snapshot.wifi_info.is_some(),
             last_ssid: snapshot.wifi_info.as_ref().map(|w| w.ssid.clone()),
@@ -797,12 +2816,599 @@ impl WifiMonitor {
             last_channel: snapshot.wifi_info.as_ref().map(|w| w.channel),
             last_band: snapshot.wifi_info.as_ref().map(|w| w.band.clone()),
             last_signal_dbm: snapshot.wifi_info.as_ref().map(|w| w.signal_strength_dbm),
-            last_ip: snapshot.wifi_info.as_ref().and_then(|w| w.ipv4_address.clone()),
+            // Keep the last known addresses across disconnects so a reconnect with new ones is noticed
+            last_ip: snapshot.wifi_info.as_ref().and_then(|w| w.ipv4_address.clone())
+                .or_else(|| previous.and_then(|s| s.last_ip.clone())),
+            last_ipv6: snapshot.wifi_info.as_ref().and_then(|w| w.ipv6_address.clone())
+                .or_else(|| previous.and_then(|s| s.last_ipv6.clone())),
+            last_gateway: snapshot.wifi_info.as_ref().and_then(|w| w.gateway.clone())
+                .or_else(|| previous.and_then(|s| s.last_gateway.clone())),
             internet_was_reachable: snapshot.connectivity.internet_reachable,
+            adapter_present,
+            adapter_missing_since,
+            last_adapter_bytes_received,
+            last_default_route: snapshot.system_info.default_route.clone(),
+            last_timestamp: snapshot.timestamp,
+            last_system_info: snapshot.system_info.clone(),
         });
     }
 }
 
+/// Split netsh output into per-interface blocks (each starting with `start_key`) and
+/// return the one whose name, description or GUID matches `wanted`, or the first one
+fn select_block(output: &str, start_key: &str, wanted: Option<&str>) -> Option<String> {
+    let key_of = |line: &str| line.split_once(':').map(|(key, value)| (key.trim().to_lowercase(), value.trim().to_string()));
+
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    for line in output.lines() {
+        if key_of(line).is_some_and(|(key, _)| key == start_key) {
+            blocks.push(Vec::new());
+        }
+        if let Some(block) = blocks.last_mut() {
+            block.push(line);
+        }
+    }
+
+    blocks.into_iter()
+        .find(|block| match wanted {
+            None => true,
+            Some(wanted) => block.iter().filter_map(|line| key_of(line)).any(|(key, value)| {
+                (key == start_key || key == "description" || key == "guid") && value.eq_ignore_ascii_case(wanted)
+            }),
+        })
+        .map(|block| block.join("\n"))
+}
+
+/// SSID and signal quality of a wireless interface, if it is connected
+async fn wlan_connection(runner: &CommandRunner, interface: &str) -> Option<(String, Option<u8>)> {
+    let output = runner.output("netsh", &["wlan", "show", "interfaces"]).await.ok()?;
+    let block = select_block(&String::from_utf8_lossy(&output.stdout), "name", Some(interface))?;
+
+    let (mut connected, mut ssid, mut signal) = (false, None, None);
+    for line in block.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "state" => connected = value.eq_ignore_ascii_case("connected"),
+            "ssid" => ssid = Some(value.to_string()),
+            "signal" => signal = value.trim_end_matches('%').parse().ok(),
+            _ => {}
+        }
+    }
+    ssid.filter(|_| connected).map(|ssid| (ssid, signal))
+}
+
+/// Join `ssid` (its saved profile) on `interface` and wait until it has a usable address
+async fn connect_wlan(runner: &CommandRunner, interface: &str, ssid: &str) -> Result<(), String> {
+    let name = format!("name={}", ssid);
+    let iface = format!("interface={}", interface);
+    let output = runner.output("netsh", &["wlan", "connect", &name, &iface]).await.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(format!("netsh could not connect to {}: {}", ssid, stdout.trim()));
+    }
+
+    let started = Instant::now();
+    while started.elapsed() < WLAN_CONNECT_TIMEOUT {
+        time::sleep(Duration::from_secs(1)).await;
+        if wlan_connection(runner, interface).await.is_none_or(|(current, _)| current != ssid) {
+            continue;
+        }
+        if interface_ipv4_address(runner, interface).await.is_some_and(|a| !is_apipa(&a)) {
+            return Ok(());
+        }
+    }
+    Err(format!("{} did not join {} within {}s", interface, ssid, WLAN_CONNECT_TIMEOUT.as_secs()))
+}
+
+/// Sum the TCP segment counters of the IPv4 and IPv6 sections of `netstat -s`
+async fn collect_tcp_counters(runner: &CommandRunner, info: &mut SystemNetworkInfo) {
+    let output = match runner.output("netstat", &["-s"]).await {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Failed to run netstat: {}", e);
+            return;
+        }
+    };
+
+    let mut in_tcp = false;
+    let (mut sent, mut received, mut retransmitted) = (None::<u64>, None::<u64>, None::<u64>);
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let trimmed = line.trim();
+        if trimmed.ends_with("Statistics for IPv4") || trimmed.ends_with("Statistics for IPv6") {
+            in_tcp = trimmed.starts_with("TCP ");
+            continue;
+        }
+        if !in_tcp {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=') else { continue };
+        let Ok(value) = value.trim().parse::<u64>() else { continue };
+        let counter = match key.trim().to_lowercase().as_str() {
+            "segments sent" => &mut sent,
+            "segments received" => &mut received,
+            "segments retransmitted" => &mut retransmitted,
+            _ => continue,
+        };
+        *counter = Some(counter.unwrap_or(0) + value);
+    }
+
+    info.tcp_segments_sent = sent;
+    info.tcp_segments_received = received;
+    info.tcp_segments_retransmitted = retransmitted;
+}
+
+/// First IPv4 address of a named interface, from `netsh interface ipv4 show addresses`
+async fn interface_ipv4_address(runner: &CommandRunner, interface: &str) -> Option<String> {
+    let name = format!("name={}", interface);
+    let output = runner.output("netsh", &["interface", "ipv4", "show", "addresses", &name]).await.ok()?;
+
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("IP Address"))
+            .then(|| value.trim().to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+/// Parse Windows `ping` output into a result (times, loss, min/avg/max, stddev)
+pub fn parse_ping_output(output: &str, result: &mut PingResult) {
+    let mut times = Vec::new();
+    let mut error_replies = 0u32;
+    
+    for line in output.lines() {
+        let line_lower = line.to_lowercase();
+
+        // Windows counts these as received although the target never answered
+        if line_lower.contains("ttl expired") || line_lower.contains("unreachable") {
+            error_replies += 1;
+            if result.error.is_none() {
+                result.error = line.split(':').nth(1).map(|e| e.trim().trim_end_matches('.').to_string());
+            }
+            continue;
+        }
+        
+        // Parse individual ping times
+        if line_lower.contains("time=") || line_lower.contains("time<") {
+            if let Some(time_part) = line.split("time").nth(1) {
+                let time_str: String = time_part
+                    .chars()
+                    .skip_while(|c| !c.is_ascii_digit())
+                    .take_while(|c| c.is_ascii_digit() || *c == '.')
+                    .collect();
+                
+                if let Ok(time) = time_str.parse::<f64>() {
+                    times.push(time);
+                }
+            }
+        }
+        
+        // Parse reply from IP
+        if line_lower.contains("reply from") {
+            if let Some(ip_part) = line.split("Reply from").nth(1) {
+                let ip: String = ip_part
+                    .trim()
+                    .chars()
+                    .take_while(|c| *c != ':' && *c != ' ')
+                    .collect();
+                result.resolved_ip = Some(ip);
+            }
+        }
+        
+        // Parse statistics
+        if line_lower.contains("packets:") || line_lower.contains("received =") {
+            // Windows format: "Packets: Sent = 4, Received = 4, Lost = 0"
+            if let Some(recv_part) = line.split("Received").nth(1) {
+                let recv_str: String = recv_part
+                    .chars()
+                    .skip_while(|c| !c.is_ascii_digit())
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                
+                if let Ok(recv) = recv_str.parse::<u32>() {
+                    result.packets_received = recv;
+                }
+            }
+        }
+        
+        // Parse min/max/avg from statistics line
+        if line_lower.contains("minimum") && line_lower.contains("maximum") {
+            // Windows format: "Minimum = 10ms, Maximum = 15ms, Average = 12ms"
+            for part in line.split(',') {
+                let part_lower = part.to_lowercase();
+                let value: String = part
+                    .chars()
+                    .filter(|c| c.is_ascii_digit() || *c == '.')
+                    .collect();
+                
+                if let Ok(val) = value.parse::<f64>() {
+                    if part_lower.contains("minimum") {
+                        result.min_ms = Some(val);
+                    } else if part_lower.contains("maximum") {
+                        result.max_ms = Some(val);
+                    } else if part_lower.contains("average") {
+                        result.avg_ms = Some(val);
+                    }
+                }
+            }
+        }
+    }
+
+    result.individual_times_ms = times;
+    result.packets_received = result.packets_received.saturating_sub(error_replies);
+    
+    if result.packets_sent > 0 {
+        result.packet_loss_percent = 
+            ((result.packets_sent - result.packets_received) as f64 / result.packets_sent as f64) * 100.0;
+    }
+
+    // Calculate stddev if we have individual times
+    if result.individual_times_ms.len() > 1 {
+        let mean = result.individual_times_ms.iter().sum::<f64>() / result.individual_times_ms.len() as f64;
+        let variance: f64 = result.individual_times_ms.iter()
+            .map(|t| (t - mean).powi(2))
+            .sum::<f64>() / result.individual_times_ms.len() as f64;
+        result.stddev_ms = Some(variance.sqrt());
+    }
+}
+
+/// Write a snapshot to stdout for dry-run mode
+fn print_snapshot(snapshot: &WifiSnapshot, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => match serde_json::to_string(snapshot) {
+            Ok(line) => println!("{}", line),
+            Err(e) => error!("Failed to serialize snapshot: {}", e),
+        },
+        OutputFormat::Pretty => {
+            println!("── {} ──", snapshot.timestamp.format("%Y-%m-%d %H:%M:%S UTC"));
+            match snapshot.wifi_info {
+                Some(ref wifi) => println!(
+                    "  WiFi:         {} ({}) ch {} {:?}, {} dBm ({}%), {} Mbps",
+                    wifi.ssid, wifi.bssid, wifi.channel, wifi.band,
+                    wifi.signal_strength_dbm, wifi.signal_quality_percent, wifi.link_speed_mbps
+                ),
+                None => println!("  WiFi:         not connected"),
+            }
+            let fmt_ms = |v: Option<f64>| v.map(|v| format!("{:.1}", v)).unwrap_or_else(|| "--".to_string());
+            println!(
+                "  Latency:      avg {} / min {} / max {} ms, jitter {} ms (RFC 3550 {} ms), loss {:.1}%, MOS {}",
+                fmt_ms(snapshot.latency.average_latency_ms),
+                fmt_ms(snapshot.latency.min_latency_ms),
+                fmt_ms(snapshot.latency.max_latency_ms),
+                fmt_ms(snapshot.latency.jitter_ms),
+                fmt_ms(snapshot.latency.rfc3550_jitter_ms),
+                snapshot.latency.packet_loss_percent,
+                snapshot.latency.mos.map(|m| format!("{:.2}", m)).unwrap_or_else(|| "--".to_string())
+            );
+            for stats in &snapshot.latency.continuous {
+                println!(
+                    "  1s ping:      {} {} of {} replies, avg {} / max {} ms, longest outage {}s",
+                    stats.role,
+                    stats.received, stats.samples,
+                    fmt_ms(stats.avg_ms), fmt_ms(stats.max_ms),
+                    stats.longest_loss_streak
+                );
+            }
+            if let Some(ref stream) = snapshot.latency.udp_stream {
+                println!(
+                    "  UDP stream:   {} of {} back, loss up {:.1}% / down {:.1}%, {} reordered, rtt avg {} ms",
+                    stream.received, stream.sent,
+                    stream.upstream_loss_percent, stream.downstream_loss_percent,
+                    stream.reordered, fmt_ms(stream.rtt_avg_ms)
+                );
+            }
+            if let Some(ref peer) = snapshot.latency.peer {
+                println!(
+                    "  Peer:         {} of {} back, one-way to {} / from {} ms, loss up {:.1}% / down {:.1}%",
+                    peer.received, peer.sent,
+                    fmt_ms(peer.forward_delay_avg_ms), fmt_ms(peer.reverse_delay_avg_ms),
+                    peer.upstream_loss_percent, peer.downstream_loss_percent
+                );
+            }
+            if let Some(ref wired) = snapshot.wired {
+                println!(
+                    "  Wired:        {} ({}), avg {} ms, loss {:.1}%",
+                    wired.interface_name,
+                    wired.ipv4_address.as_deref().unwrap_or("no address"),
+                    fmt_ms(wired.average_latency_ms),
+                    wired.packet_loss_percent
+                );
+            }
+            if let Some(ref guest) = snapshot.guest {
+                println!(
+                    "  Guest:        {} via {}{}, avg {} ms, loss {:.1}%{}",
+                    guest.ssid,
+                    guest.interface_name,
+                    if guest.switched { " (switched)" } else { "" },
+                    fmt_ms(guest.average_latency_ms),
+                    guest.packet_loss_percent,
+                    guest.error.as_deref().map(|e| format!(" - {}", e)).unwrap_or_default()
+                );
+            }
+            if let Some(ref public_ip) = snapshot.public_ip {
+                println!(
+                    "  Public IP:    {}{}{}",
+                    public_ip.ip,
+                    public_ip.asn.map(|asn| format!(" AS{}", asn)).unwrap_or_default(),
+                    public_ip.isp.as_deref().map(|isp| format!(" ({})", isp)).unwrap_or_default()
+                );
+            }
+            println!(
+                "  Connectivity: loopback {}, router {}, internet {}",
+                snapshot.connectivity.loopback_reachable,
+                snapshot.connectivity.router_reachable,
+                snapshot.connectivity.internet_reachable
+            );
+            println!(
+                "  DNS:          avg {} ms, {} failure(s)",
+                fmt_ms(snapshot.dns_metrics.average_resolution_time_ms),
+                snapshot.dns_metrics.failures
+            );
+            for event in &snapshot.events {
+                println!("  [{:?}] {:?}: {}", event.severity, event.event_type, event.description);
+            }
+        }
+    }
+}
+
+/// Ping, optionally forcing the source address (and so the interface) with `-S`
+async fn ping(runner: &CommandRunner, target: &str, options: &PingOptions, source: Option<&str>) -> PingResult {
+    let mut result = PingResult {
+        target: target.to_string(),
+        resolved_ip: None,
+        packets_sent: options.count,
+        packets_received: 0,
+        packet_loss_percent: 100.0,
+        min_ms: None,
+        avg_ms: None,
+        max_ms: None,
+        stddev_ms: None,
+        individual_times_ms: Vec::new(),
+        packet_size: options.size,
+        rfc3550_jitter_ms: None,
+        error: None,
+    };
+
+    // Use Windows ping command
+    let mut args = options.args();
+    if let Some(source) = source {
+        args.extend(["-S".to_string(), source.to_string()]);
+    }
+    args.push(target.to_string());
+
+    match runner.output("ping", &args).await {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            parse_ping_output(&stdout, &mut result);
+        }
+        Err(e) => {
+            result.error = Some(e.to_string());
+        }
+    }
+
+    result
+}
+
+/// Run `ping` as its own task so several targets can be pinged at once
+fn spawn_ping(runner: &CommandRunner, target: &str, options: &PingOptions, source: Option<&str>) -> tokio::task::JoinHandle<PingResult> {
+    let runner = runner.clone();
+    let target = target.to_string();
+    let options = options.clone();
+    let source = source.map(str::to_string);
+    tokio::spawn(async move { ping(&runner, &target, &options, source.as_deref()).await })
+}
+
+/// Run `dns_query` as its own task so several queries can be in flight at once
+fn spawn_dns_query(runner: &CommandRunner, domain: &str, dns_server: &str) -> tokio::task::JoinHandle<DnsQueryResult> {
+    let runner = runner.clone();
+    let domain = domain.to_string();
+    let dns_server = dns_server.to_string();
+    tokio::spawn(async move { dns_query(&runner, &domain, &dns_server).await })
+}
+
+/// Wait for probe tasks in the order they were started, skipping any that panicked
+async fn join_probes<T>(tasks: Vec<tokio::task::JoinHandle<T>>) -> Vec<T> {
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        if let Ok(result) = task.await {
+            results.push(result);
+        }
+    }
+    results
+}
+
+/// Await a probe, also returning how long it took in milliseconds
+async fn timed<F: std::future::Future>(probe: F) -> (F::Output, u64) {
+    let started = Instant::now();
+    let output = probe.await;
+    (output, started.elapsed().as_millis() as u64)
+}
+
+/// Resolve `domain` through `dns_server` with nslookup, timing the lookup
+async fn dns_query(runner: &CommandRunner, domain: &str, dns_server: &str) -> DnsQueryResult {
+    let start = Instant::now();
+
+    // Use nslookup for DNS testing on Windows
+    let output = runner.output("nslookup", &[domain, dns_server]).await;
+
+    match output {
+        Ok(output) => {
+            let elapsed = start.elapsed().as_millis() as f64;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            let mut resolved_ips = Vec::new();
+            let mut in_answer_section = false;
+
+            for line in stdout.lines() {
+                if line.contains("Name:") && line.contains(domain) {
+                    in_answer_section = true;
+                    continue;
+                }
+
+                if in_answer_section && line.contains("Address") {
+                    if let Some(ip) = line.split(':').nth(1) {
+                        let ip = ip.trim();
+                        if !ip.is_empty() && !ip.contains(dns_server) {
+                            resolved_ips.push(ip.to_string());
+                        }
+                    }
+                }
+            }
+
+            let success = !resolved_ips.is_empty() || output.status.success();
+
+            DnsQueryResult {
+                domain: domain.to_string(),
+                dns_server: dns_server.to_string(),
+                resolution_time_ms: Some(elapsed),
+                resolved_ips,
+                success,
+                error: if success { None } else { Some(stderr.to_string()) },
+            }
+        }
+        Err(e) => {
+            DnsQueryResult {
+                domain: domain.to_string(),
+                dns_server: dns_server.to_string(),
+                resolution_time_ms: None,
+                resolved_ips: Vec::new(),
+                success: false,
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Ping with a short per-reply timeout so a dead target doesn't stall the burst
+async fn burst_ping(runner: &CommandRunner, target: &str) -> PingResult {
+    let mut result = PingResult {
+        target: target.to_string(),
+        resolved_ip: None,
+        packets_sent: BURST_PING_COUNT,
+        packets_received: 0,
+        packet_loss_percent: 100.0,
+        min_ms: None,
+        avg_ms: None,
+        max_ms: None,
+        stddev_ms: None,
+        individual_times_ms: Vec::new(),
+        packet_size: None,
+        rfc3550_jitter_ms: None,
+        error: None,
+    };
+    match runner.output("ping", &["-n", &BURST_PING_COUNT.to_string(), "-w", "1000", target]).await {
+        Ok(output) => parse_ping_output(&String::from_utf8_lossy(&output.stdout), &mut result),
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result
+}
+
+/// Time until the response headers of the HTTP check arrive
+async fn http_ttfb() -> Result<f64, HttpOutcome> {
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_CHECK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|_| HttpOutcome::OtherError)?;
+    let start = Instant::now();
+    match client.get(HTTP_CHECK_URL).send().await {
+        Ok(_) => Ok(start.elapsed().as_secs_f64() * 1000.0),
+        Err(e) => Err(classify_http_error(&e)),
+    }
+}
+
+/// Classify a failed HTTP check by walking the error's source chain
+fn classify_http_error(error: &reqwest::Error) -> HttpOutcome {
+    if error.is_timeout() {
+        return HttpOutcome::Timeout;
+    }
+
+    let mut chain = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    let chain = chain.to_lowercase();
+
+    if chain.contains("dns error") || chain.contains("failed to lookup address") || chain.contains("no such host") {
+        HttpOutcome::DnsError
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") || chain.contains("handshake") {
+        HttpOutcome::TlsError
+    } else if chain.contains("timed out") {
+        HttpOutcome::Timeout
+    } else if error.is_connect() {
+        HttpOutcome::ConnectError
+    } else {
+        HttpOutcome::OtherError
+    }
+}
+
+/// Parse `netsh wlan show networks mode=bssid` into one entry per BSSID
+fn parse_neighbor_scan(output: &str) -> Vec<NeighborNetwork> {
+    let mut neighbors: Vec<NeighborNetwork> = Vec::new();
+    let mut ssid = String::new();
+    // Listed once per SSID, before its BSSIDs
+    let (mut authentication, mut encryption) = (None, None);
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        if key.starts_with("ssid") {
+            ssid = value.to_string();
+            (authentication, encryption) = (None, None);
+        } else if key == "authentication" {
+            authentication = Some(value.to_string());
+        } else if key == "encryption" {
+            encryption = Some(value.to_string());
+        } else if key.starts_with("bssid") {
+            neighbors.push(NeighborNetwork {
+                ssid: ssid.clone(),
+                bssid: value.to_lowercase(),
+                signal_quality_percent: 0,
+                signal_dbm: quality_to_dbm(0),
+                channel: 0,
+                band: WifiBand::Unknown,
+                radio_type: String::new(),
+                authentication: authentication.clone(),
+                encryption: encryption.clone(),
+            });
+        } else if let Some(neighbor) = neighbors.last_mut() {
+            match key.as_str() {
+                "signal" => {
+                    neighbor.signal_quality_percent = value.trim_end_matches('%').parse().unwrap_or(0);
+                    neighbor.signal_dbm = quality_to_dbm(neighbor.signal_quality_percent);
+                }
+                "radio type" => neighbor.radio_type = value.to_string(),
+                "channel" => {
+                    neighbor.channel = value.parse().unwrap_or(0);
+                    if neighbor.band == WifiBand::Unknown {
+                        neighbor.band = WifiBand::from_frequency(channel_to_frequency(neighbor.channel));
+                    }
+                }
+                // Newer builds report the band explicitly, which disambiguates 6 GHz channels
+                "band" => {
+                    if value.starts_with('6') {
+                        neighbor.band = WifiBand::Band6GHz;
+                    } else if value.starts_with('5') {
+                        neighbor.band = WifiBand::Band5GHz;
+                    } else if value.starts_with('2') {
+                        neighbor.band = WifiBand::Band2_4GHz;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    neighbors.retain(|n| n.channel > 0);
+    neighbors
+}
+
 /// Convert WiFi channel number to frequency in MHz
 fn channel_to_frequency(channel: u32) -> u32 {
     match channel {
@@ -841,6 +3447,11 @@ fn channel_to_frequency(channel: u32) -> u32 {
     }
 }
 
+/// Whether an IPv4 address is a link-local (APIPA) fallback, i.e. DHCP failed
+fn is_apipa(address: &str) -> bool {
+    address.trim().starts_with("169.254.")
+}
+
 /// Convert signal quality percentage to approximate dBm
 fn quality_to_dbm(quality: u8) -> i32 {
     // Windows reports quality as 0-100%
@@ -0,0 +1,443 @@
+//! OpenAPI description of the web API, served at `/api/v1/openapi.json` and browsable at `/docs`.
+//!
+//! Handlers build their JSON with `serde_json::json!`, so the response shapes below exist
+//! only to describe it. Every response carries `success`; failures carry `error` instead
+//! of data.
+#![allow(dead_code)]
+
+use crate::metrics::{
+    Alert, AlertState, Annotation, ConnectivityMetrics, ContinuousPingStats, DefaultRoute, DnsMetrics, DnsQueryResult,
+    EventSeverity, EventType, GuestMetrics, HttpOutcome, Incident, IncidentCause, IncidentNote, IncidentStatus,
+    LatencyMetrics, MuteWindow, NeighborNetwork, NetworkEvent, PauseWindow, PeriodStatistics, PingResult,
+    ProbeDurations, PublicIpInfo, SamplingPolicy, Session, SystemNetworkInfo, UdpStreamStats, WifiBand, WifiInfo,
+    WifiSnapshot, WiredMetrics,
+};
+use crate::logtail::LogRecord;
+use crate::web;
+use chrono::{DateTime, Utc};
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "WiFi Stability Tracker API",
+        description = "Snapshots, events, statistics and monitor control of a running tracker. When the \
+            server was started with --auth-token or --basic-auth, send `Authorization: Bearer <token>` \
+            or basic credentials with every request."
+    ),
+    paths(
+        web::current_handler,
+        web::snapshots_handler,
+        web::timeseries_handler,
+        web::targets_handler,
+        web::neighbors_handler,
+        web::events_handler,
+        web::event_stream_handler,
+        web::statistics_handler,
+        web::event_counts_handler,
+        web::diagnostics_handler,
+        web::report_handler,
+        web::export_handler,
+        web::router_syslog_handler,
+        web::router_syslog_ingest_handler,
+        web::incidents_handler,
+        web::incident_handler,
+        web::incident_note_handler,
+        web::annotations_handler,
+        web::annotation_handler,
+        web::delete_annotation_handler,
+        web::sessions_handler,
+        web::sessions_compare_handler,
+        web::session_handler,
+        web::delete_data_handler,
+        web::test_alert_handler,
+        web::pause_status_handler,
+        web::pause_handler,
+        web::resume_handler,
+        web::monitor_status_handler,
+        web::stop_handler,
+        web::start_handler,
+        web::diagnose_handler,
+        web::alert_state_handler,
+        web::alert_history_handler,
+        web::mute_handler,
+        web::unmute_handler,
+        web::version_handler,
+        web::capabilities_handler,
+        web::health_handler,
+        web::logs_handler,
+        web::log_stream_handler,
+    ),
+    // Nested types aren't collected on their own, so everything a response refers to is listed
+    components(schemas(
+        WifiSnapshot, WifiInfo, WifiBand, NeighborNetwork, ConnectivityMetrics, HttpOutcome, LatencyMetrics,
+        PingResult, ContinuousPingStats, UdpStreamStats, DnsMetrics, DnsQueryResult, SystemNetworkInfo,
+        DefaultRoute, WiredMetrics, GuestMetrics, PublicIpInfo, ProbeDurations, SamplingPolicy,
+        NetworkEvent, EventType, EventSeverity, PeriodStatistics, Incident, IncidentStatus, IncidentCause,
+        IncidentNote, Annotation, Session, PauseWindow, MuteWindow, AlertState, Alert,
+        web::IncidentNoteRequest, web::AnnotationRequest,
+        ErrorResponse, SuccessResponse, CurrentSnapshotResponse, SnapshotsResponse, TimeseriesPoint,
+        TimeseriesResponse, TargetSeries, TargetsResponse, NeighborsResponse, EventsResponse, StatisticsResponse, EventCount, EventCountsResponse,
+        DiagnosticsResponse, ReportResponse, SyslogIngestResponse, IncidentsResponse, IncidentResponse, IncidentNoteResponse,
+        AnnotationsResponse, AnnotationResponse, SessionSummary, SessionsResponse, SessionResponse,
+        SessionStatistics, SessionsCompareResponse, DeleteDataResponse, TestAlertResponse, PauseStatusResponse,
+        PauseResponse, MonitorStatusResponse, StopResponse, DiagnoseResponse,
+        AlertStateResponse, AlertHistoryResponse, MuteResponse, VersionResponse, CapabilitiesResponse, HealthResponse,
+        LogRecord, LogsResponse,
+    )),
+    tags(
+        (name = "snapshots", description = "Measurements and metric timeseries"),
+        (name = "events", description = "Detected events and router logs"),
+        (name = "statistics", description = "Aggregates over a time range"),
+        (name = "incidents", description = "Outages with their events and notes"),
+        (name = "annotations", description = "Notes pinned to a point in time"),
+        (name = "sessions", description = "Monitor runs"),
+        (name = "monitor", description = "Stopping, starting, pausing and resuming a running monitor"),
+        (name = "alerts", description = "Alert state, mutes and test alerts"),
+        (name = "system", description = "Build details and diagnostics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// A request that failed
+#[derive(ToSchema)]
+pub struct ErrorResponse {
+    /// Always false
+    success: bool,
+    error: String,
+}
+
+#[derive(ToSchema)]
+pub struct SuccessResponse {
+    success: bool,
+}
+
+#[derive(ToSchema)]
+pub struct CurrentSnapshotResponse {
+    success: bool,
+    data: Option<WifiSnapshot>,
+    /// Set when nothing has been collected yet
+    message: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct SnapshotsResponse {
+    success: bool,
+    /// Rows on this page
+    count: usize,
+    /// Rows matching the request over all pages
+    total: u64,
+    offset: u64,
+    limit: u32,
+    /// Path and query of the next page; null on the last page
+    next: Option<String>,
+    data: Vec<WifiSnapshot>,
+}
+
+#[derive(ToSchema)]
+pub struct TimeseriesPoint {
+    /// The point's time, or the start of its bucket
+    timestamp: DateTime<Utc>,
+    /// The point's value, or the average over its bucket
+    value: f64,
+    /// Smallest value in the bucket, with `bucket` or `max_points`
+    min: Option<f64>,
+    /// Largest value in the bucket, with `bucket` or `max_points`
+    max: Option<f64>,
+    /// Points in the bucket, with `bucket` or `max_points`
+    samples: Option<i64>,
+}
+
+#[derive(ToSchema)]
+pub struct TimeseriesResponse {
+    success: bool,
+    metric: String,
+    /// Bucket size the points were aggregated over, with `bucket` or `max_points`
+    bucket_secs: Option<f64>,
+    count: usize,
+    data: Vec<TimeseriesPoint>,
+}
+
+/// One ping target's timeseries
+#[derive(ToSchema)]
+pub struct TargetSeries {
+    /// The target as configured, e.g. `8.8.8.8` or `google.com`
+    target: String,
+    /// Bucket size the points were aggregated over, with `bucket` or `max_points`
+    bucket_secs: Option<f64>,
+    /// Average round trip in ms
+    latency: Vec<TimeseriesPoint>,
+    /// Packet loss in percent
+    loss: Vec<TimeseriesPoint>,
+    /// Standard deviation of the round trips in ms
+    jitter: Vec<TimeseriesPoint>,
+}
+
+#[derive(ToSchema)]
+pub struct TargetsResponse {
+    success: bool,
+    count: usize,
+    data: Vec<TargetSeries>,
+}
+
+#[derive(ToSchema)]
+pub struct NeighborsResponse {
+    success: bool,
+    /// Scans in the range
+    scans: usize,
+    /// Time of the latest scan; null when there is none
+    scanned_at: Option<DateTime<Utc>>,
+    /// Access points in the latest scan
+    latest: Vec<NeighborNetwork>,
+    /// Channels in use in the latest scan: `band`, `channel`, `networks`, `strongest_dbm` and `ssids`
+    #[schema(value_type = Vec<Object>)]
+    channels: Vec<serde_json::Value>,
+    /// Per BSSID: SSID, band, channel, security, first and last seen, signal average, minimum
+    /// and maximum, `missed_scans` between its first and last sighting, and its `readings`
+    #[schema(value_type = Vec<Object>)]
+    history: Vec<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct EventsResponse {
+    success: bool,
+    /// Rows on this page
+    count: usize,
+    /// Rows matching the request over all pages
+    total: u64,
+    offset: u64,
+    limit: u32,
+    /// Path and query of the next page; null on the last page
+    next: Option<String>,
+    data: Vec<NetworkEvent>,
+}
+
+#[derive(ToSchema)]
+pub struct StatisticsResponse {
+    success: bool,
+    data: PeriodStatistics,
+}
+
+#[derive(ToSchema)]
+pub struct EventCount {
+    event_type: String,
+    count: i64,
+}
+
+#[derive(ToSchema)]
+pub struct EventCountsResponse {
+    success: bool,
+    data: Vec<EventCount>,
+}
+
+#[derive(ToSchema)]
+pub struct DiagnosticsResponse {
+    success: bool,
+    /// Current snapshot, adapter, routes, ARP table, DNS settings and recent events
+    #[schema(value_type = Object)]
+    data: serde_json::Value,
+}
+
+/// With `format=json`; the default is the report as plain text
+#[derive(ToSchema)]
+pub struct ReportResponse {
+    success: bool,
+    start: Option<String>,
+    end: Option<String>,
+    /// The report as `analyze` writes it
+    data: String,
+}
+
+#[derive(ToSchema)]
+pub struct SyslogIngestResponse {
+    success: bool,
+    /// Lines that parsed as syslog messages
+    accepted: usize,
+    message: String,
+}
+
+#[derive(ToSchema)]
+pub struct IncidentsResponse {
+    success: bool,
+    count: usize,
+    data: Vec<Incident>,
+}
+
+#[derive(ToSchema)]
+pub struct IncidentResponse {
+    success: bool,
+    data: Incident,
+    events: Vec<NetworkEvent>,
+}
+
+#[derive(ToSchema)]
+pub struct IncidentNoteResponse {
+    success: bool,
+    data: Incident,
+}
+
+#[derive(ToSchema)]
+pub struct AnnotationsResponse {
+    success: bool,
+    count: usize,
+    data: Vec<Annotation>,
+}
+
+#[derive(ToSchema)]
+pub struct AnnotationResponse {
+    success: bool,
+    data: Annotation,
+}
+
+/// A session with the number of snapshots it recorded
+#[derive(ToSchema)]
+pub struct SessionSummary {
+    id: String,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    host: Option<String>,
+    #[schema(value_type = Object)]
+    config: serde_json::Value,
+    snapshot_count: i64,
+}
+
+#[derive(ToSchema)]
+pub struct SessionsResponse {
+    success: bool,
+    count: usize,
+    data: Vec<SessionSummary>,
+}
+
+#[derive(ToSchema)]
+pub struct SessionResponse {
+    success: bool,
+    data: Session,
+    statistics: PeriodStatistics,
+    event_counts: Vec<EventCount>,
+}
+
+#[derive(ToSchema)]
+pub struct SessionStatistics {
+    session: Session,
+    statistics: PeriodStatistics,
+}
+
+#[derive(ToSchema)]
+pub struct SessionsCompareResponse {
+    success: bool,
+    count: usize,
+    data: Vec<SessionStatistics>,
+}
+
+#[derive(ToSchema)]
+pub struct DeleteDataResponse {
+    success: bool,
+    /// Rows deleted over all tables
+    removed: usize,
+    /// The cutoff, when deleting by time
+    before: Option<DateTime<Utc>>,
+    /// The session's full id, when deleting a session
+    session: Option<String>,
+    vacuumed: bool,
+}
+
+#[derive(ToSchema)]
+pub struct TestAlertResponse {
+    success: bool,
+    event_id: String,
+    message: String,
+}
+
+#[derive(ToSchema)]
+pub struct PauseStatusResponse {
+    success: bool,
+    paused: bool,
+    pause: Option<PauseWindow>,
+}
+
+#[derive(ToSchema)]
+pub struct PauseResponse {
+    success: bool,
+    pause: PauseWindow,
+}
+
+#[derive(ToSchema)]
+pub struct MonitorStatusResponse {
+    success: bool,
+    /// Whether the monitoring loop runs, and since when it is stopped or paused
+    #[schema(value_type = Object)]
+    data: serde_json::Value,
+}
+
+#[derive(ToSchema)]
+pub struct StopResponse {
+    success: bool,
+    stopped_at: DateTime<Utc>,
+}
+
+#[derive(ToSchema)]
+pub struct DiagnoseResponse {
+    success: bool,
+    /// The snapshot, with the burst's pings, traceroute, AP scan and DNS answers in its
+    /// `DiagnosticsRun` event
+    data: WifiSnapshot,
+}
+
+#[derive(ToSchema)]
+pub struct AlertStateResponse {
+    success: bool,
+    data: AlertState,
+    /// Whether a mute is in effect now
+    muted: bool,
+}
+
+#[derive(ToSchema)]
+pub struct AlertHistoryResponse {
+    success: bool,
+    count: usize,
+    /// Newest first
+    data: Vec<Alert>,
+}
+
+#[derive(ToSchema)]
+pub struct MuteResponse {
+    success: bool,
+    mute: MuteWindow,
+}
+
+#[derive(ToSchema)]
+pub struct VersionResponse {
+    success: bool,
+    /// Version, git hash, build date, enabled features, platform and process details
+    #[schema(value_type = Object)]
+    data: serde_json::Value,
+}
+
+#[derive(ToSchema)]
+pub struct CapabilitiesResponse {
+    success: bool,
+    /// `api_version` (the current one) and `api_versions` served, the build's `version`,
+    /// its `capabilities`, e.g. `monitor_control`, `export_formats` and `features`
+    #[schema(value_type = Object)]
+    data: serde_json::Value,
+}
+
+#[derive(ToSchema)]
+pub struct LogsResponse {
+    success: bool,
+    count: usize,
+    /// Oldest first
+    data: Vec<LogRecord>,
+}
+
+#[derive(ToSchema)]
+pub struct HealthResponse {
+    success: bool,
+    healthy: bool,
+    /// What is wrong, e.g. "monitoring loop has stopped"; empty when healthy
+    problems: Vec<String>,
+    /// Server start time and uptime, database access, the latest snapshot's time and age,
+    /// and whether the monitoring loop runs (null without a monitor in this process)
+    #[schema(value_type = Object)]
+    data: serde_json::Value,
+}
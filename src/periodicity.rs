@@ -0,0 +1,193 @@
+use crate::metrics::WifiSnapshot;
+use crate::monitor::NEIGHBOR_SCAN_INTERVAL;
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Shortest and longest repeat interval looked for
+const MIN_PERIOD_SECS: i64 = 60;
+const MAX_PERIOD_SECS: i64 = 2 * 3600;
+/// Resampling step when snapshots are closer together than this
+const MIN_BIN_SECS: i64 = 10;
+/// Caps the resampled series (and so the work) on multi-week captures
+const MAX_BINS: i64 = 50_000;
+/// A period must repeat at least this many times within the capture
+const MIN_CYCLES: i64 = 3;
+/// Autocorrelation at the period, and its rise over the dip before it, to count as periodic
+const MIN_AUTOCORRELATION: f64 = 0.3;
+const MIN_PROMINENCE: f64 = 0.2;
+/// Harmonics within this share of the strongest peak are taken as the fundamental
+const HARMONIC_RATIO: f64 = 0.8;
+/// Relative tolerance when matching a period to a known interval
+const MATCH_TOLERANCE: f64 = 0.05;
+
+/// Reads one metric from a snapshot
+type Metric = fn(&WifiSnapshot) -> Option<f64>;
+
+/// A metric that rises and falls on a fixed cycle
+#[derive(Debug, Clone)]
+pub struct PeriodicPattern {
+    pub metric: &'static str,
+    pub period_secs: i64,
+    /// Autocorrelation at the period, 0..1
+    pub strength: f64,
+    pub cycles: i64,
+    /// A moment the metric peaked, to line up with scheduled jobs
+    pub example_peak: DateTime<Utc>,
+    pub culprits: Vec<String>,
+}
+
+impl PeriodicPattern {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} peaks every {} (autocorrelation {:.2}, {} cycles), e.g. at {}",
+            self.metric,
+            format_period(self.period_secs),
+            self.strength,
+            self.cycles,
+            self.example_peak.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+/// Look for latency and loss that repeat on a fixed cycle
+pub fn detect(snapshots: &[WifiSnapshot]) -> Vec<PeriodicPattern> {
+    let metrics: [(&'static str, Metric); 2] = [
+        ("Latency", |s| s.latency.average_latency_ms),
+        ("Packet loss", |s| Some(s.latency.packet_loss_percent)),
+    ];
+    metrics.into_iter()
+        .filter_map(|(metric, value)| {
+            let mut samples: Vec<(DateTime<Utc>, f64)> = snapshots.iter()
+                .filter_map(|s| value(s).map(|v| (s.timestamp, v)))
+                .collect();
+            samples.sort_by_key(|(at, _)| *at);
+            detect_period(metric, &samples)
+        })
+        .collect()
+}
+
+fn detect_period(metric: &'static str, samples: &[(DateTime<Utc>, f64)]) -> Option<PeriodicPattern> {
+    let (start, end) = (samples.first()?.0, samples.last()?.0);
+    let span = (end - start).num_seconds();
+    if span < MIN_PERIOD_SECS * MIN_CYCLES {
+        return None;
+    }
+
+    // Resample onto an even grid at about the snapshot interval; empty bins stay None
+    let mut gaps: Vec<i64> = samples.windows(2).map(|w| (w[1].0 - w[0].0).num_seconds()).collect();
+    gaps.sort_unstable();
+    let bin = gaps[gaps.len() / 2].max(MIN_BIN_SECS).max(span / MAX_BINS + 1);
+    let mut sums = vec![(0.0, 0u32); (span / bin + 1) as usize];
+    for (at, value) in samples {
+        let slot = &mut sums[((*at - start).num_seconds() / bin) as usize];
+        slot.0 += value;
+        slot.1 += 1;
+    }
+    let series: Vec<Option<f64>> = sums.iter().map(|&(sum, n)| (n > 0).then(|| sum / n as f64)).collect();
+
+    let present: Vec<f64> = series.iter().flatten().copied().collect();
+    let mean = present.iter().sum::<f64>() / present.len() as f64;
+    if present.iter().all(|v| (v - mean).abs() < f64::EPSILON) {
+        return None;
+    }
+    let centered: Vec<Option<f64>> = series.iter().map(|v| v.map(|v| v - mean)).collect();
+
+    let min_lag = (MIN_PERIOD_SECS / bin).max(2) as usize;
+    let max_lag = ((span / MIN_CYCLES).min(MAX_PERIOD_SECS) / bin) as usize;
+    if max_lag <= min_lag {
+        return None;
+    }
+    let acf: Vec<f64> = (0..=max_lag + 1).map(|lag| autocorrelation(&centered, lag)).collect();
+
+    // Local maxima that clearly rise above the dip before them
+    let peaks: Vec<(usize, f64)> = (min_lag..=max_lag)
+        .filter(|&lag| acf[lag] >= acf[lag - 1] && acf[lag] >= acf[lag + 1])
+        .filter(|&lag| acf[lag] >= MIN_AUTOCORRELATION)
+        .filter(|&lag| acf[lag] - acf[1..lag].iter().cloned().fold(f64::MAX, f64::min) >= MIN_PROMINENCE)
+        .map(|lag| (lag, acf[lag]))
+        .collect();
+    let strongest = peaks.iter().map(|&(_, r)| r).fold(0.0, f64::max);
+    // Multiples of the period peak too; the shortest strong one is the period itself
+    let (lag, strength) = peaks.into_iter().find(|&(_, r)| r >= strongest * HARMONIC_RATIO)?;
+    // A real cycle repeats at twice the period as well, where the capture is long enough to tell
+    if 2 * lag < acf.len() - 1 && acf[2 * lag - 1..=2 * lag + 1].iter().all(|&r| r < MIN_AUTOCORRELATION / 2.0) {
+        return None;
+    }
+    let period_secs = lag as i64 * bin;
+
+    // Fold the series over the period to find where in the cycle it peaks
+    let mut phases = vec![(0.0, 0u32); lag];
+    for (i, value) in centered.iter().enumerate() {
+        if let Some(value) = value {
+            phases[i % lag].0 += value;
+            phases[i % lag].1 += 1;
+        }
+    }
+    let peak_phase = phases.iter()
+        .enumerate()
+        .filter(|(_, (_, n))| *n > 0)
+        .max_by(|(_, a), (_, b)| (a.0 / a.1 as f64).partial_cmp(&(b.0 / b.1 as f64)).unwrap())
+        .map(|(i, _)| i)?;
+
+    Some(PeriodicPattern {
+        metric,
+        period_secs,
+        strength,
+        cycles: span / period_secs,
+        example_peak: start + TimeDelta::seconds(peak_phase as i64 * bin),
+        culprits: likely_culprits(period_secs),
+    })
+}
+
+/// Pearson correlation of the series with itself shifted by `lag`, over bins present in both
+fn autocorrelation(series: &[Option<f64>], lag: usize) -> f64 {
+    let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+    for (a, b) in series.iter().zip(series.iter().skip(lag)) {
+        if let (Some(a), Some(b)) = (a, b) {
+            xy += a * b;
+            xx += a * a;
+            yy += b * b;
+        }
+    }
+    if xx == 0.0 || yy == 0.0 {
+        return 0.0;
+    }
+    xy / (xx * yy).sqrt()
+}
+
+fn near(period_secs: i64, target_secs: i64) -> bool {
+    (period_secs - target_secs).abs() as f64 <= target_secs as f64 * MATCH_TOLERANCE
+}
+
+/// Usual suspects for a cycle of this length, most specific first
+fn likely_culprits(period_secs: i64) -> Vec<String> {
+    let mut culprits = Vec::new();
+    if near(period_secs, NEIGHBOR_SCAN_INTERVAL.as_secs() as i64) {
+        culprits.push(format!(
+            "this tracker's own neighbor scan (every {}) - a scan takes the radio off channel",
+            format_period(NEIGHBOR_SCAN_INTERVAL.as_secs() as i64)
+        ));
+    }
+    if (50..=130).contains(&period_secs) {
+        culprits.push("Windows WLAN background scans (about every 60-120s; triggered by location services or apps listing networks)".to_string());
+    }
+    if period_secs >= 15 * 60 && [15, 30, 60, 120].iter().any(|m| near(period_secs, m * 60)) {
+        culprits.push("a job scheduled on the clock: backup, antivirus scan, Windows Update or a cron job on the router or NAS".to_string());
+    }
+    if (3 * 60..=30 * 60).contains(&period_secs) {
+        culprits.push("a sync or backup client polling its server (OneDrive, Dropbox, Time Machine-style backups)".to_string());
+    }
+    if period_secs >= 30 * 60 {
+        culprits.push("the router re-evaluating its channel (auto channel) or renewing a DHCP/PPPoE lease".to_string());
+    }
+    culprits.push("a neighbor's device or IoT gadget (camera, smart plug) reporting on a fixed interval on the same channel".to_string());
+    culprits
+}
+
+fn format_period(secs: i64) -> String {
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, 0) => format!("{}m", m),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
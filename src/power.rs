@@ -0,0 +1,162 @@
+use crate::metrics::SamplingPolicy;
+use crate::probe::CommandRunner;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// How often battery and metered status are re-read
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Which conditions switch the monitor to reduced sampling
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum PowerTrigger {
+    /// Always sample at the configured interval
+    Off,
+    /// Reduce sampling while running on battery
+    Battery,
+    /// Reduce sampling while the WiFi network is marked metered
+    Metered,
+    /// Reduce sampling on battery or on a metered network
+    Both,
+}
+
+/// How the monitor backs off on battery or metered connections
+#[derive(Debug, Clone, Copy)]
+pub struct PowerPolicy {
+    pub trigger: PowerTrigger,
+    /// Multiplies the base interval while reduced
+    pub interval_multiplier: u64,
+    /// Skip neighbor scans, outage diagnostic bursts and latency investigations while reduced
+    pub skip_heavy_probes: bool,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            trigger: PowerTrigger::Both,
+            interval_multiplier: 3,
+            skip_heavy_probes: true,
+        }
+    }
+}
+
+/// Tracks battery and metered status and whether the policy currently applies
+pub struct PowerState {
+    policy: PowerPolicy,
+    last_check: Option<Instant>,
+    checked_ssid: Option<String>,
+    on_battery: Option<bool>,
+    metered: Option<bool>,
+    reduced: bool,
+}
+
+impl PowerState {
+    pub fn new(policy: PowerPolicy) -> Self {
+        Self {
+            policy,
+            last_check: None,
+            checked_ssid: None,
+            on_battery: None,
+            metered: None,
+            reduced: false,
+        }
+    }
+
+    /// Re-read battery and metered status when due (or when the network changed) and
+    /// return the policy in effect for this snapshot
+    pub async fn refresh(&mut self, runner: &CommandRunner, ssid: Option<&str>) -> SamplingPolicy {
+        let watch_battery = matches!(self.policy.trigger, PowerTrigger::Battery | PowerTrigger::Both);
+        let watch_metered = matches!(self.policy.trigger, PowerTrigger::Metered | PowerTrigger::Both);
+        let due = self.last_check.is_none_or(|t| t.elapsed() >= POWER_CHECK_INTERVAL);
+
+        if watch_battery && due {
+            self.on_battery = on_battery(runner).await;
+        }
+        if watch_metered && (due || self.checked_ssid.as_deref() != ssid) {
+            self.metered = match ssid {
+                Some(ssid) => is_metered(runner, ssid).await,
+                None => None,
+            };
+            self.checked_ssid = ssid.map(str::to_string);
+        }
+        if due {
+            self.last_check = Some(Instant::now());
+        }
+
+        let reduced = self.on_battery == Some(true) || self.metered == Some(true);
+        if reduced != self.reduced {
+            self.reduced = reduced;
+            if reduced {
+                info!(
+                    "{}, sampling {}x less often{}",
+                    self.reason(),
+                    self.policy.interval_multiplier,
+                    if self.policy.skip_heavy_probes { " and skipping heavy probes" } else { "" }
+                );
+            } else {
+                info!("No longer on battery or a metered network, restoring normal sampling");
+            }
+        }
+
+        SamplingPolicy {
+            on_battery: self.on_battery,
+            metered: self.metered,
+            reduced,
+            heavy_probes_skipped: self.skip_heavy_probes(),
+        }
+    }
+
+    /// The interval to use between calm snapshots
+    pub fn base_interval(&self, interval_secs: u64) -> u64 {
+        if self.reduced {
+            interval_secs * self.policy.interval_multiplier.max(1)
+        } else {
+            interval_secs
+        }
+    }
+
+    pub fn skip_heavy_probes(&self) -> bool {
+        self.reduced && self.policy.skip_heavy_probes
+    }
+
+    fn reason(&self) -> &'static str {
+        match (self.on_battery == Some(true), self.metered == Some(true)) {
+            (true, true) => "On battery and a metered network",
+            (true, false) => "On battery",
+            _ => "On a metered network",
+        }
+    }
+}
+
+/// Whether the machine is running on battery; `Some(false)` on AC or without a battery
+async fn on_battery(runner: &CommandRunner) -> Option<bool> {
+    let output = runner
+        .output("powershell", &["-NoProfile", "-Command", "(Get-CimInstance Win32_Battery).BatteryStatus"])
+        .await;
+    match output {
+        Ok(output) => {
+            // 1 = discharging, 4 = low, 5 = critical; the rest mean AC power
+            let status = String::from_utf8_lossy(&output.stdout);
+            Some(status.lines().filter_map(|l| l.trim().parse::<u32>().ok()).any(|s| matches!(s, 1 | 4 | 5)))
+        }
+        Err(e) => {
+            debug!("Failed to read battery status: {}", e);
+            None
+        }
+    }
+}
+
+/// Whether the WiFi profile for `ssid` has a metered cost (anything but "Unrestricted")
+async fn is_metered(runner: &CommandRunner, ssid: &str) -> Option<bool> {
+    let name = format!("name={}", ssid);
+    let output = match runner.output("netsh", &["wlan", "show", "profile", &name]).await {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Failed to read the cost of profile {}: {}", ssid, e);
+            return None;
+        }
+    };
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("cost")).then(|| !value.trim().eq_ignore_ascii_case("unrestricted"))
+    })
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::process::Output;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::warn;
+
+/// How long each kind of spawned command may run before it is killed
+#[derive(Debug, Clone, Copy)]
+pub struct CommandTimeouts {
+    /// netsh, ipconfig, route, netstat, wevtutil and the rest
+    pub default: Duration,
+    /// A whole `ping` or `tracert` run, not a single reply
+    pub ping: Duration,
+    /// One `nslookup` query
+    pub dns: Duration,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_secs(15),
+            ping: Duration::from_secs(30),
+            dns: Duration::from_secs(10),
+        }
+    }
+}
+
+impl CommandTimeouts {
+    fn for_program(&self, program: &str) -> Duration {
+        match program {
+            "ping" | "tracert" => self.ping,
+            "nslookup" => self.dns,
+            _ => self.default,
+        }
+    }
+}
+
+/// Why a spawned command produced no output
+#[derive(Debug)]
+pub enum ProbeError {
+    /// The command overran its timeout and was killed
+    TimedOut { command: String, timeout: Duration },
+    /// The command could not be started
+    Failed { command: String, error: std::io::Error },
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeError::TimedOut { command, timeout } => {
+                write!(f, "ProbeTimedOut: `{}` was killed after {}s", command, timeout.as_secs())
+            }
+            ProbeError::Failed { command, error } => write!(f, "Failed to run `{}`: {}", command, error),
+        }
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+/// A command that was killed for overrunning its timeout
+#[derive(Debug, Clone)]
+pub struct ProbeTimeout {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+/// Runs the monitor's commands without blocking the runtime, killing any that
+/// hang and remembering them so each can be reported as a `ProbeTimedOut` event
+#[derive(Clone, Default)]
+pub struct CommandRunner {
+    timeouts: CommandTimeouts,
+    timed_out: Arc<Mutex<Vec<ProbeTimeout>>>,
+}
+
+impl CommandRunner {
+    pub fn new(timeouts: CommandTimeouts) -> Self {
+        Self {
+            timeouts,
+            timed_out: Arc::default(),
+        }
+    }
+
+    /// Run `program` to completion and capture its output
+    pub async fn output<S: AsRef<str>>(&self, program: &str, args: &[S]) -> Result<Output, ProbeError> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let command = format!("{} {}", program, args.join(" ")).trim_end().to_string();
+        let timeout = self.timeouts.for_program(program);
+
+        // Dropping the future on timeout drops the child, and kill_on_drop kills it
+        let child = Command::new(program).args(&args).kill_on_drop(true).output();
+        match tokio::time::timeout(timeout, child).await {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(error)) => Err(ProbeError::Failed { command, error }),
+            Err(_) => {
+                warn!("`{}` did not finish within {}s, killed it", command, timeout.as_secs());
+                self.timed_out.lock().unwrap().push(ProbeTimeout {
+                    command: command.clone(),
+                    timeout,
+                });
+                Err(ProbeError::TimedOut { command, timeout })
+            }
+        }
+    }
+
+    /// Commands that timed out since the last call
+    pub fn take_timeouts(&self) -> Vec<ProbeTimeout> {
+        std::mem::take(&mut *self.timed_out.lock().unwrap())
+    }
+}
+
+/// Options for one `ping` run; unset values use Windows' defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct PingOptions {
+    /// Echo requests per run (`-n`)
+    pub count: u32,
+    /// Payload size in bytes (`-l`, Windows default 32)
+    pub size: Option<u32>,
+    /// Per-reply timeout in milliseconds (`-w`, Windows default 4000)
+    pub timeout_ms: Option<u32>,
+    /// Time to live (`-i`, Windows default 128)
+    pub ttl: Option<u32>,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            size: None,
+            timeout_ms: None,
+            ttl: None,
+        }
+    }
+}
+
+impl PingOptions {
+    /// Arguments for `ping` ahead of the target
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-n".to_string(), self.count.to_string()];
+        if let Some(size) = self.size {
+            args.extend(["-l".to_string(), size.to_string()]);
+        }
+        if let Some(timeout) = self.timeout_ms {
+            args.extend(["-w".to_string(), timeout.to_string()]);
+        }
+        if let Some(ttl) = self.ttl {
+            args.extend(["-i".to_string(), ttl.to_string()]);
+        }
+        args
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        let number: u32 = value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Ping option {} needs a number, got {:?}", key, value))?;
+        match key {
+            "count" if number >= 1 => self.count = number,
+            "size" if number <= 65500 => self.size = Some(number),
+            "timeout" if number >= 1 => self.timeout_ms = Some(number),
+            "ttl" if (1..=255).contains(&number) => self.ttl = Some(number),
+            "count" | "size" | "timeout" | "ttl" => anyhow::bail!("Ping option {}={} is out of range", key, number),
+            _ => anyhow::bail!("Unknown ping option {:?} (expected count, size, timeout or ttl)", key),
+        }
+        Ok(())
+    }
+}
+
+/// A ping target with its own options, parsed from `host[:key=value...]`,
+/// e.g. `8.8.8.8:count=10:size=1400`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PingTarget {
+    pub host: String,
+    pub options: PingOptions,
+}
+
+impl PingTarget {
+    /// Parse a target, starting from the global `defaults` for anything it doesn't set.
+    /// Colon-separated parts without `=` belong to the host, so IPv6 addresses work.
+    pub fn parse(spec: &str, defaults: &PingOptions) -> anyhow::Result<Self> {
+        let mut options = defaults.clone();
+        let mut host = Vec::new();
+        for part in spec.trim().split(':') {
+            match part.split_once('=') {
+                Some((key, value)) => options.set(key.trim(), value.trim())?,
+                None => host.push(part),
+            }
+        }
+        let host = host.join(":");
+        if host.is_empty() {
+            anyhow::bail!("Ping target {:?} has no host", spec);
+        }
+        Ok(Self { host, options })
+    }
+}
@@ -0,0 +1,95 @@
+use crate::metrics::{PingResult, WifiSnapshot};
+use std::fmt::Write;
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The latest snapshot as `wifi_*` gauges and the stored events as `wifi_events_total`
+/// counters by type, in the Prometheus text exposition format. Values the snapshot doesn't
+/// have (no WiFi, no successful ping) are left out rather than reported as zero.
+pub fn render(snapshot: Option<&WifiSnapshot>, event_counts: &[(String, i64)]) -> String {
+    let mut out = Exposition::default();
+
+    if let Some(snapshot) = snapshot {
+        let wifi = snapshot.wifi_info.as_ref();
+        let latency = &snapshot.latency;
+        let flag = |on: bool| Some(if on { 1.0 } else { 0.0 });
+
+        out.gauge("wifi_snapshot_timestamp_seconds", "Time of the latest snapshot", Some(snapshot.timestamp.timestamp_millis() as f64 / 1000.0));
+        if let Some(wifi) = wifi {
+            let labels = labels(&[("ssid", &wifi.ssid), ("bssid", &wifi.bssid), ("band", wifi.band.label())]);
+            out.metric("wifi_info", "gauge", "The network the latest snapshot was taken on", [(labels, 1.0)]);
+        }
+        out.gauge("wifi_connected", "Whether WiFi is connected", flag(snapshot.connectivity.is_connected));
+        out.gauge("wifi_router_reachable", "Whether the router answers", flag(snapshot.connectivity.router_reachable));
+        out.gauge("wifi_internet_reachable", "Whether the internet is reachable", flag(snapshot.connectivity.internet_reachable));
+        out.gauge("wifi_other_network", "Whether the snapshot was taken on a network other than --only-ssid", flag(snapshot.other_network));
+        out.gauge("wifi_maintenance", "Whether the snapshot was taken in a maintenance window left out of uptime", flag(snapshot.maintenance));
+        out.gauge("wifi_signal_dbm", "Signal strength in dBm", wifi.map(|w| w.signal_strength_dbm as f64));
+        out.gauge("wifi_signal_quality_percent", "Signal quality as reported by the adapter", wifi.map(|w| w.signal_quality_percent as f64));
+        out.gauge("wifi_channel", "WiFi channel", wifi.map(|w| w.channel as f64));
+        out.gauge("wifi_link_speed_mbps", "Negotiated link speed in Mbps", wifi.map(|w| w.link_speed_mbps as f64));
+        out.gauge("wifi_router_latency_ms", "Round trip time to the router in ms", latency.router_latency_ms);
+        out.gauge("wifi_latency_avg_ms", "Average round trip time across ping targets in ms", latency.average_latency_ms);
+        out.gauge("wifi_latency_min_ms", "Lowest round trip time across ping targets in ms", latency.min_latency_ms);
+        out.gauge("wifi_latency_max_ms", "Highest round trip time across ping targets in ms", latency.max_latency_ms);
+        out.gauge("wifi_jitter_ms", "Latency jitter in ms", latency.jitter_ms);
+        out.gauge("wifi_packet_loss_percent", "Packet loss across ping targets", Some(latency.packet_loss_percent));
+        out.gauge("wifi_mos", "Estimated call quality (MOS, 1 to 4.5)", latency.mos);
+        out.gauge("wifi_dns_resolution_ms", "Average DNS resolution time in ms", snapshot.dns_metrics.average_resolution_time_ms);
+        out.gauge("wifi_dns_failures", "Failed DNS queries in the latest snapshot", Some(snapshot.dns_metrics.failures as f64));
+        out.gauge("wifi_throughput_rx_bytes_per_second", "Bytes received per second on the WiFi adapter", snapshot.system_info.throughput_rx_bytes_per_sec);
+        out.gauge("wifi_throughput_tx_bytes_per_second", "Bytes sent per second on the WiFi adapter", snapshot.system_info.throughput_tx_bytes_per_sec);
+
+        let by_target = |value: fn(&PingResult) -> Option<f64>| {
+            latency.targets.iter().filter_map(move |t| Some((labels(&[("target", &t.target)]), value(t)?)))
+        };
+        out.metric("wifi_ping_avg_ms", "gauge", "Average round trip time per ping target in ms", by_target(|t| t.avg_ms));
+        out.metric("wifi_ping_packet_loss_percent", "gauge", "Packet loss per ping target", by_target(|t| Some(t.packet_loss_percent)));
+    }
+
+    out.metric(
+        "wifi_events_total",
+        "counter",
+        "Events stored in the database by type; pruning lowers it, which Prometheus treats as a reset",
+        event_counts.iter().map(|(event_type, count)| (labels(&[("event_type", event_type)]), *count as f64)),
+    );
+    out.0
+}
+
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn gauge(&mut self, name: &str, help: &str, value: Option<f64>) {
+        self.metric(name, "gauge", help, value.map(|v| (String::new(), v)));
+    }
+
+    /// `# HELP` and `# TYPE` lines and one sample per labelled value; a metric without
+    /// finite values is left out entirely
+    fn metric(&mut self, name: &str, kind: &str, help: &str, samples: impl IntoIterator<Item = (String, f64)>) {
+        let mut samples = samples.into_iter().filter(|(_, v)| v.is_finite()).peekable();
+        if samples.peek().is_none() {
+            return;
+        }
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(self.0, "{}{} {}", name, labels, value);
+        }
+    }
+}
+
+/// `{key="value",...}`, leaving out empty values
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| format!("{}=\"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
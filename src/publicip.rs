@@ -0,0 +1,60 @@
+use crate::metrics::PublicIpInfo;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Returns the caller's address with its ASN and ISP; ip-api.com style responses work too
+pub const DEFAULT_LOOKUP_URL: &str = "https://ipinfo.io/json";
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Looks up the public IP every so often, and right after the internet comes back
+pub struct PublicIpLookup {
+    url: String,
+    every: Duration,
+    last_check: Option<Instant>,
+    last: Option<PublicIpInfo>,
+}
+
+impl PublicIpLookup {
+    pub fn new(url: String, every: Duration) -> Self {
+        Self { url, every, last_check: None, last: None }
+    }
+
+    /// Whether a lookup is due; `recovered` forces one, since outages are when the address rotates
+    pub fn due(&self, recovered: bool) -> bool {
+        recovered || self.last_check.is_none_or(|t| t.elapsed() >= self.every)
+    }
+
+    /// Look the address up, returning it with the previous one when it changed
+    pub async fn check(&mut self) -> Option<(PublicIpInfo, Option<PublicIpInfo>)> {
+        self.last_check = Some(Instant::now());
+        let info = match lookup(&self.url).await {
+            Ok(info) => info,
+            Err(e) => {
+                debug!("Public IP lookup via {} failed: {}", self.url, e);
+                return None;
+            }
+        };
+        let previous = self.last.replace(info.clone()).filter(|p| p.ip != info.ip);
+        Some((info, previous))
+    }
+}
+
+async fn lookup(url: &str) -> anyhow::Result<PublicIpInfo> {
+    let client = reqwest::Client::builder().timeout(LOOKUP_TIMEOUT).build()?;
+    let body: serde_json::Value = client.get(url).send().await?.error_for_status()?.json().await?;
+
+    // ipinfo.io: {"ip", "org": "AS7922 Comcast Cable"}; ip-api.com: {"query", "as": "AS7922 ...", "isp"}
+    let ip = body["ip"].as_str().or(body["query"].as_str())
+        .ok_or_else(|| anyhow::anyhow!("no address in the response"))?;
+    let org = body["org"].as_str().or(body["as"].as_str()).unwrap_or_default();
+    let (asn, org_name) = match org.split_once(' ') {
+        Some((asn, name)) if asn.starts_with("AS") => (asn[2..].parse().ok(), Some(name.trim())),
+        _ => (None, Some(org).filter(|o| !o.is_empty())),
+    };
+
+    Ok(PublicIpInfo {
+        ip: ip.to_string(),
+        asn,
+        isp: body["isp"].as_str().or(org_name).map(str::to_string),
+    })
+}
@@ -0,0 +1,101 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Requests a client may make at once after being idle, as seconds' worth of its rate
+const BURST_SECS: f64 = 10.0;
+
+/// Clients tracked before idle ones are forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Per-IP limit on web requests, so a runaway script or an exposed port can't keep the
+/// database busy enough to hold up the monitoring loop. Off when built with a rate of 0.
+#[derive(Clone, Default)]
+pub struct RateLimit {
+    limiter: Option<Arc<Limiter>>,
+}
+
+struct Limiter {
+    per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimit {
+    pub fn new(per_minute: u32) -> Self {
+        if per_minute == 0 {
+            return Self::default();
+        }
+        let per_second = per_minute as f64 / 60.0;
+        Self {
+            limiter: Some(Arc::new(Limiter {
+                per_second,
+                burst: (per_second * BURST_SECS).max(1.0),
+                buckets: Mutex::new(HashMap::new()),
+            })),
+        }
+    }
+}
+
+impl Limiter {
+    /// Take one request from `ip`'s allowance; `Err` holds the seconds until the next one is allowed
+    fn check(&self, ip: IpAddr) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&ip) {
+            // A client whose bucket has refilled is the same as one never seen
+            let refill_secs = self.burst / self.per_second;
+            buckets.retain(|_, b| now.duration_since(b.updated).as_secs_f64() < refill_secs);
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: self.burst, updated: now });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / self.per_second).ceil() as u64)
+        }
+    }
+}
+
+/// Middleware answering 429 with `Retry-After` to a client over its rate. Readiness
+/// probes and the dashboard's static scripts don't count.
+pub async fn limit(
+    State(limit): State<RateLimit>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ref limiter) = limit.limiter else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if path == "/readyz" || path.contains("/assets/") {
+        return next.run(request).await;
+    }
+    match limiter.check(addr.ip()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.max(1).to_string())],
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Too many requests; slow down"
+            })),
+        ).into_response(),
+    }
+}
@@ -0,0 +1,336 @@
+use crate::metrics::*;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// Identifies our probe packets
+const MAGIC: &[u8; 4] = b"WSTR";
+/// magic + stream id + sequence + send time + reflector count + three wall-clock stamps
+const PACKET_LEN: usize = 4 + 8 * 7;
+/// Reflector forgets streams it has not heard from for this long
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// One probe packet. The client fills the stream, sequence and send times; the
+/// reflector sets `reflector_count` to the number of packets it has received on this
+/// stream and stamps its own receive/send wall-clock times for one-way delay.
+#[derive(Debug, Clone, Copy, Default)]
+struct ProbePacket {
+    stream_id: u64,
+    seq: u64,
+    /// Client monotonic send time, for round-trip time
+    sent_at_us: u64,
+    reflector_count: u64,
+    /// Client wall-clock send time (unix µs)
+    sent_unix_us: u64,
+    /// Reflector wall-clock receive and send times (unix µs)
+    reflected_rx_unix_us: u64,
+    reflected_tx_unix_us: u64,
+}
+
+impl ProbePacket {
+    fn encode(&self) -> [u8; PACKET_LEN] {
+        let mut buf = [0u8; PACKET_LEN];
+        buf[..4].copy_from_slice(MAGIC);
+        buf[4..12].copy_from_slice(&self.stream_id.to_be_bytes());
+        buf[12..20].copy_from_slice(&self.seq.to_be_bytes());
+        buf[20..28].copy_from_slice(&self.sent_at_us.to_be_bytes());
+        buf[28..36].copy_from_slice(&self.reflector_count.to_be_bytes());
+        buf[36..44].copy_from_slice(&self.sent_unix_us.to_be_bytes());
+        buf[44..52].copy_from_slice(&self.reflected_rx_unix_us.to_be_bytes());
+        buf[52..60].copy_from_slice(&self.reflected_tx_unix_us.to_be_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < PACKET_LEN || &buf[..4] != MAGIC {
+            return None;
+        }
+        let field = |at: usize| u64::from_be_bytes(buf[at..at + 8].try_into().unwrap());
+        Some(Self {
+            stream_id: field(4),
+            seq: field(12),
+            sent_at_us: field(20),
+            reflector_count: field(28),
+            sent_unix_us: field(36),
+            reflected_rx_unix_us: field(44),
+            reflected_tx_unix_us: field(52),
+        })
+    }
+}
+
+/// Companion server: echo every probe packet back with a per-stream receive count
+pub async fn run_reflector(bind: SocketAddr) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(bind).await?;
+    info!("UDP reflector listening on {}", socket.local_addr()?);
+
+    // (client address, stream id) -> (packets received, last seen)
+    let mut streams: HashMap<(SocketAddr, u64), (u64, Instant)> = HashMap::new();
+    let mut last_cleanup = Instant::now();
+    let mut buf = [0u8; 1500];
+
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf).await?;
+        let received_at = unix_micros();
+        let Some(mut packet) = ProbePacket::decode(&buf[..len]) else {
+            debug!("Ignoring {} byte datagram from {}", len, peer);
+            continue;
+        };
+
+        let entry = streams.entry((peer, packet.stream_id)).or_insert_with(|| {
+            info!("New probe stream {:016x} from {}", packet.stream_id, peer);
+            (0, Instant::now())
+        });
+        entry.0 += 1;
+        entry.1 = Instant::now();
+        packet.reflector_count = entry.0;
+        packet.reflected_rx_unix_us = received_at;
+        packet.reflected_tx_unix_us = unix_micros();
+
+        if let Err(e) = socket.send_to(&packet.encode(), peer).await {
+            warn!("Failed to reflect packet to {}: {}", peer, e);
+        }
+
+        if last_cleanup.elapsed() > STREAM_IDLE_TIMEOUT {
+            streams.retain(|_, (_, seen)| seen.elapsed() < STREAM_IDLE_TIMEOUT);
+            last_cleanup = Instant::now();
+        }
+    }
+}
+
+/// Running totals for the stream; windows are the difference between two readings
+#[derive(Debug, Default, Clone)]
+struct StreamCounters {
+    sent: u64,
+    received: u64,
+    /// Highest sequence number echoed back so far
+    highest_seq: Option<u64>,
+    /// Reflector's receive count as of the reply carrying `highest_seq`
+    reflector_count_at_highest: u64,
+    /// Highest receive count the reflector has reported
+    reflector_count_max: u64,
+    reordered: u64,
+    duplicates: u64,
+    samples: Vec<DelaySample>,
+}
+
+/// Timing of one answered packet. The raw one-way values include the (unknown)
+/// clock offset between the two machines: forward = delay + offset, reverse = delay - offset.
+#[derive(Debug, Clone, Copy)]
+struct DelaySample {
+    rtt_ms: f64,
+    forward_raw_ms: f64,
+    reverse_raw_ms: f64,
+}
+
+impl StreamCounters {
+    /// Packets the reflector never saw (only counting sequence numbers already answered past)
+    fn upstream_lost(&self) -> u64 {
+        self.highest_seq
+            .map(|seq| (seq + 1).saturating_sub(self.reflector_count_at_highest))
+            .unwrap_or(0)
+    }
+
+    /// Packets the reflector sent back that never arrived
+    fn downstream_lost(&self) -> u64 {
+        self.reflector_count_max.saturating_sub(self.received - self.duplicates)
+    }
+}
+
+/// Continuous UDP stream to a user-run reflector (or a peer instance), measuring one-way
+/// loss, delay and reordering far more sensitively than periodic ICMP bursts
+#[derive(Clone)]
+pub struct UdpStreamProbe {
+    reflector: String,
+    rate_pps: u32,
+    counters: Arc<Mutex<StreamCounters>>,
+    window_start: Arc<Mutex<StreamCounters>>,
+}
+
+impl UdpStreamProbe {
+    pub fn new(reflector: String, rate_pps: u32) -> Self {
+        Self {
+            reflector,
+            rate_pps: rate_pps.max(1),
+            counters: Arc::new(Mutex::new(StreamCounters::default())),
+            window_start: Arc::new(Mutex::new(StreamCounters::default())),
+        }
+    }
+
+    /// Stats since the previous call; `None` before anything was sent
+    pub fn take_window(&self) -> Option<UdpStreamStats> {
+        let mut counters = self.counters.lock().unwrap();
+        let mut start = self.window_start.lock().unwrap();
+
+        let sent = counters.sent - start.sent;
+        if sent == 0 {
+            return None;
+        }
+        let samples = std::mem::take(&mut counters.samples);
+        let one_way = split_one_way(&samples);
+        let upstream_lost = counters.upstream_lost().saturating_sub(start.upstream_lost());
+        let downstream_lost = counters.downstream_lost().saturating_sub(start.downstream_lost());
+        let percent = |lost: u64| lost as f64 / sent as f64 * 100.0;
+
+        let stats = UdpStreamStats {
+            reflector: self.reflector.clone(),
+            sent,
+            received: counters.received - start.received,
+            upstream_lost,
+            downstream_lost,
+            upstream_loss_percent: percent(upstream_lost),
+            downstream_loss_percent: percent(downstream_lost),
+            reordered: counters.reordered - start.reordered,
+            duplicates: counters.duplicates - start.duplicates,
+            rtt_avg_ms: (!samples.is_empty()).then(|| samples.iter().map(|s| s.rtt_ms).sum::<f64>() / samples.len() as f64),
+            rtt_max_ms: samples.iter().map(|s| s.rtt_ms).reduce(f64::max),
+            forward_delay_avg_ms: one_way.map(|d| d.forward_avg_ms),
+            forward_delay_max_ms: one_way.map(|d| d.forward_max_ms),
+            reverse_delay_avg_ms: one_way.map(|d| d.reverse_avg_ms),
+            reverse_delay_max_ms: one_way.map(|d| d.reverse_max_ms),
+            clock_offset_ms: one_way.map(|d| d.clock_offset_ms),
+        };
+        *start = counters.clone();
+        Some(stats)
+    }
+
+    /// Send and receive forever; restarts the stream if the socket fails
+    pub async fn run(self) {
+        info!("Starting UDP stream to reflector {} at {} packets/s", self.reflector, self.rate_pps);
+        loop {
+            if let Err(e) = self.stream().await {
+                warn!("UDP stream to {} failed: {}; retrying in 10s", self.reflector, e);
+            }
+            time::sleep(Duration::from_secs(10)).await;
+        }
+    }
+
+    async fn stream(&self) -> anyhow::Result<()> {
+        let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
+        socket.connect(&self.reflector).await?;
+
+        let stream_id = uuid::Uuid::new_v4().as_u64_pair().0;
+        let epoch = Instant::now();
+
+        // A new stream restarts sequence numbers, so restart the totals as well
+        *self.counters.lock().unwrap() = StreamCounters::default();
+        *self.window_start.lock().unwrap() = StreamCounters::default();
+
+        let sender = {
+            let socket = socket.clone();
+            let counters = self.counters.clone();
+            let period = Duration::from_micros(1_000_000 / self.rate_pps as u64);
+            async move {
+                let mut interval = time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                let mut seq = 0u64;
+                loop {
+                    interval.tick().await;
+                    let packet = ProbePacket {
+                        stream_id,
+                        seq,
+                        sent_at_us: epoch.elapsed().as_micros() as u64,
+                        sent_unix_us: unix_micros(),
+                        ..Default::default()
+                    };
+                    // Send errors (e.g. no route while disconnected) simply count as loss
+                    let _ = socket.send(&packet.encode()).await;
+                    counters.lock().unwrap().sent += 1;
+                    seq += 1;
+                }
+            }
+        };
+
+        let receiver = async {
+            let mut buf = [0u8; 1500];
+            let mut seen = std::collections::VecDeque::with_capacity(1024);
+            loop {
+                let len = match socket.recv(&mut buf).await {
+                    Ok(len) => len,
+                    // ICMP port unreachable surfaces as a receive error on connected sockets
+                    Err(e) => {
+                        debug!("UDP stream receive error: {}", e);
+                        continue;
+                    }
+                };
+                let Some(packet) = ProbePacket::decode(&buf[..len]) else { continue };
+                if packet.stream_id != stream_id {
+                    continue;
+                }
+
+                let received_unix_us = unix_micros();
+                let rtt_ms = epoch.elapsed().as_micros().saturating_sub(packet.sent_at_us as u128) as f64 / 1000.0;
+                // Remove the time the reflector held the packet
+                let held_ms = packet.reflected_tx_unix_us.saturating_sub(packet.reflected_rx_unix_us) as f64 / 1000.0;
+                let sample = DelaySample {
+                    rtt_ms: (rtt_ms - held_ms).max(0.0),
+                    forward_raw_ms: (packet.reflected_rx_unix_us as f64 - packet.sent_unix_us as f64) / 1000.0,
+                    reverse_raw_ms: (received_unix_us as f64 - packet.reflected_tx_unix_us as f64) / 1000.0,
+                };
+                let mut counters = self.counters.lock().unwrap();
+                counters.received += 1;
+                if seen.contains(&packet.seq) {
+                    counters.duplicates += 1;
+                    continue;
+                }
+                if seen.len() == seen.capacity() {
+                    seen.pop_front();
+                }
+                seen.push_back(packet.seq);
+
+                counters.samples.push(sample);
+                counters.reflector_count_max = counters.reflector_count_max.max(packet.reflector_count);
+                match counters.highest_seq {
+                    Some(highest) if packet.seq < highest => counters.reordered += 1,
+                    _ => {
+                        counters.highest_seq = Some(packet.seq);
+                        counters.reflector_count_at_highest = packet.reflector_count;
+                    }
+                }
+            }
+        };
+
+        tokio::join!(sender, receiver);
+        Ok(())
+    }
+}
+
+fn unix_micros() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// One-way delays with the clock offset between the two machines removed
+#[derive(Debug, Clone, Copy)]
+struct OneWayDelays {
+    forward_avg_ms: f64,
+    forward_max_ms: f64,
+    reverse_avg_ms: f64,
+    reverse_max_ms: f64,
+    clock_offset_ms: f64,
+}
+
+/// Estimate the clock offset from the fastest round trip (where both directions are
+/// closest to their base delay and so most symmetric), then correct every sample by it
+fn split_one_way(samples: &[DelaySample]) -> Option<OneWayDelays> {
+    let fastest = samples.iter().min_by(|a, b| a.rtt_ms.partial_cmp(&b.rtt_ms).unwrap())?;
+    let offset = (fastest.forward_raw_ms - fastest.reverse_raw_ms) / 2.0;
+
+    let forward: Vec<f64> = samples.iter().map(|s| (s.forward_raw_ms - offset).max(0.0)).collect();
+    let reverse: Vec<f64> = samples.iter().map(|s| (s.reverse_raw_ms + offset).max(0.0)).collect();
+    let avg = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+    let max = |v: &[f64]| v.iter().cloned().fold(0.0, f64::max);
+
+    Some(OneWayDelays {
+        forward_avg_ms: avg(&forward),
+        forward_max_ms: max(&forward),
+        reverse_avg_ms: avg(&reverse),
+        reverse_max_ms: max(&reverse),
+        clock_offset_ms: offset,
+    })
+}
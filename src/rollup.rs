@@ -0,0 +1,239 @@
+use crate::metrics::*;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Latency histogram resolution: buckets are this many milliseconds wide
+pub(crate) const HISTOGRAM_BUCKET_MS: f64 = 0.1;
+
+/// Running sum, count and extremes of one metric
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub sum: f64,
+    pub count: u32,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Summary {
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+
+    fn merge(&mut self, other: &Summary) {
+        self.sum += other.sum;
+        self.count += other.count;
+        self.min = [self.min, other.min].into_iter().flatten().reduce(f64::min);
+        self.max = [self.max, other.max].into_iter().flatten().reduce(f64::max);
+    }
+
+    fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// Everything `PeriodStatistics` is computed from, in a form that adds up across
+/// hours and days so long ranges don't need every snapshot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsRollup {
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    pub samples: u32,
+    pub other_network_samples: u32,
+    #[serde(default)]
+    pub maintenance_samples: u32,
+    /// Interval-weighted seconds, as uptime is
+    pub total_secs: f64,
+    pub connected_secs: f64,
+    pub internet_secs: f64,
+    pub reduced_secs: f64,
+    pub signal_dbm: Summary,
+    pub signal_quality: Summary,
+    pub latency_ms: Summary,
+    /// Snapshot average latencies in 0.1ms buckets, for percentiles
+    pub latency_histogram: BTreeMap<u32, u32>,
+    pub jitter_ms: Summary,
+    pub rfc3550_jitter_ms: Summary,
+    pub mos: Summary,
+    pub poor_calls: u32,
+    pub packet_loss_percent: Summary,
+    pub disconnections: u32,
+    pub warning_events: u32,
+    pub error_events: u32,
+    pub critical_events: u32,
+    /// Whether the last snapshot was connected, so the next period counts a drop at its start
+    pub ended_connected: bool,
+}
+
+impl StatsRollup {
+    /// Summarize snapshots in time order. `was_connected` is the state before the first one;
+    /// snapshots from other networks are only counted when `include_other_networks` is set,
+    /// and those from excluded maintenance windows never are.
+    pub fn from_snapshots<'a>(
+        snapshots: impl IntoIterator<Item = &'a WifiSnapshot>,
+        was_connected: bool,
+        include_other_networks: bool,
+    ) -> Self {
+        let mut rollup = Self { ended_connected: was_connected, ..Self::default() };
+        for snapshot in snapshots {
+            if snapshot.other_network && !include_other_networks {
+                rollup.other_network_samples += 1;
+                continue;
+            }
+            if snapshot.maintenance {
+                rollup.maintenance_samples += 1;
+                continue;
+            }
+            rollup.add(snapshot);
+        }
+        rollup
+    }
+
+    fn add(&mut self, snapshot: &WifiSnapshot) {
+        self.first = Some(self.first.map_or(snapshot.timestamp, |t| t.min(snapshot.timestamp)));
+        self.last = Some(self.last.map_or(snapshot.timestamp, |t| t.max(snapshot.timestamp)));
+        self.samples += 1;
+
+        let weight = snapshot.interval_secs.unwrap_or(1) as f64;
+        self.total_secs += weight;
+        if snapshot.sampling.reduced {
+            self.reduced_secs += weight;
+        }
+        match snapshot.wifi_info {
+            Some(ref wifi) => {
+                self.signal_dbm.add(wifi.signal_strength_dbm as f64);
+                self.signal_quality.add(wifi.signal_quality_percent as f64);
+                self.connected_secs += weight;
+                self.ended_connected = true;
+            }
+            None => {
+                if self.ended_connected {
+                    self.disconnections += 1;
+                }
+                self.ended_connected = false;
+            }
+        }
+        if snapshot.connectivity.internet_reachable {
+            self.internet_secs += weight;
+        }
+
+        if let Some(avg) = snapshot.latency.average_latency_ms {
+            self.latency_ms.add(avg);
+            *self.latency_histogram.entry((avg / HISTOGRAM_BUCKET_MS).round() as u32).or_default() += 1;
+        }
+        if let Some(jitter) = snapshot.latency.jitter_ms {
+            self.jitter_ms.add(jitter);
+        }
+        if let Some(jitter) = snapshot.latency.rfc3550_jitter_ms {
+            self.rfc3550_jitter_ms.add(jitter);
+        }
+        if let Some(mos) = snapshot.latency.mos {
+            self.mos.add(mos);
+            if mos < MOS_POOR_CALL {
+                self.poor_calls += 1;
+            }
+        }
+        self.packet_loss_percent.add(snapshot.latency.packet_loss_percent);
+
+        for event in snapshot.events.iter().filter(|e| e.event_type != EventType::TestAlert) {
+            match event.severity {
+                EventSeverity::Warning => self.warning_events += 1,
+                EventSeverity::Error => self.error_events += 1,
+                EventSeverity::Critical => self.critical_events += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Fold in another period; order doesn't matter
+    pub fn merge(&mut self, other: &StatsRollup) {
+        if other.last > self.last {
+            self.ended_connected = other.ended_connected;
+        }
+        self.first = [self.first, other.first].into_iter().flatten().min();
+        self.last = [self.last, other.last].into_iter().flatten().max();
+        self.samples += other.samples;
+        self.other_network_samples += other.other_network_samples;
+        self.maintenance_samples += other.maintenance_samples;
+        self.total_secs += other.total_secs;
+        self.connected_secs += other.connected_secs;
+        self.internet_secs += other.internet_secs;
+        self.reduced_secs += other.reduced_secs;
+        self.signal_dbm.merge(&other.signal_dbm);
+        self.signal_quality.merge(&other.signal_quality);
+        self.latency_ms.merge(&other.latency_ms);
+        for (bucket, count) in &other.latency_histogram {
+            *self.latency_histogram.entry(*bucket).or_default() += count;
+        }
+        self.jitter_ms.merge(&other.jitter_ms);
+        self.rfc3550_jitter_ms.merge(&other.rfc3550_jitter_ms);
+        self.mos.merge(&other.mos);
+        self.poor_calls += other.poor_calls;
+        self.packet_loss_percent.merge(&other.packet_loss_percent);
+        self.disconnections += other.disconnections;
+        self.warning_events += other.warning_events;
+        self.error_events += other.error_events;
+        self.critical_events += other.critical_events;
+    }
+
+    /// Latency below which `fraction` of the snapshots fall
+    fn latency_percentile(&self, fraction: f64) -> Option<f64> {
+        let total: u32 = self.latency_histogram.values().sum();
+        let index = ((total as f64 * fraction) as u32).min(total.checked_sub(1)?);
+        let mut seen = 0;
+        self.latency_histogram.iter()
+            .find(|(_, count)| {
+                seen += **count;
+                seen > index
+            })
+            .map(|(bucket, _)| *bucket as f64 * HISTOGRAM_BUCKET_MS)
+    }
+
+    pub fn into_statistics(self, paused_secs: i64) -> PeriodStatistics {
+        let percent = |secs: f64| if self.total_secs > 0.0 { secs / self.total_secs * 100.0 } else { 0.0 };
+        PeriodStatistics {
+            start_time: self.first.unwrap_or_else(Utc::now),
+            end_time: self.last.unwrap_or_else(Utc::now),
+            sample_count: self.samples,
+            reduced_sampling_percent: percent(self.reduced_secs),
+            paused_secs,
+            other_network_samples: self.other_network_samples,
+            maintenance_samples: self.maintenance_samples,
+            signal_strength_avg_dbm: self.signal_dbm.avg(),
+            signal_strength_min_dbm: self.signal_dbm.min.map(|v| v as i32),
+            signal_strength_max_dbm: self.signal_dbm.max.map(|v| v as i32),
+            signal_quality_avg_percent: self.signal_quality.avg(),
+            latency_avg_ms: self.latency_ms.avg(),
+            latency_min_ms: self.latency_ms.min,
+            latency_max_ms: self.latency_ms.max,
+            latency_p95_ms: self.latency_percentile(0.95),
+            latency_p99_ms: self.latency_percentile(0.99),
+            jitter_avg_ms: self.jitter_ms.avg(),
+            rfc3550_jitter_avg_ms: self.rfc3550_jitter_ms.avg(),
+            mos_avg: self.mos.avg(),
+            mos_min: self.mos.min,
+            poor_call_percent: if self.mos.count > 0 { self.poor_calls as f64 / self.mos.count as f64 * 100.0 } else { 0.0 },
+            packet_loss_avg_percent: self.packet_loss_percent.avg().unwrap_or(0.0),
+            connection_uptime_percent: percent(self.connected_secs),
+            internet_uptime_percent: percent(self.internet_secs),
+            total_disconnections: self.disconnections,
+            warning_events: self.warning_events,
+            error_events: self.error_events,
+            critical_events: self.critical_events,
+        }
+    }
+}
+
+/// Start of the hour or day containing `at`
+pub fn floor_to(at: DateTime<Utc>, unit: TimeDelta) -> DateTime<Utc> {
+    at.duration_trunc(unit).unwrap_or(at)
+}
+
+/// Start of the first whole hour or day at or after `at`
+pub fn ceil_to(at: DateTime<Utc>, unit: TimeDelta) -> DateTime<Utc> {
+    let floor = floor_to(at, unit);
+    if floor == at { floor } else { floor + unit }
+}
@@ -0,0 +1,192 @@
+use crate::metrics::*;
+use crate::monitor::parse_ping_output;
+use crate::probe::{CommandRunner, CommandTimeouts, ProbeError};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// Per-ping timeout in milliseconds, so a lost reply never outlasts the sample period
+const PING_TIMEOUT_MS: u32 = 900;
+/// How long one ping process may run before it is killed, well past its reply timeout
+const PING_PROCESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pings the gateway and one internet target every second, independent of the
+/// snapshot interval, so outages shorter than a snapshot still show up
+#[derive(Clone)]
+pub struct LatencySampler {
+    internet_target: String,
+    runner: CommandRunner,
+    state: Arc<Mutex<SamplerState>>,
+}
+
+#[derive(Default)]
+struct SamplerState {
+    gateway: Option<String>,
+    windows: HashMap<String, TargetWindow>,
+}
+
+/// Samples collected for one target since the last snapshot
+#[derive(Debug, Default)]
+struct TargetWindow {
+    role: String,
+    sent: u32,
+    times: Vec<f64>,
+    loss_streak: u32,
+    longest_loss_streak: u32,
+}
+
+impl TargetWindow {
+    fn record(&mut self, time_ms: Option<f64>) {
+        self.sent += 1;
+        match time_ms {
+            Some(t) => {
+                self.times.push(t);
+                self.loss_streak = 0;
+            }
+            None => {
+                self.loss_streak += 1;
+                self.longest_loss_streak = self.longest_loss_streak.max(self.loss_streak);
+            }
+        }
+    }
+
+    fn summarize(&self, target: &str) -> ContinuousPingStats {
+        let received = self.times.len() as u32;
+        let min_ms = self.times.iter().cloned().reduce(f64::min);
+        let max_ms = self.times.iter().cloned().reduce(f64::max);
+        let avg_ms = (!self.times.is_empty())
+            .then(|| self.times.iter().sum::<f64>() / self.times.len() as f64);
+
+        ContinuousPingStats {
+            target: target.to_string(),
+            role: self.role.clone(),
+            samples: self.sent,
+            received,
+            packet_loss_percent: if self.sent > 0 {
+                (self.sent - received) as f64 / self.sent as f64 * 100.0
+            } else {
+                0.0
+            },
+            min_ms,
+            avg_ms,
+            max_ms,
+            longest_loss_streak: self.longest_loss_streak,
+        }
+    }
+}
+
+impl LatencySampler {
+    pub fn new(internet_target: String) -> Self {
+        Self {
+            internet_target,
+            runner: CommandRunner::new(CommandTimeouts { ping: PING_PROCESS_TIMEOUT, ..Default::default() }),
+            state: Arc::new(Mutex::new(SamplerState::default())),
+        }
+    }
+
+    /// Point the gateway probe at the router of the current connection
+    pub fn set_gateway(&self, gateway: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        if state.gateway.as_deref() != gateway {
+            debug!("Continuous sampler gateway: {:?}", gateway);
+            state.gateway = gateway.map(|g| g.to_string());
+        }
+    }
+
+    /// Return per-target stats since the previous call and start a new window
+    pub fn take_window(&self) -> Vec<ContinuousPingStats> {
+        let mut state = self.state.lock().unwrap();
+        let mut stats: Vec<ContinuousPingStats> = state.windows.drain()
+            .filter(|(_, window)| window.sent > 0)
+            .map(|(target, window)| window.summarize(&target))
+            .collect();
+        stats.sort_by(|a, b| a.role.cmp(&b.role));
+        stats
+    }
+
+    /// Run the 1-second sampling loop forever
+    pub async fn run(self) {
+        info!("Starting continuous 1s latency sampling (internet target {})", self.internet_target);
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // Whether ping couldn't be run last time, so the failure is logged once per streak
+        let mut failing = false;
+
+        loop {
+            interval.tick().await;
+
+            let gateway = self.state.lock().unwrap().gateway.clone();
+            let router = async {
+                match gateway.as_deref() {
+                    Some(gw) => Some(ping_once(&self.runner, gw).await),
+                    None => None,
+                }
+            };
+            let (internet, router) = tokio::join!(ping_once(&self.runner, &self.internet_target), router);
+            // Hung pings were killed and come back as errors; nothing reports them as events
+            self.runner.take_timeouts();
+
+            let mut results = vec![(self.internet_target.clone(), "internet", internet)];
+            if let (Some(gw), Some(result)) = (gateway, router) {
+                results.push((gw, "gateway", result));
+            }
+
+            // A ping that couldn't be run says nothing about the network, so it isn't loss
+            let mut error = None;
+            let mut state = self.state.lock().unwrap();
+            for (target, role, result) in results {
+                match result {
+                    Ok(time_ms) => Self::record(&mut state, &target, role, time_ms),
+                    Err(e) => error = Some(e),
+                }
+            }
+            drop(state);
+            match error {
+                Some(e) if !failing => {
+                    warn!("Continuous sampling can't run ping, skipping samples until it can: {}", e);
+                    failing = true;
+                }
+                None if failing => {
+                    info!("Continuous sampling can run ping again");
+                    failing = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn record(state: &mut SamplerState, target: &str, role: &str, time_ms: Option<f64>) {
+        let window = state.windows.entry(target.to_string()).or_insert_with(|| TargetWindow {
+            role: role.to_string(),
+            ..Default::default()
+        });
+        window.record(time_ms);
+    }
+}
+
+/// Send a single echo request; `Ok(None)` when no reply came back in time, `Err` when
+/// ping couldn't be run at all, e.g. it is missing or not permitted, or hung
+async fn ping_once(runner: &CommandRunner, target: &str) -> Result<Option<f64>, ProbeError> {
+    let timeout_ms = PING_TIMEOUT_MS.to_string();
+    let output = runner.output("ping", &["-n", "1", "-w", timeout_ms.as_str(), target]).await?;
+
+    let mut result = PingResult {
+        target: target.to_string(),
+        resolved_ip: None,
+        packets_sent: 1,
+        packets_received: 0,
+        packet_loss_percent: 100.0,
+        min_ms: None,
+        avg_ms: None,
+        max_ms: None,
+        stddev_ms: None,
+        individual_times_ms: Vec::new(),
+        packet_size: None,
+        rfc3550_jitter_ms: None,
+        error: None,
+    };
+    parse_ping_output(&String::from_utf8_lossy(&output.stdout), &mut result);
+    Ok(result.individual_times_ms.first().cloned())
+}
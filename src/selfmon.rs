@@ -0,0 +1,160 @@
+use crate::probe::CommandRunner;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, System};
+use tracing::{debug, error, info, warn};
+use tracing_appender::non_blocking::WorkerGuard;
+
+/// How often the tool checks its own resource usage
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Minimum time between repeated warnings for the same condition
+const WARNING_REPEAT: Duration = Duration::from_secs(30 * 60);
+
+/// Log writer guard, dropped before a restart so buffered lines reach the file
+static LOG_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+/// Keep the file logger's guard alive for the life of the process
+pub fn hold_log_guard(guard: WorkerGuard) {
+    *LOG_GUARD.lock().unwrap() = Some(guard);
+}
+
+/// Flush and close the file logger; call last, nothing is written to the file afterwards
+pub fn release_log_guard() {
+    LOG_GUARD.lock().unwrap().take();
+}
+
+/// The tool's own memory and handle usage
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    pub memory_mb: f64,
+    pub handles: Option<u64>,
+}
+
+/// What the monitor should do after a resource check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuardAction {
+    Continue,
+    Restart,
+}
+
+/// Watches the tool's own footprint so slow leaks in dependencies don't take down
+/// a multi-week capture
+pub struct ResourceGuard {
+    memory_warning_mb: u64,
+    handle_warning: u64,
+    restart_memory_mb: Option<u64>,
+    last_check: Option<Instant>,
+    last_warning: Option<Instant>,
+    system: System,
+}
+
+impl ResourceGuard {
+    pub fn new(memory_warning_mb: u64, handle_warning: u64, restart_memory_mb: Option<u64>) -> Self {
+        Self {
+            memory_warning_mb,
+            handle_warning,
+            restart_memory_mb,
+            last_check: None,
+            last_warning: None,
+            system: System::new(),
+        }
+    }
+
+    /// Check usage at most once per `CHECK_INTERVAL`
+    pub async fn check(&mut self, runner: &CommandRunner) -> GuardAction {
+        if self.last_check.is_some_and(|t| t.elapsed() < CHECK_INTERVAL) {
+            return GuardAction::Continue;
+        }
+        self.last_check = Some(Instant::now());
+
+        let Some(usage) = self.usage(runner).await else {
+            return GuardAction::Continue;
+        };
+        debug!(memory_mb = usage.memory_mb, handles = ?usage.handles, "Self resource usage");
+
+        if let Some(limit) = self.restart_memory_mb {
+            if usage.memory_mb >= limit as f64 {
+                warn!(
+                    "Memory usage {:.0} MB reached the restart limit of {} MB; restarting",
+                    usage.memory_mb, limit
+                );
+                return GuardAction::Restart;
+            }
+        }
+
+        let memory_high = usage.memory_mb >= self.memory_warning_mb as f64;
+        let handles_high = usage.handles.is_some_and(|h| h >= self.handle_warning);
+        let warned_recently = self.last_warning.is_some_and(|t| t.elapsed() < WARNING_REPEAT);
+        if (memory_high || handles_high) && !warned_recently {
+            self.last_warning = Some(Instant::now());
+            warn!(
+                "Tracker resource usage is high: {:.0} MB memory (warn at {} MB), {} handles (warn at {})",
+                usage.memory_mb,
+                self.memory_warning_mb,
+                usage.handles.map(|h| h.to_string()).unwrap_or_else(|| "?".to_string()),
+                self.handle_warning
+            );
+        }
+
+        GuardAction::Continue
+    }
+
+    async fn usage(&mut self, runner: &CommandRunner) -> Option<ResourceUsage> {
+        let pid = Pid::from_u32(std::process::id());
+        self.system.refresh_process(pid);
+        let memory_mb = self.system.process(pid)?.memory() as f64 / (1024.0 * 1024.0);
+
+        Some(ResourceUsage {
+            memory_mb,
+            handles: handle_count(runner).await,
+        })
+    }
+}
+
+/// When this process was started, as the OS reports it
+pub fn process_started_at() -> Option<chrono::DateTime<chrono::Utc>> {
+    let pid = Pid::from_u32(std::process::id());
+    let mut system = System::new();
+    system.refresh_process(pid);
+    chrono::DateTime::from_timestamp(system.process(pid)?.start_time() as i64, 0)
+}
+
+/// Open handle count (Windows) or file descriptor count (Linux)
+async fn handle_count(runner: &CommandRunner) -> Option<u64> {
+    if cfg!(windows) {
+        let query = format!("(Get-Process -Id {}).HandleCount", std::process::id());
+        let output = runner.output("powershell", &["-NoProfile", "-Command", query.as_str()]).await.ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        std::fs::read_dir("/proc/self/fd").ok().map(|dir| dir.count() as u64)
+    }
+}
+
+/// Flush logs, start a fresh copy of this process that resumes the same capture,
+/// then exit. Snapshots are committed as they are saved, so only logs need flushing.
+/// Returns only if the replacement could not be started.
+pub fn restart_process() {
+    // The replacement continues this session, so it must not start over with --fresh,
+    // which --resume also conflicts with
+    let mut args: Vec<String> = std::env::args().skip(1)
+        .filter(|a| a != "--fresh" && !a.starts_with("--fresh="))
+        .collect();
+    if !args.iter().any(|a| a == "--resume") {
+        args.push("--resume".to_string());
+    }
+
+    let spawned = std::env::current_exe()
+        .map_err(anyhow::Error::from)
+        .and_then(|exe| Command::new(exe).args(&args).spawn().map_err(anyhow::Error::from));
+    match spawned {
+        Ok(child) => info!("Started replacement process {}", child.id()),
+        Err(e) => {
+            error!("Failed to start replacement process, continuing: {}", e);
+            return;
+        }
+    }
+
+    release_log_guard();
+    std::process::exit(0);
+}
@@ -0,0 +1,359 @@
+use crate::assets;
+use crate::auth::{self, WebAuth};
+use crate::forward::Upload;
+use crate::metrics::WifiSnapshot;
+use crate::ratelimit::{self, RateLimit};
+use crate::shutdown::ShutdownHandle;
+use crate::storage::{self, MetricsStore, SqliteStore};
+use crate::web::{self, Tls};
+use axum::{
+    extract::{DefaultBodyLimit, Path, Request, State},
+    http::{StatusCode, Uri},
+    response::{Html, IntoResponse, Json, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::{TimeDelta, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use tracing::{error, info, warn};
+
+/// Largest upload accepted; agents send at most 100 snapshots per request
+const MAX_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+
+/// A host that hasn't uploaded for this long is shown as offline
+const OFFLINE_AFTER_SECS: i64 = 300;
+
+/// One monitored machine: its database and the dashboard over it
+#[derive(Clone)]
+struct Host {
+    store: Arc<dyn MetricsStore>,
+    dashboard: Router,
+}
+
+#[derive(Clone)]
+struct Hosts {
+    data_dir: PathBuf,
+    retention: Option<TimeDelta>,
+    shutdown: ShutdownHandle,
+    open: Arc<Mutex<BTreeMap<String, Host>>>,
+}
+
+impl Hosts {
+    /// Open every host database already in `data_dir`
+    fn load(data_dir: PathBuf, retention: Option<TimeDelta>, shutdown: ShutdownHandle) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&data_dir)?;
+        let hosts = Self { data_dir, retention, shutdown, open: Arc::default() };
+        for entry in std::fs::read_dir(&hosts.data_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "db") {
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()).filter(|s| valid_host(s)) {
+                    hosts.open_or_create(name)?;
+                }
+            }
+        }
+        Ok(hosts)
+    }
+
+    fn get(&self, name: &str) -> Option<Host> {
+        self.open.lock().unwrap().get(name).cloned()
+    }
+
+    /// The host's database, created on its first upload
+    fn open_or_create(&self, name: &str) -> anyhow::Result<Host> {
+        let mut open = self.open.lock().unwrap();
+        if let Some(host) = open.get(name) {
+            return Ok(host.clone());
+        }
+
+        let path = self.data_dir.join(format!("{}.db", name));
+        if path.exists() {
+            SqliteStore::migrate(&path)?;
+        } else {
+            info!("New host {}, storing its snapshots in {:?}", name, path);
+        }
+        let store: Arc<dyn MetricsStore> = Arc::new(SqliteStore::new(&path)?);
+        tokio::spawn(storage::maintain(store.clone(), self.retention, self.shutdown.clone()));
+        let host = Host {
+            store: store.clone(),
+            dashboard: web::dashboard_router(store, None, WebAuth::default(), self.shutdown.clone()),
+        };
+        open.insert(name.to_string(), host.clone());
+        Ok(host)
+    }
+
+    fn all(&self) -> Vec<(String, Host)> {
+        self.open.lock().unwrap().iter().map(|(name, host)| (name.clone(), host.clone())).collect()
+    }
+}
+
+/// Host names become file names, so only plain ones are accepted
+fn valid_host(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 253
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Where and how the aggregation server listens
+pub struct ServerOptions {
+    pub addr: SocketAddr,
+    /// Guards the dashboards; uploads use the agent token instead
+    pub auth: WebAuth,
+    pub rate_limit: RateLimit,
+    pub tls: Option<Tls>,
+}
+
+/// Run the aggregation server until shutdown. Monitors on other machines upload their
+/// snapshots with `agent_token`, each host gets a database of its own in `data_dir`, and
+/// one page, behind `options.auth`, lists them all with each host's full dashboard a click away.
+pub async fn run(
+    data_dir: PathBuf,
+    agent_token: String,
+    retention: Option<TimeDelta>,
+    options: ServerOptions,
+    shutdown: ShutdownHandle,
+) -> anyhow::Result<()> {
+    let ServerOptions { addr, auth, rate_limit, tls } = options;
+    let hosts = Hosts::load(data_dir.clone(), retention, shutdown.clone())?;
+    info!("Serving {} host(s) from {:?}", hosts.all().len(), data_dir);
+    if !auth.is_enabled() {
+        warn!("The dashboards are open to anyone who can reach {}; set --auth-token or --basic-auth", addr);
+    }
+
+    let agents = WebAuth::new(Some(agent_token), None)?;
+    let ingest = Router::new()
+        .route("/api/v1/ingest", post(ingest_handler))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+        .layer(axum::middleware::from_fn_with_state(agents, auth::require));
+    let app = Router::new()
+        .route("/", get(overview_handler))
+        .route("/api/v1/hosts", get(hosts_handler))
+        .route("/hosts/:host", get(host_redirect_handler))
+        .route("/hosts/:host/", axum::routing::any(host_handler))
+        .route("/hosts/:host/*path", axum::routing::any(host_handler))
+        .layer(axum::middleware::from_fn_with_state(auth, auth::require))
+        .merge(ingest)
+        .route("/readyz", get(|| async { "ready\n" }))
+        .route("/assets/:name", get(assets::handler))
+        .fallback(web::legacy_api_redirect)
+        .layer(axum::middleware::from_fn_with_state(rate_limit, ratelimit::limit))
+        .layer(web::cors_layer())
+        .with_state(hosts);
+
+    web::serve(app, addr, tls, shutdown).await
+}
+
+/// Store an agent's snapshots and incidents under its host name
+async fn ingest_handler(State(hosts): State<Hosts>, Json(mut upload): Json<Upload>) -> Response {
+    if !valid_host(&upload.host) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("invalid host name {:?}", upload.host)
+            })),
+        ).into_response();
+    }
+    upload.session.host = Some(upload.host.clone());
+
+    let result = tokio::task::spawn_blocking(move || {
+        let host = hosts.open_or_create(&upload.host)?;
+        let added = host.store.import_snapshots(&upload.session, &upload.snapshots)?;
+        for incident in &upload.incidents {
+            host.store.save_incident(incident)?;
+        }
+        Ok::<_, anyhow::Error>(added)
+    }).await;
+    match result.map_err(anyhow::Error::from).and_then(|r| r) {
+        Ok(added) => Json(serde_json::json!({
+            "success": true,
+            "accepted": added
+        })).into_response(),
+        Err(e) => {
+            error!("Failed to store an upload: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": e.to_string()
+                })),
+            ).into_response()
+        }
+    }
+}
+
+/// Every host with its latest snapshot and open incident, most recently seen first
+async fn hosts_handler(State(hosts): State<Hosts>) -> Json<serde_json::Value> {
+    let now = Utc::now();
+    let mut rows: Vec<_> = hosts.all().into_iter().map(|(name, host)| {
+        let latest = host.store.get_latest_snapshot().unwrap_or_else(|e| {
+            error!("Failed to read the latest snapshot of {}: {}", name, e);
+            None
+        });
+        let open_incident = host.store.get_incidents(None, None, Some("open")).unwrap_or_default().into_iter().next();
+        let last_seen = latest.as_ref().map(|s| s.timestamp);
+        (last_seen, serde_json::json!({
+            "host": name,
+            "last_seen": last_seen,
+            "online": last_seen.is_some_and(|t| (now - t).num_seconds() < OFFLINE_AFTER_SECS),
+            "summary": latest.as_ref().map(summary),
+            "open_incident": open_incident,
+        }))
+    }).collect();
+    rows.sort_by_key(|(last_seen, _)| std::cmp::Reverse(*last_seen));
+    let data: Vec<_> = rows.into_iter().map(|(_, host)| host).collect();
+    Json(serde_json::json!({
+        "success": true,
+        "count": data.len(),
+        "data": data
+    }))
+}
+
+/// The few readings the overview shows per host
+fn summary(snapshot: &WifiSnapshot) -> serde_json::Value {
+    let wifi = snapshot.wifi_info.as_ref();
+    serde_json::json!({
+        "ssid": wifi.map(|w| &w.ssid),
+        "band": wifi.map(|w| w.band.label()),
+        "signal_dbm": wifi.map(|w| w.signal_strength_dbm),
+        "connected": snapshot.connectivity.is_connected,
+        "internet_reachable": snapshot.connectivity.internet_reachable,
+        "latency_avg_ms": snapshot.latency.average_latency_ms,
+        "packet_loss_percent": snapshot.latency.packet_loss_percent,
+    })
+}
+
+async fn overview_handler() -> Html<&'static str> {
+    Html(OVERVIEW_HTML)
+}
+
+/// The dashboard's links are relative, so it needs the trailing slash
+async fn host_redirect_handler(Path(host): Path<String>) -> Response {
+    if !valid_host(&host) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Redirect::permanent(&format!("/hosts/{}/", host)).into_response()
+}
+
+/// Hand the request to the host's own dashboard and API, with `/hosts/<name>` taken off the path
+async fn host_handler(
+    State(hosts): State<Hosts>,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request,
+) -> Response {
+    let Some(host) = params.get("host").and_then(|name| hosts.get(name)) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "No such host"
+            })),
+        ).into_response();
+    };
+    let path = format!("/{}", params.get("path").map(String::as_str).unwrap_or_default());
+    let uri = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    match uri.parse::<Uri>() {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    }
+    match host.dashboard.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+const OVERVIEW_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>WiFi Stability Tracker - All Hosts</title>
+    <script src="assets/tailwind.js"></script>
+</head>
+<body class="bg-gray-900 text-gray-100 min-h-screen">
+    <div class="container mx-auto px-4 py-6">
+        <header class="mb-8">
+            <h1 class="text-3xl font-bold text-white mb-2">WiFi Stability Tracker</h1>
+            <p class="text-gray-400"><span id="host-count">0</span> monitored hosts</p>
+        </header>
+
+        <div class="bg-gray-800 rounded-lg border border-gray-700 overflow-x-auto">
+            <table class="w-full text-sm">
+                <thead class="text-gray-400 text-left border-b border-gray-700">
+                    <tr>
+                        <th class="px-4 py-3">Host</th>
+                        <th class="px-4 py-3">Status</th>
+                        <th class="px-4 py-3">Network</th>
+                        <th class="px-4 py-3">Signal</th>
+                        <th class="px-4 py-3">Latency</th>
+                        <th class="px-4 py-3">Packet Loss</th>
+                        <th class="px-4 py-3">Last Seen</th>
+                        <th class="px-4 py-3">Open Incident</th>
+                    </tr>
+                </thead>
+                <tbody id="hosts"></tbody>
+            </table>
+            <p id="no-hosts" class="text-gray-500 p-4 hidden">No host has uploaded yet. Start a monitor with --forward-to pointing at this server's /api/v1/ingest.</p>
+        </div>
+    </div>
+
+    <script>
+        const REFRESH_MS = 30000;
+
+        function escapeHtml(text) {
+            const div = document.createElement('div');
+            div.textContent = text ?? '';
+            return div.innerHTML;
+        }
+
+        function status(host) {
+            const s = host.summary;
+            if (!host.online) return ['Offline', 'text-gray-500'];
+            if (!s.connected) return ['Disconnected', 'text-red-500'];
+            if (!s.internet_reachable) return ['No internet', 'text-red-500'];
+            if (host.open_incident) return ['Degraded', 'text-yellow-500'];
+            return ['OK', 'text-green-500'];
+        }
+
+        async function refresh() {
+            try {
+                const response = await fetch('api/v1/hosts');
+                const result = await response.json();
+                if (!result.success) return;
+                document.getElementById('host-count').textContent = result.count;
+                document.getElementById('no-hosts').classList.toggle('hidden', result.count > 0);
+                document.getElementById('hosts').innerHTML = result.data.map(host => {
+                    const s = host.summary || {};
+                    const [label, color] = host.summary ? status(host) : ['No data', 'text-gray-500'];
+                    const link = `hosts/${encodeURIComponent(host.host)}/`;
+                    return `<tr class="border-b border-gray-700 hover:bg-gray-700">
+                        <td class="px-4 py-3"><a href="${link}" class="text-blue-400 hover:underline">${escapeHtml(host.host)}</a></td>
+                        <td class="px-4 py-3 ${color}">${label}</td>
+                        <td class="px-4 py-3">${escapeHtml(s.ssid || '--')}${s.band ? ` <span class="text-gray-500">${s.band}</span>` : ''}</td>
+                        <td class="px-4 py-3">${s.signal_dbm != null ? `${s.signal_dbm} dBm` : '--'}</td>
+                        <td class="px-4 py-3">${s.latency_avg_ms != null ? `${s.latency_avg_ms.toFixed(1)} ms` : '--'}</td>
+                        <td class="px-4 py-3">${s.packet_loss_percent != null ? `${s.packet_loss_percent.toFixed(1)}%` : '--'}</td>
+                        <td class="px-4 py-3">${host.last_seen ? new Date(host.last_seen).toLocaleString() : '--'}</td>
+                        <td class="px-4 py-3">${host.open_incident ? escapeHtml(host.open_incident.summary) : ''}</td>
+                    </tr>`;
+                }).join('');
+            } catch (e) {
+                console.error('Failed to fetch hosts:', e);
+            }
+        }
+
+        document.addEventListener('DOMContentLoaded', () => {
+            refresh();
+            setInterval(refresh, REFRESH_MS);
+        });
+    </script>
+</body>
+</html>
+"##;
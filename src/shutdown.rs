@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Shared stop signal for the monitor, the web server and the GUI. Triggering it
+/// lets each finish what it is doing (the current snapshot, in-flight requests)
+/// and return, instead of the process exiting underneath them.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Ask everything holding this handle to stop
+    pub fn trigger(&self) {
+        if !self.sender.send_replace(true) {
+            info!("Shutting down...");
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once shutdown has been triggered
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Trigger on Ctrl+C; a second Ctrl+C exits immediately in case something hangs
+    pub fn trigger_on_ctrl_c(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            shutdown.trigger();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                warn!("Second Ctrl+C, exiting without waiting");
+                std::process::exit(130);
+            }
+        });
+    }
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,238 +1,1664 @@
 use crate::metrics::*;
-use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
-use std::path::PathBuf;
-use std::sync::Mutex;
-use tracing::debug;
-
-pub struct MetricsStore {
-    #[allow(dead_code)]
+use crate::rollup::{ceil_to, floor_to, StatsRollup, Summary, HISTOGRAM_BUCKET_MS};
+use crate::shutdown::ShutdownHandle;
+use chrono::{DateTime, TimeDelta, Utc};
+use r2d2::{ManageConnection, Pool, PooledConnection};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Metrics in a local SQLite file
+pub struct SqliteStore {
     db_path: PathBuf,
-    conn: Mutex<Connection>,
+    pool: Pool<ConnectionManager>,
+    writable: bool,
+    omitted_details: Vec<SnapshotDetail>,
+    include_other_networks: bool,
+    /// Session new snapshots are tagged with
+    session_id: Option<String>,
 }
 
-unsafe impl Send for MetricsStore {}
-unsafe impl Sync for MetricsStore {}
+/// Bulky parts of a snapshot that can be left out of the stored JSON. The
+/// aggregates derived from them (averages, loss, failure counts) are still kept.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SnapshotDetail {
+    /// Every reply time of every ping target
+    PingTimes,
+    /// The individual DNS queries
+    DnsQueries,
+}
+
+/// Opens pooled connections, all with the same flags and settings
+struct ConnectionManager {
+    path: PathBuf,
+    flags: OpenFlags,
+}
+
+impl ManageConnection for ConnectionManager {
+    type Connection = Connection;
+    type Error = rusqlite::Error;
+
+    fn connect(&self) -> Result<Connection, rusqlite::Error> {
+        let conn = open_connection(&self.path, self.flags)?;
+        // Wait for another connection's write instead of failing with SQLITE_BUSY
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        if self.flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE) {
+            // Readers see the last commit instead of waiting for a snapshot write to finish
+            conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+            conn.pragma_update(None, "synchronous", "NORMAL")?;
+            // Give back the space of a log that once held a vacuum or a big delete
+            conn.pragma_update(None, "journal_size_limit", WAL_SIZE_LIMIT)?;
+        }
+        Ok(conn)
+    }
+
+    fn is_valid(&self, conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        conn.execute_batch("")
+    }
+
+    fn has_broken(&self, _conn: &mut Connection) -> bool {
+        false
+    }
+}
+
+/// Passphrase of an encrypted database, set once at startup
+static DATABASE_KEY: OnceLock<String> = OnceLock::new();
+
+/// Encrypt new databases, and unlock encrypted ones, with this passphrase. Builds without
+/// the `sqlcipher` feature refuse a key rather than keep storing data in plaintext.
+pub fn set_database_key(key: String) -> anyhow::Result<()> {
+    if !cfg!(feature = "sqlcipher") {
+        anyhow::bail!("Database encryption needs a build with the sqlcipher feature (cargo build --release --features sqlcipher)");
+    }
+    let _ = DATABASE_KEY.set(key);
+    Ok(())
+}
+
+/// Open a database file, unlocking it with the database key if there is one
+fn open_connection(path: &Path, flags: OpenFlags) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(path, flags)?;
+    if let Some(key) = DATABASE_KEY.get() {
+        conn.pragma_update(None, "key", key)?;
+    }
+    Ok(conn)
+}
+
+/// Like `open_connection`, explaining a missing or wrong passphrase
+fn open_file(path: &Path, flags: OpenFlags) -> anyhow::Result<Connection> {
+    let conn = open_connection(path, flags)?;
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::NotADatabase => {
+            if DATABASE_KEY.get().is_some() {
+                anyhow::bail!("Could not unlock {:?}: wrong passphrase, or not an encrypted database", path);
+            }
+            anyhow::bail!("{:?} is encrypted or not a database. Pass its passphrase with --db-key or WIFI_TRACKER_DB_KEY", path);
+        }
+        result => result?,
+    }
+    Ok(conn)
+}
+
+/// Connections per store: the monitor's writes, dashboard reads and background maintenance
+const POOL_SIZE: u32 = 4;
+/// The write-ahead log is cut back to this size once checkpointed
+const WAL_SIZE_LIMIT: i64 = 64 * 1024 * 1024;
+/// How long a write waits for another one before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether a write failed only because another connection held the database for longer
+/// than `BUSY_TIMEOUT`, so trying again later can succeed
+pub fn is_busy(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<r2d2::Error>()
+            || matches!(
+                cause.downcast_ref::<rusqlite::Error>(),
+                Some(rusqlite::Error::SqliteFailure(e, _))
+                    if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+            )
+    })
+}
+
+/// How often completed hours are rolled up and old rows pruned, and the minimum time between vacuums
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+const VACUUM_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+/// How often the size cap is checked, and the fewest snapshots a round of trimming removes
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const TRIM_MIN_SNAPSHOTS: i64 = 100;
+pub const MB: u64 = 1024 * 1024;
+
+const HOUR: TimeDelta = TimeDelta::hours(1);
+const DAY: TimeDelta = TimeDelta::days(1);
+/// Statistics for ranges at least this long come from rollups where available
+const ROLLUP_MIN_RANGE: TimeDelta = TimeDelta::days(2);
+/// Timeseries this long are returned as hourly, and from this long as daily averages
+pub(crate) const HOURLY_TIMESERIES_MIN_RANGE: TimeDelta = TimeDelta::days(2);
+pub(crate) const DAILY_TIMESERIES_MIN_RANGE: TimeDelta = TimeDelta::days(60);
+/// An hour is rolled up once it ended this long ago, so its last snapshots are saved
+const ROLLUP_GRACE: TimeDelta = TimeDelta::minutes(5);
+
+/// Bucket sizes `BucketSize::MaxPoints` chooses from, smallest first
+const BUCKET_STEPS: &[TimeDelta] = &[
+    TimeDelta::seconds(1), TimeDelta::seconds(5), TimeDelta::seconds(10), TimeDelta::seconds(30),
+    TimeDelta::minutes(1), TimeDelta::minutes(5), TimeDelta::minutes(10), TimeDelta::minutes(15), TimeDelta::minutes(30),
+    TimeDelta::hours(1), TimeDelta::hours(3), TimeDelta::hours(6), TimeDelta::hours(12),
+    TimeDelta::days(1), TimeDelta::days(7),
+];
+
+/// Per-ping-target metrics are stored as `<prefix><target>`, e.g. `target_latency:8.8.8.8`
+pub const TARGET_LATENCY_PREFIX: &str = "target_latency:";
+pub const TARGET_LOSS_PREFIX: &str = "target_loss:";
+pub const TARGET_JITTER_PREFIX: &str = "target_jitter:";
+
+/// Upper bound for open-ended ranges; sorts after every RFC 3339 timestamp
+const TIME_MAX: &str = "9999";
+
+/// Timestamps of the snapshots from other networks and excluded maintenance windows that
+/// a summary leaves out. `?1`..`?3` bound the range (from, through, before); `?4` includes
+/// other networks.
+const EXCLUDED_SQL: &str = "
+    WITH other_networks AS (
+        SELECT timestamp FROM timeseries
+        WHERE metric_name = 'other_network' AND value > 0 AND NOT ?4
+          AND timestamp >= ?1 AND timestamp <= ?2 AND timestamp < ?3
+    ),
+    maintenance AS (
+        SELECT timestamp FROM timeseries
+        WHERE metric_name = 'maintenance' AND value > 0
+          AND timestamp >= ?1 AND timestamp <= ?2 AND timestamp < ?3
+          AND timestamp NOT IN other_networks
+    ),
+    excluded AS (
+        SELECT timestamp FROM other_networks UNION ALL SELECT timestamp FROM maintenance
+    )";
+
+/// Condition for `column` being a timestamp a summary counts, with `EXCLUDED_SQL`'s parameters
+fn in_summary(column: &str) -> String {
+    format!("{column} >= ?1 AND {column} <= ?2 AND {column} < ?3 AND {column} NOT IN excluded")
+}
+
+/// A timeseries range and the bucket size its points are averaged over
+type AveragingWindow = (DateTime<Utc>, DateTime<Utc>, TimeDelta);
+
+/// One hour of a metric's rollup: start of the hour in epoch milliseconds, avg, min, max, samples
+type HourlyPoint = (i64, f64, f64, f64, i64);
+
+/// How `get_timeseries_buckets` sizes its buckets
+#[derive(Debug, Clone, Copy)]
+pub enum BucketSize {
+    Fixed(TimeDelta),
+    /// The smallest of `BUCKET_STEPS` that gives at most this many buckets over the range
+    MaxPoints(u32),
+}
+
+impl BucketSize {
+    /// The bucket for a range from `from` to `to`
+    pub(crate) fn resolve(self, from: DateTime<Utc>, to: DateTime<Utc>) -> TimeDelta {
+        match self {
+            BucketSize::Fixed(bucket) => bucket,
+            BucketSize::MaxPoints(points) => {
+                let span = (to - from).num_milliseconds();
+                BUCKET_STEPS.iter().copied()
+                    .find(|step| step.num_milliseconds() * i64::from(points.max(1)) > span)
+                    .unwrap_or(BUCKET_STEPS[BUCKET_STEPS.len() - 1])
+            }
+        }
+    }
+}
+
+/// Schema version written by this build. Bump it and add a step to
+/// `apply_migration` whenever the table layout changes.
+pub const SCHEMA_VERSION: i64 = 9;
+
+const SCHEMA_SQL: &str = r#"
+    -- Main snapshots table
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id TEXT PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        data JSON NOT NULL,
+        session_id TEXT
+    );
+
+    -- Index for time-based queries
+    CREATE INDEX IF NOT EXISTS idx_snapshots_timestamp ON snapshots(timestamp);
+
+    -- Events table for quick event queries
+    CREATE TABLE IF NOT EXISTS events (
+        id TEXT PRIMARY KEY,
+        snapshot_id TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        description TEXT NOT NULL,
+        details JSON,
+        FOREIGN KEY (snapshot_id) REFERENCES snapshots(id)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events(timestamp);
+    CREATE INDEX IF NOT EXISTS idx_events_type ON events(event_type);
+    CREATE INDEX IF NOT EXISTS idx_events_severity ON events(severity);
+    CREATE INDEX IF NOT EXISTS idx_events_snapshot ON events(snapshot_id);
+
+    -- Time series data for efficient charting, stamped in Unix epoch milliseconds.
+    -- Clustered by metric so a metric's range is read without touching other rows.
+    CREATE TABLE IF NOT EXISTS timeseries (
+        timestamp INTEGER NOT NULL,
+        metric_name TEXT NOT NULL,
+        value REAL NOT NULL,
+        PRIMARY KEY (metric_name, timestamp)
+    ) WITHOUT ROWID;
+
+    CREATE INDEX IF NOT EXISTS idx_timeseries_timestamp ON timeseries(timestamp);
+
+    -- Statistics rolled up per completed hour and day, so long ranges don't read every snapshot
+    CREATE TABLE IF NOT EXISTS hourly_stats (
+        hour TEXT PRIMARY KEY,
+        sample_count INTEGER NOT NULL,
+        data JSON NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_stats (
+        day TEXT PRIMARY KEY,
+        sample_count INTEGER NOT NULL,
+        data JSON NOT NULL
+    );
+
+    -- Timeseries averaged per completed hour and day, for charting long ranges
+    CREATE TABLE IF NOT EXISTS timeseries_hourly (
+        hour TEXT NOT NULL,
+        metric_name TEXT NOT NULL,
+        avg REAL NOT NULL,
+        min REAL NOT NULL,
+        max REAL NOT NULL,
+        samples INTEGER NOT NULL,
+        PRIMARY KEY (metric_name, hour)
+    );
+
+    CREATE TABLE IF NOT EXISTS timeseries_daily (
+        day TEXT NOT NULL,
+        metric_name TEXT NOT NULL,
+        avg REAL NOT NULL,
+        min REAL NOT NULL,
+        max REAL NOT NULL,
+        samples INTEGER NOT NULL,
+        PRIMARY KEY (metric_name, day)
+    );
+
+    -- Schema version history, used to detect databases from other builds
+    CREATE TABLE IF NOT EXISTS schema_version (
+        version INTEGER NOT NULL,
+        app_version TEXT NOT NULL,
+        applied_at TEXT NOT NULL
+    );
+
+    -- Adapter driver and power settings, one row per monitoring run
+    CREATE TABLE IF NOT EXISTS adapter_info (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collected_at TEXT NOT NULL,
+        data JSON NOT NULL
+    );
+
+    -- Outage windows grouping related events
+    CREATE TABLE IF NOT EXISTS incidents (
+        id TEXT PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        ended_at TEXT,
+        status TEXT NOT NULL,
+        root_cause TEXT NOT NULL,
+        data JSON NOT NULL,
+        -- Kept apart from `data` so the monitor's updates don't overwrite notes added meanwhile
+        notes JSON NOT NULL DEFAULT '[]'
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_incidents_started ON incidents(started_at);
+
+    -- Windows during which monitoring was paused on purpose
+    CREATE TABLE IF NOT EXISTS pauses (
+        id TEXT PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        ended_at TEXT,
+        reason TEXT
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_pauses_started ON pauses(started_at);
+
+    -- Alerting state that must survive a restart: 'last_notified' and 'mute'
+    CREATE TABLE IF NOT EXISTS alert_state (
+        key TEXT PRIMARY KEY,
+        value JSON NOT NULL
+    );
+
+    -- Monitoring runs, from start to shutdown; snapshots record the one that took them
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        ended_at TEXT,
+        host TEXT,
+        config JSON NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_sessions_started ON sessions(started_at);
+
+    -- Notes users pin to points in time
+    CREATE TABLE IF NOT EXISTS annotations (
+        id TEXT PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        text TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_annotations_timestamp ON annotations(timestamp);
+
+    -- Alerts fired by alert rules, and when they resolved
+    CREATE TABLE IF NOT EXISTS alerts (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        fired_at TEXT NOT NULL,
+        resolved_at TEXT,
+        data JSON NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_alerts_fired ON alerts(fired_at);
+    "#;
+
+/// Indexes on columns added after v1. Migrations from before the column existed run
+/// `SCHEMA_SQL` too, so these can't live there.
+const LATE_INDEX_SQL: &str = r#"
+    CREATE INDEX IF NOT EXISTS idx_snapshots_session ON snapshots(session_id, timestamp);
+    "#;
+
+/// Everything the monitor, dashboard, reports and exports read and write, so they work
+/// the same on a local SQLite file and on a Postgres database shared by several monitors
+pub trait MetricsStore: Send + Sync {
+    /// Whether a snapshot counts towards statistics; ones from other networks don't
+    /// unless included
+    fn in_scope(&self, snapshot: &WifiSnapshot) -> bool;
+
+    /// Start a monitoring session; snapshots saved from now on are tagged with it.
+    /// Sessions and pauses a crash left open are closed at their last snapshot.
+    fn start_session(&mut self, config: serde_json::Value) -> anyhow::Result<Session>;
+
+    /// Continue the latest session that is still open, as an automatic restart does,
+    /// or start a new one if there is none
+    fn resume_session(&mut self, config: serde_json::Value) -> anyhow::Result<Session>;
+
+    /// End the current session and flush anything pending, reporting what could not be finished
+    fn close(&self) -> anyhow::Result<()>;
+
+    /// Delete snapshots, events, timeseries points, finished incidents, pauses and sessions,
+    /// and superseded adapter records from before `cutoff`. Returns the number of rows removed.
+    fn prune_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<usize>;
+
+    /// Delete a finished session with its snapshots, events, timeseries points and pauses,
+    /// and the incidents and rollups that lie wholly within it. Returns the number of rows
+    /// removed, or `None` if there is no session with this id; a running session is refused.
+    fn delete_session(&self, id: &str) -> anyhow::Result<Option<usize>>;
+
+    /// Give space freed by pruning back to the disk
+    fn vacuum(&self) -> anyhow::Result<()>;
+
+    /// Delete the oldest snapshots, events and timeseries points, keeping rollups, once the
+    /// database outgrows `max_bytes`. Returns the number of rows removed.
+    fn trim_to_size(&self, max_bytes: u64) -> anyhow::Result<usize>;
+
+    /// Summarize completed hours and days ahead of time where the backend keeps rollups,
+    /// returning the number of hours added
+    fn roll_up(&self) -> anyhow::Result<usize>;
+
+    /// Store snapshots with their events and timeseries points in one transaction, tagged
+    /// with the current session
+    fn save_snapshots(&self, snapshots: &[WifiSnapshot]) -> anyhow::Result<()>;
+
+    /// Store snapshots recorded elsewhere, e.g. read back from an export or uploaded by an
+    /// agent, as `session`. Snapshots already in the database are skipped; returns how many
+    /// were added. The session is only created when there was something to add, and later
+    /// batches for it update its end.
+    fn import_snapshots(&self, session: &Session, snapshots: &[WifiSnapshot]) -> anyhow::Result<usize>;
+
+    /// Record the adapter driver and power settings for this run
+    fn save_adapter_info(&self, info: &AdapterInfo) -> anyhow::Result<()>;
+
+    fn get_latest_adapter_info(&self) -> anyhow::Result<Option<AdapterInfo>>;
+
+    /// Insert or update an incident, leaving its notes alone
+    fn save_incident(&self, incident: &Incident) -> anyhow::Result<()>;
+
+    /// Record a pause window, or its end once monitoring resumes
+    fn save_pause(&self, pause: &PauseWindow) -> anyhow::Result<()>;
 
-impl MetricsStore {
+    /// Last alerted incident and the current mute window
+    fn get_alert_state(&self) -> anyhow::Result<AlertState>;
+
+    /// Remember that an outage alert went out (or was muted) for this incident
+    fn record_notified(&self, incident_id: &str, at: DateTime<Utc>) -> anyhow::Result<()>;
+
+    /// Set the mute window, or clear it with `None`
+    fn save_mute(&self, mute: Option<&MuteWindow>) -> anyhow::Result<()>;
+
+    /// Insert a rule alert, or update it once resolved
+    fn save_alert(&self, alert: &Alert) -> anyhow::Result<()>;
+
+    /// Rule alerts fired in the range, newest first, optionally only those of one rule
+    fn get_alerts(&self, start: Option<&str>, end: Option<&str>, name: Option<&str>, limit: Option<u32>) -> anyhow::Result<Vec<Alert>>;
+
+    /// Add an annotation, or replace the one with the same id
+    fn save_annotation(&self, annotation: &Annotation) -> anyhow::Result<()>;
+
+    /// Annotations in the range, newest first
+    fn get_annotations(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<Annotation>>;
+
+    /// Remove an annotation; false if there was none with this id
+    fn delete_annotation(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// Pause windows overlapping the range, newest first
+    fn get_pauses(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<PauseWindow>>;
+
+    /// Incidents that started in the range, newest first, optionally only `open` or `resolved` ones
+    fn get_incidents(&self, start: Option<&str>, end: Option<&str>, status: Option<&str>) -> anyhow::Result<Vec<Incident>>;
+
+    /// Monitoring sessions with the number of snapshots each took, newest first
+    fn get_sessions(&self) -> anyhow::Result<Vec<(Session, i64)>>;
+
+    /// The session with this id, or the only one whose id starts with it
+    fn get_session(&self, id: &str) -> anyhow::Result<Option<Session>>;
+
+    fn get_incident(&self, id: &str) -> anyhow::Result<Option<Incident>>;
+
+    /// Append a note to an incident; `None` if there is no such incident
+    fn add_incident_note(&self, id: &str, text: &str) -> anyhow::Result<Option<Incident>>;
+
+    /// Snapshots in the range, newest first, skipping the first `offset`
+    fn get_snapshots(&self, start: Option<&str>, end: Option<&str>, limit: Option<u32>, offset: u64) -> anyhow::Result<Vec<WifiSnapshot>>;
+
+    /// How many snapshots the range holds, for paging through `get_snapshots`
+    fn count_snapshots(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<u64>;
+
+    /// The neighbor scans in the range as (time, access points seen), newest first, at most `limit`
+    fn get_neighbor_scans(&self, start: Option<&str>, end: Option<&str>, limit: u32) -> anyhow::Result<Vec<(DateTime<Utc>, Vec<NeighborNetwork>)>>;
+
+    fn get_latest_snapshot(&self) -> anyhow::Result<Option<WifiSnapshot>> {
+        let snapshots = self.get_snapshots(None, None, Some(1), 0)?;
+        Ok(snapshots.into_iter().next())
+    }
+
+    /// Points of one metric, oldest first. Ranges of two days or more come back as hourly
+    /// averages, and of 60 days or more as daily ones.
+    fn get_timeseries(&self, metric: &str, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, f64)>>;
+
+    /// Average, minimum and maximum of one metric per bucket, oldest first, aggregated by
+    /// the database, with the bucket size used. Buckets are aligned to the Unix epoch and
+    /// empty ones are left out.
+    fn get_timeseries_buckets(&self, metric: &str, start: Option<&str>, end: Option<&str>, size: BucketSize) -> anyhow::Result<(TimeDelta, Vec<TimeseriesBucket>)>;
+
+    /// Names of the stored metrics starting with `prefix`, sorted
+    fn get_metric_names(&self, prefix: &str) -> anyhow::Result<Vec<String>>;
+
+    /// Hand the range's snapshots to `f` one at a time, oldest first, without holding them
+    /// all in memory. Returns how many there were.
+    fn for_each_snapshot(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(WifiSnapshot) -> anyhow::Result<()>) -> anyhow::Result<usize>;
+
+    /// Every event in the range, oldest first, one at a time like `for_each_snapshot`
+    fn for_each_event(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(NetworkEvent) -> anyhow::Result<()>) -> anyhow::Result<usize>;
+
+    /// Every timeseries point in the range as (timestamp, metric, value), oldest first,
+    /// one at a time like `for_each_snapshot`
+    fn for_each_timeseries_point(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(DateTime<Utc>, String, f64) -> anyhow::Result<()>) -> anyhow::Result<usize>;
+
+    /// Events in the range, newest first, skipping the first `offset`
+    fn get_events(&self, start: Option<&str>, end: Option<&str>, severity: Option<&str>, event_type: Option<&str>, limit: Option<u32>, offset: u64) -> anyhow::Result<Vec<NetworkEvent>>;
+
+    /// How many events `get_events` would return without a limit
+    fn count_events(&self, start: Option<&str>, end: Option<&str>, severity: Option<&str>, event_type: Option<&str>) -> anyhow::Result<u64>;
+
+    /// Events grouped into an incident, oldest first
+    fn get_incident_events(&self, incident_id: &str) -> anyhow::Result<Vec<NetworkEvent>>;
+
+    /// Statistics for the range
+    fn get_statistics(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<PeriodStatistics>;
+
+    /// How often each event type occurred in the range, most frequent first
+    fn get_event_counts_by_type(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, i64)>>;
+}
+
+impl SqliteStore {
+    /// Open a database for reading and writing. Databases from a newer build are refused,
+    /// and older ones must be upgraded with `migrate` first.
     pub fn new<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
         let db_path = path.as_ref().to_path_buf();
-        let conn = Connection::open(&db_path)?;
-        let store = Self { 
+        let conn = open_file(&db_path, OpenFlags::default())?;
+
+        match stored_schema_version(&conn)? {
+            None => initialize_schema(&conn)?,
+            Some(SCHEMA_VERSION) => conn.execute_batch(SCHEMA_SQL)?,
+            Some(version) if version > SCHEMA_VERSION => anyhow::bail!(
+                "{:?} was created by a newer version of wifi-stability-tracker (schema v{}, this build supports v{}). \
+                 Upgrade the tool, or use a different --database.",
+                db_path, version, SCHEMA_VERSION
+            ),
+            Some(version) => anyhow::bail!(
+                "{:?} uses an older schema (v{}, this build uses v{}). \
+                 Run `wifi-stability-tracker migrate --database {}` to upgrade it.",
+                db_path, version, SCHEMA_VERSION, db_path.display()
+            ),
+        }
+
+        drop(conn);
+        Ok(Self {
+            pool: pool(&db_path, OpenFlags::default(), POOL_SIZE)?,
             db_path,
-            conn: Mutex::new(conn),
-        };
-        store.initialize_schema()?;
-        Ok(store)
-    }
-
-    fn initialize_schema(&self) -> anyhow::Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute_batch(
-            r#"
-            -- Main snapshots table
-            CREATE TABLE IF NOT EXISTS snapshots (
-                id TEXT PRIMARY KEY,
-                timestamp TEXT NOT NULL,
-                data JSON NOT NULL
-            );
+            writable: true,
+            omitted_details: Vec::new(),
+            include_other_networks: false,
+            session_id: None,
+        })
+    }
 
-            -- Index for time-based queries
-            CREATE INDEX IF NOT EXISTS idx_snapshots_timestamp ON snapshots(timestamp);
-
-            -- Events table for quick event queries
-            CREATE TABLE IF NOT EXISTS events (
-                id TEXT PRIMARY KEY,
-                snapshot_id TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                severity TEXT NOT NULL,
-                description TEXT NOT NULL,
-                details JSON,
-                FOREIGN KEY (snapshot_id) REFERENCES snapshots(id)
-            );
+    /// Leave these snapshot details out of the stored snapshots
+    pub fn with_omitted_details(mut self, details: Vec<SnapshotDetail>) -> Self {
+        self.omitted_details = details;
+        self
+    }
 
-            CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events(timestamp);
-            CREATE INDEX IF NOT EXISTS idx_events_type ON events(event_type);
-            CREATE INDEX IF NOT EXISTS idx_events_severity ON events(severity);
+    /// Count snapshots tagged as taken on another network (`--only-ssid`) in statistics
+    pub fn with_other_networks_included(mut self, include: bool) -> Self {
+        self.include_other_networks = include;
+        self
+    }
 
-            -- Time series data for efficient charting
-            CREATE TABLE IF NOT EXISTS timeseries (
-                timestamp TEXT NOT NULL,
-                metric_name TEXT NOT NULL,
-                value REAL NOT NULL,
-                PRIMARY KEY (timestamp, metric_name)
-            );
+    /// Open a database for viewing (dashboard, export, analyze). A database from another
+    /// schema version is opened read-only with a warning instead of being refused.
+    pub fn open_compatible<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        let db_path = path.as_ref().to_path_buf();
+        if !db_path.exists() {
+            return Self::new(&db_path);
+        }
 
-            CREATE INDEX IF NOT EXISTS idx_timeseries_metric ON timeseries(metric_name, timestamp);
-
-            -- Statistics aggregates (hourly)
-            CREATE TABLE IF NOT EXISTS hourly_stats (
-                hour TEXT PRIMARY KEY,
-                sample_count INTEGER NOT NULL,
-                signal_avg REAL,
-                signal_min INTEGER,
-                signal_max INTEGER,
-                latency_avg REAL,
-                latency_min REAL,
-                latency_max REAL,
-                jitter_avg REAL,
-                packet_loss_avg REAL,
-                uptime_percent REAL,
-                internet_uptime_percent REAL,
-                disconnections INTEGER,
-                warning_events INTEGER,
-                error_events INTEGER,
-                critical_events INTEGER
+        let conn = open_file(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        match stored_schema_version(&conn)? {
+            None | Some(SCHEMA_VERSION) => {
+                drop(conn);
+                Self::new(&db_path)
+            }
+            Some(version) => {
+                if version > SCHEMA_VERSION {
+                    warn!(
+                        "{:?} was created by a newer version (schema v{}, this build supports v{}); opening read-only, some data may not be shown",
+                        db_path, version, SCHEMA_VERSION
+                    );
+                } else {
+                    warn!(
+                        "{:?} uses an older schema (v{}); opening read-only. Run `migrate` to upgrade it",
+                        db_path, version
+                    );
+                }
+                drop(conn);
+                let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI;
+                Ok(Self {
+                    pool: pool(&db_path, flags, POOL_SIZE)?,
+                    db_path,
+                    writable: false,
+                    omitted_details: Vec::new(),
+                    include_other_networks: false,
+                    session_id: None,
+                })
+            }
+        }
+    }
+
+    /// A throwaway in-memory store, used when nothing should be written to disk
+    pub fn in_memory() -> anyhow::Result<Self> {
+        let db_path = PathBuf::from(":memory:");
+        // Every connection to :memory: is its own database, so keep exactly one open
+        let pool = Pool::builder()
+            .max_size(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .build(ConnectionManager { path: db_path.clone(), flags: OpenFlags::default() })?;
+        let conn = pool.get()?;
+        initialize_schema(&conn)?;
+        drop(conn);
+        Ok(Self {
+            db_path,
+            pool,
+            writable: true,
+            omitted_details: Vec::new(),
+            include_other_networks: false,
+            session_id: None,
+        })
+    }
+
+    /// A connection from the pool; waits while all of them are in use
+    fn conn(&self) -> anyhow::Result<PooledConnection<ConnectionManager>> {
+        Ok(self.pool.get()?)
+    }
+
+    /// Upgrade a database to the current schema version, returning the (from, to) versions
+    pub fn migrate<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<(i64, i64)> {
+        let db_path = path.as_ref();
+        if !db_path.exists() {
+            anyhow::bail!("Database {:?} does not exist", db_path);
+        }
+
+        let mut conn = open_file(db_path, OpenFlags::default())?;
+        let from = stored_schema_version(&conn)?.unwrap_or(0);
+        if from > SCHEMA_VERSION {
+            anyhow::bail!(
+                "{:?} was created by a newer version (schema v{}, this build supports v{}); cannot downgrade",
+                db_path, from, SCHEMA_VERSION
             );
-            "#,
-        )?;
+        }
+
+        for version in (from + 1)..=SCHEMA_VERSION {
+            let tx = conn.transaction()?;
+            apply_migration(&tx, version)?;
+            record_schema_version(&tx, version)?;
+            tx.commit()?;
+            info!("Migrated {:?} to schema v{}", db_path, version);
+        }
+
+        Ok((from, SCHEMA_VERSION))
+    }
+
+    /// Merge the hourly rollups of each day whose hours are all rolled up
+    fn roll_up_days(&self) -> anyhow::Result<()> {
+        let Some((first_hour, last_hour)) = self.bucket_bounds("hourly_stats", "hour")? else { return Ok(()) };
+        let hours_done = last_hour + HOUR;
+        let mut day = match self.bucket_bounds("daily_stats", "day")? {
+            Some((_, last)) => last + DAY,
+            None => floor_to(first_hour, DAY),
+        };
 
+        while day + DAY <= hours_done {
+            let next = day + DAY;
+            let hours = self.rollup_rows("hourly_stats", "hour", day, next)?;
+            if !hours.is_empty() {
+                let mut rollup = StatsRollup::default();
+                for hour in &hours {
+                    rollup.merge(hour);
+                }
+
+                let mut conn = self.conn()?;
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO daily_stats (day, sample_count, data) VALUES (?1, ?2, ?3)",
+                    params![day.to_rfc3339(), rollup.samples, serde_json::to_string(&rollup)?],
+                )?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO timeseries_daily (day, metric_name, avg, min, max, samples)
+                     SELECT ?1, metric_name, SUM(avg * samples) / SUM(samples), MIN(min), MAX(max), SUM(samples)
+                     FROM timeseries_hourly WHERE hour >= ?1 AND hour < ?2 GROUP BY metric_name",
+                    params![day.to_rfc3339(), next.to_rfc3339()],
+                )?;
+                tx.commit()?;
+            }
+            day = next;
+        }
         Ok(())
     }
 
-    pub fn save_snapshot(&self, snapshot: &WifiSnapshot) -> anyhow::Result<()> {
-        let mut conn = self.conn.lock().unwrap();
-        let tx = conn.transaction()?;
+    /// Earliest and latest bucket in a rollup table
+    fn bucket_bounds(&self, table: &str, column: &str) -> anyhow::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let conn = self.conn()?;
+        let (first, last): (Option<String>, Option<String>) = conn.query_row(
+            &format!("SELECT MIN({column}), MAX({column}) FROM {table}"),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(first.as_deref().and_then(parse_time).zip(last.as_deref().and_then(parse_time)))
+    }
+
+    /// Rollups with a bucket in `[from, to)`
+    fn rollup_rows(&self, table: &str, column: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> anyhow::Result<Vec<StatsRollup>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT data FROM {table} WHERE {column} >= ?1 AND {column} < ?2"))?;
+        let rows = stmt.query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| row.get::<_, String>(0))?;
+        Ok(rows.flatten().filter_map(|data| serde_json::from_str(&data).ok()).collect())
+    }
+
+    /// Bytes in the database file holding data, and the file's size without the write-ahead log.
+    /// Pages freed by deletes are reused before the file grows again.
+    fn size_in_bytes(&self) -> anyhow::Result<(u64, u64)> {
+        let conn = self.conn()?;
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {name}"), [], |row| row.get::<_, u64>(0));
+        let (pages, free, page_size) = (pragma("page_count")?, pragma("freelist_count")?, pragma("page_size")?);
+        Ok(((pages - free) * page_size, pages * page_size))
+    }
+
+    /// Time of the first snapshot, or of the first one at or after `after`
+    fn first_snapshot_at(&self, after: Option<DateTime<Utc>>) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let conn = self.conn()?;
+        let first: Option<String> = conn.query_row(
+            "SELECT MIN(timestamp) FROM snapshots WHERE timestamp >= ?1",
+            params![after.map(|t| t.to_rfc3339()).unwrap_or_default()],
+            |row| row.get(0),
+        )?;
+        Ok(first.as_deref().and_then(parse_time))
+    }
+
+    /// Whether WiFi was connected in the last snapshot before `at`; true without one
+    fn connected_before(&self, at: DateTime<Utc>) -> anyhow::Result<bool> {
+        let conn = self.conn()?;
+        let data: Option<String> = conn.query_row(
+            "SELECT data FROM snapshots WHERE timestamp < ?1 ORDER BY timestamp DESC LIMIT 1",
+            params![at.to_rfc3339()],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(data
+            .and_then(|data| serde_json::from_str::<WifiSnapshot>(&data).ok())
+            .is_none_or(|s| s.wifi_info.is_some()))
+    }
+
+    /// The requested range, with open ends closed by the metric's first and last point
+    fn timeseries_span(&self, metric: &str, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let (first, last): (Option<i64>, Option<i64>) = self.conn()?.query_row(
+            "SELECT MIN(timestamp), MAX(timestamp) FROM timeseries WHERE metric_name = ?1",
+            params![metric],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let from = start.and_then(parse_time).or_else(|| first.and_then(from_epoch_millis));
+        let to = end.and_then(parse_time).or_else(|| last.and_then(from_epoch_millis));
+        Ok(from.zip(to))
+    }
+
+    /// The range to average over and the bucket size, when the range is long enough to need it
+    fn timeseries_resolution(&self, metric: &str, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Option<AveragingWindow>> {
+        let Some((from, to)) = self.timeseries_span(metric, start, end)? else { return Ok(None) };
+
+        Ok(match to - from {
+            span if span >= DAILY_TIMESERIES_MIN_RANGE => Some((from, to, DAY)),
+            span if span >= HOURLY_TIMESERIES_MIN_RANGE => Some((from, to, HOUR)),
+            _ => None,
+        })
+    }
+
+    /// Per-hour or per-day averages: rolled-up buckets first, then the newer points averaged here
+    fn get_timeseries_averages(&self, metric: &str, from: DateTime<Utc>, to: DateTime<Utc>, unit: TimeDelta) -> anyhow::Result<Vec<(String, f64)>> {
+        let (table, column) = if unit == DAY { ("timeseries_daily", "day") } else { ("timeseries_hourly", "hour") };
+        let rolled_until = self.bucket_bounds(table, column)?.map(|(_, last)| last + unit);
+        let from = floor_to(from, unit);
+
+        let conn = self.conn()?;
+        let mut data = Vec::new();
+        if rolled_until.is_some_and(|t| t > from) {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {column}, avg FROM {table} WHERE metric_name = ?1 AND {column} >= ?2 AND {column} <= ?3 ORDER BY {column} ASC"
+            ))?;
+            let rows = stmt.query_map(params![metric, from.to_rfc3339(), to.to_rfc3339()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })?;
+            data.extend(rows.flatten());
+        }
+
+        let tail_from = rolled_until.map_or(from, |t| t.max(from));
+        let mut stmt = conn.prepare(
+            "SELECT timestamp / ?4 * ?4 AS bucket, AVG(value) FROM timeseries
+             WHERE metric_name = ?1 AND timestamp >= ?2 AND timestamp <= ?3 GROUP BY bucket ORDER BY bucket ASC"
+        )?;
+        let rows = stmt.query_map(
+            params![metric, tail_from.timestamp_millis(), to.timestamp_millis(), unit.num_milliseconds()],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+        )?;
+        data.extend(rows.flatten().filter_map(|(bucket, avg)| Some((from_epoch_millis(bucket)?.to_rfc3339(), avg))));
+        Ok(data)
+    }
+
+    /// Hourly rollups of one metric for the hours from `from` up to `end`, and when the rollups end
+    fn hourly_timeseries_rollups(&self, metric: &str, from: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> anyhow::Result<(Vec<HourlyPoint>, Option<DateTime<Utc>>)> {
+        let Some(rolled_until) = self.bucket_bounds("timeseries_hourly", "hour")?.map(|(_, last)| last + HOUR) else {
+            return Ok((Vec::new(), None));
+        };
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT hour, avg, min, max, samples FROM timeseries_hourly
+             WHERE metric_name = ?1 AND hour >= ?2 AND hour <= ?3 ORDER BY hour ASC"
+        )?;
+        let from = from.map_or(String::new(), |t| floor_to(t, HOUR).to_rfc3339());
+        let end = end.map_or(TIME_MAX.to_string(), |t| t.to_rfc3339());
+        let rows = stmt.query_map(params![metric, from, end], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?, row.get::<_, i64>(4)?))
+        })?;
+        let hours = rows.flatten()
+            .filter_map(|(hour, avg, min, max, samples)| Some((parse_time(&hour)?.timestamp_millis(), avg, min, max, samples)))
+            .collect();
+        Ok((hours, Some(rolled_until)))
+    }
+
+    /// Summarize the snapshots from `start`, up to `end` or before `until`
+    fn summarize_snapshots(&self, start: Option<&str>, end: Option<&str>, until: Option<DateTime<Utc>>, was_connected: bool) -> anyhow::Result<StatsRollup> {
+        if let Some(rollup) = self.summarize_in_sql(start, end, until, was_connected)? {
+            return Ok(rollup);
+        }
+        let until_str = until.map(|t| t.to_rfc3339());
+        let mut snapshots = self.get_snapshots(start, until_str.as_deref().or(end), None, 0)?;
+        snapshots.retain(|s| until.is_none_or(|t| s.timestamp < t));
+        Ok(StatsRollup::from_snapshots(snapshots.iter().rev(), was_connected, self.include_other_networks))
+    }
+
+    /// `summarize_snapshots` aggregated in SQL over the timeseries and events tables, without
+    /// reading the snapshot JSON. `None` when some snapshots in the range lack the points it
+    /// needs, e.g. ones stored by older builds.
+    fn summarize_in_sql(&self, start: Option<&str>, end: Option<&str>, until: Option<DateTime<Utc>>, was_connected: bool) -> anyhow::Result<Option<StatsRollup>> {
+        let parse = |bound: Option<&str>| bound.map(|b| parse_time(b).ok_or(())).transpose();
+        let (Ok(start), Ok(end)) = (parse(start), parse(end)) else { return Ok(None) };
+        let conn = self.conn()?;
+        let millis = |bound: Option<DateTime<Utc>>, unset: i64| bound.map_or(unset, |t| t.timestamp_millis());
+        let (from, through, before) = (millis(start, i64::MIN), millis(end, i64::MAX), millis(until, i64::MAX));
+        // Snapshots are stamped in RFC 3339, which sorts by time as long as the bounds are written alike
+        let rfc3339 = |bound: Option<DateTime<Utc>>, unset: &str| bound.map_or(unset.to_string(), |t| t.to_rfc3339());
+        let snapshot_range = [rfc3339(start, ""), rfc3339(end, TIME_MAX), rfc3339(until, TIME_MAX)];
+
+        let snapshots: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM snapshots WHERE timestamp >= ?1 AND timestamp <= ?2 AND timestamp < ?3",
+            params![snapshot_range[0], snapshot_range[1], snapshot_range[2]],
+            |row| row.get(0),
+        )?;
+        let (weighted, flagged): (i64, i64) = conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM timeseries WHERE metric_name = 'effective_interval' AND timestamp >= ?1 AND timestamp <= ?2 AND timestamp < ?3),
+                (SELECT COUNT(*) FROM timeseries WHERE metric_name = 'other_network' AND timestamp >= ?1 AND timestamp <= ?2 AND timestamp < ?3)",
+            params![from, through, before],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if weighted != snapshots || flagged != snapshots {
+            return Ok(None);
+        }
+
+        let range = params![from, through, before, self.include_other_networks];
+        let mut rollup = StatsRollup { ended_connected: was_connected, ..StatsRollup::default() };
+        (rollup.other_network_samples, rollup.maintenance_samples) = conn.query_row(
+            &format!("{EXCLUDED_SQL} SELECT (SELECT COUNT(*) FROM other_networks), (SELECT COUNT(*) FROM maintenance)"),
+            range,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        // Every snapshot has an effective_interval point, so its count and range are the samples'
+        let mut stmt = conn.prepare(&format!(
+            "{EXCLUDED_SQL}
+             SELECT metric_name, COUNT(*), SUM(value), MIN(value), MAX(value), SUM(value < ?5), MIN(timestamp), MAX(timestamp)
+             FROM timeseries
+             WHERE metric_name IN ('effective_interval', 'signal_dbm', 'signal_percent', 'latency_avg',
+                                   'jitter', 'jitter_rfc3550', 'mos', 'packet_loss')
+               AND {}
+             GROUP BY metric_name",
+            in_summary("timestamp"),
+        ))?;
+        let mut rows = stmt.query(params![from, through, before, self.include_other_networks, MOS_POOR_CALL])?;
+        while let Some(row) = rows.next()? {
+            let metric: String = row.get(0)?;
+            let summary = Summary { count: row.get(1)?, sum: row.get(2)?, min: row.get(3)?, max: row.get(4)? };
+            match metric.as_str() {
+                "effective_interval" => {
+                    rollup.samples = summary.count;
+                    rollup.total_secs = summary.sum;
+                    rollup.first = row.get::<_, Option<i64>>(6)?.and_then(from_epoch_millis);
+                    rollup.last = row.get::<_, Option<i64>>(7)?.and_then(from_epoch_millis);
+                }
+                "signal_dbm" => rollup.signal_dbm = summary,
+                "signal_percent" => rollup.signal_quality = summary,
+                "latency_avg" => rollup.latency_ms = summary,
+                "jitter" => rollup.jitter_ms = summary,
+                "jitter_rfc3550" => rollup.rfc3550_jitter_ms = summary,
+                "mos" => {
+                    rollup.poor_calls = row.get(5)?;
+                    rollup.mos = summary;
+                }
+                "packet_loss" => rollup.packet_loss_percent = summary,
+                _ => {}
+            }
+        }
+
+        // Time-weighted: each snapshot stands for its interval
+        let mut stmt = conn.prepare(&format!(
+            "{EXCLUDED_SQL}
+             SELECT t.metric_name, SUM(w.value), SUM(t.value * w.value)
+             FROM timeseries t JOIN timeseries w ON w.timestamp = t.timestamp AND w.metric_name = 'effective_interval'
+             WHERE t.metric_name IN ('reduced_sampling', 'internet_reachable', 'signal_dbm') AND {}
+             GROUP BY t.metric_name",
+            in_summary("t.timestamp"),
+        ))?;
+        let mut rows = stmt.query(range)?;
+        while let Some(row) = rows.next()? {
+            let (metric, secs, weighted_value): (String, f64, f64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            match metric.as_str() {
+                "reduced_sampling" => rollup.reduced_secs = weighted_value,
+                "internet_reachable" => rollup.internet_secs = weighted_value,
+                "signal_dbm" => rollup.connected_secs = secs,
+                _ => {}
+            }
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "{EXCLUDED_SQL}
+             SELECT CAST(ROUND(value / ?5) AS INTEGER), COUNT(*) FROM timeseries
+             WHERE metric_name = 'latency_avg' AND {}
+             GROUP BY 1",
+            in_summary("timestamp"),
+        ))?;
+        let buckets = stmt.query_map(params![from, through, before, self.include_other_networks, HISTOGRAM_BUCKET_MS], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?))
+        })?;
+        for bucket in buckets {
+            let (bucket, count) = bucket?;
+            rollup.latency_histogram.insert(bucket, count);
+        }
+
+        // WiFi counts as connected while there is a signal reading
+        let (disconnections, ended_connected): (u32, Option<bool>) = conn.query_row(
+            &format!(
+                "{EXCLUDED_SQL},
+                 states AS (
+                     SELECT timestamp, EXISTS(
+                         SELECT 1 FROM timeseries s WHERE s.timestamp = w.timestamp AND s.metric_name = 'signal_dbm'
+                     ) AS up
+                     FROM timeseries w WHERE metric_name = 'effective_interval' AND {}
+                 ),
+                 transitions AS (
+                     SELECT timestamp, up, LAG(up, 1, ?5) OVER (ORDER BY timestamp) AS was_up FROM states
+                 )
+                 SELECT COALESCE(SUM(was_up AND NOT up), 0), (SELECT up FROM transitions ORDER BY timestamp DESC LIMIT 1)
+                 FROM transitions",
+                in_summary("w.timestamp"),
+            ),
+            params![from, through, before, self.include_other_networks, was_connected],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        rollup.disconnections = disconnections;
+        rollup.ended_connected = ended_connected.unwrap_or(was_connected);
+
+        // Events link to their snapshot, so other networks and maintenance are told apart by its JSON
+        let other_networks = if rollup.other_network_samples > 0 {
+            "AND NOT COALESCE(json_extract(n.data, '$.other_network'), 0)"
+        } else {
+            ""
+        };
+        let maintenance = if rollup.maintenance_samples > 0 {
+            "AND NOT COALESCE(json_extract(n.data, '$.maintenance'), 0)"
+        } else {
+            ""
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.severity, COUNT(*)
+             FROM snapshots n JOIN events e ON e.snapshot_id = n.id
+             WHERE n.timestamp >= ?1 AND n.timestamp <= ?2 AND n.timestamp < ?3
+               AND e.event_type != 'TestAlert' {other_networks} {maintenance}
+             GROUP BY e.severity"
+        ))?;
+        let counts = stmt.query_map(params![snapshot_range[0], snapshot_range[1], snapshot_range[2]], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+        })?;
+        for count in counts {
+            let (severity, count) = count?;
+            match parse_severity(&severity) {
+                EventSeverity::Warning => rollup.warning_events = count,
+                EventSeverity::Error => rollup.error_events = count,
+                EventSeverity::Critical => rollup.critical_events = count,
+                EventSeverity::Info => {}
+            }
+        }
+
+        Ok(Some(rollup))
+    }
+
+    /// The whole hours of a long range that hourly rollups cover, as `[from, to)`
+    fn rollup_window(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        // Rollups leave snapshots from other networks out
+        if self.include_other_networks {
+            return Ok(None);
+        }
+        let Some((first_hour, last_hour)) = self.bucket_bounds("hourly_stats", "hour")? else { return Ok(None) };
+        let (from, to) = {
+            let conn = self.conn()?;
+            let (first, last): (Option<String>, Option<String>) = conn.query_row(
+                "SELECT MIN(timestamp), MAX(timestamp) FROM snapshots",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            (start.or(first.as_deref()).and_then(parse_time), end.or(last.as_deref()).and_then(parse_time))
+        };
+        let (Some(from), Some(to)) = (from, to) else { return Ok(None) };
+        if to - from < ROLLUP_MIN_RANGE {
+            return Ok(None);
+        }
+
+        let window_from = ceil_to(from, HOUR).max(first_hour);
+        let window_to = floor_to(to, HOUR).min(last_hour + HOUR);
+        Ok((window_from < window_to).then_some((window_from, window_to)))
+    }
+
+    /// Rollups covering `[from, to)`: daily ones for whole rolled-up days, hourly ones around them
+    fn get_rollups(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> anyhow::Result<Vec<StatsRollup>> {
+        let days = self.bucket_bounds("daily_stats", "day")?
+            .map(|(first, last)| (ceil_to(from, DAY).max(first), floor_to(to, DAY).min(last + DAY)))
+            .filter(|(day_from, day_to)| day_from < day_to);
+        let Some((day_from, day_to)) = days else {
+            return self.rollup_rows("hourly_stats", "hour", from, to);
+        };
 
-        // Save main snapshot
-        let data = serde_json::to_string(snapshot)?;
+        let mut rollups = self.rollup_rows("hourly_stats", "hour", from, day_from)?;
+        rollups.extend(self.rollup_rows("daily_stats", "day", day_from, day_to)?);
+        rollups.extend(self.rollup_rows("hourly_stats", "hour", day_to, to)?);
+        Ok(rollups)
+    }
+}
+
+impl MetricsStore for SqliteStore {
+    fn in_scope(&self, snapshot: &WifiSnapshot) -> bool {
+        self.include_other_networks || !snapshot.other_network
+    }
+
+    fn start_session(&mut self, config: serde_json::Value) -> anyhow::Result<Session> {
+        let session = Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            host: sysinfo::System::host_name(),
+            config,
+        };
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let closed = tx.execute(
+            "UPDATE sessions SET ended_at = COALESCE(
+                (SELECT MAX(timestamp) FROM snapshots WHERE session_id = sessions.id), started_at)
+             WHERE ended_at IS NULL",
+            [],
+        )? + tx.execute(
+            "UPDATE pauses SET ended_at = MAX(started_at, COALESCE((SELECT MAX(timestamp) FROM snapshots), started_at))
+             WHERE ended_at IS NULL",
+            [],
+        )?;
+        if closed > 0 {
+            info!("Closed {} session(s) and pause(s) left open by an unclean shutdown", closed);
+        }
         tx.execute(
-            "INSERT INTO snapshots (id, timestamp, data) VALUES (?1, ?2, ?3)",
+            "INSERT INTO sessions (id, started_at, ended_at, host, config) VALUES (?1, ?2, NULL, ?3, ?4)",
             params![
-                snapshot.id,
-                snapshot.timestamp.to_rfc3339(),
-                data
+                session.id,
+                session.started_at.to_rfc3339(),
+                session.host,
+                serde_json::to_string(&session.config)?
             ],
         )?;
+        tx.commit()?;
+        drop(conn);
 
-        // Save events
-        for event in &snapshot.events {
-            let details = serde_json::to_string(&event.details)?;
-            tx.execute(
-                "INSERT INTO events (id, snapshot_id, timestamp, event_type, severity, description, details) 
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                params![
-                    event.id,
-                    snapshot.id,
-                    event.timestamp.to_rfc3339(),
-                    format!("{:?}", event.event_type),
-                    format!("{:?}", event.severity),
-                    event.description,
-                    details
-                ],
+        self.session_id = Some(session.id.clone());
+        Ok(session)
+    }
+
+    fn resume_session(&mut self, config: serde_json::Value) -> anyhow::Result<Session> {
+        let open = self.conn()?
+            .query_row(
+                "SELECT id, started_at, ended_at, host, config FROM sessions
+                 WHERE ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+                [],
+                read_session,
+            )
+            .optional()?;
+        match open {
+            Some(session) => {
+                self.session_id = Some(session.id.clone());
+                Ok(session)
+            }
+            None => self.start_session(config),
+        }
+    }
+
+    /// Also folds the write-ahead log back into the database file
+    fn close(&self) -> anyhow::Result<()> {
+        if let Some(ref id) = self.session_id {
+            self.conn()?.execute(
+                "UPDATE sessions SET ended_at = ?1 WHERE id = ?2",
+                params![Utc::now().to_rfc3339(), id],
             )?;
         }
+        if self.writable {
+            self.conn()?
+                .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+                .map_err(|e| anyhow::anyhow!("Failed to close {:?}: {}", self.db_path, e))?;
+        }
+        Ok(())
+    }
 
-        // Save time series data
-        let ts = snapshot.timestamp.to_rfc3339();
+    fn prune_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<usize> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let mut removed = tx.execute("DELETE FROM timeseries WHERE timestamp < ?1", params![cutoff.timestamp_millis()])?;
+        if removed > 0 {
+            debug!("Pruned {} rows from timeseries", removed);
+        }
+        let cutoff = cutoff.to_rfc3339();
+        for (table, sql) in [
+            ("events", "DELETE FROM events WHERE timestamp < ?1"),
+            ("snapshots", "DELETE FROM snapshots WHERE timestamp < ?1"),
+            ("incidents", "DELETE FROM incidents WHERE ended_at IS NOT NULL AND ended_at < ?1"),
+            ("pauses", "DELETE FROM pauses WHERE ended_at IS NOT NULL AND ended_at < ?1"),
+            ("sessions", "DELETE FROM sessions WHERE ended_at IS NOT NULL AND ended_at < ?1"),
+            ("annotations", "DELETE FROM annotations WHERE timestamp < ?1"),
+            ("alerts", "DELETE FROM alerts WHERE resolved_at IS NOT NULL AND resolved_at < ?1"),
+            ("adapter_info", "DELETE FROM adapter_info WHERE collected_at < ?1
+                AND id <> (SELECT id FROM adapter_info ORDER BY collected_at DESC LIMIT 1)"),
+            ("hourly_stats", "DELETE FROM hourly_stats WHERE hour < ?1"),
+            ("daily_stats", "DELETE FROM daily_stats WHERE day < ?1"),
+            ("timeseries_hourly", "DELETE FROM timeseries_hourly WHERE hour < ?1"),
+            ("timeseries_daily", "DELETE FROM timeseries_daily WHERE day < ?1"),
+        ] {
+            let rows = tx.execute(sql, params![cutoff])?;
+            if rows > 0 {
+                debug!("Pruned {} rows from {}", rows, table);
+            }
+            removed += rows;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
 
-        if let Some(ref wifi) = snapshot.wifi_info {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "signal_dbm", wifi.signal_strength_dbm as f64],
-            )?;
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "signal_percent", wifi.signal_quality_percent as f64],
-            )?;
+    /// Sessions in one file never overlap, so the session's span identifies its timeseries points
+    fn delete_session(&self, id: &str) -> anyhow::Result<Option<usize>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let session = tx
+            .query_row("SELECT id, started_at, ended_at, host, config FROM sessions WHERE id = ?1", params![id], read_session)
+            .optional()?;
+        let Some(session) = session else {
+            return Ok(None);
+        };
+        let Some(ended_at) = session.ended_at else {
+            anyhow::bail!("Session {} is still running; stop it before deleting it", id);
+        };
+
+        let mut removed = tx.execute(
+            "DELETE FROM timeseries WHERE timestamp >= ?1 AND timestamp <= ?2",
+            params![session.started_at.timestamp_millis(), ended_at.timestamp_millis()],
+        )?;
+        let (start, end) = (session.started_at.to_rfc3339(), ended_at.to_rfc3339());
+        // Rollups of hours and days partly outside the session also hold other data
+        let hours = (ceil_to(session.started_at, HOUR).to_rfc3339(), floor_to(ended_at, HOUR).to_rfc3339());
+        let days = (ceil_to(session.started_at, DAY).to_rfc3339(), floor_to(ended_at, DAY).to_rfc3339());
+        let deletes: [(&str, &str, &[&dyn rusqlite::ToSql]); 10] = [
+            ("events", "DELETE FROM events WHERE snapshot_id IN (SELECT id FROM snapshots WHERE session_id = ?1)", &[&id]),
+            ("snapshots", "DELETE FROM snapshots WHERE session_id = ?1", &[&id]),
+            ("incidents", "DELETE FROM incidents WHERE started_at >= ?1 AND ended_at IS NOT NULL AND ended_at <= ?2", &[&start, &end]),
+            ("pauses", "DELETE FROM pauses WHERE started_at >= ?1 AND ended_at IS NOT NULL AND ended_at <= ?2", &[&start, &end]),
+            ("alerts", "DELETE FROM alerts WHERE fired_at >= ?1 AND resolved_at IS NOT NULL AND resolved_at <= ?2", &[&start, &end]),
+            ("hourly_stats", "DELETE FROM hourly_stats WHERE hour >= ?1 AND hour < ?2", &[&hours.0, &hours.1]),
+            ("timeseries_hourly", "DELETE FROM timeseries_hourly WHERE hour >= ?1 AND hour < ?2", &[&hours.0, &hours.1]),
+            ("daily_stats", "DELETE FROM daily_stats WHERE day >= ?1 AND day < ?2", &[&days.0, &days.1]),
+            ("timeseries_daily", "DELETE FROM timeseries_daily WHERE day >= ?1 AND day < ?2", &[&days.0, &days.1]),
+            ("sessions", "DELETE FROM sessions WHERE id = ?1", &[&id]),
+        ];
+        for (table, sql, values) in deletes {
+            let rows = tx.execute(sql, values)?;
+            if rows > 0 {
+                debug!("Deleted {} rows of session {} from {}", rows, id, table);
+            }
+            removed += rows;
+        }
+        tx.commit()?;
+        Ok(Some(removed))
+    }
+
+    fn vacuum(&self) -> anyhow::Result<()> {
+        self.conn()?.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    /// Trims to 90% of the cap, so it doesn't run again with every snapshot. Hours are rolled
+    /// up before their raw data goes, and data not rolled up yet is always kept.
+    fn trim_to_size(&self, max_bytes: u64) -> anyhow::Result<usize> {
+        let (used, _) = self.size_in_bytes()?;
+        if used <= max_bytes {
+            return Ok(0);
+        }
+        self.roll_up()?;
+
+        let target = max_bytes / 10 * 9;
+        let keep_from = floor_to(Utc::now() - ROLLUP_GRACE, HOUR);
+        let mut removed = 0;
+        loop {
+            let (used, _) = self.size_in_bytes()?;
+            if used <= target {
+                break;
+            }
+            let Some(oldest) = self.first_snapshot_at(None)? else { break };
+            if oldest >= keep_from {
+                warn!("Rollups and the current hour alone take up more than {} MB", max_bytes / MB);
+                break;
+            }
+
+            // Assume snapshots take up about the same space each and drop the share over the
+            // target, at least a few at a time so every round makes progress
+            let mut conn = self.conn()?;
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM snapshots", [], |row| row.get(0))?;
+            let share = (used - target) as f64 / used as f64;
+            let drop = ((count as f64 * share) as i64).max(TRIM_MIN_SNAPSHOTS).min(count - 1);
+            let cutoff: Option<String> = conn.query_row(
+                "SELECT timestamp FROM snapshots ORDER BY timestamp LIMIT 1 OFFSET ?1",
+                params![drop],
+                |row| row.get(0),
+            ).optional()?;
+            let Some(cutoff) = cutoff.as_deref().and_then(parse_time) else { break };
+            let cutoff = cutoff.min(keep_from).max(oldest + TimeDelta::seconds(1));
+
+            let tx = conn.transaction()?;
+            removed += tx.execute("DELETE FROM timeseries WHERE timestamp < ?1", params![cutoff.timestamp_millis()])?;
+            removed += tx.execute("DELETE FROM events WHERE timestamp < ?1", params![cutoff.to_rfc3339()])?;
+            removed += tx.execute("DELETE FROM snapshots WHERE timestamp < ?1", params![cutoff.to_rfc3339()])?;
+            tx.commit()?;
+            debug!("Trimmed raw data before {}", cutoff.to_rfc3339());
+        }
+
+        if removed > 0 {
+            // Shrink a file that had grown past the cap before it was set
+            let (_, file) = self.size_in_bytes()?;
+            if file > max_bytes {
+                self.vacuum()?;
+            }
+            // The deletes went through the write-ahead log, which keeps its size until truncated
+            self.conn()?.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        }
+        Ok(removed)
+    }
+
+    /// Roll every completed hour since the last run up into `hourly_stats` and
+    /// `timeseries_hourly`, then every completed day into the daily tables. Snapshots
+    /// from other networks are left out.
+    fn roll_up(&self) -> anyhow::Result<usize> {
+        let complete_before = floor_to(Utc::now() - ROLLUP_GRACE, HOUR);
+        let mut hour = match (self.bucket_bounds("hourly_stats", "hour")?, self.first_snapshot_at(None)?) {
+            (Some((_, last)), _) => last + HOUR,
+            (None, Some(first)) => floor_to(first, HOUR),
+            (None, None) => return Ok(0),
+        };
+
+        let mut added = 0;
+        while hour < complete_before {
+            let next = hour + HOUR;
+            let mut snapshots = self.get_snapshots(Some(&hour.to_rfc3339()), Some(&next.to_rfc3339()), None, 0)?;
+            snapshots.retain(|s| s.timestamp < next);
+            if snapshots.is_empty() {
+                // Jump over gaps, e.g. while the laptop slept
+                match self.first_snapshot_at(Some(next))? {
+                    Some(at) => hour = floor_to(at, HOUR),
+                    None => break,
+                }
+                continue;
+            }
+            snapshots.reverse();
+            let rollup = StatsRollup::from_snapshots(&snapshots, self.connected_before(hour)?, false);
+
+            let mut conn = self.conn()?;
+            let tx = conn.transaction()?;
             tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "channel", wifi.channel as f64],
+                "INSERT OR REPLACE INTO hourly_stats (hour, sample_count, data) VALUES (?1, ?2, ?3)",
+                params![hour.to_rfc3339(), rollup.samples, serde_json::to_string(&rollup)?],
             )?;
             tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "link_speed", wifi.link_speed_mbps as f64],
+                "INSERT OR REPLACE INTO timeseries_hourly (hour, metric_name, avg, min, max, samples)
+                 SELECT ?1, metric_name, AVG(value), MIN(value), MAX(value), COUNT(*) FROM timeseries
+                 WHERE timestamp >= ?2 AND timestamp < ?3 GROUP BY metric_name",
+                params![hour.to_rfc3339(), hour.timestamp_millis(), next.timestamp_millis()],
             )?;
+            tx.commit()?;
+            added += 1;
+            hour = next;
         }
 
-        if let Some(loopback) = snapshot.latency.loopback_latency_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "latency_loopback", loopback],
-            )?;
+        self.roll_up_days()?;
+        Ok(added)
+    }
+
+    fn save_snapshots(&self, snapshots: &[WifiSnapshot]) -> anyhow::Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        for snapshot in snapshots {
+            let data = if self.omitted_details.is_empty() {
+                serde_json::to_string(snapshot)?
+            } else {
+                serde_json::to_string(&trimmed(snapshot, &self.omitted_details))?
+            };
+            insert_snapshot(&tx, snapshot, &data, self.session_id.as_deref())?;
         }
-        if let Some(router) = snapshot.latency.router_latency_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "latency_router", router],
-            )?;
+        tx.commit()?;
+        debug!("Saved {} snapshot(s)", snapshots.len());
+        Ok(())
+    }
+
+    /// Rollups from the first imported hour on are thrown away and rebuilt by the next `roll_up`
+    fn import_snapshots(&self, session: &Session, snapshots: &[WifiSnapshot]) -> anyhow::Result<usize> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let mut added = 0;
+        let mut earliest: Option<DateTime<Utc>> = None;
+        for snapshot in snapshots {
+            let exists = tx
+                .query_row("SELECT 1 FROM snapshots WHERE id = ?1", params![snapshot.id], |_| Ok(()))
+                .optional()?
+                .is_some();
+            if exists {
+                continue;
+            }
+            insert_snapshot(&tx, snapshot, &serde_json::to_string(snapshot)?, Some(&session.id))?;
+            earliest = Some(earliest.map_or(snapshot.timestamp, |t| t.min(snapshot.timestamp)));
+            added += 1;
         }
-        if let Some(avg) = snapshot.latency.average_latency_ms {
+
+        if let Some(earliest) = earliest {
             tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "latency_avg", avg],
+                "INSERT INTO sessions (id, started_at, ended_at, host, config) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET ended_at = excluded.ended_at",
+                params![
+                    session.id,
+                    session.started_at.to_rfc3339(),
+                    session.ended_at.map(|t| t.to_rfc3339()),
+                    session.host,
+                    serde_json::to_string(&session.config)?
+                ],
             )?;
+            let hour = floor_to(earliest, HOUR).to_rfc3339();
+            let day = floor_to(earliest, DAY).to_rfc3339();
+            tx.execute("DELETE FROM hourly_stats WHERE hour >= ?1", params![hour])?;
+            tx.execute("DELETE FROM timeseries_hourly WHERE hour >= ?1", params![hour])?;
+            tx.execute("DELETE FROM daily_stats WHERE day >= ?1", params![day])?;
+            tx.execute("DELETE FROM timeseries_daily WHERE day >= ?1", params![day])?;
         }
-        if let Some(min) = snapshot.latency.min_latency_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "latency_min", min],
-            )?;
+        tx.commit()?;
+        Ok(added)
+    }
+
+    fn save_adapter_info(&self, info: &AdapterInfo) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        let collected_at = info.collected_at.unwrap_or_else(Utc::now).to_rfc3339();
+        conn.execute(
+            "INSERT INTO adapter_info (collected_at, data) VALUES (?1, ?2)",
+            params![collected_at, serde_json::to_string(info)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_latest_adapter_info(&self) -> anyhow::Result<Option<AdapterInfo>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT data FROM adapter_info ORDER BY collected_at DESC LIMIT 1")?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => {
+                let data: String = row.get(0)?;
+                Ok(serde_json::from_str(&data).ok())
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save_incident(&self, incident: &Incident) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO incidents (id, started_at, ended_at, status, root_cause, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET ended_at = excluded.ended_at, status = excluded.status,
+                 root_cause = excluded.root_cause, data = excluded.data",
+            params![
+                incident.id,
+                incident.started_at.to_rfc3339(),
+                incident.ended_at.map(|t| t.to_rfc3339()),
+                format!("{:?}", incident.status),
+                format!("{:?}", incident.root_cause),
+                serde_json::to_string(incident)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_pause(&self, pause: &PauseWindow) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO pauses (id, started_at, ended_at, reason) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                pause.id,
+                pause.started_at.to_rfc3339(),
+                pause.ended_at.map(|t| t.to_rfc3339()),
+                pause.reason
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_alert_state(&self) -> anyhow::Result<AlertState> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT key, value FROM alert_state")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut state = AlertState::default();
+        for (key, value) in rows.flatten() {
+            match key.as_str() {
+                "last_notified" => {
+                    if let Ok((incident, at)) = serde_json::from_str(&value) {
+                        state.last_notified_incident = Some(incident);
+                        state.last_notified_at = Some(at);
+                    }
+                }
+                "mute" => state.mute = serde_json::from_str(&value).ok(),
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+
+    fn record_notified(&self, incident_id: &str, at: DateTime<Utc>) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO alert_state (key, value) VALUES ('last_notified', ?1)",
+            params![serde_json::to_string(&(incident_id, at))?],
+        )?;
+        Ok(())
+    }
+
+    fn save_mute(&self, mute: Option<&MuteWindow>) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        match mute {
+            Some(mute) => conn.execute(
+                "INSERT OR REPLACE INTO alert_state (key, value) VALUES ('mute', ?1)",
+                params![serde_json::to_string(mute)?],
+            )?,
+            None => conn.execute("DELETE FROM alert_state WHERE key = 'mute'", [])?,
+        };
+        Ok(())
+    }
+
+    fn save_alert(&self, alert: &Alert) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO alerts (id, name, fired_at, resolved_at, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                alert.id,
+                alert.name,
+                alert.fired_at.to_rfc3339(),
+                alert.resolved_at.map(|t| t.to_rfc3339()),
+                serde_json::to_string(alert)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_alerts(&self, start: Option<&str>, end: Option<&str>, name: Option<&str>, limit: Option<u32>) -> anyhow::Result<Vec<Alert>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT data FROM alerts
+             WHERE (?1 IS NULL OR fired_at >= ?1) AND (?2 IS NULL OR fired_at <= ?2) AND (?3 IS NULL OR name = ?3)
+             ORDER BY fired_at DESC{}",
+            limit_clause(limit, 0)
+        ))?;
+        let rows = stmt.query_map(params![start, end, name], |row| row.get::<_, String>(0))?;
+        Ok(rows.flatten().filter_map(|data| serde_json::from_str(&data).ok()).collect())
+    }
+
+    fn save_annotation(&self, annotation: &Annotation) -> anyhow::Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO annotations (id, timestamp, text, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                annotation.id,
+                annotation.timestamp.to_rfc3339(),
+                annotation.text,
+                annotation.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_annotations(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<Annotation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, text, created_at FROM annotations
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+             ORDER BY timestamp DESC",
+        )?;
+        let rows = stmt.query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+
+        Ok(rows.flatten().filter_map(|(id, timestamp, text, created_at)| {
+            Some(Annotation {
+                id,
+                timestamp: parse_time(&timestamp)?,
+                text,
+                created_at: parse_time(&created_at)?,
+            })
+        }).collect())
+    }
+
+    fn delete_annotation(&self, id: &str) -> anyhow::Result<bool> {
+        Ok(self.conn()?.execute("DELETE FROM annotations WHERE id = ?1", params![id])? > 0)
+    }
+
+    fn get_pauses(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<PauseWindow>> {
+        let mut query = String::from("SELECT id, started_at, ended_at, reason FROM pauses WHERE 1=1");
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(s) = start {
+            query.push_str(" AND (ended_at IS NULL OR ended_at >= ?)");
+            params_vec.push(Box::new(s.to_string()));
+        }
+        if let Some(e) = end {
+            query.push_str(" AND started_at <= ?");
+            params_vec.push(Box::new(e.to_string()));
+        }
+
+        query.push_str(" ORDER BY started_at DESC");
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+
+        Ok(rows.flatten().filter_map(|(id, started_at, ended_at, reason)| {
+            Some(PauseWindow {
+                id,
+                started_at: DateTime::parse_from_rfc3339(&started_at).ok()?.with_timezone(&Utc),
+                ended_at: ended_at
+                    .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                    .map(|t| t.with_timezone(&Utc)),
+                reason,
+            })
+        }).collect())
+    }
+
+    fn get_incidents(&self, start: Option<&str>, end: Option<&str>, status: Option<&str>) -> anyhow::Result<Vec<Incident>> {
+        let mut query = String::from("SELECT data, notes FROM incidents WHERE 1=1");
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(s) = start {
+            query.push_str(" AND started_at >= ?");
+            params_vec.push(Box::new(s.to_string()));
         }
-        if let Some(max) = snapshot.latency.max_latency_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "latency_max", max],
-            )?;
+        if let Some(e) = end {
+            query.push_str(" AND started_at <= ?");
+            params_vec.push(Box::new(e.to_string()));
         }
-        if let Some(jitter) = snapshot.latency.jitter_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "jitter", jitter],
-            )?;
+        if let Some(st) = status {
+            query.push_str(" AND lower(status) = lower(?)");
+            params_vec.push(Box::new(st.to_string()));
         }
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "packet_loss", snapshot.latency.packet_loss_percent],
-        )?;
 
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "connected", if snapshot.connectivity.is_connected { 1.0 } else { 0.0 }],
-        )?;
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "loopback_reachable", if snapshot.connectivity.loopback_reachable { 1.0 } else { 0.0 }],
-        )?;
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "router_reachable", if snapshot.connectivity.router_reachable { 1.0 } else { 0.0 }],
-        )?;
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "internet_reachable", if snapshot.connectivity.internet_reachable { 1.0 } else { 0.0 }],
+        query.push_str(" ORDER BY started_at DESC");
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        Ok(rows.flatten().filter_map(|(data, notes)| read_incident(&data, &notes)).collect())
+    }
+
+    fn get_sessions(&self) -> anyhow::Result<Vec<(Session, i64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, started_at, ended_at, host, config,
+                    (SELECT COUNT(*) FROM snapshots WHERE session_id = sessions.id)
+             FROM sessions ORDER BY started_at DESC",
         )?;
+        let rows = stmt.query_map([], |row| Ok((read_session(row)?, row.get::<_, i64>(5)?)))?;
+        Ok(rows.flatten().collect())
+    }
 
-        if let Some(http_time) = snapshot.connectivity.http_response_time_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "http_response_time", http_time as f64],
-            )?;
+    fn get_session(&self, id: &str) -> anyhow::Result<Option<Session>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, started_at, ended_at, host, config FROM sessions
+             WHERE id = ?1 OR id LIKE ?1 || '%' ORDER BY id = ?1 DESC LIMIT 2",
+        )?;
+        let sessions: Vec<Session> = stmt.query_map(params![id], read_session)?.flatten().collect();
+        match sessions.as_slice() {
+            [] => Ok(None),
+            [first, ..] if first.id == id => Ok(Some(first.clone())),
+            [only] => Ok(Some(only.clone())),
+            _ => anyhow::bail!("More than one session id starts with {:?}", id),
         }
+    }
 
-        if let Some(dns_time) = snapshot.dns_metrics.average_resolution_time_ms {
-            tx.execute(
-                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-                params![ts, "dns_resolution_time", dns_time],
-            )?;
+    fn get_incident(&self, id: &str) -> anyhow::Result<Option<Incident>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT data, notes FROM incidents WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(read_incident(&row.get::<_, String>(0)?, &row.get::<_, String>(1)?)),
+            None => Ok(None),
         }
+    }
 
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "cpu_usage", snapshot.system_info.cpu_usage_percent as f64],
-        )?;
-        tx.execute(
-            "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES (?1, ?2, ?3)",
-            params![ts, "memory_usage", snapshot.system_info.memory_usage_percent as f64],
+    fn add_incident_note(&self, id: &str, text: &str) -> anyhow::Result<Option<Incident>> {
+        let note = IncidentNote {
+            added_at: Utc::now(),
+            text: text.to_string(),
+        };
+        let updated = self.conn()?.execute(
+            "UPDATE incidents SET notes = json_insert(notes, '$[#]', json(?1)) WHERE id = ?2",
+            params![serde_json::to_string(&note)?, id],
         )?;
-
-        tx.commit()?;
-        debug!("Saved snapshot {}", snapshot.id);
-        Ok(())
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.get_incident(id)
     }
 
-    pub fn get_snapshots(&self, start: Option<&str>, end: Option<&str>, limit: Option<u32>) -> anyhow::Result<Vec<WifiSnapshot>> {
+    fn get_snapshots(&self, start: Option<&str>, end: Option<&str>, limit: Option<u32>, offset: u64) -> anyhow::Result<Vec<WifiSnapshot>> {
         let mut query = String::from("SELECT data FROM snapshots WHERE 1=1");
         let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
@@ -246,14 +1672,11 @@ impl MetricsStore {
         }
 
         query.push_str(" ORDER BY timestamp DESC");
-
-        if let Some(l) = limit {
-            query.push_str(&format!(" LIMIT {}", l));
-        }
+        query.push_str(&limit_clause(limit, offset));
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
         
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn()?;
         let mut stmt = conn.prepare(&query)?;
         let rows = stmt.query_map(params_refs.as_slice(), |row| {
             let data: String = row.get(0)?;
@@ -272,47 +1695,202 @@ impl MetricsStore {
         Ok(snapshots)
     }
 
-    pub fn get_latest_snapshot(&self) -> anyhow::Result<Option<WifiSnapshot>> {
-        let snapshots = self.get_snapshots(None, None, Some(1))?;
-        Ok(snapshots.into_iter().next())
+    fn count_snapshots(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<u64> {
+        let conn = self.conn()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM snapshots WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)",
+            params![start, end],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    fn get_neighbor_scans(&self, start: Option<&str>, end: Option<&str>, limit: u32) -> anyhow::Result<Vec<(DateTime<Utc>, Vec<NeighborNetwork>)>> {
+        let conn = self.conn()?;
+        // Only the neighbor list is extracted, so the rest of each snapshot isn't parsed
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, json_extract(data, '$.neighbors') FROM snapshots
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+               AND json_array_length(data, '$.neighbors') > 0
+             ORDER BY timestamp DESC LIMIT ?3"
+        )?;
+        let rows = stmt.query_map(params![start, end, limit], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        Ok(rows.flatten()
+            .filter_map(|(timestamp, neighbors)| Some((parse_time(&timestamp)?, serde_json::from_str(&neighbors).ok()?)))
+            .collect())
     }
 
-    pub fn get_timeseries(&self, metric: &str, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, f64)>> {
+    /// Long ranges are read from the rollups where available
+    fn get_timeseries(&self, metric: &str, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, f64)>> {
+        if let Some((from, to, unit)) = self.timeseries_resolution(metric, start, end)? {
+            return self.get_timeseries_averages(metric, from, to, unit);
+        }
+
         let mut query = String::from(
             "SELECT timestamp, value FROM timeseries WHERE metric_name = ?"
         );
         let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(metric.to_string())];
 
-        if let Some(s) = start {
+        if let Some(s) = epoch_millis(start)? {
             query.push_str(" AND timestamp >= ?");
-            params_vec.push(Box::new(s.to_string()));
+            params_vec.push(Box::new(s));
         }
-        if let Some(e) = end {
+        if let Some(e) = epoch_millis(end)? {
             query.push_str(" AND timestamp <= ?");
-            params_vec.push(Box::new(e.to_string()));
+            params_vec.push(Box::new(e));
         }
 
         query.push_str(" ORDER BY timestamp ASC");
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn()?;
         let mut stmt = conn.prepare(&query)?;
         let rows = stmt.query_map(params_refs.as_slice(), |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
         })?;
 
         let mut data = Vec::new();
         for row in rows {
-            if let Ok(point) = row {
-                data.push(point);
+            if let Ok((millis, value)) = row {
+                if let Some(timestamp) = from_epoch_millis(millis) {
+                    data.push((timestamp.to_rfc3339(), value));
+                }
             }
         }
 
         Ok(data)
     }
 
-    pub fn get_events(&self, start: Option<&str>, end: Option<&str>, severity: Option<&str>, event_type: Option<&str>) -> anyhow::Result<Vec<NetworkEvent>> {
+    fn get_timeseries_buckets(&self, metric: &str, start: Option<&str>, end: Option<&str>, size: BucketSize) -> anyhow::Result<(TimeDelta, Vec<TimeseriesBucket>)> {
+        let (from, to) = (epoch_millis(start)?, epoch_millis(end)?);
+        let bucket = match size {
+            BucketSize::Fixed(bucket) => bucket,
+            BucketSize::MaxPoints(_) => match self.timeseries_span(metric, start, end)? {
+                Some((from, to)) => size.resolve(from, to),
+                None => return Ok((TimeDelta::zero(), Vec::new())),
+            },
+        };
+        let bucket_ms = bucket.num_milliseconds().max(1);
+
+        // (sum of values, min, max, samples) by bucket start
+        let mut buckets: BTreeMap<i64, (f64, f64, f64, i64)> = BTreeMap::new();
+        let mut add = |at: i64, avg: f64, min: f64, max: f64, samples: i64| {
+            let entry = buckets.entry(at.div_euclid(bucket_ms) * bucket_ms).or_insert((0.0, f64::INFINITY, f64::NEG_INFINITY, 0));
+            entry.0 += avg * samples as f64;
+            entry.1 = entry.1.min(min);
+            entry.2 = entry.2.max(max);
+            entry.3 += samples;
+        };
+
+        // Whole-hour buckets can start from the hourly rollups, which outlive pruned points
+        let mut raw_from = from.unwrap_or(i64::MIN);
+        if bucket_ms % HOUR.num_milliseconds() == 0 {
+            let (hours, rolled_until) = self.hourly_timeseries_rollups(metric, from.and_then(from_epoch_millis), to.and_then(from_epoch_millis))?;
+            for (hour, avg, min, max, samples) in hours {
+                add(hour, avg, min, max, samples);
+            }
+            if let Some(rolled_until) = rolled_until {
+                raw_from = raw_from.max(rolled_until.timestamp_millis());
+            }
+        }
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp / ?2 * ?2 AS bucket, AVG(value), MIN(value), MAX(value), COUNT(*) FROM timeseries
+             WHERE metric_name = ?1 AND timestamp >= ?3 AND timestamp <= ?4 GROUP BY bucket ORDER BY bucket ASC"
+        )?;
+        let rows = stmt.query_map(params![metric, bucket_ms, raw_from, to.unwrap_or(i64::MAX)], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?, row.get::<_, i64>(4)?))
+        })?;
+        for (at, avg, min, max, samples) in rows.flatten() {
+            add(at, avg, min, max, samples);
+        }
+
+        let buckets = buckets.into_iter()
+            .filter(|(_, (_, _, _, samples))| *samples > 0)
+            .filter_map(|(at, (sum, min, max, samples))| Some(TimeseriesBucket {
+                timestamp: from_epoch_millis(at)?,
+                avg: sum / samples as f64,
+                min,
+                max,
+                samples,
+            }))
+            .collect();
+        Ok((bucket, buckets))
+    }
+
+    fn get_metric_names(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        // A range rather than LIKE, so the (metric_name, timestamp) keys are searched instead of scanned
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT metric_name FROM timeseries WHERE metric_name >= ?1 AND metric_name < ?2
+             UNION SELECT metric_name FROM timeseries_hourly WHERE metric_name >= ?1 AND metric_name < ?2
+             ORDER BY metric_name ASC"
+        )?;
+        let rows = stmt.query_map(params![prefix, prefix_end(prefix)], |row| row.get::<_, String>(0))?;
+        Ok(rows.flatten().collect())
+    }
+
+    fn for_each_snapshot(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(WifiSnapshot) -> anyhow::Result<()>) -> anyhow::Result<usize> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT data FROM snapshots
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+             ORDER BY timestamp ASC",
+        )?;
+        let mut rows = stmt.query(params![start, end])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            let data: String = row.get(0)?;
+            // Unreadable rows are skipped, as in get_snapshots
+            if let Ok(snapshot) = serde_json::from_str(&data) {
+                f(snapshot)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn for_each_event(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(NetworkEvent) -> anyhow::Result<()>) -> anyhow::Result<usize> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, event_type, severity, description, details FROM events
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+             ORDER BY timestamp ASC",
+        )?;
+        let mut rows = stmt.query(params![start, end])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            if let Ok(event) = read_event(row) {
+                f(event)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn for_each_timeseries_point(&self, start: Option<&str>, end: Option<&str>, f: &mut dyn FnMut(DateTime<Utc>, String, f64) -> anyhow::Result<()>) -> anyhow::Result<usize> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, metric_name, value FROM timeseries
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+             ORDER BY timestamp ASC, metric_name ASC",
+        )?;
+        let mut rows = stmt.query(params![epoch_millis(start)?, epoch_millis(end)?])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            if let Some(timestamp) = from_epoch_millis(row.get(0)?) {
+                f(timestamp, row.get(1)?, row.get(2)?)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn get_events(&self, start: Option<&str>, end: Option<&str>, severity: Option<&str>, event_type: Option<&str>, limit: Option<u32>, offset: u64) -> anyhow::Result<Vec<NetworkEvent>> {
         let mut query = String::from(
             "SELECT id, timestamp, event_type, severity, description, details FROM events WHERE 1=1"
         );
@@ -335,226 +1913,58 @@ impl MetricsStore {
             params_vec.push(Box::new(et.to_string()));
         }
 
-        query.push_str(" ORDER BY timestamp DESC LIMIT 1000");
+        query.push_str(" ORDER BY timestamp DESC");
+        query.push_str(&limit_clause(limit, offset));
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn()?;
         let mut stmt = conn.prepare(&query)?;
-        let rows = stmt.query_map(params_refs.as_slice(), |row| {
-            let event_type_str: String = row.get(2)?;
-            let severity_str: String = row.get(3)?;
-            let details_str: String = row.get(5)?;
-
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                event_type_str,
-                severity_str,
-                row.get::<_, String>(4)?,
-                details_str,
-            ))
-        })?;
-
-        let mut events = Vec::new();
-        for row in rows {
-            if let Ok((id, timestamp, event_type_str, severity_str, description, details_str)) = row {
-                let event_type = parse_event_type(&event_type_str);
-                let severity = parse_severity(&severity_str);
-                let details: serde_json::Value = serde_json::from_str(&details_str).unwrap_or(serde_json::Value::Null);
-                let timestamp = DateTime::parse_from_rfc3339(&timestamp)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now());
-
-                events.push(NetworkEvent {
-                    id,
-                    timestamp,
-                    event_type,
-                    severity,
-                    description,
-                    details,
-                });
-            }
-        }
-
-        Ok(events)
+        let rows = stmt.query_map(params_refs.as_slice(), read_event)?;
+        Ok(rows.flatten().collect())
     }
 
-    pub fn get_statistics(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<PeriodStatistics> {
-        let snapshots = self.get_snapshots(start, end, None)?;
-        
-        if snapshots.is_empty() {
-            return Ok(PeriodStatistics {
-                start_time: Utc::now(),
-                end_time: Utc::now(),
-                sample_count: 0,
-                signal_strength_avg_dbm: None,
-                signal_strength_min_dbm: None,
-                signal_strength_max_dbm: None,
-                signal_quality_avg_percent: None,
-                latency_avg_ms: None,
-                latency_min_ms: None,
-                latency_max_ms: None,
-                latency_p95_ms: None,
-                latency_p99_ms: None,
-                jitter_avg_ms: None,
-                packet_loss_avg_percent: 0.0,
-                connection_uptime_percent: 0.0,
-                internet_uptime_percent: 0.0,
-                total_disconnections: 0,
-                warning_events: 0,
-                error_events: 0,
-                critical_events: 0,
-            });
-        }
-
-        let mut signal_values: Vec<i32> = Vec::new();
-        let mut quality_values: Vec<u8> = Vec::new();
-        let mut latency_values: Vec<f64> = Vec::new();
-        let mut jitter_values: Vec<f64> = Vec::new();
-        let mut packet_loss_values: Vec<f64> = Vec::new();
-        let mut connected_count = 0u32;
-        let mut internet_count = 0u32;
-        let mut disconnections = 0u32;
-        let mut warning_events = 0u32;
-        let mut error_events = 0u32;
-        let mut critical_events = 0u32;
-        let mut was_connected = true;
-
-        for snapshot in &snapshots {
-            if let Some(ref wifi) = snapshot.wifi_info {
-                signal_values.push(wifi.signal_strength_dbm);
-                quality_values.push(wifi.signal_quality_percent);
-                connected_count += 1;
-                
-                if !was_connected {
-                    // Was disconnected, now connected - this is a reconnection after disconnection
-                }
-                was_connected = true;
-            } else {
-                if was_connected {
-                    disconnections += 1;
-                }
-                was_connected = false;
-            }
+    fn count_events(&self, start: Option<&str>, end: Option<&str>, severity: Option<&str>, event_type: Option<&str>) -> anyhow::Result<u64> {
+        let conn = self.conn()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM events
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)
+               AND (?3 IS NULL OR severity = ?3) AND (?4 IS NULL OR event_type = ?4)",
+            params![start, end, severity, event_type],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
 
-            if snapshot.connectivity.internet_reachable {
-                internet_count += 1;
-            }
+    fn get_incident_events(&self, incident_id: &str) -> anyhow::Result<Vec<NetworkEvent>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, event_type, severity, description, details FROM events
+             WHERE json_extract(details, '$.incident_id') = ?1 ORDER BY timestamp ASC"
+        )?;
+        let rows = stmt.query_map(params![incident_id], read_event)?;
+        Ok(rows.flatten().collect())
+    }
 
-            if let Some(avg) = snapshot.latency.average_latency_ms {
-                latency_values.push(avg);
-            }
-            if let Some(jitter) = snapshot.latency.jitter_ms {
-                jitter_values.push(jitter);
-            }
-            packet_loss_values.push(snapshot.latency.packet_loss_percent);
-
-            for event in &snapshot.events {
-                match event.severity {
-                    EventSeverity::Warning => warning_events += 1,
-                    EventSeverity::Error => error_events += 1,
-                    EventSeverity::Critical => critical_events += 1,
-                    _ => {}
+    /// Long ranges use hourly and daily rollups for the whole hours they cover, and
+    /// snapshots only for the rest
+    fn get_statistics(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<PeriodStatistics> {
+        let stats = match self.rollup_window(start, end)? {
+            Some((from, to)) => {
+                let mut stats = self.summarize_snapshots(start, None, Some(from), true)?;
+                for rollup in self.get_rollups(from, to)? {
+                    stats.merge(&rollup);
                 }
+                let tail = self.summarize_snapshots(Some(&to.to_rfc3339()), end, None, stats.ended_connected)?;
+                stats.merge(&tail);
+                stats
             }
-        }
-
-        let sample_count = snapshots.len() as u32;
-
-        // Calculate statistics
-        let signal_strength_avg_dbm = if !signal_values.is_empty() {
-            Some(signal_values.iter().map(|&v| v as f64).sum::<f64>() / signal_values.len() as f64)
-        } else {
-            None
-        };
-
-        let signal_strength_min_dbm = signal_values.iter().min().cloned();
-        let signal_strength_max_dbm = signal_values.iter().max().cloned();
-
-        let signal_quality_avg_percent = if !quality_values.is_empty() {
-            Some(quality_values.iter().map(|&v| v as f64).sum::<f64>() / quality_values.len() as f64)
-        } else {
-            None
-        };
-
-        latency_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let latency_avg_ms = if !latency_values.is_empty() {
-            Some(latency_values.iter().sum::<f64>() / latency_values.len() as f64)
-        } else {
-            None
-        };
-        let latency_min_ms = latency_values.first().cloned();
-        let latency_max_ms = latency_values.last().cloned();
-        let latency_p95_ms = if !latency_values.is_empty() {
-            let idx = (latency_values.len() as f64 * 0.95) as usize;
-            latency_values.get(idx.min(latency_values.len() - 1)).cloned()
-        } else {
-            None
-        };
-        let latency_p99_ms = if !latency_values.is_empty() {
-            let idx = (latency_values.len() as f64 * 0.99) as usize;
-            latency_values.get(idx.min(latency_values.len() - 1)).cloned()
-        } else {
-            None
-        };
-
-        let jitter_avg_ms = if !jitter_values.is_empty() {
-            Some(jitter_values.iter().sum::<f64>() / jitter_values.len() as f64)
-        } else {
-            None
-        };
-
-        let packet_loss_avg_percent = if !packet_loss_values.is_empty() {
-            packet_loss_values.iter().sum::<f64>() / packet_loss_values.len() as f64
-        } else {
-            0.0
+            None => self.summarize_snapshots(start, end, None, true)?,
         };
-
-        let connection_uptime_percent = (connected_count as f64 / sample_count as f64) * 100.0;
-        let internet_uptime_percent = (internet_count as f64 / sample_count as f64) * 100.0;
-
-        Ok(PeriodStatistics {
-            start_time: snapshots.last().map(|s| s.timestamp).unwrap_or_else(Utc::now),
-            end_time: snapshots.first().map(|s| s.timestamp).unwrap_or_else(Utc::now),
-            sample_count,
-            signal_strength_avg_dbm,
-            signal_strength_min_dbm,
-            signal_strength_max_dbm,
-            signal_quality_avg_percent,
-            latency_avg_ms,
-            latency_min_ms,
-            latency_max_ms,
-            latency_p95_ms,
-            latency_p99_ms,
-            jitter_avg_ms,
-            packet_loss_avg_percent,
-            connection_uptime_percent,
-            internet_uptime_percent,
-            total_disconnections: disconnections,
-            warning_events,
-            error_events,
-            critical_events,
-        })
-    }
-
-    pub fn export_json(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<String> {
-        let snapshots = self.get_snapshots(start, end, None)?;
-        let events = self.get_events(start, end, None, None)?;
-        let stats = self.get_statistics(start, end)?;
-
-        let export = serde_json::json!({
-            "exported_at": Utc::now().to_rfc3339(),
-            "statistics": stats,
-            "events": events,
-            "snapshots": snapshots,
-        });
-
-        Ok(serde_json::to_string_pretty(&export)?)
+        with_paused_time(self, stats)
     }
 
-    pub fn get_event_counts_by_type(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, i64)>> {
+    fn get_event_counts_by_type(&self, start: Option<&str>, end: Option<&str>) -> anyhow::Result<Vec<(String, i64)>> {
         let mut query = String::from(
             "SELECT event_type, COUNT(*) as count FROM events WHERE 1=1"
         );
@@ -573,7 +1983,7 @@ impl MetricsStore {
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn()?;
         let mut stmt = conn.prepare(&query)?;
         let rows = stmt.query_map(params_refs.as_slice(), |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
@@ -590,7 +2000,472 @@ impl MetricsStore {
     }
 }
 
-fn parse_event_type(s: &str) -> EventType {
+/// Connect to a Postgres database given as a connection string, creating its tables if needed
+#[cfg(feature = "postgres")]
+pub fn connect_postgres(url: &str, omitted_details: Vec<SnapshotDetail>, include_other_networks: bool) -> anyhow::Result<Box<dyn MetricsStore>> {
+    let store = crate::postgres::PostgresStore::connect(url)?
+        .with_omitted_details(omitted_details)
+        .with_other_networks_included(include_other_networks);
+    Ok(Box::new(store))
+}
+
+#[cfg(not(feature = "postgres"))]
+pub fn connect_postgres(_url: &str, _omitted_details: Vec<SnapshotDetail>, _include_other_networks: bool) -> anyhow::Result<Box<dyn MetricsStore>> {
+    anyhow::bail!("built without the `postgres` feature; rebuild with `cargo build --release --features postgres`")
+}
+
+/// Open a store for viewing: the Postgres database at `url` if one is given, otherwise
+/// the SQLite file (see `SqliteStore::open_compatible`)
+pub fn open_compatible(database: &Path, url: Option<&str>, include_other_networks: bool) -> anyhow::Result<Box<dyn MetricsStore>> {
+    match url {
+        Some(url) => connect_postgres(url, Vec::new(), include_other_networks),
+        None => Ok(Box::new(SqliteStore::open_compatible(database)?.with_other_networks_included(include_other_networks))),
+    }
+}
+
+/// Size of a SQLite database on disk, with its write-ahead log
+pub fn file_size(database: &Path) -> u64 {
+    let mut wal = database.as_os_str().to_owned();
+    wal.push("-wal");
+    [database.as_os_str(), &wal].iter().filter_map(|path| std::fs::metadata(path).ok()).map(|m| m.len()).sum()
+}
+
+/// Every minute until shutdown, drop the oldest raw data once the database outgrows `max_bytes`
+pub async fn cap_size(store: Arc<dyn MetricsStore>, max_bytes: u64, shutdown: ShutdownHandle) {
+    info!("Keeping the database under {} MB, dropping the oldest raw data first", max_bytes / MB);
+    let mut interval = tokio::time::interval(SIZE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait() => break,
+        }
+
+        let task_store = store.clone();
+        match tokio::task::spawn_blocking(move || task_store.trim_to_size(max_bytes)).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(removed)) => info!("Removed {} rows of the oldest data to stay under {} MB", removed, max_bytes / MB),
+            Ok(Err(e)) => warn!("Failed to trim the database: {}", e),
+            Err(e) => warn!("Trimming task failed: {}", e),
+        }
+    }
+}
+
+/// Every hour until shutdown, roll up completed hours and days and, with a
+/// `retention`, prune older data and vacuum at most once a day after something was pruned
+pub async fn maintain(store: Arc<dyn MetricsStore>, retention: Option<TimeDelta>, shutdown: ShutdownHandle) {
+    if let Some(max_age) = retention {
+        info!("Keeping the last {} day(s) of data", max_age.num_days());
+    }
+    let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+    let mut last_vacuum = Instant::now();
+    let mut pruned_since_vacuum = 0;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait() => break,
+        }
+
+        let task_store = store.clone();
+        match tokio::task::spawn_blocking(move || task_store.roll_up()).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(hours)) => debug!("Rolled up {} hour(s) of statistics", hours),
+            Ok(Err(e)) => warn!("Failed to roll up statistics: {}", e),
+            Err(e) => warn!("Rollup task failed: {}", e),
+        }
+
+        let Some(max_age) = retention else { continue };
+        let task_store = store.clone();
+        let cutoff = Utc::now() - max_age;
+        match tokio::task::spawn_blocking(move || task_store.prune_before(cutoff)).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(removed)) => {
+                info!("Pruned {} rows older than {}", removed, cutoff.format("%Y-%m-%d %H:%M UTC"));
+                pruned_since_vacuum += removed;
+            }
+            Ok(Err(e)) => warn!("Failed to prune old data: {}", e),
+            Err(e) => warn!("Pruning task failed: {}", e),
+        }
+
+        if pruned_since_vacuum > 0 && last_vacuum.elapsed() >= VACUUM_INTERVAL {
+            let task_store = store.clone();
+            let started = Instant::now();
+            match tokio::task::spawn_blocking(move || task_store.vacuum()).await {
+                Ok(Ok(())) => info!("Vacuumed the database in {:.1}s", started.elapsed().as_secs_f64()),
+                Ok(Err(e)) => warn!("Failed to vacuum the database: {}", e),
+                Err(e) => warn!("Vacuum task failed: {}", e),
+            }
+            last_vacuum = Instant::now();
+            pruned_since_vacuum = 0;
+        }
+    }
+}
+
+/// Final statistics, with how long monitoring was paused during the summarized period
+pub(crate) fn with_paused_time(store: &dyn MetricsStore, stats: StatsRollup) -> anyhow::Result<PeriodStatistics> {
+    if stats.samples == 0 {
+        return Ok(stats.into_statistics(0));
+    }
+
+    // Snapshots aren't taken while paused, so uptime already leaves pauses out;
+    // report how long they were, clipped to the period
+    let start_time = stats.first.unwrap_or_else(Utc::now);
+    let end_time = stats.last.unwrap_or_else(Utc::now);
+    let paused_secs = store.get_pauses(Some(&start_time.to_rfc3339()), Some(&end_time.to_rfc3339()))?
+        .iter()
+        .map(|p| {
+            let from = p.started_at.max(start_time);
+            let to = p.ended_at.unwrap_or(end_time).min(end_time);
+            (to - from).num_seconds().max(0)
+        })
+        .sum();
+    Ok(stats.into_statistics(paused_secs))
+}
+
+/// The chartable metrics of a snapshot, as stored in the `timeseries` table
+pub(crate) fn timeseries_points(snapshot: &WifiSnapshot) -> Vec<(Cow<'static, str>, f64)> {
+    let mut points: Vec<(Cow<'static, str>, f64)> = fixed_timeseries_points(snapshot)
+        .into_iter()
+        .map(|(metric, value)| (Cow::Borrowed(metric), value))
+        .collect();
+    // Each ping target apart, to tell one bad target from a bad link
+    for target in &snapshot.latency.targets {
+        if let Some(avg) = target.avg_ms {
+            points.push((format!("{}{}", TARGET_LATENCY_PREFIX, target.target).into(), avg));
+        }
+        if target.packets_sent > 0 {
+            points.push((format!("{}{}", TARGET_LOSS_PREFIX, target.target).into(), target.packet_loss_percent));
+        }
+        if let Some(stddev) = target.stddev_ms {
+            points.push((format!("{}{}", TARGET_JITTER_PREFIX, target.target).into(), stddev));
+        }
+    }
+    points
+}
+
+/// The metrics every snapshot may have, under fixed names
+fn fixed_timeseries_points(snapshot: &WifiSnapshot) -> Vec<(&'static str, f64)> {
+    let flag = |on: bool| if on { 1.0 } else { 0.0 };
+    let mut points = Vec::new();
+    if let Some(ref wifi) = snapshot.wifi_info {
+        points.push(("signal_dbm", wifi.signal_strength_dbm as f64));
+        points.push(("signal_percent", wifi.signal_quality_percent as f64));
+        points.push(("channel", wifi.channel as f64));
+        points.push(("link_speed", wifi.link_speed_mbps as f64));
+    }
+
+    let latency = &snapshot.latency;
+    points.extend([
+        ("latency_loopback", latency.loopback_latency_ms),
+        ("latency_router", latency.router_latency_ms),
+        ("latency_avg", latency.average_latency_ms),
+        ("latency_min", latency.min_latency_ms),
+        ("latency_max", latency.max_latency_ms),
+        ("jitter", latency.jitter_ms),
+        ("jitter_rfc3550", latency.rfc3550_jitter_ms),
+        ("mos", latency.mos),
+    ].into_iter().filter_map(|(metric, value)| Some((metric, value?))));
+    points.push(("packet_loss", latency.packet_loss_percent));
+
+    if let Some(ref wired) = snapshot.wired {
+        if let Some(avg) = wired.average_latency_ms {
+            points.push(("wired_latency_avg", avg));
+        }
+        points.push(("wired_packet_loss", wired.packet_loss_percent));
+    }
+    if let Some(guest) = snapshot.guest.as_ref().filter(|g| g.connected) {
+        if let Some(avg) = guest.average_latency_ms {
+            points.push(("guest_latency_avg", avg));
+        }
+        points.push(("guest_packet_loss", guest.packet_loss_percent));
+    }
+
+    // ASN as a number, so a failover to a backup ISP shows as a step in the chart
+    if let Some(asn) = snapshot.public_ip.as_ref().and_then(|p| p.asn) {
+        points.push(("public_ip_asn", asn as f64));
+    }
+
+    let connectivity = &snapshot.connectivity;
+    points.push(("connected", flag(connectivity.is_connected)));
+    points.push(("loopback_reachable", flag(connectivity.loopback_reachable)));
+    points.push(("router_reachable", flag(connectivity.router_reachable)));
+    points.push(("internet_reachable", flag(connectivity.internet_reachable)));
+    if let Some(http_time) = connectivity.http_response_time_ms {
+        points.push(("http_response_time", http_time as f64));
+    }
+    // Categorical: values are `HttpOutcome::code()` / HTTP status codes
+    if let Some(outcome) = connectivity.http_outcome {
+        points.push(("http_outcome", outcome.code() as f64));
+    }
+    if let Some(status) = connectivity.http_status {
+        points.push(("http_status", status as f64));
+    }
+    if let Some(outcome) = connectivity.https_outcome {
+        points.push(("https_outcome", outcome.code() as f64));
+    }
+
+    if let Some(dns_time) = snapshot.dns_metrics.average_resolution_time_ms {
+        points.push(("dns_resolution_time", dns_time));
+    }
+    if let Some(interval) = snapshot.interval_secs {
+        points.push(("effective_interval", interval as f64));
+    }
+    points.push(("cycle_duration", snapshot.probe_durations.total_ms as f64));
+
+    let system = &snapshot.system_info;
+    points.extend([
+        ("throughput_rx", system.throughput_rx_bytes_per_sec),
+        ("throughput_tx", system.throughput_tx_bytes_per_sec),
+        ("tcp_retransmit_percent", system.tcp_retransmit_percent),
+        ("tcp_retransmits_per_sec", system.tcp_retransmits_per_sec),
+    ].into_iter().filter_map(|(metric, value)| Some((metric, value?))));
+    points.push(("cpu_usage", system.cpu_usage_percent as f64));
+    points.push(("memory_usage", system.memory_usage_percent as f64));
+
+    // With effective_interval, what statistics need to be computed without the snapshot JSON
+    points.push(("reduced_sampling", flag(snapshot.sampling.reduced)));
+    points.push(("other_network", flag(snapshot.other_network)));
+    if snapshot.maintenance {
+        points.push(("maintenance", 1.0));
+    }
+    points
+}
+
+/// A copy of the snapshot without the omitted details
+pub(crate) fn trimmed(snapshot: &WifiSnapshot, omitted: &[SnapshotDetail]) -> WifiSnapshot {
+    let mut snapshot = snapshot.clone();
+    for detail in omitted {
+        match detail {
+            SnapshotDetail::PingTimes => {
+                let wired = snapshot.wired.iter_mut().flat_map(|w| w.targets.iter_mut());
+                let guest = snapshot.guest.iter_mut().flat_map(|g| g.targets.iter_mut());
+                for target in snapshot.latency.targets.iter_mut().chain(wired).chain(guest) {
+                    target.individual_times_ms = Vec::new();
+                }
+            }
+            SnapshotDetail::DnsQueries => snapshot.dns_metrics.queries = Vec::new(),
+        }
+    }
+    snapshot
+}
+
+/// Insert a snapshot row with its events and timeseries points
+fn insert_snapshot(tx: &rusqlite::Transaction, snapshot: &WifiSnapshot, data: &str, session_id: Option<&str>) -> anyhow::Result<()> {
+    // Save main snapshot
+    tx.prepare_cached("INSERT INTO snapshots (id, timestamp, data, session_id) VALUES (?1, ?2, ?3, ?4)")?.execute(
+        params![
+            snapshot.id,
+            snapshot.timestamp.to_rfc3339(),
+            data,
+            session_id
+        ],
+    )?;
+
+    // Save events
+    for event in &snapshot.events {
+        let details = serde_json::to_string(&event.details)?;
+        tx.prepare_cached(
+            "INSERT INTO events (id, snapshot_id, timestamp, event_type, severity, description, details)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?.execute(
+            params![
+                event.id,
+                snapshot.id,
+                event.timestamp.to_rfc3339(),
+                format!("{:?}", event.event_type),
+                format!("{:?}", event.severity),
+                event.description,
+                details
+            ],
+        )?;
+    }
+
+    // Save time series data in one statement; `?1` is the shared timestamp
+    let points = timeseries_points(snapshot);
+    if points.is_empty() {
+        return Ok(());
+    }
+    let rows = (0..points.len()).map(|i| format!("(?1, ?{}, ?{})", 2 * i + 2, 2 * i + 3)).collect::<Vec<_>>().join(", ");
+    let mut values: Vec<&dyn rusqlite::ToSql> = Vec::with_capacity(1 + 2 * points.len());
+    let timestamp = snapshot.timestamp.timestamp_millis();
+    values.push(&timestamp);
+    for (metric, value) in &points {
+        values.push(metric);
+        values.push(value);
+    }
+    tx.prepare_cached(&format!("INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value) VALUES {rows}"))?
+        .execute(values.as_slice())?;
+    Ok(())
+}
+
+pub(crate) fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// A range bound as the epoch milliseconds timeseries points are stamped with
+fn epoch_millis(bound: Option<&str>) -> anyhow::Result<Option<i64>> {
+    bound
+        .map(|b| parse_time(b).map(|t| t.timestamp_millis()).ok_or_else(|| anyhow::anyhow!("Invalid timestamp {:?}, expected RFC 3339", b)))
+        .transpose()
+}
+
+/// `LIMIT`/`OFFSET` for one page of a listing; SQLite only takes an offset after a limit,
+/// and -1 means none
+fn limit_clause(limit: Option<u32>, offset: u64) -> String {
+    match (limit, offset) {
+        (None, 0) => String::new(),
+        (limit, offset) => format!(" LIMIT {} OFFSET {}", limit.map_or(-1, i64::from), offset),
+    }
+}
+
+/// The first string after every one starting with `prefix`
+fn prefix_end(prefix: &str) -> String {
+    let mut end = prefix.to_string();
+    match end.pop() {
+        Some(last) => {
+            end.push(char::from_u32(last as u32 + 1).unwrap_or(char::MAX));
+            end
+        }
+        None => char::MAX.to_string(),
+    }
+}
+
+/// A timeseries point's timestamp
+fn from_epoch_millis(millis: i64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(millis)
+}
+
+fn read_session(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    let started_at: String = row.get(1)?;
+    let ended_at: Option<String> = row.get(2)?;
+    let config: String = row.get(4)?;
+    Ok(Session {
+        id: row.get(0)?,
+        started_at: parse_time(&started_at).unwrap_or_default(),
+        ended_at: ended_at.as_deref().and_then(parse_time),
+        host: row.get(3)?,
+        config: serde_json::from_str(&config).unwrap_or_default(),
+    })
+}
+
+/// An event from a `SELECT id, timestamp, event_type, severity, description, details` row
+fn read_event(row: &rusqlite::Row) -> rusqlite::Result<NetworkEvent> {
+    let timestamp: String = row.get(1)?;
+    let details_str: String = row.get(5)?;
+    Ok(NetworkEvent {
+        id: row.get(0)?,
+        timestamp: DateTime::parse_from_rfc3339(&timestamp)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        event_type: parse_event_type(&row.get::<_, String>(2)?),
+        severity: parse_severity(&row.get::<_, String>(3)?),
+        description: row.get(4)?,
+        details: serde_json::from_str(&details_str).unwrap_or(serde_json::Value::Null),
+    })
+}
+
+pub(crate) fn read_incident(data: &str, notes: &str) -> Option<Incident> {
+    let mut incident: Incident = serde_json::from_str(data).ok()?;
+    incident.notes = serde_json::from_str(notes).unwrap_or_default();
+    Some(incident)
+}
+
+fn pool(path: &Path, flags: OpenFlags, size: u32) -> anyhow::Result<Pool<ConnectionManager>> {
+    Ok(Pool::builder()
+        .max_size(size)
+        .build(ConnectionManager { path: path.to_path_buf(), flags })?)
+}
+
+/// Create all tables in a new database and stamp it with the current schema version
+fn initialize_schema(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(SCHEMA_SQL)?;
+    conn.execute_batch(LATE_INDEX_SQL)?;
+    record_schema_version(conn, SCHEMA_VERSION)?;
+    Ok(())
+}
+
+/// `None` for an empty database, `Some(0)` for one written before versioning existed
+fn stored_schema_version(conn: &Connection) -> anyhow::Result<Option<i64>> {
+    let table_exists = |name: &str| -> rusqlite::Result<bool> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |row| row.get::<_, i64>(0),
+        ).map(|count| count > 0)
+    };
+
+    if table_exists("schema_version")? {
+        let version: Option<i64> = conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))?;
+        return Ok(Some(version.unwrap_or(0)));
+    }
+    Ok(table_exists("snapshots")?.then_some(0))
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> anyhow::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt.query_map([], |row| row.get::<_, String>(1))?.flatten().any(|name| name == column);
+    Ok(exists)
+}
+
+fn record_schema_version(conn: &Connection, version: i64) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO schema_version (version, app_version, applied_at) VALUES (?1, ?2, ?3)",
+        params![version, env!("CARGO_PKG_VERSION"), Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Bring a database from `version - 1` up to `version`
+fn apply_migration(conn: &Connection, version: i64) -> anyhow::Result<()> {
+    match version {
+        // v1: first versioned layout; pre-versioning databases only lack newer tables
+        1 => conn.execute_batch(SCHEMA_SQL)?,
+        // v2: incidents table
+        2 => conn.execute_batch(SCHEMA_SQL)?,
+        // v3: pauses table
+        3 => conn.execute_batch(SCHEMA_SQL)?,
+        // v4: alert_state table
+        4 => conn.execute_batch(SCHEMA_SQL)?,
+        // v5: rollup tables; the old hourly_stats layout was never written to
+        5 => {
+            conn.execute_batch("DROP TABLE IF EXISTS hourly_stats;")?;
+            conn.execute_batch(SCHEMA_SQL)?;
+        }
+        // v6: sessions table, and the session each snapshot was taken in (none for older ones)
+        6 => {
+            if !column_exists(conn, "snapshots", "session_id")? {
+                conn.execute_batch("ALTER TABLE snapshots ADD COLUMN session_id TEXT;")?;
+            }
+            conn.execute_batch(SCHEMA_SQL)?;
+            conn.execute_batch(LATE_INDEX_SQL)?;
+        }
+        // v7: timeseries stamped in epoch milliseconds and keyed by metric first. Stored
+        // timestamps are UTC RFC 3339 with 0, 3, 6 or 9 fractional digits.
+        7 => {
+            conn.execute_batch(
+                "DROP INDEX IF EXISTS idx_timeseries_metric;
+                 DROP INDEX IF EXISTS idx_timeseries_timestamp;
+                 ALTER TABLE timeseries RENAME TO timeseries_v6;",
+            )?;
+            conn.execute_batch(SCHEMA_SQL)?;
+            conn.execute_batch(
+                "INSERT OR REPLACE INTO timeseries (timestamp, metric_name, value)
+                 SELECT CAST(strftime('%s', substr(timestamp, 1, 19)) AS INTEGER) * 1000
+                        + CASE WHEN substr(timestamp, 20, 1) = '.' THEN CAST(substr(timestamp, 21, 3) AS INTEGER) ELSE 0 END,
+                        metric_name, value
+                 FROM timeseries_v6;
+                 DROP TABLE timeseries_v6;",
+            )?;
+        }
+        // v8: annotations table
+        8 => conn.execute_batch(SCHEMA_SQL)?,
+        // v9: alerts table
+        9 => conn.execute_batch(SCHEMA_SQL)?,
+        _ => anyhow::bail!("No migration defined for schema v{}", version),
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_event_type(s: &str) -> EventType {
     match s {
         "ConnectionDropped" => EventType::ConnectionDropped,
         "ConnectionRestored" => EventType::ConnectionRestored,
@@ -599,23 +2474,34 @@ fn parse_event_type(s: &str) -> EventType {
         "HighLatency" => EventType::HighLatency,
         "LatencyNormalized" => EventType::LatencyNormalized,
         "PacketLoss" => EventType::PacketLoss,
+        "PacketLossRecovered" => EventType::PacketLossRecovered,
         "DnsFailure" => EventType::DnsFailure,
         "DnsRecovered" => EventType::DnsRecovered,
         "BandSwitch" => EventType::BandSwitch,
         "ChannelChange" => EventType::ChannelChange,
         "BssidChange" => EventType::BssidChange,
         "IpAddressChange" => EventType::IpAddressChange,
+        "DefaultRouteChange" => EventType::DefaultRouteChange,
         "GatewayUnreachable" => EventType::GatewayUnreachable,
         "InternetUnreachable" => EventType::InternetUnreachable,
         "HighJitter" => EventType::HighJitter,
+        "JitterNormalized" => EventType::JitterNormalized,
+        "TcpRetransmissions" => EventType::TcpRetransmissions,
+        "TcpRetransmissionsNormalized" => EventType::TcpRetransmissionsNormalized,
         "AdapterReset" => EventType::AdapterReset,
         "SpeedDegraded" => EventType::SpeedDegraded,
         "SpeedRecovered" => EventType::SpeedRecovered,
+        "WlanDisconnectReason" => EventType::WlanDisconnectReason,
+        "RouterLog" => EventType::RouterLog,
+        "ProbeTimedOut" => EventType::ProbeTimedOut,
+        "PublicIpChange" => EventType::PublicIpChange,
+        "DiagnosticsRun" => EventType::DiagnosticsRun,
+        "TestAlert" => EventType::TestAlert,
         _ => EventType::ConnectionDropped,
     }
 }
 
-fn parse_severity(s: &str) -> EventSeverity {
+pub(crate) fn parse_severity(s: &str) -> EventSeverity {
     match s {
         "Info" => EventSeverity::Info,
         "Warning" => EventSeverity::Warning,
@@ -0,0 +1,227 @@
+use crate::metrics::*;
+use crate::monitor::MonitorHandle;
+use std::net::{IpAddr, SocketAddr};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tracing::{debug, info, warn};
+
+/// Largest syslog datagram we accept (RFC 5426 recommends supporting 2048+)
+const MAX_DATAGRAM: usize = 8192;
+/// Syslog severity 7 (debug) is too chatty to record
+const MAX_RECORDED_SEVERITY: u8 = 6;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// One parsed syslog line (RFC 3164 or RFC 5424)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: u8,
+    pub host: Option<String>,
+    pub app: Option<String>,
+    pub message: String,
+}
+
+/// Listen for router syslog on UDP and TCP at the same address, recording each
+/// entry as a `RouterLog` event with the next snapshot
+pub async fn run_listener(addr: SocketAddr, monitor: MonitorHandle) -> anyhow::Result<()> {
+    let udp = UdpSocket::bind(addr).await?;
+    let tcp = TcpListener::bind(addr).await?;
+    info!("Syslog listener on {} (UDP and TCP)", addr);
+
+    let udp_monitor = monitor.clone();
+    let receive_udp = async move {
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            match udp.recv_from(&mut buf).await {
+                Ok((len, from)) => {
+                    // Some routers batch several lines into one datagram
+                    for line in String::from_utf8_lossy(&buf[..len]).lines() {
+                        record(&udp_monitor, line, from.ip());
+                    }
+                }
+                Err(e) => debug!("Syslog receive error: {}", e),
+            }
+        }
+    };
+
+    let accept_tcp = async move {
+        loop {
+            match tcp.accept().await {
+                Ok((stream, from)) => {
+                    tokio::spawn(read_stream(stream, from, monitor.clone()));
+                }
+                Err(e) => warn!("Syslog accept error: {}", e),
+            }
+        }
+    };
+
+    tokio::join!(receive_udp, accept_tcp);
+    Ok(())
+}
+
+/// Newline-delimited syslog over TCP, with or without RFC 6587 octet-count prefixes
+async fn read_stream(stream: TcpStream, from: SocketAddr, monitor: MonitorHandle) {
+    debug!("Syslog TCP connection from {}", from);
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                record(&monitor, &line, from.ip());
+            }
+            Ok(None) => break,
+            Err(e) => {
+                debug!("Syslog TCP connection from {} closed: {}", from, e);
+                break;
+            }
+        }
+    }
+}
+
+/// Parse one line and queue it as an event; returns whether it was recorded
+pub fn record(monitor: &MonitorHandle, line: &str, from: IpAddr) -> bool {
+    let Some(message) = parse_syslog(line) else {
+        return false;
+    };
+    if message.severity > MAX_RECORDED_SEVERITY {
+        return false;
+    }
+    if let Err(e) = monitor.inject_event(syslog_event(&message, from)) {
+        debug!("Dropping router syslog entry: {}", e);
+        return false;
+    }
+    true
+}
+
+/// Parse an RFC 5424 or RFC 3164 line; text without a `<PRI>` header is taken as a
+/// notice-level message
+pub fn parse_syslog(line: &str) -> Option<SyslogMessage> {
+    let line = strip_octet_count(line.trim());
+    if line.is_empty() {
+        return None;
+    }
+
+    let (pri, rest) = match line.strip_prefix('<').and_then(|l| l.split_once('>')) {
+        Some((pri, rest)) => (pri.parse::<u8>().ok()?, rest),
+        None => (13, line), // user.notice, the RFC 3164 default
+    };
+    let mut message = SyslogMessage {
+        facility: pri / 8,
+        severity: pri % 8,
+        host: None,
+        app: None,
+        message: String::new(),
+    };
+
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        // RFC 5424: TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+        let mut fields = rest.splitn(6, ' ');
+        let _timestamp = fields.next();
+        message.host = fields.next().filter(|h| *h != "-").map(str::to_string);
+        message.app = fields.next().filter(|a| *a != "-").map(str::to_string);
+        let _procid = fields.next();
+        let _msgid = fields.next();
+        message.message = skip_structured_data(fields.next().unwrap_or_default()).to_string();
+    } else {
+        // RFC 3164: [Mmm dd hh:mm:ss HOST] TAG[PID]: MSG. The hostname is only
+        // trusted after a timestamp, otherwise "Radar detected: ..." would parse as one.
+        let mut rest = rest;
+        let mut max_head_words = 1;
+        if MONTHS.iter().any(|m| rest.starts_with(m)) {
+            if let Some(after) = rest.get(15..) {
+                rest = after.trim_start();
+                max_head_words = 2;
+            }
+        }
+        let head = rest.find(':').map(|i| &rest[..i]).unwrap_or_default();
+        if !head.is_empty() && head.split_whitespace().count() <= max_head_words {
+            let mut parts = head.split_whitespace().rev();
+            message.app = parts.next().map(|tag| tag.split('[').next().unwrap_or(tag).to_string());
+            message.host = parts.next().map(str::to_string);
+            rest = rest[head.len() + 1..].trim_start();
+        }
+        message.message = rest.to_string();
+    }
+
+    Some(message)
+}
+
+fn strip_octet_count(line: &str) -> &str {
+    match line.split_once(' ') {
+        Some((count, rest)) if count.bytes().all(|b| b.is_ascii_digit()) && rest.starts_with('<') => rest,
+        _ => line,
+    }
+}
+
+fn skip_structured_data(rest: &str) -> &str {
+    if let Some(msg) = rest.strip_prefix("- ") {
+        return msg;
+    }
+    if rest == "-" || !rest.starts_with('[') {
+        return rest.trim_start_matches('-').trim_start();
+    }
+    // Walk past [id k="v"]... elements, honouring escaped brackets in values
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 && !rest[i + 1..].starts_with('[') {
+                    return rest[i + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// What a router log line is about, from keywords common across vendors
+fn classify(text: &str) -> (&'static str, Option<EventSeverity>) {
+    let text = text.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| text.contains(w));
+
+    if has(&["radar", "dfs"]) {
+        ("dfs_radar", Some(EventSeverity::Warning))
+    } else if has(&["wan link down", "wan down", "wan disconnected", "pppoe down", "ppp down", "lost carrier"]) {
+        ("wan_down", Some(EventSeverity::Error))
+    } else if has(&["deauth", "disassoc"]) {
+        ("client_disconnect", None)
+    } else if has(&["dhcp"]) {
+        ("dhcp", None)
+    } else if has(&["reboot", "restart", "firmware"]) {
+        ("router_restart", Some(EventSeverity::Warning))
+    } else {
+        ("other", None)
+    }
+}
+
+fn syslog_event(message: &SyslogMessage, from: IpAddr) -> NetworkEvent {
+    let by_level = match message.severity {
+        0..=2 => EventSeverity::Critical,
+        3 => EventSeverity::Error,
+        4 => EventSeverity::Warning,
+        _ => EventSeverity::Info,
+    };
+    let (category, keyword_severity) = classify(&message.message);
+    let severity = keyword_severity.map_or(by_level.clone(), |s| s.max(by_level));
+
+    let source = message.host.clone().unwrap_or_else(|| from.to_string());
+    let description = match message.app {
+        Some(ref app) => format!("Router {} {}: {}", source, app, message.message),
+        None => format!("Router {}: {}", source, message.message),
+    };
+    NetworkEvent::new(EventType::RouterLog, severity, &description).with_details(serde_json::json!({
+        "source_ip": from.to_string(),
+        "host": message.host,
+        "app": message.app,
+        "facility": message.facility,
+        "syslog_severity": message.severity,
+        "category": category,
+        "message": message.message
+    }))
+}
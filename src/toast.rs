@@ -0,0 +1,107 @@
+use crate::metrics::{Alert, EventSeverity, NetworkEvent};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+const APP_NAME: &str = "Wi-Fi Stability Tracker";
+/// Toasts shown per hour at most, whatever their kind
+const MAX_PER_HOUR: usize = 12;
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// What a toast is about, for the per-kind cooldown
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Kind {
+    Event(String),
+    Alert(String),
+}
+
+/// Shows OS notifications (Windows toasts, macOS Notification Center, freedesktop
+/// notifications on Linux) for events of the enabled severities, whether or not the
+/// dashboard window is open. Each kind of event toasts at most once per cooldown, and
+/// no more than 12 toasts are shown in an hour, so a flapping link doesn't bury the desktop.
+pub struct DesktopToasts {
+    severities: Vec<EventSeverity>,
+    cooldown: Duration,
+    last_shown: HashMap<Kind, Instant>,
+    recent: Vec<Instant>,
+    /// Toasts held back since the last one shown, mentioned in the next
+    held_back: usize,
+    /// Set once showing one has failed, so the failure is logged once
+    warned: Arc<AtomicBool>,
+}
+
+impl DesktopToasts {
+    pub fn new(severities: Vec<EventSeverity>, cooldown: Duration) -> Option<Self> {
+        if severities.is_empty() {
+            return None;
+        }
+        info!("Showing desktop notifications for {:?} events", severities);
+        Some(Self {
+            severities,
+            cooldown,
+            last_shown: HashMap::new(),
+            recent: Vec::new(),
+            held_back: 0,
+            warned: Arc::default(),
+        })
+    }
+
+    pub fn wants(&self, severity: &EventSeverity) -> bool {
+        self.severities.contains(severity)
+    }
+
+    pub fn send(&mut self, event: &NetworkEvent) {
+        if self.wants(&event.severity) {
+            let title = format!("{:?}", event.event_type);
+            self.show(Kind::Event(title.clone()), &event.severity, &title, &event.description);
+        }
+    }
+
+    /// An alert rule fired; resolving doesn't toast
+    pub fn send_alert(&mut self, alert: &Alert) {
+        if alert.resolved_at.is_none() && self.wants(&alert.severity) {
+            let title = format!("Alert: {}", alert.name);
+            self.show(Kind::Alert(alert.name.clone()), &alert.severity, &title, &alert.message);
+        }
+    }
+
+    fn show(&mut self, kind: Kind, severity: &EventSeverity, title: &str, body: &str) {
+        let now = Instant::now();
+        self.recent.retain(|t| now.duration_since(*t) < HOUR);
+        let cooling = self.last_shown.get(&kind).is_some_and(|t| now.duration_since(*t) < self.cooldown);
+        if cooling || self.recent.len() >= MAX_PER_HOUR {
+            self.held_back += 1;
+            debug!("Held back a desktop notification for {:?}", kind);
+            return;
+        }
+        self.last_shown.insert(kind, now);
+        self.recent.push(now);
+
+        let mut body = body.to_string();
+        if self.held_back > 0 {
+            body.push_str(&format!(" (and {} more since the last notification)", self.held_back));
+            self.held_back = 0;
+        }
+        let mut notification = notify_rust::Notification::new();
+        notification.appname(APP_NAME).summary(&format!("{:?}: {}", severity, title)).body(&body);
+        #[cfg(all(unix, not(target_os = "macos")))]
+        notification.urgency(match severity {
+            EventSeverity::Critical => notify_rust::Urgency::Critical,
+            EventSeverity::Info => notify_rust::Urgency::Low,
+            _ => notify_rust::Urgency::Normal,
+        });
+
+        // Showing one can block on the notification service, so it's kept off the monitoring loop
+        let warned = self.warned.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = notification.show() {
+                // Typical on a headless machine, so said once
+                if !warned.swap(true, Ordering::Relaxed) {
+                    warn!("Failed to show a desktop notification: {}", e);
+                }
+            }
+        });
+    }
+}
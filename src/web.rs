@@ -1,77 +1,2282 @@
-use crate::storage::MetricsStore;
+use crate::analysis;
+use crate::assets;
+use crate::auth::{self, WebAuth};
+use crate::diagnostics;
+use crate::export::{self, ExportFormat};
+use crate::logtail::{self, LogRecord};
+use crate::metrics::{Annotation, EventSeverity, MuteWindow, NeighborNetwork, NetworkEvent, WifiSnapshot};
+use crate::monitor::{test_alert_event, MonitorHandle, PLATFORM_BACKEND};
+use crate::openapi::ApiDoc;
+use crate::prometheus;
+use crate::ratelimit::{self, RateLimit};
+use crate::selfmon;
+use crate::shutdown::ShutdownHandle;
+use crate::storage::{self, BucketSize, MetricsStore};
+use crate::syslog;
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
-    routing::get,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, FromRef, Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Response,
+    },
+    routing::{delete, get, post},
     Router,
 };
 use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{debug, info};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
-type SharedStore = Arc<MetricsStore>;
+type SharedStore = Arc<dyn MetricsStore>;
 
-pub async fn start_web_server(store: SharedStore, port: u16) -> anyhow::Result<()> {
-    let cors = CorsLayer::new()
+/// Events an event stream client may fall behind by before it starts missing some
+const EVENT_STREAM_BUFFER: usize = 64;
+
+/// Export bytes gathered before each chunk is sent to the client
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks an export download may queue before the database reads wait for the client
+const EXPORT_STREAM_BUFFER: usize = 8;
+
+/// A snapshot older than this many monitoring intervals makes `/api/v1/health` report a stalled loop
+const STALE_SNAPSHOT_INTERVALS: i64 = 3;
+
+/// Shortest snapshot age reported as stale, so one slow cycle isn't
+const MIN_STALE_SNAPSHOT_SECS: i64 = 60;
+
+/// Neighbor scans `/api/v1/neighbors` reads, newest first
+const MAX_NEIGHBOR_SCANS: u32 = 2000;
+
+/// API versions served, oldest first; the last is the current one
+const API_VERSIONS: &[&str] = &["v1"];
+
+/// Rows per page of snapshot and event listings, unless `limit` asks otherwise
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Largest `limit` a listing accepts
+pub(crate) const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Clone)]
+struct AppState {
+    store: SharedStore,
+    monitor: Option<MonitorHandle>,
+    shutdown: ShutdownHandle,
+    /// When the web server started
+    started_at: chrono::DateTime<chrono::Utc>,
+    /// Whether requests must authenticate; deleting data is refused otherwise
+    auth_enabled: bool,
+}
+
+impl FromRef<AppState> for SharedStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.store.clone()
+    }
+}
+
+/// Certificate and key to serve HTTPS with, loaded before the server starts so a bad file
+/// fails the command instead of leaving it without a dashboard
+#[derive(Clone)]
+#[cfg_attr(not(feature = "tls"), allow(dead_code))]
+pub struct Tls(#[cfg(feature = "tls")] axum_server::tls_rustls::RustlsConfig);
+
+impl Tls {
+    /// Read a PEM certificate chain and private key
+    #[cfg(feature = "tls")]
+    pub async fn load(cert: &std::path::Path, key: &std::path::Path) -> anyhow::Result<Self> {
+        let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key)
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not load the TLS certificate {:?} and key {:?}: {}", cert, key, e))?;
+        Ok(Self(config))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub async fn load(_cert: &std::path::Path, _key: &std::path::Path) -> anyhow::Result<Self> {
+        anyhow::bail!("built without the `tls` feature; rebuild with `cargo build --release --features tls`")
+    }
+}
+
+pub async fn start_web_server(
+    store: SharedStore,
+    addr: SocketAddr,
+    monitor: Option<MonitorHandle>,
+    auth: WebAuth,
+    rate_limit: RateLimit,
+    tls: Option<Tls>,
+    shutdown: ShutdownHandle,
+) -> anyhow::Result<()> {
+    if auth.is_enabled() {
+        info!("Dashboard and web API require authentication");
+    }
+    let app = dashboard_router(store, monitor, auth, shutdown.clone())
+        .layer(axum::middleware::from_fn_with_state(rate_limit, ratelimit::limit))
+        .layer(cors_layer());
+    serve(app, addr, tls, shutdown).await
+}
+
+/// The dashboard and web API over one store. Relative links keep the dashboard working
+/// when the aggregation server mounts it under a host's path.
+pub(crate) fn dashboard_router(
+    store: SharedStore,
+    monitor: Option<MonitorHandle>,
+    auth: WebAuth,
+    shutdown: ShutdownHandle,
+) -> Router {
+    let auth_enabled = auth.is_enabled();
+    let router = Router::new()
+        .route("/", get(dashboard_handler))
+        .route("/api/v1/current", get(current_handler))
+        .route("/api/v1/snapshots", get(snapshots_handler))
+        .route("/api/v1/timeseries", get(timeseries_handler))
+        .route("/api/v1/targets", get(targets_handler))
+        .route("/api/v1/neighbors", get(neighbors_handler))
+        .route("/api/v1/events", get(events_handler))
+        .route("/api/v1/events/stream", get(event_stream_handler))
+        .route("/api/v1/logs", get(logs_handler))
+        .route("/api/v1/logs/stream", get(log_stream_handler))
+        .route("/logs", get(logs_page_handler))
+        .route("/api/v1/statistics", get(statistics_handler))
+        .route("/api/v1/event-counts", get(event_counts_handler))
+        .route("/api/v1/diagnostics", get(diagnostics_handler))
+        .route("/api/v1/report", get(report_handler))
+        .route("/api/v1/export", get(export_handler))
+        .route("/api/v1/router-syslog", get(router_syslog_handler).post(router_syslog_ingest_handler))
+        .route("/api/v1/incidents", get(incidents_handler))
+        .route("/api/v1/incidents/:id", get(incident_handler))
+        .route("/api/v1/incidents/:id/notes", post(incident_note_handler))
+        .route("/api/v1/annotations", get(annotations_handler).post(annotation_handler))
+        .route("/api/v1/annotations/:id", delete(delete_annotation_handler))
+        .route("/api/v1/sessions", get(sessions_handler))
+        .route("/api/v1/sessions/compare", get(sessions_compare_handler))
+        .route("/api/v1/sessions/:id", get(session_handler))
+        .route("/api/v1/data", delete(delete_data_handler))
+        .route("/api/v1/admin/test-alert", post(test_alert_handler))
+        .route("/api/v1/monitor/pause", get(pause_status_handler).post(pause_handler))
+        .route("/api/v1/monitor/resume", post(resume_handler))
+        .route("/api/v1/monitor", get(monitor_status_handler))
+        .route("/api/v1/monitor/stop", post(stop_handler))
+        .route("/api/v1/monitor/start", post(start_handler))
+        .route("/api/v1/diagnose", post(diagnose_handler))
+        .route("/api/v1/alerts", get(alert_state_handler))
+        .route("/api/v1/alerts/history", get(alert_history_handler))
+        .route("/api/v1/alerts/mute", post(mute_handler))
+        .route("/api/v1/alerts/unmute", post(unmute_handler))
+        .route("/api/v1/version", get(version_handler))
+        .route("/api/v1/capabilities", get(capabilities_handler))
+        .route("/api/v1/health", get(health_handler))
+        .route("/ws", get(live_updates_handler))
+        .route("/metrics", get(prometheus_handler))
+        .merge(SwaggerUi::new("/docs").url("/api/v1/openapi.json", ApiDoc::openapi()));
+    let router = router.merge(crate::grafana::routes(store.clone()));
+    #[cfg(feature = "graphql")]
+    let router = router.merge(crate::graphql::routes(store.clone()));
+    router
+        .layer(axum::middleware::from_fn_with_state(auth, auth::require))
+        // gzip or brotli, as the client accepts; event streams and tiny responses are left alone
+        .layer(CompressionLayer::new())
+        // Left outside authentication, so supervisors and container health checks can probe it
+        .route("/readyz", get(readyz_handler))
+        // Public libraries only
+        .route("/assets/:name", get(assets::handler))
+        .fallback(legacy_api_redirect)
+        .with_state(AppState { store, monitor, shutdown, started_at: chrono::Utc::now(), auth_enabled })
+}
+
+/// Open to any origin, so other tools can read the API from a browser
+pub(crate) fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+}
 
-    let app = Router::new()
-        .route("/", get(dashboard_handler))
-        .route("/api/current", get(current_handler))
-        .route("/api/snapshots", get(snapshots_handler))
-        .route("/api/timeseries", get(timeseries_handler))
-        .route("/api/events", get(events_handler))
-        .route("/api/statistics", get(statistics_handler))
-        .route("/api/event-counts", get(event_counts_handler))
-        .layer(cors)
-        .with_state(store);
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    info!("Web server listening on port {}", port);
-    axum::serve(listener, app).await?;
+/// Serve `app` on `addr`, over HTTPS with `tls`, until shutdown
+pub(crate) async fn serve(app: Router, addr: SocketAddr, tls: Option<Tls>, shutdown: ShutdownHandle) -> anyhow::Result<()> {
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    #[cfg(feature = "tls")]
+    if let Some(Tls(config)) = tls {
+        let handle = axum_server::Handle::new();
+        let stopping = handle.clone();
+        tokio::spawn(async move {
+            shutdown.wait().await;
+            stopping.graceful_shutdown(Some(TLS_SHUTDOWN_GRACE));
+        });
+        info!("Web server listening on {} (HTTPS)", addr);
+        axum_server::bind_rustls(addr, config)
+            .handle(handle)
+            .serve(app)
+            .await?;
+        info!("Web server stopped");
+        return Ok(());
+    }
+    #[cfg(not(feature = "tls"))]
+    let _ = tls;
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Web server listening on {}", addr);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await?;
+    info!("Web server stopped");
     Ok(())
 }
 
+/// How long open requests may take to finish once an HTTPS server is asked to stop
+#[cfg(feature = "tls")]
+const TLS_SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The dashboard's address on this machine for a server bound to `bind`
+pub fn local_url(bind: IpAddr, port: u16, tls: bool) -> String {
+    let scheme = if tls { "https" } else { "http" };
+    if bind.is_loopback() || bind.is_unspecified() {
+        format!("{}://localhost:{}", scheme, port)
+    } else {
+        format!("{}://{}", scheme, SocketAddr::new(bind, port))
+    }
+}
+
 async fn dashboard_handler() -> Html<&'static str> {
     Html(DASHBOARD_HTML)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TimeRangeQuery {
+    /// Start of the range (ISO 8601)
+    start: Option<String>,
+    /// End of the range (ISO 8601)
+    end: Option<String>,
+}
+
+/// What `DELETE /api/v1/data` removes
+enum Deletion {
+    Before(chrono::DateTime<chrono::Utc>),
+    Session(String),
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeleteDataQuery {
+    /// Delete everything recorded before this time (ISO 8601)
+    before: Option<String>,
+    /// Delete this finished session; the start of its id is enough
+    session: Option<String>,
+    /// Give the freed space back to the disk afterwards, which can take a while
+    #[serde(default)]
+    vacuum: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NeighborsQuery {
+    /// Start of the range (ISO 8601)
+    start: Option<String>,
+    /// End of the range (ISO 8601)
+    end: Option<String>,
+    /// Only this access point's history, e.g. `aa:bb:cc:dd:ee:ff`
+    bssid: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TargetsQuery {
+    /// Start of the range (ISO 8601)
     start: Option<String>,
+    /// End of the range (ISO 8601)
     end: Option<String>,
+    /// Only these targets, comma-separated, e.g. `8.8.8.8,1.1.1.1`
+    targets: Option<String>,
+    /// Aggregate the points into buckets of this size, e.g. `30s`, `5m`, `1h` or `1d`
+    bucket: Option<String>,
+    /// Aggregate each series into buckets just large enough to return at most this many points
+    max_points: Option<u32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PageQuery {
+    /// Rows per page, at most 1000; defaults to 100
     limit: Option<u32>,
+    /// Rows to skip
+    offset: Option<u64>,
+}
+
+impl PageQuery {
+    /// The page size asked for, kept within `MAX_PAGE_SIZE`
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn offset(&self) -> u64 {
+        self.offset.unwrap_or(0)
+    }
+
+    /// Link to the page after this one, with the request's other parameters; `None` on the last page
+    fn next_link(&self, uri: &Uri, total: u64) -> Option<String> {
+        let next = self.offset() + self.limit() as u64;
+        if next >= total {
+            return None;
+        }
+        let paging = format!("limit={}&offset={}", self.limit(), next);
+        let query = uri.query().unwrap_or_default().split('&')
+            .filter(|pair| !pair.is_empty() && !pair.starts_with("limit=") && !pair.starts_with("offset="))
+            .chain(std::iter::once(paging.as_str()))
+            .collect::<Vec<_>>()
+            .join("&");
+        Some(format!("{}?{}", uri.path(), query))
+    }
+
+    /// A page of `data` out of `total` rows as the JSON listing endpoints return
+    fn response<T: serde::Serialize>(&self, uri: &Uri, data: Vec<T>, total: u64) -> Response {
+        Json(self.body(uri, data, total)).into_response()
+    }
+
+    fn body<T: serde::Serialize>(&self, uri: &Uri, data: Vec<T>, total: u64) -> serde_json::Value {
+        serde_json::json!({
+            "success": true,
+            "count": data.len(),
+            "total": total,
+            "offset": self.offset(),
+            "limit": self.limit(),
+            "next": self.next_link(uri, total),
+            "data": data
+        })
+    }
+}
+
+/// A bucket size such as `30s`, `5m`, `1h` or `1d`
+pub(crate) fn parse_bucket(value: &str) -> Option<chrono::TimeDelta> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let count: i64 = value[..split].parse().ok().filter(|n| *n > 0)?;
+    match &value[split..] {
+        "s" => chrono::TimeDelta::try_seconds(count),
+        "m" => chrono::TimeDelta::try_minutes(count),
+        "h" => chrono::TimeDelta::try_hours(count),
+        "d" => chrono::TimeDelta::try_days(count),
+        _ => None,
+    }
+}
+
+/// `value` as JSON with a weak ETag of its content, or an empty 304 when the client's
+/// `If-None-Match` already names it. `no-cache` makes browsers revalidate every time, so
+/// a dashboard refreshing an unchanged range gets 304s instead of the same data again.
+fn json_with_etag(headers: &HeaderMap, value: &serde_json::Value) -> Response {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(e) => return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    };
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    // Weak, since compression changes the bytes on the wire but not the content
+    let etag = format!("W/\"{:016x}-{:x}\"", hasher.finish(), body.len());
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    let matches = headers.get_all(header::IF_NONE_MATCH).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag.trim_start_matches("W/"));
+    if matches {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, "application/json".to_string())],
+        body,
+    ).into_response()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TimeseriesQuery {
+    /// Metric name, e.g. `signal_dbm`, `latency_avg` or `packet_loss`
     metric: String,
+    /// Start of the range (ISO 8601)
     start: Option<String>,
+    /// End of the range (ISO 8601)
     end: Option<String>,
+    /// Aggregate the points into buckets of this size, e.g. `30s`, `5m`, `1h` or `1d`
+    bucket: Option<String>,
+    /// Aggregate the points into buckets just large enough to return at most this many
+    max_points: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct EventsQuery {
+    /// Start of the range (ISO 8601)
     start: Option<String>,
+    /// End of the range (ISO 8601)
     end: Option<String>,
+    /// Only events of this severity (info, warning, error or critical)
+    severity: Option<String>,
+    /// Only events of this type, e.g. `ConnectionDropped`
+    event_type: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EventStreamQuery {
+    /// Only events of exactly this severity
     severity: Option<String>,
+    /// Only events of this severity or worse
+    min_severity: Option<String>,
+    /// Only events of this type, e.g. `ConnectionDropped`
     event_type: Option<String>,
 }
 
-async fn current_handler(State(store): State<SharedStore>) -> impl IntoResponse {
-    match store.get_latest_snapshot() {
-        Ok(Some(snapshot)) => Json(serde_json::json!({
-            "success": true,
-            "data": snapshot
-        })).into_response(),
-        Ok(None) => Json(serde_json::json!({
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LogsQuery {
+    /// Only records at this level or more severe: error, warn, info (default), debug or trace
+    level: Option<String>,
+    /// Most recent records to return, default and at most 1000
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct IncidentsQuery {
+    /// Start of the range (ISO 8601)
+    start: Option<String>,
+    /// End of the range (ISO 8601)
+    end: Option<String>,
+    /// `open` or `resolved`
+    status: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SessionsCompareQuery {
+    /// Comma-separated session ids; all sessions when left out
+    ids: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct IncidentNoteRequest {
+    /// e.g. "ISP confirmed area outage"
+    text: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct AnnotationRequest {
+    /// e.g. "rebooted router"
+    text: String,
+    /// Defaults to now
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/current",
+    tag = "snapshots",
+    responses(
+        (status = 200, description = "The latest snapshot, or null before the first one", body = openapi::CurrentSnapshotResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn current_handler(State(store): State<SharedStore>) -> impl IntoResponse {
+    match store.get_latest_snapshot() {
+        Ok(Some(snapshot)) => Json(serde_json::json!({
+            "success": true,
+            "data": snapshot
+        })).into_response(),
+        Ok(None) => Json(serde_json::json!({
+            "success": true,
+            "data": null,
+            "message": "No data collected yet"
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/snapshots",
+    tag = "snapshots",
+    params(TimeRangeQuery, PageQuery),
+    responses(
+        (status = 200, description = "One page of snapshots, newest first", body = openapi::SnapshotsResponse),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn snapshots_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeRangeQuery>,
+    Query(page): Query<PageQuery>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let (start, end) = (params.start.as_deref(), params.end.as_deref());
+    let result = store.get_snapshots(start, end, Some(page.limit()), page.offset())
+        .and_then(|snapshots| Ok((snapshots, store.count_snapshots(start, end)?)));
+    match result {
+        Ok((snapshots, total)) => json_with_etag(&headers, &page.body(&uri, snapshots, total)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/timeseries",
+    tag = "snapshots",
+    params(TimeseriesQuery),
+    responses(
+        (status = 200, description = "Points of one metric, oldest first", body = openapi::TimeseriesResponse),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 400, description = "Unknown bucket size", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn timeseries_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeseriesQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let size = match requested_buckets(params.bucket.as_deref(), params.max_points) {
+        Ok(size) => size,
+        Err(response) => return response,
+    };
+    match timeseries_data(store.as_ref(), &params.metric, params.start.as_deref(), params.end.as_deref(), size) {
+        Ok((bucket, data)) => {
+            let mut body = serde_json::json!({
+                "success": true,
+                "metric": params.metric,
+                "count": data.len(),
+                "data": data
+            });
+            if let Some(bucket) = bucket {
+                body["bucket_secs"] = serde_json::json!(bucket.num_milliseconds() as f64 / 1000.0);
+            }
+            json_with_etag(&headers, &body)
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// The aggregation a timeseries request asks for with `bucket` or `max_points`; `Err` is
+/// the 400 response for a bucket that doesn't parse
+#[allow(clippy::result_large_err)]
+fn requested_buckets(bucket: Option<&str>, max_points: Option<u32>) -> Result<Option<BucketSize>, Response> {
+    match (bucket, max_points) {
+        (Some(bucket), _) => match parse_bucket(bucket) {
+            Some(bucket) => Ok(Some(BucketSize::Fixed(bucket))),
+            None => Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Invalid bucket {:?}, expected a size like 30s, 5m, 1h or 1d", bucket)
+                })),
+            ).into_response()),
+        },
+        (None, Some(points)) => Ok(Some(BucketSize::MaxPoints(points))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// One metric's points as the timeseries endpoints return them, and the bucket size when
+/// aggregated with `size`
+fn timeseries_data(
+    store: &dyn MetricsStore,
+    metric: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+    size: Option<BucketSize>,
+) -> anyhow::Result<(Option<chrono::TimeDelta>, Vec<serde_json::Value>)> {
+    let Some(size) = size else {
+        let data = store.get_timeseries(metric, start, end)?;
+        return Ok((None, data.into_iter().map(|(ts, val)| {
+            serde_json::json!({ "timestamp": ts, "value": val })
+        }).collect()));
+    };
+    let (bucket, buckets) = store.get_timeseries_buckets(metric, start, end, size)?;
+    Ok((Some(bucket), buckets.into_iter().map(|b| serde_json::json!({
+        "timestamp": b.timestamp,
+        "value": b.avg,
+        "min": b.min,
+        "max": b.max,
+        "samples": b.samples,
+    })).collect()))
+}
+
+/// Latency, loss and jitter of each ping target on its own, to tell whether one target or
+/// the link itself is the problem
+#[utoipa::path(
+    get,
+    path = "/api/v1/targets",
+    tag = "snapshots",
+    params(TargetsQuery),
+    responses(
+        (status = 200, description = "Each target's timeseries, by target name", body = openapi::TargetsResponse),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 400, description = "Unknown bucket size", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn targets_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TargetsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let size = match requested_buckets(params.bucket.as_deref(), params.max_points) {
+        Ok(size) => size,
+        Err(response) => return response,
+    };
+    let (start, end) = (params.start.as_deref(), params.end.as_deref());
+    let wanted: Option<Vec<&str>> = params.targets.as_deref()
+        .map(|targets| targets.split(',').map(str::trim).filter(|t| !t.is_empty()).collect());
+    let result = (|| -> anyhow::Result<Vec<serde_json::Value>> {
+        // A target that never answered has loss but no latency
+        let mut names = std::collections::BTreeSet::new();
+        for prefix in [storage::TARGET_LATENCY_PREFIX, storage::TARGET_LOSS_PREFIX] {
+            names.extend(store.get_metric_names(prefix)?.into_iter()
+                .filter_map(|metric| metric.strip_prefix(prefix).map(str::to_string)));
+        }
+        names.into_iter()
+            .filter(|name| wanted.as_ref().is_none_or(|wanted| wanted.contains(&name.as_str())))
+            .map(|name| {
+                let series = |prefix: &str| timeseries_data(store.as_ref(), &format!("{}{}", prefix, name), start, end, size);
+                let (bucket, latency) = series(storage::TARGET_LATENCY_PREFIX)?;
+                let (_, loss) = series(storage::TARGET_LOSS_PREFIX)?;
+                let (_, jitter) = series(storage::TARGET_JITTER_PREFIX)?;
+                Ok(serde_json::json!({
+                    "target": name,
+                    "bucket_secs": bucket.map(|b| b.num_milliseconds() as f64 / 1000.0),
+                    "latency": latency,
+                    "loss": loss,
+                    "jitter": jitter,
+                }))
+            })
+            .collect()
+    })();
+    match result {
+        Ok(data) => json_with_etag(&headers, &serde_json::json!({
+            "success": true,
+            "count": data.len(),
+            "data": data
+        })),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    tag = "events",
+    params(EventsQuery, PageQuery),
+    responses(
+        (status = 200, description = "One page of events, newest first", body = openapi::EventsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn events_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<EventsQuery>,
+    Query(page): Query<PageQuery>,
+    uri: Uri,
+) -> impl IntoResponse {
+    let (start, end) = (params.start.as_deref(), params.end.as_deref());
+    let (severity, event_type) = (params.severity.as_deref(), params.event_type.as_deref());
+    let result = store.get_events(start, end, severity, event_type, Some(page.limit()), page.offset())
+        .and_then(|events| Ok((events, store.count_events(start, end, severity, event_type)?)));
+    match result {
+        Ok((events, total)) => page.response(&uri, events, total),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/router-syslog",
+    tag = "events",
+    params(TimeRangeQuery, PageQuery),
+    responses(
+        (status = 200, description = "One page of RouterLog events, newest first", body = openapi::EventsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn router_syslog_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeRangeQuery>,
+    Query(page): Query<PageQuery>,
+    uri: Uri,
+) -> impl IntoResponse {
+    let (start, end) = (params.start.as_deref(), params.end.as_deref());
+    let result = store.get_events(start, end, None, Some("RouterLog"), Some(page.limit()), page.offset())
+        .and_then(|events| Ok((events, store.count_events(start, end, None, Some("RouterLog"))?)));
+    match result {
+        Ok((events, total)) => page.response(&uri, events, total),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/incidents",
+    tag = "incidents",
+    params(IncidentsQuery),
+    responses(
+        (status = 200, description = "Incidents, newest first", body = openapi::IncidentsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn incidents_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<IncidentsQuery>,
+) -> impl IntoResponse {
+    match store.get_incidents(params.start.as_deref(), params.end.as_deref(), params.status.as_deref()) {
+        Ok(incidents) => Json(serde_json::json!({
+            "success": true,
+            "count": incidents.len(),
+            "data": incidents
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// One incident with the events grouped into it
+#[utoipa::path(
+    get,
+    path = "/api/v1/incidents/{id}",
+    tag = "incidents",
+    params(("id" = String, Path, description = "Incident id")),
+    responses(
+        (status = 200, description = "The incident and its events", body = openapi::IncidentResponse),
+        (status = 404, description = "No such incident", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn incident_handler(
+    State(store): State<SharedStore>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let result = store.get_incident(&id).and_then(|incident| match incident {
+        Some(incident) => Ok(Some((incident, store.get_incident_events(&id)?))),
+        None => Ok(None),
+    });
+    match result {
+        Ok(Some((incident, events))) => Json(serde_json::json!({
+            "success": true,
+            "data": incident,
+            "events": events
+        })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No incident {}", id)
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/incidents/{id}/notes",
+    tag = "incidents",
+    params(("id" = String, Path, description = "Incident id")),
+    request_body = IncidentNoteRequest,
+    responses(
+        (status = 200, description = "The incident with the note added", body = openapi::IncidentNoteResponse),
+        (status = 400, description = "Empty note", body = openapi::ErrorResponse),
+        (status = 404, description = "No such incident", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn incident_note_handler(
+    State(store): State<SharedStore>,
+    Path(id): Path<String>,
+    Json(request): Json<IncidentNoteRequest>,
+) -> impl IntoResponse {
+    if request.text.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Note text is empty"
+            })),
+        ).into_response();
+    }
+
+    match store.add_incident_note(&id, request.text.trim()) {
+        Ok(Some(incident)) => Json(serde_json::json!({
+            "success": true,
+            "data": incident
+        })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No incident {}", id)
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations",
+    tag = "annotations",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, description = "Annotations in the time range", body = openapi::AnnotationsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn annotations_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeRangeQuery>,
+) -> impl IntoResponse {
+    match store.get_annotations(params.start.as_deref(), params.end.as_deref()) {
+        Ok(annotations) => Json(serde_json::json!({
+            "success": true,
+            "count": annotations.len(),
+            "data": annotations
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/annotations",
+    tag = "annotations",
+    request_body = AnnotationRequest,
+    responses(
+        (status = 200, description = "The stored annotation", body = openapi::AnnotationResponse),
+        (status = 400, description = "Empty text", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn annotation_handler(
+    State(store): State<SharedStore>,
+    Json(request): Json<AnnotationRequest>,
+) -> impl IntoResponse {
+    if request.text.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Annotation text is empty"
+            })),
+        ).into_response();
+    }
+
+    let now = chrono::Utc::now();
+    let annotation = Annotation {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: request.timestamp.unwrap_or(now),
+        text: request.text.trim().to_string(),
+        created_at: now,
+    };
+    match store.save_annotation(&annotation) {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "data": annotation
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/annotations/{id}",
+    tag = "annotations",
+    params(("id" = String, Path, description = "Annotation id")),
+    responses(
+        (status = 200, description = "Annotation deleted", body = openapi::SuccessResponse),
+        (status = 404, description = "No such annotation", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn delete_annotation_handler(
+    State(store): State<SharedStore>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match store.delete_annotation(&id) {
+        Ok(true) => Json(serde_json::json!({
+            "success": true
+        })).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No annotation {}", id)
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions",
+    tag = "sessions",
+    responses(
+        (status = 200, description = "Sessions, newest first", body = openapi::SessionsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn sessions_handler(State(store): State<SharedStore>) -> impl IntoResponse {
+    match store.get_sessions() {
+        Ok(sessions) => Json(serde_json::json!({
+            "success": true,
+            "count": sessions.len(),
+            "data": sessions.into_iter().map(|(session, snapshots)| {
+                let mut value = serde_json::json!(session);
+                value["snapshot_count"] = snapshots.into();
+                value
+            }).collect::<Vec<_>>()
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// One session with the statistics and event counts over its time span
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/{id}",
+    tag = "sessions",
+    params(("id" = String, Path, description = "Session id")),
+    responses(
+        (status = 200, description = "The session with its statistics and event counts", body = openapi::SessionResponse),
+        (status = 404, description = "No such session", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn session_handler(
+    State(store): State<SharedStore>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let result = store.get_session(&id).and_then(|session| match session {
+        Some(session) => {
+            let (start, end) = session.range();
+            let stats = store.get_statistics(Some(&start), Some(&end))?;
+            let counts = store.get_event_counts_by_type(Some(&start), Some(&end))?;
+            Ok(Some((session, stats, counts)))
+        }
+        None => Ok(None),
+    });
+    match result {
+        Ok(Some((session, stats, counts))) => Json(serde_json::json!({
+            "success": true,
+            "data": session,
+            "statistics": stats,
+            "event_counts": counts.into_iter().map(|(event_type, count)| {
+                serde_json::json!({ "event_type": event_type, "count": count })
+            }).collect::<Vec<_>>()
+        })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No session {}", id)
+            })),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// Delete old data or one session, e.g. to manage a headless monitor's disk from its
+/// dashboard. Only served when the server requires authentication.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/data",
+    tag = "sessions",
+    params(DeleteDataQuery),
+    responses(
+        (status = 200, description = "Data deleted", body = openapi::DeleteDataResponse),
+        (status = 400, description = "Neither or both of before and session, or an invalid time", body = openapi::ErrorResponse),
+        (status = 403, description = "The server doesn't require authentication", body = openapi::ErrorResponse),
+        (status = 404, description = "No such session", body = openapi::ErrorResponse),
+        (status = 409, description = "The session is still running", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be written", body = openapi::ErrorResponse),
+    )
+)]
+async fn delete_data_handler(
+    State(state): State<AppState>,
+    Query(params): Query<DeleteDataQuery>,
+) -> impl IntoResponse {
+    let fail = |status: StatusCode, error: String| {
+        (status, Json(serde_json::json!({ "success": false, "error": error }))).into_response()
+    };
+    if !state.auth_enabled {
+        return fail(StatusCode::FORBIDDEN,
+            "Deleting data requires a server started with --auth-token or --basic-auth".to_string());
+    }
+    let store = state.store;
+    let deletion = match (params.before, params.session) {
+        (Some(before), None) => match storage::parse_time(&before) {
+            Some(cutoff) => Deletion::Before(cutoff),
+            None => return fail(StatusCode::BAD_REQUEST, format!("Invalid before {:?}, expected an ISO 8601 timestamp", before)),
+        },
+        (None, Some(id)) => match store.get_session(&id) {
+            Ok(Some(session)) if session.ended_at.is_none() => {
+                return fail(StatusCode::CONFLICT, format!("Session {} is still running; stop it before deleting it", session.id));
+            }
+            Ok(Some(session)) => Deletion::Session(session.id),
+            Ok(None) => return fail(StatusCode::NOT_FOUND, format!("No session {}", id)),
+            Err(e) => return fail(StatusCode::BAD_REQUEST, e.to_string()),
+        },
+        _ => return fail(StatusCode::BAD_REQUEST, "Pass either before=<ISO 8601 time> or session=<id>".to_string()),
+    };
+
+    // A large delete, and a vacuum more so, can take a while; keep it off the async workers
+    let vacuum = params.vacuum;
+    let result = tokio::task::spawn_blocking(move || {
+        let removed = match deletion {
+            Deletion::Before(cutoff) => store.prune_before(cutoff)?,
+            Deletion::Session(ref id) => store.delete_session(id)?.unwrap_or_default(),
+        };
+        if vacuum {
+            store.vacuum()?;
+        }
+        anyhow::Ok((removed, deletion))
+    }).await;
+    match result.map_err(anyhow::Error::from).and_then(|r| r) {
+        Ok((removed, deletion)) => {
+            let (before, session) = match deletion {
+                Deletion::Before(cutoff) => {
+                    info!("Deleted {} rows from before {} through the web API", removed, cutoff.to_rfc3339());
+                    (Some(cutoff), None)
+                }
+                Deletion::Session(id) => {
+                    info!("Deleted session {} ({} rows) through the web API", id, removed);
+                    (None, Some(id))
+                }
+            };
+            Json(serde_json::json!({
+                "success": true,
+                "removed": removed,
+                "before": before,
+                "session": session,
+                "vacuumed": vacuum
+            })).into_response()
+        }
+        Err(e) => fail(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Statistics for several sessions side by side, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/compare",
+    tag = "sessions",
+    params(SessionsCompareQuery),
+    responses(
+        (status = 200, description = "Statistics of each session", body = openapi::SessionsCompareResponse),
+        (status = 400, description = "Unknown session id", body = openapi::ErrorResponse),
+    )
+)]
+async fn sessions_compare_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<SessionsCompareQuery>,
+) -> impl IntoResponse {
+    let sessions = match params.ids {
+        Some(ids) => ids.split(',')
+            .map(|id| store.get_session(id.trim())?.ok_or_else(|| anyhow::anyhow!("No session {}", id.trim())))
+            .collect::<anyhow::Result<Vec<_>>>(),
+        None => store.get_sessions().map(|all| all.into_iter().rev().map(|(session, _)| session).collect()),
+    };
+    let result = sessions.and_then(|sessions| {
+        sessions.into_iter().map(|session| {
+            let (start, end) = session.range();
+            let stats = store.get_statistics(Some(&start), Some(&end))?;
+            Ok(serde_json::json!({ "session": session, "statistics": stats }))
+        }).collect::<anyhow::Result<Vec<_>>>()
+    });
+    match result {
+        Ok(rows) => Json(serde_json::json!({
+            "success": true,
+            "count": rows.len(),
+            "data": rows
+        })).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// Accept syslog lines over HTTP, for routers that can only push logs to a URL
+#[utoipa::path(
+    post,
+    path = "/api/v1/router-syslog",
+    tag = "events",
+    request_body(content = String, description = "Syslog lines, one per line", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "Lines recorded as RouterLog events", body = openapi::SyslogIngestResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn router_syslog_ingest_handler(
+    State(state): State<AppState>,
+    ConnectInfo(from): ConnectInfo<SocketAddr>,
+    body: String,
+) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is not running in this process"
+            })),
+        ).into_response();
+    };
+
+    let accepted = body.lines()
+        .filter(|line| syslog::record(&monitor, line, from.ip()))
+        .count();
+    Json(serde_json::json!({
+        "success": true,
+        "accepted": accepted,
+        "message": "Entries will be recorded with the next snapshot"
+    })).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/statistics",
+    tag = "statistics",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, description = "Statistics over the time range", body = openapi::StatisticsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn statistics_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeRangeQuery>,
+) -> impl IntoResponse {
+    match store.get_statistics(params.start.as_deref(), params.end.as_deref()) {
+        Ok(stats) => Json(serde_json::json!({
+            "success": true,
+            "data": stats
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/event-counts",
+    tag = "statistics",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, description = "Number of events of each type", body = openapi::EventCountsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn event_counts_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<TimeRangeQuery>,
+) -> impl IntoResponse {
+    match store.get_event_counts_by_type(params.start.as_deref(), params.end.as_deref()) {
+        Ok(counts) => Json(serde_json::json!({
+            "success": true,
+            "data": counts.into_iter().map(|(event_type, count)| {
+                serde_json::json!({ "event_type": event_type, "count": count })
+            }).collect::<Vec<_>>()
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// Nearby access points: the latest scan, how crowded each channel is in it, and each
+/// access point's signal over the range
+#[utoipa::path(
+    get,
+    path = "/api/v1/neighbors",
+    tag = "snapshots",
+    params(NeighborsQuery),
+    responses(
+        (status = 200, description = "Latest scan, channel use and history per BSSID", body = openapi::NeighborsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn neighbors_handler(
+    State(store): State<SharedStore>,
+    Query(params): Query<NeighborsQuery>,
+) -> impl IntoResponse {
+    match store.get_neighbor_scans(params.start.as_deref(), params.end.as_deref(), MAX_NEIGHBOR_SCANS) {
+        Ok(mut scans) => {
+            scans.reverse();
+            let bssid = params.bssid.map(|b| b.to_lowercase());
+            Json(neighbors_json(&scans, bssid.as_deref())).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// The `/api/v1/neighbors` response for `scans`, oldest first
+fn neighbors_json(scans: &[(chrono::DateTime<chrono::Utc>, Vec<NeighborNetwork>)], only_bssid: Option<&str>) -> serde_json::Value {
+    let latest = scans.last();
+
+    // Channels in use in the latest scan, by band and channel
+    let mut channels = std::collections::BTreeMap::new();
+    for neighbor in latest.map(|(_, n)| n.as_slice()).unwrap_or_default() {
+        let entry = channels.entry((neighbor.band.label(), neighbor.channel)).or_insert((&neighbor.band, 0, i32::MIN, Vec::<&str>::new()));
+        entry.1 += 1;
+        entry.2 = entry.2.max(neighbor.signal_dbm);
+        if !neighbor.ssid.is_empty() && !entry.3.contains(&neighbor.ssid.as_str()) {
+            entry.3.push(&neighbor.ssid);
+        }
+    }
+
+    // Each access point's readings, and the scans it was missing from while in range
+    let mut history = std::collections::BTreeMap::new();
+    for (index, (at, neighbors)) in scans.iter().enumerate() {
+        for neighbor in neighbors.iter().filter(|n| only_bssid.is_none_or(|b| n.bssid == b)) {
+            let entry = history.entry(neighbor.bssid.as_str()).or_insert((index, index, Vec::new()));
+            entry.1 = index;
+            entry.2.push((*at, neighbor));
+        }
+    }
+
+    serde_json::json!({
+        "success": true,
+        "scans": scans.len(),
+        "scanned_at": latest.map(|(at, _)| at),
+        "latest": latest.map(|(_, n)| n.as_slice()).unwrap_or_default(),
+        "channels": channels.into_iter().map(|((_, channel), (band, networks, strongest, ssids))| serde_json::json!({
+            "band": band,
+            "channel": channel,
+            "networks": networks,
+            "strongest_dbm": strongest,
+            "ssids": ssids,
+        })).collect::<Vec<_>>(),
+        "history": history.into_iter().map(|(bssid, (first, last, readings))| {
+            let (_, newest) = readings[readings.len() - 1];
+            let signals = readings.iter().map(|(_, n)| n.signal_dbm);
+            serde_json::json!({
+                "bssid": bssid,
+                "ssid": newest.ssid,
+                "band": newest.band,
+                "channel": newest.channel,
+                "radio_type": newest.radio_type,
+                "authentication": newest.authentication,
+                "encryption": newest.encryption,
+                "first_seen": readings[0].0,
+                "last_seen": readings[readings.len() - 1].0,
+                "signal_avg_dbm": signals.clone().map(f64::from).sum::<f64>() / readings.len() as f64,
+                "signal_min_dbm": signals.clone().min(),
+                "signal_max_dbm": signals.max(),
+                "missed_scans": (last - first + 1).saturating_sub(readings.len()),
+                "readings": readings.iter().map(|(at, n)| serde_json::json!({
+                    "timestamp": at,
+                    "signal_dbm": n.signal_dbm,
+                    "signal_quality_percent": n.signal_quality_percent,
+                    "channel": n.channel,
+                })).collect::<Vec<_>>(),
+            })
+        }).collect::<Vec<_>>(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/diagnostics",
+    tag = "system",
+    responses(
+        (status = 200, description = "Diagnostics bundle, as written by dump-diagnostics", body = openapi::DiagnosticsResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn diagnostics_handler(State(store): State<SharedStore>) -> impl IntoResponse {
+    match diagnostics::collect_bundle(store.as_ref()).await {
+        Ok(bundle) => Json(serde_json::json!({
+            "success": true,
+            "data": bundle
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReportQuery {
+    /// `text` (default) for the plain-text report, or `json` to get it inside the usual envelope
+    format: Option<String>,
+}
+
+/// The report `analyze` writes, over any time range; over the whole database without one
+#[utoipa::path(
+    get,
+    path = "/api/v1/report",
+    tag = "statistics",
+    params(TimeRangeQuery, ReportQuery),
+    responses(
+        (status = 200, description = "The report, as text/plain or JSON by `format`", body = openapi::ReportResponse),
+        (status = 400, description = "Unknown format", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn report_handler(
+    State(store): State<SharedStore>,
+    Query(range): Query<TimeRangeQuery>,
+    Query(params): Query<ReportQuery>,
+) -> impl IntoResponse {
+    let json = match params.format.as_deref().unwrap_or("text") {
+        "text" => false,
+        "json" => true,
+        other => return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("unknown format '{}' (expected text or json)", other)
+            })),
+        ).into_response(),
+    };
+
+    // Reads every snapshot in the range, keep it off the async workers
+    let (start, end) = (range.start.clone(), range.end.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        analysis::generate_range_report(store.as_ref(), start.as_deref(), end.as_deref())
+    }).await;
+    match result.map_err(anyhow::Error::from).and_then(|r| r) {
+        Ok(report) if json => Json(serde_json::json!({
+            "success": true,
+            "start": range.start,
+            "end": range.end,
+            "data": report
+        })).into_response(),
+        Ok(report) => ([(axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8")], report).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportQuery {
+    /// `json` (default) for the document `export` writes, or `csv` for one row per snapshot
+    format: Option<String>,
+}
+
+/// Download what `export` writes, streamed from the database as it is read
+#[utoipa::path(
+    get,
+    path = "/api/v1/export",
+    tag = "snapshots",
+    params(TimeRangeQuery, ExportQuery),
+    responses(
+        (status = 200, description = "Export file as an attachment", content_type = ["application/json", "text/csv"]),
+        (status = 400, description = "Unknown format", body = openapi::ErrorResponse),
+    )
+)]
+async fn export_handler(
+    State(store): State<SharedStore>,
+    Query(range): Query<TimeRangeQuery>,
+    Query(params): Query<ExportQuery>,
+) -> Response {
+    let (format, content_type, extension) = match params.format.as_deref().unwrap_or("json") {
+        "json" => (ExportFormat::Json, "application/json", "json"),
+        "csv" => (ExportFormat::Csv, "text/csv; charset=utf-8", "csv"),
+        other => return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("unknown format '{}' (expected json or csv)", other)
+            })),
+        ).into_response(),
+    };
+    let filename = format!("wifi_export_{}.{}", chrono::Utc::now().format("%Y%m%d_%H%M%S"), extension);
+
+    // The store is read on a blocking thread that hands the file over in chunks; an error
+    // halfway through aborts the response, since the status has already been sent
+    let (sender, receiver) = mpsc::channel(EXPORT_STREAM_BUFFER);
+    tokio::task::spawn_blocking(move || {
+        let out = std::io::BufWriter::with_capacity(EXPORT_CHUNK_BYTES, ChunkSender(sender.clone()));
+        let (start, end) = (range.start.as_deref(), range.end.as_deref());
+        let written = match format {
+            ExportFormat::Csv => export::write_csv_to(store.as_ref(), out, start, end),
+            _ => export::write_json_to(store.as_ref(), out, start, end),
+        };
+        if let Err(e) = written {
+            debug!("Export download ended early: {}", e);
+            let _ = sender.blocking_send(Err(std::io::Error::other(e.to_string())));
+        }
+    });
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        axum::body::Body::from_stream(stream),
+    ).into_response()
+}
+
+/// Sends each write to an export download as one response chunk; fails once the client has gone away
+struct ChunkSender(mpsc::Sender<std::io::Result<Vec<u8>>>);
+
+impl std::io::Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export download was cancelled"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TestAlertQuery {
+    /// Severity of the test event; defaults to critical
+    severity: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/test-alert",
+    tag = "alerts",
+    params(TestAlertQuery),
+    responses(
+        (status = 200, description = "Test event queued", body = openapi::TestAlertResponse),
+        (status = 400, description = "Unknown severity", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn test_alert_handler(
+    State(state): State<AppState>,
+    Query(params): Query<TestAlertQuery>,
+) -> impl IntoResponse {
+    let severity = match params.severity.as_deref().unwrap_or("critical").parse::<EventSeverity>() {
+        Ok(severity) => severity,
+        Err(e) => return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e
+            })),
+        ).into_response(),
+    };
+
+    let Some(monitor) = state.monitor else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is not running in this process"
+            })),
+        ).into_response();
+    };
+
+    let event = test_alert_event(severity, "api");
+    let event_id = event.id.clone();
+    match monitor.inject_event(event) {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "event_id": event_id,
+            "message": "Test alert queued; it will be recorded with the next snapshot"
+        })).into_response(),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PauseQuery {
+    /// Stored with the pause window
+    reason: Option<String>,
+}
+
+fn monitor_not_running() -> axum::response::Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({
+            "success": false,
+            "error": "Monitoring is not running in this process"
+        })),
+    ).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/monitor/pause",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "Whether monitoring is paused", body = openapi::PauseStatusResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn pause_status_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    let pause = monitor.paused();
+    Json(serde_json::json!({
+        "success": true,
+        "paused": pause.is_some(),
+        "pause": pause
+    })).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/monitor/pause",
+    tag = "monitor",
+    params(PauseQuery),
+    responses(
+        (status = 200, description = "Monitoring paused", body = openapi::PauseResponse),
+        (status = 409, description = "Already paused", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn pause_handler(
+    State(state): State<AppState>,
+    Query(params): Query<PauseQuery>,
+) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    match monitor.pause(params.reason) {
+        Some(pause) => Json(serde_json::json!({
+            "success": true,
+            "pause": pause
+        })).into_response(),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is already paused",
+                "pause": monitor.paused()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/monitor/resume",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "Monitoring resumed; the pause that ended", body = openapi::PauseResponse),
+        (status = 409, description = "Not paused", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn resume_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    let pause = monitor.paused();
+    if monitor.resume() {
+        Json(serde_json::json!({
+            "success": true,
+            "pause": pause
+        })).into_response()
+    } else {
+        (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is not paused"
+            })),
+        ).into_response()
+    }
+}
+
+/// Whether the monitoring loop runs, and whether it is stopped or paused
+#[utoipa::path(
+    get,
+    path = "/api/v1/monitor",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "State of the monitoring loop", body = openapi::MonitorStatusResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn monitor_status_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    let (stopped_at, pause) = (monitor.stopped(), monitor.paused());
+    Json(serde_json::json!({
+        "success": true,
+        "data": {
+            "running": monitor.is_running(),
+            "stopped": stopped_at.is_some(),
+            "stopped_at": stopped_at,
+            "paused": pause.is_some(),
+            "pause": pause,
+        }
+    })).into_response()
+}
+
+/// Stop taking snapshots until started again; unlike a pause, the time isn't recorded
+#[utoipa::path(
+    post,
+    path = "/api/v1/monitor/stop",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "Monitoring stopped", body = openapi::StopResponse),
+        (status = 409, description = "Already stopped", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn stop_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    match monitor.stop() {
+        Some(stopped_at) => Json(serde_json::json!({
+            "success": true,
+            "stopped_at": stopped_at
+        })).into_response(),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is already stopped",
+                "stopped_at": monitor.stopped()
+            })),
+        ).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/monitor/start",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "Monitoring started; when it had stopped", body = openapi::StopResponse),
+        (status = 409, description = "Not stopped", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn start_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    match monitor.start() {
+        Some(stopped_at) => Json(serde_json::json!({
+            "success": true,
+            "stopped_at": stopped_at
+        })).into_response(),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is not stopped"
+            })),
+        ).into_response(),
+    }
+}
+
+/// Take a snapshot now with the full diagnostic burst instead of waiting for the next
+/// interval. The burst's results are in the snapshot's `DiagnosticsRun` event.
+#[utoipa::path(
+    post,
+    path = "/api/v1/diagnose",
+    tag = "monitor",
+    responses(
+        (status = 200, description = "Snapshot taken on request", body = openapi::DiagnoseResponse),
+        (status = 409, description = "Monitoring is stopped or paused", body = openapi::ErrorResponse),
+        (status = 500, description = "The snapshot could not be collected", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn diagnose_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    if monitor.stopped().is_some() || monitor.paused().is_some() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Monitoring is stopped or paused; start or resume it first"
+            })),
+        ).into_response();
+    }
+    match monitor.diagnose().await {
+        Ok(snapshot) => Json(serde_json::json!({
+            "success": true,
+            "data": snapshot
+        })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        ).into_response(),
+    }
+}
+
+/// Optional cargo features compiled into this binary
+const FEATURES: [(&str, bool); 6] = [
+    ("pcap", cfg!(feature = "pcap")),
+    ("parquet", cfg!(feature = "parquet")),
+    ("postgres", cfg!(feature = "postgres")),
+    ("sqlcipher", cfg!(feature = "sqlcipher")),
+    ("graphql", cfg!(feature = "graphql")),
+    ("grpc", cfg!(feature = "grpc")),
+];
+
+/// Build and runtime details, for matching bug reports and fleet agents to exact builds
+#[utoipa::path(
+    get,
+    path = "/api/v1/version",
+    tag = "system",
+    responses(
+        (status = 200, description = "Build and runtime details", body = openapi::VersionResponse),
+    )
+)]
+async fn version_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let built_at = env!("BUILD_TIMESTAMP").parse().ok().and_then(|secs| chrono::DateTime::from_timestamp(secs, 0));
+    let started_at = selfmon::process_started_at();
+    Json(serde_json::json!({
+        "success": true,
+        "data": {
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_hash": env!("GIT_HASH"),
+            "build_date": built_at,
+            "features": FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| name).collect::<Vec<_>>(),
+            "debug_build": cfg!(debug_assertions),
+            "platform": {
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "backend": PLATFORM_BACKEND,
+            },
+            "process": {
+                "pid": std::process::id(),
+                "started_at": started_at,
+                "uptime_secs": started_at.map(|t| (chrono::Utc::now() - t).num_seconds()),
+            },
+            "monitoring": state.monitor.is_some(),
+        }
+    }))
+}
+
+/// The API versions served and what this server can do, so third-party dashboards and
+/// the aggregation server can adapt to builds older or newer than themselves
+#[utoipa::path(
+    get,
+    path = "/api/v1/capabilities",
+    tag = "system",
+    responses(
+        (status = 200, description = "API versions and capabilities", body = openapi::CapabilitiesResponse),
+    )
+)]
+async fn capabilities_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let monitoring = state.monitor.is_some();
+    let capabilities = [
+        ("snapshots", true),
+        ("timeseries", true),
+        ("events", true),
+        ("event_stream", true),
+        ("live_updates", true),
+        ("statistics", true),
+        ("report", true),
+        ("export", true),
+        ("router_syslog", true),
+        ("incidents", true),
+        ("annotations", true),
+        ("sessions", true),
+        ("alerts", true),
+        ("diagnostics", true),
+        ("prometheus", true),
+        ("logs", true),
+        ("grafana", true),
+        ("graphql", cfg!(feature = "graphql")),
+        // Only with a monitor in this process, not under `dashboard`
+        ("monitor_control", monitoring),
+        ("diagnose", monitoring),
+    ];
+    Json(serde_json::json!({
+        "success": true,
+        "data": {
+            "api_version": API_VERSIONS.last(),
+            "api_versions": API_VERSIONS,
+            "version": env!("CARGO_PKG_VERSION"),
+            "capabilities": capabilities.iter().filter(|(_, on)| *on).map(|(name, _)| name).collect::<Vec<_>>(),
+            "export_formats": ["json", "csv"],
+            "features": FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| name).collect::<Vec<_>>(),
+        }
+    }))
+}
+
+/// Server uptime, whether the database can be read, how old the latest snapshot is and
+/// whether the monitoring loop is running. Answers 503 with the problems found when
+/// anything is wrong.
+#[utoipa::path(
+    get,
+    path = "/api/v1/health",
+    tag = "system",
+    responses(
+        (status = 200, description = "Everything is working", body = openapi::HealthResponse),
+        (status = 503, description = "Something is wrong; `problems` says what", body = openapi::HealthResponse),
+    )
+)]
+async fn health_handler(State(state): State<AppState>) -> Response {
+    let now = chrono::Utc::now();
+    let uptime_secs = (now - state.started_at).num_seconds();
+    let mut problems = Vec::new();
+
+    let latest = state.store.get_latest_snapshot();
+    let database_error = latest.as_ref().err().map(|e| e.to_string());
+    if let Some(ref e) = database_error {
+        problems.push(format!("database is not accessible: {}", e));
+    }
+    let latest = latest.ok().flatten();
+    let age_secs = latest.as_ref().map(|snapshot| (now - snapshot.timestamp).num_seconds());
+
+    let monitor = state.monitor.as_ref().map(|monitor| (monitor.is_running(), monitor.stopped(), monitor.paused()));
+    match monitor {
+        Some((false, _, _)) => problems.push("monitoring loop has exited".to_string()),
+        Some((true, None, None)) if database_error.is_none() => {
+            let interval = latest.as_ref().and_then(|s| s.interval_secs).unwrap_or(0) as i64;
+            let stale_after = (interval * STALE_SNAPSHOT_INTERVALS).max(MIN_STALE_SNAPSHOT_SECS);
+            match age_secs {
+                Some(age) if age > stale_after => problems.push(format!("no snapshot for {}s", age)),
+                None if uptime_secs > stale_after => problems.push(format!("no snapshot in {}s of running", uptime_secs)),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    let status = if problems.is_empty() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(serde_json::json!({
+        "success": true,
+        "healthy": problems.is_empty(),
+        "problems": problems,
+        "data": {
+            "started_at": state.started_at,
+            "uptime_secs": uptime_secs,
+            "database": {
+                "accessible": database_error.is_none(),
+                "error": database_error,
+            },
+            "last_snapshot": latest.map(|snapshot| serde_json::json!({
+                "timestamp": snapshot.timestamp,
+                "age_secs": age_secs,
+            })),
+            "monitor": monitor.map(|(running, stopped_at, pause)| serde_json::json!({
+                "running": running,
+                "stopped": stopped_at.is_some(),
+                "stopped_at": stopped_at,
+                "paused": pause.is_some(),
+                "pause": pause,
+            })),
+        }
+    }))).into_response()
+}
+
+/// Send requests to the unversioned paths of older builds, `/api/<path>`, on to `/api/v1/<path>`
+/// with their method and body. The location is relative, so it also works under the
+/// aggregation server's `/hosts/<name>/`.
+pub(crate) async fn legacy_api_redirect(uri: Uri) -> Response {
+    let Some(rest) = uri.path().strip_prefix("/api/").filter(|rest| !rest.is_empty() && !rest.starts_with("v1/")) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let location = format!("{}v1/{}", "../".repeat(rest.matches('/').count()), rest);
+    let location = match uri.query() {
+        Some(query) => format!("{}?{}", location, query),
+        None => location,
+    };
+    (StatusCode::PERMANENT_REDIRECT, [(header::LOCATION, location)]).into_response()
+}
+
+/// Ready once the server answers and the database can be read
+async fn readyz_handler(State(store): State<SharedStore>) -> Response {
+    match store.get_latest_snapshot() {
+        Ok(_) => (StatusCode::OK, "ready\n").into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, format!("database is not accessible: {}\n", e)).into_response(),
+    }
+}
+
+/// The latest snapshot and event totals for Prometheus to scrape
+async fn prometheus_handler(State(store): State<SharedStore>) -> Response {
+    let metrics = store.get_latest_snapshot().and_then(|snapshot| {
+        let event_counts = store.get_event_counts_by_type(None, None)?;
+        Ok(prometheus::render(snapshot.as_ref(), &event_counts))
+    });
+    match metrics {
+        Ok(metrics) => ([(axum::http::header::CONTENT_TYPE, prometheus::CONTENT_TYPE)], metrics).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Stream events as Server-Sent Events as soon as they are stored, one JSON `NetworkEvent`
+/// per message with the event id as the message id
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/stream",
+    tag = "events",
+    params(EventStreamQuery),
+    responses(
+        (status = 200, description = "Server-Sent Events, one JSON NetworkEvent per message", content_type = "text/event-stream", body = NetworkEvent),
+        (status = 400, description = "Unknown severity", body = openapi::ErrorResponse),
+        (status = 503, description = "Monitoring is not running in this process", body = openapi::ErrorResponse),
+    )
+)]
+async fn event_stream_handler(State(state): State<AppState>, Query(params): Query<EventStreamQuery>) -> Response {
+    let parse = |severity: Option<String>| severity.map(|s| s.parse::<EventSeverity>()).transpose();
+    let (severity, min_severity) = match (parse(params.severity), parse(params.min_severity)) {
+        (Ok(severity), Ok(min_severity)) => (severity, min_severity),
+        (Err(e), _) | (_, Err(e)) => return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e
+            })),
+        ).into_response(),
+    };
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    let wanted = move |event: &NetworkEvent| {
+        severity.as_ref().is_none_or(|s| event.severity == *s)
+            && min_severity.as_ref().is_none_or(|s| event.severity >= *s)
+            && params.event_type.as_ref().is_none_or(|t| format!("{:?}", event.event_type) == *t)
+    };
+
+    // Ends the response on shutdown, which would otherwise wait for the client to leave
+    let (sender, receiver) = mpsc::channel(EVENT_STREAM_BUFFER);
+    tokio::spawn(forward_events(monitor.subscribe(), sender, wanted, state.shutdown));
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok::<_, std::convert::Infallible>(event), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn forward_events(
+    mut updates: broadcast::Receiver<Arc<WifiSnapshot>>,
+    sender: mpsc::Sender<Event>,
+    wanted: impl Fn(&NetworkEvent) -> bool,
+    shutdown: ShutdownHandle,
+) {
+    loop {
+        let snapshot = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(snapshot) => snapshot,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("Event stream fell {} snapshots behind", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = sender.closed() => return,
+            _ = shutdown.wait() => return,
+        };
+        for event in snapshot.events.iter().filter(|event| wanted(event)) {
+            let Ok(message) = Event::default().id(&event.id).json_data(event) else { continue };
+            if sender.send(message).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The level a log request asks for; `Err` is the 400 response for one that doesn't parse
+#[allow(clippy::result_large_err)]
+fn requested_log_level(level: Option<&str>) -> Result<tracing::Level, Response> {
+    match level {
+        None => Ok(tracing::Level::INFO),
+        Some(level) => level.parse().map_err(|_| (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unknown level {:?}, expected error, warn, info, debug or trace", level)
+            })),
+        ).into_response()),
+    }
+}
+
+/// The process's latest log records, oldest first, as the console and the files in `logs/`
+/// show them
+#[utoipa::path(
+    get,
+    path = "/api/v1/logs",
+    tag = "system",
+    params(LogsQuery),
+    responses(
+        (status = 200, description = "The latest log records", body = openapi::LogsResponse),
+        (status = 400, description = "Unknown level", body = openapi::ErrorResponse),
+    )
+)]
+async fn logs_handler(Query(params): Query<LogsQuery>) -> Response {
+    let level = match requested_log_level(params.level.as_deref()) {
+        Ok(level) => level,
+        Err(response) => return response,
+    };
+    let records = logtail::recent(level, params.limit.unwrap_or(usize::MAX));
+    Json(serde_json::json!({
+        "success": true,
+        "count": records.len(),
+        "data": records.iter().map(|r| &**r).collect::<Vec<&LogRecord>>()
+    })).into_response()
+}
+
+/// Stream log records as Server-Sent Events as soon as they are logged, one JSON record
+/// per message
+#[utoipa::path(
+    get,
+    path = "/api/v1/logs/stream",
+    tag = "system",
+    params(LogsQuery),
+    responses(
+        (status = 200, description = "Server-Sent Events, one JSON LogRecord per message", content_type = "text/event-stream", body = LogRecord),
+        (status = 400, description = "Unknown level", body = openapi::ErrorResponse),
+    )
+)]
+async fn log_stream_handler(State(state): State<AppState>, Query(params): Query<LogsQuery>) -> Response {
+    let level = match requested_log_level(params.level.as_deref()) {
+        Ok(level) => level,
+        Err(response) => return response,
+    };
+    // Ends the response on shutdown, which would otherwise wait for the client to leave
+    let (sender, receiver) = mpsc::channel(EVENT_STREAM_BUFFER);
+    tokio::spawn(forward_logs(logtail::subscribe(), sender, level, state.shutdown));
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok::<_, std::convert::Infallible>(event), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn forward_logs(
+    mut records: broadcast::Receiver<Arc<LogRecord>>,
+    sender: mpsc::Sender<Event>,
+    level: tracing::Level,
+    shutdown: ShutdownHandle,
+) {
+    loop {
+        let record = tokio::select! {
+            record = records.recv() => match record {
+                Ok(record) => record,
+                // Logging here would feed the stream that is behind
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    let notice = serde_json::json!({ "skipped": missed });
+                    let Ok(message) = Event::default().event("skipped").json_data(notice) else { continue };
+                    if sender.send(message).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = sender.closed() => return,
+            _ = shutdown.wait() => return,
+        };
+        if !record.at_least(level) {
+            continue;
+        }
+        let Ok(message) = Event::default().json_data(record.as_ref()) else { continue };
+        if sender.send(message).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn logs_page_handler() -> Html<&'static str> {
+    Html(LOGS_HTML)
+}
+
+/// Push each snapshot, then each of its events, to a dashboard as soon as it is stored
+async fn live_updates_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
+    let Some(monitor) = state.monitor else {
+        return monitor_not_running();
+    };
+    let updates = monitor.subscribe();
+    ws.on_upgrade(move |socket| push_live_updates(socket, updates, state.shutdown))
+}
+
+async fn push_live_updates(mut socket: WebSocket, mut updates: broadcast::Receiver<Arc<WifiSnapshot>>, shutdown: ShutdownHandle) {
+    loop {
+        let snapshot = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(snapshot) => snapshot,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("Dashboard connection fell {} snapshots behind", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Dashboards only listen; this notices when one goes away
+            message = socket.recv() => match message {
+                Some(Ok(_)) => continue,
+                _ => return,
+            },
+            _ = shutdown.wait() => break,
+        };
+
+        let messages = std::iter::once(serde_json::json!({ "type": "snapshot", "data": snapshot.as_ref() }))
+            .chain(snapshot.events.iter().map(|event| serde_json::json!({ "type": "event", "data": event })));
+        for message in messages {
+            if socket.send(Message::Text(message.to_string())).await.is_err() {
+                return;
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/alerts",
+    tag = "alerts",
+    responses(
+        (status = 200, description = "Alert state and whether a mute is in effect", body = openapi::AlertStateResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn alert_state_handler(State(store): State<SharedStore>) -> impl IntoResponse {
+    match store.get_alert_state() {
+        Ok(alerts) => Json(serde_json::json!({
             "success": true,
-            "data": null,
-            "message": "No data collected yet"
+            "data": alerts,
+            "muted": alerts.active_mute(chrono::Utc::now()).is_some()
         })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -83,15 +2288,37 @@ async fn current_handler(State(store): State<SharedStore>) -> impl IntoResponse
     }
 }
 
-async fn snapshots_handler(
-    State(store): State<SharedStore>,
-    Query(params): Query<TimeRangeQuery>,
-) -> impl IntoResponse {
-    match store.get_snapshots(params.start.as_deref(), params.end.as_deref(), params.limit) {
-        Ok(snapshots) => Json(serde_json::json!({
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AlertHistoryQuery {
+    /// Start of the range (ISO 8601)
+    start: Option<String>,
+    /// End of the range (ISO 8601)
+    end: Option<String>,
+    /// Only alerts of the rule with this name
+    name: Option<String>,
+    /// Most alerts to return, default 100, at most 1000
+    limit: Option<u32>,
+}
+
+/// Alerts fired by the `--alert-rules` in a range, newest first, with when each resolved
+#[utoipa::path(
+    get,
+    path = "/api/v1/alerts/history",
+    tag = "alerts",
+    params(AlertHistoryQuery),
+    responses(
+        (status = 200, description = "Rule alerts in the range", body = openapi::AlertHistoryResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn alert_history_handler(State(store): State<SharedStore>, Query(params): Query<AlertHistoryQuery>) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match store.get_alerts(params.start.as_deref(), params.end.as_deref(), params.name.as_deref(), Some(limit)) {
+        Ok(alerts) => Json(serde_json::json!({
             "success": true,
-            "count": snapshots.len(),
-            "data": snapshots
+            "count": alerts.len(),
+            "data": alerts
         })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -103,43 +2330,56 @@ async fn snapshots_handler(
     }
 }
 
-async fn timeseries_handler(
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MuteQuery {
+    /// How long to mute for; defaults to 60
+    #[serde(default = "default_mute_minutes")]
+    minutes: i64,
+    /// Stored with the mute window
+    reason: Option<String>,
+}
+
+fn default_mute_minutes() -> i64 {
+    60
+}
+
+/// Silence outage alerts for a while; the mute is stored, so it outlasts a restart
+#[utoipa::path(
+    post,
+    path = "/api/v1/alerts/mute",
+    tag = "alerts",
+    params(MuteQuery),
+    responses(
+        (status = 200, description = "Alerts muted", body = openapi::MuteResponse),
+        (status = 400, description = "minutes is not positive", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn mute_handler(
     State(store): State<SharedStore>,
-    Query(params): Query<TimeseriesQuery>,
+    Query(params): Query<MuteQuery>,
 ) -> impl IntoResponse {
-    match store.get_timeseries(&params.metric, params.start.as_deref(), params.end.as_deref()) {
-        Ok(data) => Json(serde_json::json!({
-            "success": true,
-            "metric": params.metric,
-            "count": data.len(),
-            "data": data.into_iter().map(|(ts, val)| {
-                serde_json::json!({ "timestamp": ts, "value": val })
-            }).collect::<Vec<_>>()
-        })).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    if params.minutes <= 0 {
+        return (
+            StatusCode::BAD_REQUEST,
             Json(serde_json::json!({
                 "success": false,
-                "error": e.to_string()
+                "error": "minutes must be positive"
             })),
-        ).into_response(),
+        ).into_response();
     }
-}
 
-async fn events_handler(
-    State(store): State<SharedStore>,
-    Query(params): Query<EventsQuery>,
-) -> impl IntoResponse {
-    match store.get_events(
-        params.start.as_deref(),
-        params.end.as_deref(),
-        params.severity.as_deref(),
-        params.event_type.as_deref(),
-    ) {
-        Ok(events) => Json(serde_json::json!({
+    let now = chrono::Utc::now();
+    let mute = MuteWindow {
+        started_at: now,
+        until: now + chrono::TimeDelta::minutes(params.minutes),
+        reason: params.reason,
+    };
+    match store.save_mute(Some(&mute)) {
+        Ok(()) => Json(serde_json::json!({
             "success": true,
-            "count": events.len(),
-            "data": events
+            "mute": mute
         })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -151,36 +2391,33 @@ async fn events_handler(
     }
 }
 
-async fn statistics_handler(
-    State(store): State<SharedStore>,
-    Query(params): Query<TimeRangeQuery>,
-) -> impl IntoResponse {
-    match store.get_statistics(params.start.as_deref(), params.end.as_deref()) {
-        Ok(stats) => Json(serde_json::json!({
+#[utoipa::path(
+    post,
+    path = "/api/v1/alerts/unmute",
+    tag = "alerts",
+    responses(
+        (status = 200, description = "Alerts unmuted; the mute that was lifted", body = openapi::MuteResponse),
+        (status = 409, description = "Not muted", body = openapi::ErrorResponse),
+        (status = 500, description = "The database could not be read", body = openapi::ErrorResponse),
+    )
+)]
+async fn unmute_handler(State(store): State<SharedStore>) -> impl IntoResponse {
+    let result = store.get_alert_state().and_then(|alerts| {
+        store.save_mute(None)?;
+        Ok(alerts.active_mute(chrono::Utc::now()).cloned())
+    });
+    match result {
+        Ok(Some(mute)) => Json(serde_json::json!({
             "success": true,
-            "data": stats
+            "mute": mute
         })).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
+        Ok(None) => (
+            StatusCode::CONFLICT,
             Json(serde_json::json!({
                 "success": false,
-                "error": e.to_string()
+                "error": "Alerts are not muted"
             })),
         ).into_response(),
-    }
-}
-
-async fn event_counts_handler(
-    State(store): State<SharedStore>,
-    Query(params): Query<TimeRangeQuery>,
-) -> impl IntoResponse {
-    match store.get_event_counts_by_type(params.start.as_deref(), params.end.as_deref()) {
-        Ok(counts) => Json(serde_json::json!({
-            "success": true,
-            "data": counts.into_iter().map(|(event_type, count)| {
-                serde_json::json!({ "event_type": event_type, "count": count })
-            }).collect::<Vec<_>>()
-        })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
@@ -197,9 +2434,9 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>WiFi Stability Tracker - Dashboard</title>
-    <script src="https://cdn.jsdelivr.net/npm/chart.js"></script>
-    <script src="https://cdn.jsdelivr.net/npm/chartjs-adapter-date-fns"></script>
-    <script src="https://cdn.tailwindcss.com"></script>
+    <script src="assets/chart.umd.js"></script>
+    <script src="assets/chartjs-adapter-date-fns.bundle.min.js"></script>
+    <script src="assets/tailwind.js"></script>
     <style>
         .status-good { color: #10b981; }
         .status-warning { color: #f59e0b; }
@@ -218,8 +2455,15 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         <header class="mb-8">
             <div class="flex justify-between items-start">
                 <div>
+                    <a id="all-hosts" href="/" class="hidden text-sm text-blue-400 hover:underline">&larr; All hosts</a>
                     <h1 class="text-3xl font-bold text-white mb-2">WiFi Stability Tracker</h1>
                     <p class="text-gray-400">Real-time monitoring and analysis dashboard</p>
+                    <div id="monitor-controls" class="mt-3 flex items-center gap-2 hidden">
+                        <span id="monitor-state" class="text-sm text-gray-400 mr-2"></span>
+                        <button id="pause-button" onclick="controlMonitor(this)" class="bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm">Pause</button>
+                        <button id="stop-button" onclick="controlMonitor(this)" class="bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm">Stop</button>
+                        <button id="diagnose-button" onclick="runDiagnostics(this)" class="bg-blue-700 hover:bg-blue-600 px-3 py-1 rounded text-sm">Run diagnostics now</button>
+                    </div>
                 </div>
                 <div class="bg-gray-800 rounded-lg p-4 border border-gray-700">
                     <label class="text-gray-400 text-sm font-medium mb-2 block">Time Range</label>
@@ -234,6 +2478,7 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                         <option value="1440">Last 24 hours</option>
                         <option value="4320">Last 3 days</option>
                         <option value="10080">Last 7 days</option>
+                        <optgroup id="session-options" label="Sessions"></optgroup>
                         <option value="custom">Custom Range</option>
                     </select>
                     <div id="custom-range" class="mt-3 space-y-2 hidden">
@@ -241,6 +2486,13 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                         <input type="datetime-local" id="end-time" class="bg-gray-700 border border-gray-600 rounded px-3 py-1 text-sm w-full">
                         <button onclick="applyCustomRange()" class="bg-blue-600 hover:bg-blue-700 px-3 py-1 rounded text-sm w-full">Apply</button>
                     </div>
+                    <button onclick="openReport()" class="mt-3 bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm w-full">Report for this range</button>
+                    <div class="mt-2 flex gap-2">
+                        <button onclick="downloadExport('json')" class="bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm flex-1">Export JSON</button>
+                        <button onclick="downloadExport('csv')" class="bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm flex-1">Export CSV</button>
+                    </div>
+                    <button onclick="deleteData()" class="mt-2 bg-gray-700 hover:bg-red-700 px-3 py-1 rounded text-sm w-full">Delete data...</button>
+                    <a href="logs" class="mt-2 block text-center bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm w-full">View logs</a>
                 </div>
             </div>
         </header>
@@ -267,6 +2519,7 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                 </div>
                 <p id="latency-range" class="text-gray-500 text-sm mt-2">Min: -- / Max: --</p>
                 <p id="jitter-value" class="text-gray-500 text-sm">Jitter: -- ms</p>
+                <p id="mos-value" class="text-gray-500 text-sm">Call quality (MOS): --</p>
             </div>
 
             <div class="bg-gray-800 rounded-lg p-4 border border-gray-700">
@@ -309,6 +2562,10 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                     <p class="text-gray-400 text-sm">P95 Latency</p>
                     <p id="stat-p95" class="text-lg font-semibold">-- ms</p>
                 </div>
+                <div>
+                    <p class="text-gray-400 text-sm">Avg Call MOS</p>
+                    <p id="stat-mos" class="text-lg font-semibold">--</p>
+                </div>
                 <div>
                     <p class="text-gray-400 text-sm">Disconnections</p>
                     <p id="stat-disconnections" class="text-lg font-semibold">--</p>
@@ -316,6 +2573,14 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             </div>
         </div>
 
+        <!-- Channel use by nearby networks in the latest neighbor scan -->
+        <div class="bg-gray-800 rounded-lg p-4 border border-gray-700 mb-8">
+            <h2 class="text-xl font-semibold mb-4">Nearby Networks <span id="neighbors-scanned" class="text-sm text-gray-400 font-normal"></span></h2>
+            <div id="neighbor-channels" class="grid grid-cols-2 md:grid-cols-4 lg:grid-cols-6 gap-4">
+                <p class="text-gray-500">No neighbor scans yet.</p>
+            </div>
+        </div>
+
         <!-- Charts -->
         <div class="grid grid-cols-1 lg:grid-cols-2 gap-6 mb-8">
             <div class="bg-gray-800 rounded-lg p-4 border border-gray-700">
@@ -347,6 +2612,22 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             </div>
         </div>
 
+        <!-- Utilization -->
+        <div class="bg-gray-800 rounded-lg p-4 border border-gray-700 mb-8">
+            <h3 class="text-lg font-semibold mb-4">Utilization vs Latency</h3>
+            <div class="chart-container">
+                <canvas id="throughput-chart"></canvas>
+            </div>
+        </div>
+
+        <!-- Call Quality -->
+        <div class="bg-gray-800 rounded-lg p-4 border border-gray-700 mb-8">
+            <h3 class="text-lg font-semibold mb-4">Estimated Call Quality (MOS)</h3>
+            <div class="chart-container">
+                <canvas id="mos-chart"></canvas>
+            </div>
+        </div>
+
         <!-- Event Counts -->
         <div class="grid grid-cols-1 lg:grid-cols-3 gap-6 mb-8">
             <div class="bg-gray-800 rounded-lg p-4 border border-gray-700">
@@ -382,6 +2663,10 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             <div id="events-container" class="max-h-96 overflow-y-auto space-y-2">
                 <p class="text-gray-500">Loading events...</p>
             </div>
+            <div class="flex justify-between items-center mt-3">
+                <span id="events-total" class="text-sm text-gray-400"></span>
+                <button id="events-more" onclick="loadMoreEvents()" class="hidden bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm">Load more</button>
+            </div>
         </div>
 
         <!-- Detailed Info -->
@@ -425,10 +2710,20 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
 
     <script>
         // Chart instances
-        let signalChart, latencyChart, packetLossChart, connectionChart, eventTypeChart, dnsChart;
+        let signalChart, latencyChart, packetLossChart, connectionChart, eventTypeChart, dnsChart, throughputChart, mosChart;
         
-        // Time range state
+        // Time range state; a running session has a start but no end
         let currentTimeRange = { minutes: 60, start: null, end: null };
+
+        const MAX_EVENT_ENTRIES = 100;
+        // Enough for a chart's width; longer ranges are averaged by the server
+        const CHART_MAX_POINTS = 1000;
+        const CHART_REFRESH_MS = 5000;
+        const STATS_REFRESH_MS = 30000;
+        const LIVE_RETRY_MS = 5000;
+
+        // Monitoring sessions by id, for the time range menu
+        let sessions = {};
         
         // Get adaptive time unit based on range
         function getTimeUnit(minutes) {
@@ -440,14 +2735,51 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         
         // Get time range parameters
         function getTimeRangeParams() {
-            if (currentTimeRange.start && currentTimeRange.end) {
-                return `start=${currentTimeRange.start}&end=${currentTimeRange.end}`;
+            if (currentTimeRange.start) {
+                const end = currentTimeRange.end || new Date().toISOString();
+                return `start=${encodeURIComponent(currentTimeRange.start)}&end=${encodeURIComponent(end)}`;
             }
             const end = new Date();
             const start = new Date(end.getTime() - currentTimeRange.minutes * 60000);
             return `start=${start.toISOString()}&end=${end.toISOString()}`;
         }
         
+        // The full analysis report over the selected range, in a new tab
+        function openReport() {
+            window.open(`api/v1/report?${getTimeRangeParams()}`, '_blank');
+        }
+        
+        // The selected range as a file; the server names it
+        function downloadExport(format) {
+            window.location.href = `api/v1/export?format=${format}&${getTimeRangeParams()}`;
+        }
+
+        // Delete the selected session, or everything older than a number of days
+        async function deleteData() {
+            const selected = document.getElementById('time-range').value;
+            let query;
+            if (selected.startsWith('session:')) {
+                const id = selected.slice('session:'.length);
+                if (!confirm(`Delete session ${id} and everything it recorded? This can't be undone.`)) return;
+                query = `session=${encodeURIComponent(id)}`;
+            } else {
+                const days = parseInt(prompt('Delete everything older than how many days?', '30'), 10);
+                if (!(days >= 0)) return;
+                const before = new Date(Date.now() - days * 86400000).toISOString();
+                if (!confirm(`Delete everything recorded before ${new Date(before).toLocaleString()}? This can't be undone.`)) return;
+                query = `before=${encodeURIComponent(before)}`;
+            }
+            try {
+                const response = await fetch(`api/v1/data?${query}&vacuum=true`, { method: 'DELETE' });
+                const result = await response.json();
+                alert(result.success ? `Deleted ${result.removed} rows` : `Could not delete: ${result.error}`);
+            } catch (e) {
+                alert(`Could not delete: ${e}`);
+            }
+            loadSessions();
+            refreshAllData();
+        }
+        
         // Initialize charts
         function initCharts() {
             const chartOptions = {
@@ -514,6 +2846,32 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                 options: { ...chartOptions, scales: { ...chartOptions.scales, y: { ...chartOptions.scales.y, min: 0, max: 1.2 } }, plugins: { legend: { display: true, labels: { color: '#9ca3af' } } } }
             });
 
+            mosChart = new Chart(document.getElementById('mos-chart'), {
+                type: 'line',
+                data: { datasets: [{ label: 'MOS', borderColor: '#8b5cf6', backgroundColor: 'rgba(139,92,246,0.1)', fill: true, tension: 0.3 }] },
+                options: { ...chartOptions, scales: { ...chartOptions.scales, y: { ...chartOptions.scales.y, min: 1, max: 4.5 } } }
+            });
+
+            throughputChart = new Chart(document.getElementById('throughput-chart'), {
+                type: 'line',
+                data: {
+                    datasets: [
+                        { label: 'Rx (Mbps)', borderColor: '#10b981', backgroundColor: 'rgba(16,185,129,0.1)', fill: true, tension: 0.3, yAxisID: 'y' },
+                        { label: 'Tx (Mbps)', borderColor: '#3b82f6', backgroundColor: 'rgba(59,130,246,0.1)', fill: true, tension: 0.3, yAxisID: 'y' },
+                        { label: 'Avg Latency (ms)', borderColor: '#ef4444', backgroundColor: 'transparent', borderDash: [5, 5], tension: 0.3, yAxisID: 'y1' }
+                    ]
+                },
+                options: {
+                    ...chartOptions,
+                    scales: {
+                        ...chartOptions.scales,
+                        y: { ...chartOptions.scales.y, min: 0, title: { display: true, text: 'Mbps', color: '#9ca3af' } },
+                        y1: { position: 'right', min: 0, grid: { drawOnChartArea: false }, ticks: { color: '#9ca3af' }, title: { display: true, text: 'ms', color: '#9ca3af' } }
+                    },
+                    plugins: { legend: { display: true, labels: { color: '#9ca3af' } } }
+                }
+            });
+
             eventTypeChart = new Chart(document.getElementById('event-type-chart'), {
                 type: 'doughnut',
                 data: { labels: [], datasets: [{ data: [], backgroundColor: ['#ef4444', '#f59e0b', '#3b82f6', '#10b981', '#8b5cf6', '#ec4899'] }] },
@@ -530,122 +2888,128 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         // Update current status
         async function updateCurrent() {
             try {
-                const response = await fetch('/api/current');
+                const response = await fetch('api/v1/current');
                 const result = await response.json();
                 console.log('API Response:', result);
                 
                 if (result.success && result.data) {
-                    const data = result.data;
-                    console.log('Data:', data);
-                    
-                    // Update signal
-                    if (data.wifi_info) {
-                        const wifi = data.wifi_info;
-                        const signalValue = document.getElementById('signal-value');
-                        const signalQuality = document.getElementById('signal-quality');
-                        const signalBar = document.getElementById('signal-bar');
-                        const ssidValue = document.getElementById('ssid-value');
-                        const channelValue = document.getElementById('channel-value');
-                        const speedValue = document.getElementById('speed-value');
-                        
-                        if (signalValue) signalValue.textContent = wifi.signal_strength_dbm;
-                        if (signalQuality) signalQuality.textContent = `${wifi.signal_quality_percent}% quality`;
-                        if (signalBar) signalBar.style.width = `${wifi.signal_quality_percent}%`;
-                        
-                        if (signalValue) {
-                            signalValue.className = wifi.signal_strength_dbm > -60 ? 'text-2xl font-bold status-good' :
-                                                wifi.signal_strength_dbm > -70 ? 'text-2xl font-bold status-warning' : 'text-2xl font-bold status-critical';
-                        }
-                        
-                        if (ssidValue) ssidValue.textContent = wifi.ssid || '--';
-                        if (channelValue) channelValue.textContent = `Channel: ${wifi.channel} (${wifi.band.replace('Band', '').replace('_', '.')})`;
-                        if (speedValue) speedValue.textContent = `Speed: ${wifi.link_speed_mbps} Mbps`;
-                        
-                        const detailBssid = document.getElementById('detail-bssid');
-                        const detailPhy = document.getElementById('detail-phy');
-                        const detailSecurity = document.getElementById('detail-security');
-                        const detailFrequency = document.getElementById('detail-frequency');
-                        const detailIpv4 = document.getElementById('detail-ipv4');
-                        const detailIpv6 = document.getElementById('detail-ipv6');
-                        const detailGateway = document.getElementById('detail-gateway');
-                        const detailDns = document.getElementById('detail-dns');
-                        
-                        if (detailBssid) detailBssid.textContent = wifi.bssid || '--';
-                        if (detailPhy) detailPhy.textContent = wifi.phy_type || '--';
-                        if (detailSecurity) detailSecurity.textContent = wifi.security_type || '--';
-                        if (detailFrequency) detailFrequency.textContent = `${wifi.frequency_mhz} MHz`;
-                        if (detailIpv4) detailIpv4.textContent = wifi.ipv4_address || '--';
-                        if (detailIpv6) detailIpv6.textContent = wifi.ipv6_address || '--';
-                        if (detailGateway) detailGateway.textContent = wifi.gateway || '--';
-                        if (detailDns) detailDns.textContent = wifi.dns_servers?.join(', ') || '--';
-                    }
-                    
-                    // Update latency
-                    if (data.latency) {
-                        const lat = data.latency;
-                        const latencyValue = document.getElementById('latency-value');
-                        const latencyRange = document.getElementById('latency-range');
-                        const jitterValue = document.getElementById('jitter-value');
-                        const packetLossValue = document.getElementById('packet-loss-value');
-                        
-                        if (latencyValue) latencyValue.textContent = lat.average_latency_ms?.toFixed(1) || '--';
-                        if (latencyRange) latencyRange.textContent = `Min: ${lat.min_latency_ms?.toFixed(1) || '--'} / Max: ${lat.max_latency_ms?.toFixed(1) || '--'}`;
-                        if (jitterValue) jitterValue.textContent = `Jitter: ${lat.jitter_ms?.toFixed(1) || '--'} ms`;
-                        if (packetLossValue) packetLossValue.textContent = lat.packet_loss_percent?.toFixed(1) || '0';
-                        
-                        if (latencyValue) {
-                            const avgLat = lat.average_latency_ms || 0;
-                            latencyValue.className = avgLat < 50 ? 'text-2xl font-bold status-good' :
-                                             avgLat < 100 ? 'text-2xl font-bold status-warning' : 'text-2xl font-bold status-critical';
-                        }
-                    }
-                    
-                    // Update connectivity
-                    if (data.connectivity) {
-                        const conn = data.connectivity;
-                        console.log('Connectivity data:', conn);
-                        const loopbackStatus = document.getElementById('loopback-status');
-                        const routerStatus = document.getElementById('router-status');
-                        const internetStatus = document.getElementById('internet-status');
-                        const connectionStatus = document.getElementById('connection-status');
-                        
-                        if (loopbackStatus) loopbackStatus.innerHTML = `Loopback: <span class="font-semibold ${conn.loopback_reachable ? 'status-good' : 'status-critical'}">${conn.loopback_reachable ? 'OK' : 'Failed'}</span>`;
-                        if (routerStatus) routerStatus.innerHTML = `Router: <span class="font-semibold ${conn.router_reachable ? 'status-good' : 'status-critical'}">${conn.router_reachable ? 'Reachable' : 'Unreachable'}</span>`;
-                        if (internetStatus) internetStatus.innerHTML = `Internet: <span class="font-semibold ${conn.internet_reachable ? 'status-good' : 'status-critical'}">${conn.internet_reachable ? 'Reachable' : 'Unreachable'}</span>`;
-                        if (connectionStatus) connectionStatus.innerHTML = `WiFi: <span class="font-semibold ${conn.is_connected ? 'status-good' : 'status-critical'}">${conn.is_connected ? 'Connected' : 'Disconnected'}</span>`;
-                    } else {
-                        console.log('No connectivity data available');
-                    }
-                    
-                    // Update system info
-                    if (data.system_info) {
-                        const sys = data.system_info;
-                        console.log('System info data:', sys);
-                        const detailCpu = document.getElementById('detail-cpu');
-                        const detailMemory = document.getElementById('detail-memory');
-                        const detailBytesSent = document.getElementById('detail-bytes-sent');
-                        const detailBytesRecv = document.getElementById('detail-bytes-recv');
-                        
-                        if (detailCpu) detailCpu.textContent = `${(sys.cpu_usage_percent || 0).toFixed(1)}%`;
-                        if (detailMemory) detailMemory.textContent = `${(sys.memory_usage_percent || 0).toFixed(1)}%`;
-                        if (detailBytesSent) detailBytesSent.textContent = formatBytes(sys.bytes_sent || 0);
-                        if (detailBytesRecv) detailBytesRecv.textContent = formatBytes(sys.bytes_received || 0);
-                    } else {
-                        console.log('No system info data available');
-                    }
-                    
-                    const lastUpdate = document.getElementById('last-update');
-                    if (lastUpdate) lastUpdate.textContent = new Date(data.timestamp).toLocaleString();
+                    showCurrent(result.data);
                 }
             } catch (e) {
                 console.error('Failed to fetch current data:', e);
             }
         }
 
+        // Show a snapshot in the status cards and details
+        function showCurrent(data) {
+            console.log('Data:', data);
+            
+            // Update signal
+            if (data.wifi_info) {
+                const wifi = data.wifi_info;
+                const signalValue = document.getElementById('signal-value');
+                const signalQuality = document.getElementById('signal-quality');
+                const signalBar = document.getElementById('signal-bar');
+                const ssidValue = document.getElementById('ssid-value');
+                const channelValue = document.getElementById('channel-value');
+                const speedValue = document.getElementById('speed-value');
+                
+                if (signalValue) signalValue.textContent = wifi.signal_strength_dbm;
+                if (signalQuality) signalQuality.textContent = `${wifi.signal_quality_percent}% quality`;
+                if (signalBar) signalBar.style.width = `${wifi.signal_quality_percent}%`;
+                
+                if (signalValue) {
+                    signalValue.className = wifi.signal_strength_dbm > -60 ? 'text-2xl font-bold status-good' :
+                                        wifi.signal_strength_dbm > -70 ? 'text-2xl font-bold status-warning' : 'text-2xl font-bold status-critical';
+                }
+                
+                if (ssidValue) ssidValue.textContent = wifi.ssid || '--';
+                if (channelValue) channelValue.textContent = `Channel: ${wifi.channel} (${wifi.band.replace('Band', '').replace('_', '.')})`;
+                if (speedValue) speedValue.textContent = `Speed: ${wifi.link_speed_mbps} Mbps`;
+                
+                const detailBssid = document.getElementById('detail-bssid');
+                const detailPhy = document.getElementById('detail-phy');
+                const detailSecurity = document.getElementById('detail-security');
+                const detailFrequency = document.getElementById('detail-frequency');
+                const detailIpv4 = document.getElementById('detail-ipv4');
+                const detailIpv6 = document.getElementById('detail-ipv6');
+                const detailGateway = document.getElementById('detail-gateway');
+                const detailDns = document.getElementById('detail-dns');
+                
+                if (detailBssid) detailBssid.textContent = wifi.bssid || '--';
+                if (detailPhy) detailPhy.textContent = wifi.phy_type || '--';
+                if (detailSecurity) detailSecurity.textContent = wifi.security_type || '--';
+                if (detailFrequency) detailFrequency.textContent = `${wifi.frequency_mhz} MHz`;
+                if (detailIpv4) detailIpv4.textContent = wifi.ipv4_address || '--';
+                if (detailIpv6) detailIpv6.textContent = wifi.ipv6_address || '--';
+                if (detailGateway) detailGateway.textContent = wifi.gateway || '--';
+                if (detailDns) detailDns.textContent = wifi.dns_servers?.join(', ') || '--';
+            }
+            
+            // Update latency
+            if (data.latency) {
+                const lat = data.latency;
+                const latencyValue = document.getElementById('latency-value');
+                const latencyRange = document.getElementById('latency-range');
+                const jitterValue = document.getElementById('jitter-value');
+                const packetLossValue = document.getElementById('packet-loss-value');
+                
+                if (latencyValue) latencyValue.textContent = lat.average_latency_ms?.toFixed(1) || '--';
+                if (latencyRange) latencyRange.textContent = `Min: ${lat.min_latency_ms?.toFixed(1) || '--'} / Max: ${lat.max_latency_ms?.toFixed(1) || '--'}`;
+                if (jitterValue) jitterValue.textContent = `Jitter: ${lat.jitter_ms?.toFixed(1) || '--'} ms`;
+                const mosValue = document.getElementById('mos-value');
+                if (mosValue) mosValue.textContent = `Call quality (MOS): ${lat.mos?.toFixed(2) || '--'}`;
+                if (packetLossValue) packetLossValue.textContent = lat.packet_loss_percent?.toFixed(1) || '0';
+                
+                if (latencyValue) {
+                    const avgLat = lat.average_latency_ms || 0;
+                    latencyValue.className = avgLat < 50 ? 'text-2xl font-bold status-good' :
+                                     avgLat < 100 ? 'text-2xl font-bold status-warning' : 'text-2xl font-bold status-critical';
+                }
+            }
+            
+            // Update connectivity
+            if (data.connectivity) {
+                const conn = data.connectivity;
+                console.log('Connectivity data:', conn);
+                const loopbackStatus = document.getElementById('loopback-status');
+                const routerStatus = document.getElementById('router-status');
+                const internetStatus = document.getElementById('internet-status');
+                const connectionStatus = document.getElementById('connection-status');
+                
+                if (loopbackStatus) loopbackStatus.innerHTML = `Loopback: <span class="font-semibold ${conn.loopback_reachable ? 'status-good' : 'status-critical'}">${conn.loopback_reachable ? 'OK' : 'Failed'}</span>`;
+                if (routerStatus) routerStatus.innerHTML = `Router: <span class="font-semibold ${conn.router_reachable ? 'status-good' : 'status-critical'}">${conn.router_reachable ? 'Reachable' : 'Unreachable'}</span>`;
+                if (internetStatus) internetStatus.innerHTML = `Internet: <span class="font-semibold ${conn.internet_reachable ? 'status-good' : 'status-critical'}">${conn.internet_reachable ? 'Reachable' : 'Unreachable'}</span>`;
+                if (connectionStatus) connectionStatus.innerHTML = `WiFi: <span class="font-semibold ${conn.is_connected ? 'status-good' : 'status-critical'}">${conn.is_connected ? 'Connected' : 'Disconnected'}</span>`;
+            } else {
+                console.log('No connectivity data available');
+            }
+            
+            // Update system info
+            if (data.system_info) {
+                const sys = data.system_info;
+                console.log('System info data:', sys);
+                const detailCpu = document.getElementById('detail-cpu');
+                const detailMemory = document.getElementById('detail-memory');
+                const detailBytesSent = document.getElementById('detail-bytes-sent');
+                const detailBytesRecv = document.getElementById('detail-bytes-recv');
+                
+                if (detailCpu) detailCpu.textContent = `${(sys.cpu_usage_percent || 0).toFixed(1)}%`;
+                if (detailMemory) detailMemory.textContent = `${(sys.memory_usage_percent || 0).toFixed(1)}%`;
+                if (detailBytesSent) detailBytesSent.textContent = formatBytes(sys.bytes_sent || 0);
+                if (detailBytesRecv) detailBytesRecv.textContent = formatBytes(sys.bytes_received || 0);
+            } else {
+                console.log('No system info data available');
+            }
+            
+            const lastUpdate = document.getElementById('last-update');
+            if (lastUpdate) lastUpdate.textContent = new Date(data.timestamp).toLocaleString();
+        }
+
         // Update chart time scales
         function updateChartTimeScales() {
             const timeUnit = getTimeUnit(currentTimeRange.minutes);
-            const charts = [signalChart, latencyChart, packetLossChart, connectionChart, dnsChart];
+            const charts = [signalChart, latencyChart, packetLossChart, connectionChart, dnsChart, throughputChart, mosChart];
             
             charts.forEach(chart => {
                 if (chart && chart.options.scales.x) {
@@ -659,21 +3023,24 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         async function updateCharts() {
             try {
                 const timeParams = getTimeRangeParams();
-                const [signalRes, latencyLoopbackRes, latencyRouterRes, latencyAvgRes, latencyMaxRes, packetLossRes, connectedRes, routerRes, internetRes, dnsRes] = await Promise.all([
-                    fetch(`/api/timeseries?metric=signal_dbm&${timeParams}`),
-                    fetch(`/api/timeseries?metric=latency_loopback&${timeParams}`),
-                    fetch(`/api/timeseries?metric=latency_router&${timeParams}`),
-                    fetch(`/api/timeseries?metric=latency_avg&${timeParams}`),
-                    fetch(`/api/timeseries?metric=latency_max&${timeParams}`),
-                    fetch(`/api/timeseries?metric=packet_loss&${timeParams}`),
-                    fetch(`/api/timeseries?metric=connected&${timeParams}`),
-                    fetch(`/api/timeseries?metric=router_reachable&${timeParams}`),
-                    fetch(`/api/timeseries?metric=internet_reachable&${timeParams}`),
-                    fetch(`/api/timeseries?metric=dns_resolution_time&${timeParams}`)
+                const [signalRes, latencyLoopbackRes, latencyRouterRes, latencyAvgRes, latencyMaxRes, packetLossRes, connectedRes, routerRes, internetRes, dnsRes, rxRes, txRes, mosRes] = await Promise.all([
+                    fetch(`api/v1/timeseries?metric=signal_dbm&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=latency_loopback&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=latency_router&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=latency_avg&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=latency_max&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=packet_loss&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=connected&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=router_reachable&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=internet_reachable&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=dns_resolution_time&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=throughput_rx&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=throughput_tx&max_points=${CHART_MAX_POINTS}&${timeParams}`),
+                    fetch(`api/v1/timeseries?metric=mos&max_points=${CHART_MAX_POINTS}&${timeParams}`)
                 ]);
 
-                const [signalData, latencyLoopbackData, latencyRouterData, latencyAvgData, latencyMaxData, packetLossData, connectedData, routerData, internetData, dnsData] = await Promise.all([
-                    signalRes.json(), latencyLoopbackRes.json(), latencyRouterRes.json(), latencyAvgRes.json(), latencyMaxRes.json(), packetLossRes.json(), connectedRes.json(), routerRes.json(), internetRes.json(), dnsRes.json()
+                const [signalData, latencyLoopbackData, latencyRouterData, latencyAvgData, latencyMaxData, packetLossData, connectedData, routerData, internetData, dnsData, rxData, txData, mosData] = await Promise.all([
+                    signalRes.json(), latencyLoopbackRes.json(), latencyRouterRes.json(), latencyAvgRes.json(), latencyMaxRes.json(), packetLossRes.json(), connectedRes.json(), routerRes.json(), internetRes.json(), dnsRes.json(), rxRes.json(), txRes.json(), mosRes.json()
                 ]);
 
                 if (signalData.success) {
@@ -705,6 +3072,19 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                     dnsChart.data.datasets[0].data = dnsData.data.map(d => ({ x: new Date(d.timestamp), y: d.value }));
                     dnsChart.update('none');
                 }
+
+                if (mosData.success) {
+                    mosChart.data.datasets[0].data = mosData.data.map(d => ({ x: new Date(d.timestamp), y: d.value }));
+                    mosChart.update('none');
+                }
+
+                if (rxData.success && txData.success && latencyAvgData.success) {
+                    const toMbps = d => ({ x: new Date(d.timestamp), y: d.value * 8 / 1000000 });
+                    throughputChart.data.datasets[0].data = rxData.data.map(toMbps);
+                    throughputChart.data.datasets[1].data = txData.data.map(toMbps);
+                    throughputChart.data.datasets[2].data = latencyAvgData.data.map(d => ({ x: new Date(d.timestamp), y: d.value }));
+                    throughputChart.update('none');
+                }
             } catch (e) {
                 console.error('Failed to update charts:', e);
             }
@@ -714,7 +3094,7 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         async function updateEventCounts() {
             try {
                 const timeParams = getTimeRangeParams();
-                const response = await fetch(`/api/event-counts?${timeParams}`);
+                const response = await fetch(`api/v1/event-counts?${timeParams}`);
                 const result = await response.json();
                 
                 if (result.success && result.data.length > 0) {
@@ -731,7 +3111,7 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
         async function updateStatistics() {
             try {
                 const timeParams = getTimeRangeParams();
-                const response = await fetch(`/api/statistics?${timeParams}`);
+                const response = await fetch(`api/v1/statistics?${timeParams}`);
                 const result = await response.json();
                 
                 if (result.success && result.data) {
@@ -741,6 +3121,7 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
                     document.getElementById('stat-internet-uptime').textContent = `${stats.internet_uptime_percent?.toFixed(1) || '--'}%`;
                     document.getElementById('stat-latency').textContent = `${stats.latency_avg_ms?.toFixed(1) || '--'} ms`;
                     document.getElementById('stat-p95').textContent = `${stats.latency_p95_ms?.toFixed(1) || '--'} ms`;
+                    document.getElementById('stat-mos').textContent = stats.mos_avg?.toFixed(2) || '--';
                     document.getElementById('stat-disconnections').textContent = stats.total_disconnections || '0';
                 }
             } catch (e) {
@@ -748,34 +3129,176 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             }
         }
 
+        // Networks per channel in the latest scan; SSIDs come from the air, so only textContent
+        async function updateNeighbors() {
+            try {
+                const response = await fetch(`api/v1/neighbors?${getTimeRangeParams()}`);
+                const result = await response.json();
+                if (!result.success || !result.scanned_at) return;
+
+                document.getElementById('neighbors-scanned').textContent =
+                    `scanned ${new Date(result.scanned_at).toLocaleTimeString()}`;
+                const container = document.getElementById('neighbor-channels');
+                container.replaceChildren(...result.channels.map(c => {
+                    const card = document.createElement('div');
+                    const label = document.createElement('p');
+                    label.className = 'text-gray-400 text-sm';
+                    label.textContent = `Channel ${c.channel} (${c.band})`;
+                    const count = document.createElement('p');
+                    count.className = `text-lg font-semibold ${c.networks > 3 ? 'status-warning' : ''}`;
+                    count.textContent = `${c.networks} network${c.networks === 1 ? '' : 's'}`;
+                    const detail = document.createElement('p');
+                    detail.className = 'text-gray-500 text-xs truncate';
+                    detail.textContent = `${c.strongest_dbm} dBm strongest`;
+                    detail.title = c.ssids.join(', ');
+                    card.append(label, count, detail);
+                    return card;
+                }));
+            } catch (e) {
+                console.error('Failed to fetch neighbor networks:', e);
+            }
+        }
+
+        // The event log shows the newest page; "Load more" follows the API's `next` link
+        let nextEventsUrl = null;
+        let eventLogSize = MAX_EVENT_ENTRIES;
+
         // Refresh events
         async function refreshEvents() {
             try {
                 const severity = document.getElementById('severity-filter').value;
                 const timeParams = getTimeRangeParams();
-                const url = severity ? `/api/events?severity=${severity}&${timeParams}` : `/api/events?${timeParams}`;
-                const response = await fetch(url);
+                const filter = severity ? `severity=${severity}&` : '';
+                const response = await fetch(`api/v1/events?${filter}limit=${MAX_EVENT_ENTRIES}&${timeParams}`);
                 const result = await response.json();
                 
                 const container = document.getElementById('events-container');
+                eventLogSize = MAX_EVENT_ENTRIES;
                 
                 if (result.success && result.data.length > 0) {
-                    container.innerHTML = result.data.slice(0, 100).map(event => `
-                        <div class="log-entry bg-gray-700 rounded p-2 flex items-start gap-3">
-                            <span class="severity-${event.severity.toLowerCase()} text-white text-xs px-2 py-0.5 rounded">${event.severity}</span>
-                            <span class="text-gray-400 whitespace-nowrap">${new Date(event.timestamp).toLocaleString()}</span>
-                            <span class="text-blue-400">[${event.event_type}]</span>
-                            <span class="text-gray-200 flex-1">${event.description}</span>
-                        </div>
-                    `).join('');
+                    container.innerHTML = result.data.map(eventEntry).join('');
                 } else {
                     container.innerHTML = '<p class="text-gray-500">No events recorded yet.</p>';
                 }
+                showEventPaging(result);
+            } catch (e) {
+                console.error('Failed to fetch events:', e);
+            }
+        }
+
+        // Append the next page of older events, skipping any a live push already shifted onto it
+        async function loadMoreEvents() {
+            if (!nextEventsUrl) return;
+            try {
+                // Relative, like every other request, in case the dashboard is mounted under a path
+                const response = await fetch(nextEventsUrl.replace(/^\//, ''));
+                const result = await response.json();
+                if (!result.success) return;
+
+                const container = document.getElementById('events-container');
+                const shown = new Set([...container.querySelectorAll('[data-event-id]')].map(entry => entry.dataset.eventId));
+                const older = result.data.filter(event => !shown.has(event.id));
+                container.insertAdjacentHTML('beforeend', older.map(eventEntry).join(''));
+                eventLogSize += older.length;
+                showEventPaging(result);
             } catch (e) {
                 console.error('Failed to fetch events:', e);
             }
         }
 
+        function showEventPaging(result) {
+            nextEventsUrl = result.success ? result.next : null;
+            const shown = document.querySelectorAll('#events-container .log-entry').length;
+            document.getElementById('events-total').textContent = result.success && result.total > 0 ? `${shown} of ${result.total} events` : '';
+            document.getElementById('events-more').classList.toggle('hidden', !nextEventsUrl);
+        }
+
+        function eventEntry(event) {
+            return `
+                <div class="log-entry bg-gray-700 rounded p-2 flex items-start gap-3" data-event-id="${event.id}">
+                    <span class="severity-${event.severity.toLowerCase()} text-white text-xs px-2 py-0.5 rounded">${event.severity}</span>
+                    <span class="text-gray-400 whitespace-nowrap">${new Date(event.timestamp).toLocaleString()}</span>
+                    <span class="text-blue-400">[${event.event_type}]</span>
+                    <span class="text-gray-200 flex-1">${event.description}</span>
+                </div>
+            `;
+        }
+
+        // Add a pushed event to the top of the log, if it passes the severity filter
+        function showLiveEvent(event) {
+            const severity = document.getElementById('severity-filter').value;
+            if (currentTimeRange.end || (severity && event.severity !== severity)) return;
+
+            const container = document.getElementById('events-container');
+            if (!container.querySelector('.log-entry')) container.innerHTML = '';
+            container.insertAdjacentHTML('afterbegin', eventEntry(event));
+            while (container.children.length > eventLogSize) container.lastElementChild.remove();
+        }
+
+        // The monitor pushes each snapshot, then its events, over /ws as soon as they are
+        // stored. Charts and statistics are refetched on a push, at most every few seconds.
+        // Without a monitor in this process, or while the connection is down, poll instead.
+        let pollTimers = [];
+        let liveSeen = false;
+        let lastChartRefresh = 0;
+        let lastStatsRefresh = 0;
+
+        function connectLive() {
+            const url = new URL('ws', location.href);
+            url.protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+            const socket = new WebSocket(url);
+            socket.onopen = () => {
+                liveSeen = true;
+                stopPolling();
+            };
+            socket.onmessage = message => onLiveUpdate(JSON.parse(message.data));
+            socket.onclose = () => {
+                startPolling();
+                // A monitor restarting itself comes back on the same port
+                if (liveSeen) setTimeout(connectLive, LIVE_RETRY_MS);
+            };
+        }
+
+        function onLiveUpdate(update) {
+            if (update.type === 'event') {
+                showLiveEvent(update.data);
+                return;
+            }
+            if (update.type !== 'snapshot') return;
+
+            showCurrent(update.data);
+            // A range that ended in the past doesn't change
+            if (currentTimeRange.end) return;
+            const now = Date.now();
+            if (now - lastChartRefresh >= CHART_REFRESH_MS) {
+                lastChartRefresh = now;
+                updateCharts();
+            }
+            if (now - lastStatsRefresh >= STATS_REFRESH_MS) {
+                lastStatsRefresh = now;
+                updateStatistics();
+                updateEventCounts();
+                updateNeighbors();
+            }
+        }
+
+        function startPolling() {
+            if (pollTimers.length > 0) return;
+            pollTimers = [
+                setInterval(updateCurrent, 5000),
+                setInterval(updateCharts, 10000),
+                setInterval(updateEventCounts, 30000),
+                setInterval(updateStatistics, 30000),
+                setInterval(updateNeighbors, 30000),
+                setInterval(refreshEvents, 15000)
+            ];
+        }
+
+        function stopPolling() {
+            pollTimers.forEach(clearInterval);
+            pollTimers = [];
+        }
+
         // Helper function
         function formatBytes(bytes) {
             if (!bytes) return '--';
@@ -793,7 +3316,10 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             const select = document.getElementById('time-range');
             const customRange = document.getElementById('custom-range');
             
-            if (select.value === 'custom') {
+            if (select.value.startsWith('session:')) {
+                customRange.classList.add('hidden');
+                applySession(select.value.slice('session:'.length));
+            } else if (select.value === 'custom') {
                 customRange.classList.remove('hidden');
                 // Set default values to last hour
                 const end = new Date();
@@ -810,6 +3336,48 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             }
         }
 
+        // List sessions in the time range menu; `?session=<id>` opens on one
+        async function loadSessions() {
+            try {
+                const response = await fetch('api/v1/sessions');
+                const result = await response.json();
+                if (!result.success) return;
+
+                const group = document.getElementById('session-options');
+                group.innerHTML = '';
+                for (const session of result.data) {
+                    sessions[session.id] = session;
+                    const option = document.createElement('option');
+                    option.value = `session:${session.id}`;
+                    const started = new Date(session.started_at).toLocaleString();
+                    option.textContent = session.ended_at ? started : `${started} (running)`;
+                    option.title = `${session.id} - ${session.snapshot_count} snapshots${session.host ? ' on ' + session.host : ''}`;
+                    group.appendChild(option);
+                }
+
+                const requested = new URLSearchParams(window.location.search).get('session');
+                if (requested && sessions[requested]) {
+                    document.getElementById('time-range').value = `session:${requested}`;
+                    applySession(requested);
+                }
+            } catch (e) {
+                console.error('Failed to load sessions:', e);
+            }
+        }
+
+        // Show one session's whole span
+        function applySession(id) {
+            const session = sessions[id];
+            if (!session) return;
+            const end = session.ended_at ? new Date(session.ended_at) : new Date();
+            currentTimeRange.start = session.started_at;
+            currentTimeRange.end = session.ended_at;
+            currentTimeRange.minutes = Math.max(1, Math.floor((end - new Date(session.started_at)) / 60000));
+
+            updateChartTimeScales();
+            refreshAllData();
+        }
+
         // Apply custom time range
         function applyCustomRange() {
             const startInput = document.getElementById('start-time').value;
@@ -841,24 +3409,88 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
             updateCharts();
             updateEventCounts();
             updateStatistics();
+            updateNeighbors();
             refreshEvents();
         }
 
+        // Pause/resume and stop/start buttons for a monitor in this process; hidden in
+        // dashboard-only mode
+        async function updateMonitorControls() {
+            const controls = document.getElementById('monitor-controls');
+            try {
+                const response = await fetch('api/v1/monitor');
+                const result = await response.json();
+                if (!result.success) {
+                    controls.classList.add('hidden');
+                    return;
+                }
+                const state = result.data;
+                document.getElementById('monitor-state').textContent =
+                    state.stopped ? 'Monitoring stopped' : state.paused ? 'Monitoring paused' : 'Monitoring';
+                document.getElementById('pause-button').textContent = state.paused ? 'Resume' : 'Pause';
+                document.getElementById('pause-button').disabled = state.stopped;
+                document.getElementById('stop-button').textContent = state.stopped ? 'Start' : 'Stop';
+                document.getElementById('diagnose-button').disabled = state.stopped || state.paused;
+                controls.classList.remove('hidden');
+            } catch (e) {
+                console.error('Failed to fetch monitor state:', e);
+            }
+        }
+
+        async function controlMonitor(button) {
+            const action = button.textContent.toLowerCase();
+            try {
+                const response = await fetch(`api/v1/monitor/${action}`, { method: 'POST' });
+                const result = await response.json();
+                if (!result.success) console.error(`Could not ${action} monitoring:`, result.error);
+            } catch (e) {
+                console.error(`Could not ${action} monitoring:`, e);
+            }
+            updateMonitorControls();
+        }
+
+        // Take a snapshot with the full diagnostic burst; the live feed shows it and its event
+        async function runDiagnostics(button) {
+            button.disabled = true;
+            button.textContent = 'Running diagnostics...';
+            try {
+                const response = await fetch('api/v1/diagnose', { method: 'POST' });
+                const result = await response.json();
+                if (!result.success) console.error('Diagnostics failed:', result.error);
+            } catch (e) {
+                console.error('Diagnostics failed:', e);
+            }
+            button.textContent = 'Run diagnostics now';
+            button.disabled = false;
+            updateMonitorControls();
+        }
+
+        // Under an aggregation server, each host's dashboard lives at /hosts/<name>/
+        function showHost() {
+            const match = location.pathname.match(/^\/hosts\/([^/]+)\//);
+            if (!match) return;
+            const host = decodeURIComponent(match[1]);
+            document.querySelector('h1').textContent = `WiFi Stability Tracker - ${host}`;
+            document.title = `${host} - WiFi Stability Tracker`;
+            document.getElementById('all-hosts').classList.remove('hidden');
+        }
+
         // Initialize
         document.addEventListener('DOMContentLoaded', () => {
+            showHost();
             initCharts();
+            loadSessions();
             updateCurrent();
             updateCharts();
             updateEventCounts();
             updateStatistics();
+            updateNeighbors();
             refreshEvents();
+            updateMonitorControls();
 
             // Auto-refresh
-            setInterval(updateCurrent, 5000);
-            setInterval(updateCharts, 10000);
-            setInterval(updateEventCounts, 30000);
-            setInterval(updateStatistics, 30000);
-            setInterval(refreshEvents, 15000);
+            connectLive();
+            setInterval(updateMonitorControls, 15000);
             
             // Event listeners
             document.getElementById('time-range').addEventListener('change', onTimeRangeChange);
@@ -868,3 +3500,119 @@ const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
 </body>
 </html>
 "##;
+
+const LOGS_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>WiFi Stability Tracker - Logs</title>
+    <script src="assets/tailwind.js"></script>
+    <style>
+        .log-entry { font-family: 'Consolas', 'Monaco', monospace; font-size: 12px; white-space: pre-wrap; word-break: break-word; }
+        .level-ERROR { color: #ef4444; }
+        .level-WARN { color: #f59e0b; }
+        .level-INFO { color: #10b981; }
+        .level-DEBUG { color: #3b82f6; }
+        .level-TRACE { color: #9ca3af; }
+    </style>
+</head>
+<body class="bg-gray-900 text-gray-100 min-h-screen">
+    <div class="container mx-auto px-4 py-6">
+        <header class="mb-6 flex justify-between items-end">
+            <div>
+                <a href="./" class="text-sm text-blue-400 hover:underline">&larr; Dashboard</a>
+                <h1 class="text-3xl font-bold text-white mb-2">Logs</h1>
+                <p id="log-status" class="text-gray-400 text-sm">Connecting...</p>
+            </div>
+            <div class="flex items-center gap-2">
+                <select id="level" class="bg-gray-700 border border-gray-600 rounded px-3 py-1 text-sm">
+                    <option value="error">Errors</option>
+                    <option value="warn">Warnings and worse</option>
+                    <option value="info" selected>Info and worse</option>
+                    <option value="debug">Debug and worse</option>
+                    <option value="trace">Everything</option>
+                </select>
+                <input id="filter" type="search" placeholder="Filter" class="bg-gray-700 border border-gray-600 rounded px-3 py-1 text-sm">
+                <button id="follow" class="bg-blue-700 hover:bg-blue-600 px-3 py-1 rounded text-sm">Following</button>
+                <button id="clear" class="bg-gray-700 hover:bg-gray-600 px-3 py-1 rounded text-sm">Clear</button>
+            </div>
+        </header>
+        <div id="logs" class="bg-gray-800 rounded-lg p-4 border border-gray-700 h-[75vh] overflow-y-auto"></div>
+    </div>
+
+    <script>
+        // Rows kept on the page; older ones are removed as new ones arrive
+        const MAX_ROWS = 2000;
+        const RETRY_MS = 5000;
+        const container = document.getElementById('logs');
+        let source = null;
+        let following = true;
+
+        function matchesFilter(row) {
+            const filter = document.getElementById('filter').value.toLowerCase();
+            return !filter || row.textContent.toLowerCase().includes(filter);
+        }
+
+        function addRecord(record) {
+            const row = document.createElement('div');
+            row.className = 'log-entry py-0.5';
+            const time = document.createElement('span');
+            time.className = 'text-gray-500';
+            time.textContent = new Date(record.timestamp).toLocaleString() + ' ';
+            const level = document.createElement('span');
+            level.className = `level-${record.level} font-semibold`;
+            level.textContent = record.level.padEnd(5) + ' ';
+            const target = document.createElement('span');
+            target.className = 'text-gray-500';
+            target.textContent = record.target + ': ';
+            const message = document.createElement('span');
+            message.textContent = record.message + (record.fields ? ' ' + record.fields : '');
+            row.append(time, level, target, message);
+            row.hidden = !matchesFilter(row);
+            container.appendChild(row);
+            while (container.childElementCount > MAX_ROWS) container.firstElementChild.remove();
+            if (following) container.scrollTop = container.scrollHeight;
+        }
+
+        async function connect() {
+            if (source) source.close();
+            container.replaceChildren();
+            const level = document.getElementById('level').value;
+            const status = document.getElementById('log-status');
+            try {
+                const response = await fetch(`api/v1/logs?level=${level}&limit=500`);
+                const result = await response.json();
+                if (result.success) result.data.forEach(addRecord);
+            } catch (e) {
+                status.textContent = `Could not load the recent logs: ${e}`;
+            }
+            source = new EventSource(`api/v1/logs/stream?level=${level}`);
+            source.onopen = () => status.textContent = 'Live';
+            source.onmessage = message => addRecord(JSON.parse(message.data));
+            source.addEventListener('skipped', message => {
+                status.textContent = `Live (skipped ${JSON.parse(message.data).skipped} records while falling behind)`;
+            });
+            source.onerror = () => {
+                status.textContent = 'Disconnected, reconnecting...';
+                source.close();
+                setTimeout(connect, RETRY_MS);
+            };
+        }
+
+        document.getElementById('level').addEventListener('change', connect);
+        document.getElementById('filter').addEventListener('input', () => {
+            for (const row of container.children) row.hidden = !matchesFilter(row);
+        });
+        document.getElementById('follow').addEventListener('click', event => {
+            following = !following;
+            event.target.textContent = following ? 'Following' : 'Paused';
+            event.target.className = `${following ? 'bg-blue-700 hover:bg-blue-600' : 'bg-gray-700 hover:bg-gray-600'} px-3 py-1 rounded text-sm`;
+            if (following) container.scrollTop = container.scrollHeight;
+        });
+        document.getElementById('clear').addEventListener('click', () => container.replaceChildren());
+        connect();
+    </script>
+</body>
+</html>
+"##;
@@ -0,0 +1,174 @@
+use crate::metrics::{Alert, EventSeverity, EventType, NetworkEvent};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// Attempts per event before it is given up on
+const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry, doubled after each failed attempt
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+/// Events kept per URL while it is unreachable; older ones are dropped first
+const MAX_QUEUED: usize = 500;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>` when a secret is set
+pub const SIGNATURE_HEADER: &str = "X-Wifi-Tracker-Signature";
+
+/// Which events are delivered: those at least `min_severity` and, when `event_types` isn't
+/// empty, of one of those types
+#[derive(Debug, Clone)]
+pub struct WebhookFilter {
+    pub min_severity: EventSeverity,
+    pub event_types: Vec<EventType>,
+}
+
+impl WebhookFilter {
+    pub fn matches(&self, event: &NetworkEvent) -> bool {
+        event.severity >= self.min_severity
+            && (self.event_types.is_empty() || self.event_types.contains(&event.event_type))
+    }
+}
+
+/// Parse an event type name as the API spells it, e.g. `ConnectionDropped`
+pub fn parse_event_type(name: &str) -> Result<EventType, String> {
+    serde_json::from_value(serde_json::Value::String(name.trim().to_string()))
+        .map_err(|_| format!("unknown event type '{}' (e.g. ConnectionDropped, HighLatency, DnsFailure)", name))
+}
+
+/// The body POSTed for each event, or each alert rule firing or resolving
+#[derive(Serialize)]
+pub(crate) struct Payload<'a> {
+    /// The monitoring host's name, so one receiver can tell several monitors apart
+    pub host: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<&'a NetworkEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<&'a Alert>,
+}
+
+/// Posts matching events as JSON to one or more URLs from background tasks, one per URL so
+/// a slow or unreachable receiver never holds up monitoring or the other receivers. Failed
+/// deliveries are retried with backoff, except ones the receiver rejected outright.
+#[derive(Clone)]
+pub struct Webhooks {
+    targets: Vec<mpsc::UnboundedSender<String>>,
+    filter: WebhookFilter,
+    host: Option<String>,
+}
+
+impl Webhooks {
+    /// Start delivering to `urls`. With a `secret`, each request is signed with it in the
+    /// `X-Wifi-Tracker-Signature` header, so receivers can check it came from this monitor.
+    pub fn spawn(urls: Vec<String>, secret: Option<String>, filter: WebhookFilter) -> Option<Self> {
+        if urls.is_empty() {
+            return None;
+        }
+        info!("Sending {:?} and more severe events to {} webhook(s)", filter.min_severity, urls.len());
+        let targets = urls.into_iter().map(|url| {
+            let (bodies, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(run(url.clone(), url, secret.clone(), receiver));
+            bodies
+        }).collect();
+        Some(Self { targets, filter, host: sysinfo::System::host_name() })
+    }
+
+    pub fn send(&self, event: &NetworkEvent) {
+        if self.filter.matches(event) {
+            self.deliver(Payload { host: self.host.as_deref(), event: Some(event), alert: None });
+        }
+    }
+
+    /// An alert rule fired or resolved; the event type filter doesn't apply
+    pub fn send_alert(&self, alert: &Alert) {
+        if alert.severity >= self.filter.min_severity {
+            self.deliver(Payload { host: self.host.as_deref(), event: None, alert: Some(alert) });
+        }
+    }
+
+    fn deliver(&self, payload: Payload) {
+        match serde_json::to_string(&payload) {
+            Ok(body) => {
+                for target in &self.targets {
+                    let _ = target.send(body.clone());
+                }
+            }
+            Err(e) => warn!("Failed to encode a webhook payload: {}", e),
+        }
+    }
+}
+
+/// `sha256=<hex>` signature of `body` under `secret`
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body.as_bytes());
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+/// POST each body to `url` in turn, retrying with backoff. `name` stands for the URL in
+/// the log, for receivers whose URL is itself the secret.
+pub(crate) async fn run(name: String, url: String, secret: Option<String>, mut receiver: mpsc::UnboundedReceiver<String>) {
+    let client = match reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Webhook {} disabled: {}", name, e);
+            return;
+        }
+    };
+
+    let mut queue: VecDeque<String> = VecDeque::new();
+    while let Some(body) = receiver.recv().await {
+        queue.push_back(body);
+        while let Some(body) = queue.pop_front() {
+            let mut wait = FIRST_RETRY;
+            for attempt in 1..=MAX_ATTEMPTS {
+                let mut request = client.post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.clone());
+                if let Some(ref secret) = secret {
+                    request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+                }
+                let reason = match request.send().await {
+                    Ok(response) if response.status().is_success() => {
+                        debug!("Delivered an event to webhook {}", name);
+                        break;
+                    }
+                    // Retrying won't fix a payload or signature the receiver refuses
+                    Ok(response) if response.status().is_client_error() && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        warn!("Webhook {} rejected an event ({}): {}", name, status, text.trim());
+                        break;
+                    }
+                    Ok(response) => response.status().to_string(),
+                    Err(e) => e.to_string(),
+                };
+                if attempt == MAX_ATTEMPTS {
+                    warn!("Giving up on an event for webhook {} after {} attempts: {}", name, MAX_ATTEMPTS, reason);
+                    break;
+                }
+                debug!("Webhook {} failed (attempt {} of {}), retrying in {:?}: {}", name, attempt, MAX_ATTEMPTS, wait, reason);
+                tokio::time::sleep(wait).await;
+                wait *= 2;
+
+                // Keep collecting what arrives meanwhile, up to a limit
+                while let Ok(body) = receiver.try_recv() {
+                    queue.push_back(body);
+                }
+                if queue.len() > MAX_QUEUED {
+                    let dropped = queue.len() - MAX_QUEUED;
+                    queue.drain(..dropped);
+                    warn!("Webhook {} is behind, dropped its {} oldest events", name, dropped);
+                }
+            }
+        }
+    }
+}
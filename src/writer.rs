@@ -0,0 +1,165 @@
+use crate::metrics::{Alert, Incident, WifiSnapshot};
+use crate::storage::{self, MetricsStore};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Snapshots, incidents and alerts waiting for the disk before the monitor has to wait too
+const QUEUE_CAPACITY: usize = 256;
+/// Most snapshots written in one transaction
+const MAX_BATCH: usize = 64;
+/// Tries for a batch while the database is busy (each try waits out the busy timeout first)
+const BUSY_ATTEMPTS: u32 = 10;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+enum Write {
+    Snapshot(Box<WifiSnapshot>),
+    Incident(Incident),
+    Alert(Alert),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Saves the monitor's snapshots and incidents from a background task, so a slow disk,
+/// a vacuum or another process holding the database never delays the next measurement.
+/// Whatever queued up meanwhile is written together in one transaction. Stored snapshots
+/// are passed on to `saved`, for the dashboard's live updates.
+pub struct SnapshotWriter {
+    writes: mpsc::Sender<Write>,
+    task: JoinHandle<()>,
+}
+
+impl SnapshotWriter {
+    pub fn spawn(store: Arc<dyn MetricsStore>, saved: broadcast::Sender<Arc<WifiSnapshot>>) -> Self {
+        let (writes, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let task = tokio::spawn(run(store, receiver, saved));
+        Self { writes, task }
+    }
+
+    pub async fn save_snapshot(&self, snapshot: &WifiSnapshot) {
+        self.send(Write::Snapshot(Box::new(snapshot.clone()))).await;
+    }
+
+    pub async fn save_incident(&self, incident: &Incident) {
+        self.send(Write::Incident(incident.clone())).await;
+    }
+
+    pub async fn save_alert(&self, alert: &Alert) {
+        self.send(Write::Alert(alert.clone())).await;
+    }
+
+    /// Wait until everything queued so far is written
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        self.send(Write::Flush(done)).await;
+        let _ = wait.await;
+    }
+
+    /// Write what is still queued and stop
+    pub async fn close(self) {
+        drop(self.writes);
+        if let Err(e) = self.task.await {
+            error!("Snapshot writer failed: {}", e);
+        }
+    }
+
+    async fn send(&self, write: Write) {
+        // A full queue holds up the monitor rather than growing without bound
+        let write = match self.writes.try_send(write) {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(write)) => {
+                warn!("Snapshot writes are {} behind, waiting for the database", QUEUE_CAPACITY);
+                write
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => return,
+        };
+        let _ = self.writes.send(write).await;
+    }
+}
+
+async fn run(store: Arc<dyn MetricsStore>, mut receiver: mpsc::Receiver<Write>, saved: broadcast::Sender<Arc<WifiSnapshot>>) {
+    while let Some(write) = receiver.recv().await {
+        let mut snapshots = Vec::new();
+        let mut incidents: Vec<Incident> = Vec::new();
+        let mut alerts: Vec<Alert> = Vec::new();
+        let mut flushed = Vec::new();
+        let mut next = Some(write);
+        while let Some(write) = next.take() {
+            match write {
+                Write::Snapshot(snapshot) => snapshots.push(*snapshot),
+                // Only the latest state of each incident needs writing
+                Write::Incident(incident) => {
+                    incidents.retain(|i| i.id != incident.id);
+                    incidents.push(incident);
+                }
+                Write::Alert(alert) => {
+                    alerts.retain(|a| a.id != alert.id);
+                    alerts.push(alert);
+                }
+                Write::Flush(done) => flushed.push(done),
+            }
+            if snapshots.len() < MAX_BATCH {
+                next = receiver.try_recv().ok();
+            }
+        }
+
+        if !snapshots.is_empty() {
+            let count = snapshots.len();
+            let snapshots = Arc::new(snapshots);
+            let (task_store, batch) = (store.clone(), snapshots.clone());
+            match with_retries(move || task_store.save_snapshots(&batch)).await {
+                // Copies are only made while a dashboard is connected
+                Ok(()) if saved.receiver_count() > 0 => {
+                    for snapshot in snapshots.iter() {
+                        let _ = saved.send(Arc::new(snapshot.clone()));
+                    }
+                }
+                Ok(()) => {}
+                Err(e) => error!("Failed to save {} snapshot(s): {}", count, e),
+            }
+        }
+        if !incidents.is_empty() {
+            let task_store = store.clone();
+            let saved = with_retries(move || incidents.iter().try_for_each(|incident| task_store.save_incident(incident)));
+            if let Err(e) = saved.await {
+                error!("Failed to save incident: {}", e);
+            }
+        }
+        if !alerts.is_empty() {
+            let task_store = store.clone();
+            let saved = with_retries(move || alerts.iter().try_for_each(|alert| task_store.save_alert(alert)));
+            if let Err(e) = saved.await {
+                error!("Failed to save alert: {}", e);
+            }
+        }
+        for done in flushed {
+            let _ = done.send(());
+        }
+    }
+    debug!("Snapshot writer stopped");
+}
+
+/// Run a write on the blocking pool, trying again while the database is busy
+async fn with_retries<F>(write: F) -> anyhow::Result<()>
+where
+    F: Fn() -> anyhow::Result<()> + Send + Sync + 'static,
+{
+    let write = Arc::new(write);
+    let mut attempt = 1;
+    loop {
+        let task_write = write.clone();
+        let result = match tokio::task::spawn_blocking(move || task_write()).await {
+            Ok(result) => result,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Err(e) if storage::is_busy(&e) && attempt < BUSY_ATTEMPTS => {
+                debug!("Database busy, retrying write ({}/{})", attempt, BUSY_ATTEMPTS);
+                attempt += 1;
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}